@@ -0,0 +1,267 @@
+// Typed abstract syntax tree built by the semantic actions of the full grammar
+// (see ast_actions.rs). Later passes pattern-match on these types instead of
+// comparing node descriptions.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+    pub line: usize,
+}
+
+impl Span {
+    pub fn new(line: usize) -> Self {
+        Span { line }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TypeName {
+    Integer,
+    Float,
+    Void,
+    Class(String),
+}
+
+impl TypeName {
+    pub fn as_string(&self) -> String {
+        match self {
+            TypeName::Integer => "integer".to_string(),
+            TypeName::Float => "float".to_string(),
+            TypeName::Void => "void".to_string(),
+            TypeName::Class(name) => name.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Visibility {
+    Public,
+    Private,
+}
+
+impl Visibility {
+    pub fn as_string(&self) -> String {
+        match self {
+            Visibility::Public => "public".to_string(),
+            Visibility::Private => "private".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Or,
+    Mul,
+    Div,
+    And,
+    Eq,
+    Neq,
+    Lt,
+    Gt,
+    Leq,
+    Geq,
+}
+
+impl BinaryOp {
+    pub fn is_relational(&self) -> bool {
+        matches!(self, BinaryOp::Eq | BinaryOp::Neq | BinaryOp::Lt | BinaryOp::Gt | BinaryOp::Leq | BinaryOp::Geq)
+    }
+
+    pub fn is_logical(&self) -> bool {
+        matches!(self, BinaryOp::And | BinaryOp::Or)
+    }
+
+    pub fn as_string(&self) -> String {
+        let symbol = match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Or => "or",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::And => "and",
+            BinaryOp::Eq => "==",
+            BinaryOp::Neq => "<>",
+            BinaryOp::Lt => "<",
+            BinaryOp::Gt => ">",
+            BinaryOp::Leq => "<=",
+            BinaryOp::Geq => ">=",
+        };
+        symbol.to_string()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum UnaryOp {
+    Plus,
+    Neg,
+    Not,
+}
+
+impl UnaryOp {
+    pub fn as_string(&self) -> String {
+        let symbol = match self {
+            UnaryOp::Plus => "+",
+            UnaryOp::Neg => "-",
+            UnaryOp::Not => "not",
+        };
+        symbol.to_string()
+    }
+}
+
+// Float literals keep their lexeme so that the tree stays hashable and prints
+// exactly like the source; use `float_value` to get the number.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Literal {
+    Int(i64),
+    Float(String),
+}
+
+impl Literal {
+    pub fn float_value(&self) -> f64 {
+        match self {
+            Literal::Int(value) => *value as f64,
+            Literal::Float(lexeme) => lexeme.parse()
+                .unwrap_or_else(|_| panic!("Invalid float literal {}", lexeme)),
+        }
+    }
+
+    pub fn as_string(&self) -> String {
+        match self {
+            Literal::Int(value) => value.to_string(),
+            Literal::Float(lexeme) => lexeme.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Expr {
+    Binary { op: BinaryOp, lhs: Box<Expr>, rhs: Box<Expr>, span: Span },
+    Unary { op: UnaryOp, operand: Box<Expr>, span: Span },
+    Literal { value: Literal, span: Span },
+    Var { name: String, span: Span },
+    // `receiver.name(args)` for member functions, `name(args)` for free functions
+    Call { receiver: Option<Box<Expr>>, name: String, args: Vec<Expr>, span: Span },
+    // one node per `[index]`, so `a[i][j]` is `Index(Index(a, i), j)`
+    Index { base: Box<Expr>, index: Box<Expr>, span: Span },
+    Member { base: Box<Expr>, name: String, span: Span },
+}
+
+impl Expr {
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Binary { span, .. } => *span,
+            Expr::Unary { span, .. } => *span,
+            Expr::Literal { span, .. } => *span,
+            Expr::Var { span, .. } => *span,
+            Expr::Call { span, .. } => *span,
+            Expr::Index { span, .. } => *span,
+            Expr::Member { span, .. } => *span,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Stmt {
+    // `localvar` declarations can be interleaved with statements in a function body
+    Local { var: VarDecl, span: Span },
+    Assign { target: Expr, value: Expr, span: Span },
+    If { condition: Expr, then_block: Vec<Stmt>, else_block: Vec<Stmt>, span: Span },
+    While { condition: Expr, body: Vec<Stmt>, span: Span },
+    Read { target: Expr, span: Span },
+    Write { value: Expr, span: Span },
+    Return { value: Expr, span: Span },
+    Call { call: Expr, span: Span },
+}
+
+impl Stmt {
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Local { span, .. } => *span,
+            Stmt::Assign { span, .. } => *span,
+            Stmt::If { span, .. } => *span,
+            Stmt::While { span, .. } => *span,
+            Stmt::Read { span, .. } => *span,
+            Stmt::Write { span, .. } => *span,
+            Stmt::Return { span, .. } => *span,
+            Stmt::Call { span, .. } => *span,
+        }
+    }
+}
+
+// Used for local variables, parameters and attributes. `dims` holds one entry per
+// `[...]`, `None` for an unsized dimension `[]`. `constructor_args` is only set for
+// locals declared as `localvar x: T(args);`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct VarDecl {
+    pub name: String,
+    pub type_name: TypeName,
+    pub dims: Vec<Option<usize>>,
+    pub constructor_args: Option<Vec<Expr>>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FuncSig {
+    pub name: String,
+    pub params: Vec<VarDecl>,
+    pub return_type: TypeName,
+    pub is_constructor: bool,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MemberDecl {
+    Attribute { visibility: Visibility, var: VarDecl },
+    Function { visibility: Visibility, sig: FuncSig },
+}
+
+impl MemberDecl {
+    pub fn visibility(&self) -> Visibility {
+        match self {
+            MemberDecl::Attribute { visibility, .. } => *visibility,
+            MemberDecl::Function { visibility, .. } => *visibility,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            MemberDecl::Attribute { var, .. } => var.span,
+            MemberDecl::Function { sig, .. } => sig.span,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ClassDecl {
+    pub name: String,
+    pub parents: Vec<String>,
+    pub members: Vec<MemberDecl>,
+    pub span: Span,
+}
+
+// `class_name` is set for member function and constructor definitions
+// (`function X::f(...)`).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FuncDef {
+    pub class_name: Option<String>,
+    pub sig: FuncSig,
+    pub body: Vec<Stmt>,
+    pub span: Span,
+}
+
+impl FuncDef {
+    pub fn qualified_name(&self) -> String {
+        match &self.class_name {
+            Some(class_name) => format!("{}::{}", class_name, self.sig.name),
+            None => self.sig.name.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Default)]
+pub struct Program {
+    pub classes: Vec<ClassDecl>,
+    pub functions: Vec<FuncDef>,
+    pub span: Span,
+}
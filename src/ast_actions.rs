@@ -0,0 +1,1491 @@
+use crate::ast::*;
+use crate::lexical_analysis::Token;
+use crate::lexical_analysis::TokenType;
+use crate::semantic_analysis::ProductionElement::{SemanticElement, SyntaxElement};
+use crate::semantic_analysis::*;
+use crate::semantic_graph::SemanticTree;
+
+// Partially built pieces of the typed AST. They live on the semantic stack (wrapped
+// in `SemanticNode::Ast`) until a gather action combines them into a bigger piece.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum AstFragment {
+    // line of the first token of a construct, pushed before the construct is parsed
+    Location(Span),
+    Name { name: String, span: Span },
+    TypeName(TypeName),
+    Dim(Option<usize>),
+    Dims(Vec<Option<usize>>),
+    Visibility(Visibility),
+    BinaryOp { op: BinaryOp, span: Span },
+    UnaryOp { op: UnaryOp, span: Span },
+    Expr(Expr),
+    Args(Vec<Expr>),
+    Stmt(Stmt),
+    Block(Vec<Stmt>),
+    VarDecl(VarDecl),
+    FuncSig(FuncSig),
+    MemberDecl(MemberDecl),
+    FuncHead { class_name: Option<String>, sig: FuncSig },
+    ClassDecl(ClassDecl),
+    FuncDef(FuncDef),
+    Program(Program),
+}
+
+impl AstFragment {
+    pub fn label(&self) -> String {
+        match self {
+            AstFragment::Location(_) => "Location".to_string(),
+            AstFragment::Name { name, .. } => format!("Name_{}", name),
+            AstFragment::TypeName(type_name) => format!("Type_{}", type_name.as_string()),
+            AstFragment::Dim(_) => "Dim".to_string(),
+            AstFragment::Dims(_) => "Dims".to_string(),
+            AstFragment::Visibility(visibility) => format!("Visibility_{}", visibility.as_string()),
+            AstFragment::BinaryOp { op, .. } => format!("Op_{:?}", op),
+            AstFragment::UnaryOp { op, .. } => format!("Op_{:?}", op),
+            AstFragment::Expr(expr) => expr_label(expr),
+            AstFragment::Args(_) => "Args".to_string(),
+            AstFragment::Stmt(statement) => stmt_label(statement),
+            AstFragment::Block(_) => "Block".to_string(),
+            AstFragment::VarDecl(var) => format!("VarDecl_{}", var.name),
+            AstFragment::FuncSig(sig) => format!("FuncSig_{}", sig.name),
+            AstFragment::MemberDecl(_) => "MemberDecl".to_string(),
+            AstFragment::FuncHead { sig, .. } => format!("FuncHead_{}", sig.name),
+            AstFragment::ClassDecl(class) => format!("ClassDecl_{}", class.name),
+            AstFragment::FuncDef(function) => format!("FuncDef_{}", function.sig.name),
+            AstFragment::Program(_) => "Program".to_string(),
+        }
+    }
+
+    pub fn into_span(self) -> Span {
+        match self {
+            AstFragment::Location(span) => span,
+            other => panic!("Expected a location but got {:?}", other),
+        }
+    }
+
+    pub fn into_name(self) -> (String, Span) {
+        match self {
+            AstFragment::Name { name, span } => (name, span),
+            other => panic!("Expected a name but got {:?}", other),
+        }
+    }
+
+    pub fn into_type_name(self) -> TypeName {
+        match self {
+            AstFragment::TypeName(type_name) => type_name,
+            other => panic!("Expected a type but got {:?}", other),
+        }
+    }
+
+    pub fn into_dims(self) -> Vec<Option<usize>> {
+        match self {
+            AstFragment::Dims(dims) => dims,
+            other => panic!("Expected array dimensions but got {:?}", other),
+        }
+    }
+
+    pub fn into_expr(self) -> Expr {
+        match self {
+            AstFragment::Expr(expr) => expr,
+            other => panic!("Expected an expression but got {:?}", other),
+        }
+    }
+
+    pub fn into_args(self) -> Vec<Expr> {
+        match self {
+            AstFragment::Args(args) => args,
+            other => panic!("Expected call arguments but got {:?}", other),
+        }
+    }
+
+    pub fn into_block(self) -> Vec<Stmt> {
+        match self {
+            AstFragment::Block(block) => block,
+            other => panic!("Expected a block but got {:?}", other),
+        }
+    }
+
+    pub fn into_var_decl(self) -> VarDecl {
+        match self {
+            AstFragment::VarDecl(var) => var,
+            other => panic!("Expected a variable declaration but got {:?}", other),
+        }
+    }
+
+    pub fn into_program(self) -> Program {
+        match self {
+            AstFragment::Program(program) => program,
+            other => panic!("Expected a program but got {:?}", other),
+        }
+    }
+}
+
+fn expr_label(expr: &Expr) -> String {
+    match expr {
+        Expr::Binary { op, .. } => format!("{:?}", op),
+        Expr::Unary { op, .. } => format!("{:?}", op),
+        Expr::Literal { value: Literal::Int(_), .. } => "IntLit".to_string(),
+        Expr::Literal { value: Literal::Float(_), .. } => "FloatLit".to_string(),
+        Expr::Var { name, .. } => format!("Var_{}", name),
+        Expr::Call { name, .. } => format!("Call_{}", name),
+        Expr::Index { .. } => "Index".to_string(),
+        Expr::Member { name, .. } => format!("Member_{}", name),
+    }
+}
+
+fn stmt_label(statement: &Stmt) -> String {
+    match statement {
+        Stmt::Local { var, .. } => format!("LocalVar_{}", var.name),
+        Stmt::Assign { .. } => "Assign".to_string(),
+        Stmt::If { .. } => "If".to_string(),
+        Stmt::While { .. } => "While".to_string(),
+        Stmt::Read { .. } => "Read".to_string(),
+        Stmt::Write { .. } => "Write".to_string(),
+        Stmt::Return { .. } => "Return".to_string(),
+        Stmt::Call { .. } => "CallStatement".to_string(),
+    }
+}
+
+// Adds the fragment to the tree, as the parent of the `consumed` nodes, and pushes it
+// on the semantic stack.
+fn push_fragment(
+    fragment: AstFragment,
+    consumed: Vec<usize>,
+    semantic_stack: &mut Vec<SemanticNode>,
+    tree: &mut SemanticTree,
+) {
+    let new_node = SemanticNode::new_ast_node(&tree.nodes, fragment);
+    let new_id = new_node.id();
+    push_new_node(new_node, semantic_stack, tree);
+    tree.attach_children(new_id, consumed);
+}
+
+// Pops fragments off the semantic stack and remembers which tree nodes they came from,
+// so that the gathered node can adopt them in source order. Markers and locations are
+// bookkeeping and don't become children.
+struct StackReader<'a> {
+    semantic_stack: &'a mut Vec<SemanticNode>,
+    consumed: Vec<usize>,
+}
+
+impl<'a> StackReader<'a> {
+    fn new(semantic_stack: &'a mut Vec<SemanticNode>) -> Self {
+        StackReader { semantic_stack, consumed: vec![] }
+    }
+
+    fn pop(&mut self) -> AstFragment {
+        match self.semantic_stack.pop() {
+            Some(SemanticNode::Ast { id, fragment }) => {
+                if !matches!(fragment, AstFragment::Location(_)) {
+                    self.consumed.push(id);
+                }
+                fragment
+            }
+            other => panic!("Expected an AST fragment on the semantic stack but got {:?}", other),
+        }
+    }
+
+    // Pops everything above the closest marker (and the marker itself), in source order.
+    fn pop_until_marker(&mut self) -> Vec<AstFragment> {
+        assert!(self.semantic_stack.iter().any(|node| node.is_marker()),
+                "To collect a list, a start marker should exist, but it doesn't in stack {:?}", self.semantic_stack);
+        let mut collected = vec![];
+        while !self.semantic_stack.last().unwrap().is_marker() {
+            collected.push(self.pop());
+        }
+        assert!(self.semantic_stack.pop().unwrap().is_marker());
+        collected.reverse();
+        collected
+    }
+
+    // Consumed node ids in source order.
+    fn into_consumed(self) -> Vec<usize> {
+        let mut consumed = self.consumed;
+        consumed.reverse();
+        consumed
+    }
+}
+
+fn expect_token(curr_token: Option<&Token>) -> &Token {
+    curr_token.expect("Expected a token for the semantic action, but none available")
+}
+
+pub struct PushLocation;
+
+impl SemanticAction for PushLocation {
+    fn take_action(
+        &self,
+        semantic_stack: &mut Vec<SemanticNode>,
+        tree: &mut SemanticTree,
+        curr_token: Option<&Token>
+    ) {
+        let line = expect_token(curr_token).line;
+        push_fragment(AstFragment::Location(Span::new(line)), vec![], semantic_stack, tree);
+    }
+}
+
+pub struct PushName;
+
+impl SemanticAction for PushName {
+    fn take_action(
+        &self,
+        semantic_stack: &mut Vec<SemanticNode>,
+        tree: &mut SemanticTree,
+        curr_token: Option<&Token>
+    ) {
+        let curr_token = expect_token(curr_token);
+        assert_eq!(curr_token.token_type, TokenType::Identifier, "Expected an identifier token but got {:?}", curr_token);
+        let fragment = AstFragment::Name { name: curr_token.lexeme.clone(), span: Span::new(curr_token.line) };
+        push_fragment(fragment, vec![], semantic_stack, tree);
+    }
+}
+
+pub struct PushVariable;
+
+impl SemanticAction for PushVariable {
+    fn take_action(
+        &self,
+        semantic_stack: &mut Vec<SemanticNode>,
+        tree: &mut SemanticTree,
+        curr_token: Option<&Token>
+    ) {
+        let curr_token = expect_token(curr_token);
+        assert_eq!(curr_token.token_type, TokenType::Identifier, "Expected an identifier token but got {:?}", curr_token);
+        let variable = Expr::Var { name: curr_token.lexeme.clone(), span: Span::new(curr_token.line) };
+        push_fragment(AstFragment::Expr(variable), vec![], semantic_stack, tree);
+    }
+}
+
+pub struct PushTypeName;
+
+impl SemanticAction for PushTypeName {
+    fn take_action(
+        &self,
+        semantic_stack: &mut Vec<SemanticNode>,
+        tree: &mut SemanticTree,
+        curr_token: Option<&Token>
+    ) {
+        let curr_token = expect_token(curr_token);
+        let type_name = match curr_token.token_type {
+            TokenType::IntegerKeyword => TypeName::Integer,
+            TokenType::FloatKeyword => TypeName::Float,
+            TokenType::Void => TypeName::Void,
+            TokenType::Identifier => TypeName::Class(curr_token.lexeme.clone()),
+            _ => panic!("Expected a type token but got {:?}", curr_token),
+        };
+        push_fragment(AstFragment::TypeName(type_name), vec![], semantic_stack, tree);
+    }
+}
+
+// Pushes the size of one `[...]`; the current token is either the size or `]`.
+pub struct PushDim;
+
+impl SemanticAction for PushDim {
+    fn take_action(
+        &self,
+        semantic_stack: &mut Vec<SemanticNode>,
+        tree: &mut SemanticTree,
+        curr_token: Option<&Token>
+    ) {
+        let curr_token = expect_token(curr_token);
+        let dim = match curr_token.token_type {
+            // the lexeme is all digits, so only a size too large to parse fails, and
+            // the semantic checks report it
+            TokenType::IntLit => Some(curr_token.lexeme.parse::<usize>().unwrap_or(usize::MAX)),
+            TokenType::CloseSquareBracket => None,
+            _ => panic!("Expected an array size but got {:?}", curr_token),
+        };
+        push_fragment(AstFragment::Dim(dim), vec![], semantic_stack, tree);
+    }
+}
+
+pub struct PushVisibility {
+    pub visibility: Visibility,
+}
+
+impl SemanticAction for PushVisibility {
+    fn take_action(
+        &self,
+        semantic_stack: &mut Vec<SemanticNode>,
+        tree: &mut SemanticTree,
+        _curr_token: Option<&Token>
+    ) {
+        push_fragment(AstFragment::Visibility(self.visibility), vec![], semantic_stack, tree);
+    }
+}
+
+pub struct PushBinaryOp {
+    pub op: BinaryOp,
+}
+
+impl SemanticAction for PushBinaryOp {
+    fn take_action(
+        &self,
+        semantic_stack: &mut Vec<SemanticNode>,
+        tree: &mut SemanticTree,
+        curr_token: Option<&Token>
+    ) {
+        let span = Span::new(expect_token(curr_token).line);
+        push_fragment(AstFragment::BinaryOp { op: self.op, span }, vec![], semantic_stack, tree);
+    }
+}
+
+pub struct PushUnaryOp {
+    pub op: UnaryOp,
+}
+
+impl SemanticAction for PushUnaryOp {
+    fn take_action(
+        &self,
+        semantic_stack: &mut Vec<SemanticNode>,
+        tree: &mut SemanticTree,
+        curr_token: Option<&Token>
+    ) {
+        let span = Span::new(expect_token(curr_token).line);
+        push_fragment(AstFragment::UnaryOp { op: self.op, span }, vec![], semantic_stack, tree);
+    }
+}
+
+pub struct PushLiteral;
+
+impl SemanticAction for PushLiteral {
+    fn take_action(
+        &self,
+        semantic_stack: &mut Vec<SemanticNode>,
+        tree: &mut SemanticTree,
+        curr_token: Option<&Token>
+    ) {
+        let curr_token = expect_token(curr_token);
+        let value = match curr_token.token_type {
+            // as for array sizes, the type checker reports literals out of range
+            TokenType::IntLit => Literal::Int(curr_token.lexeme.parse().unwrap_or(i64::MAX)),
+            TokenType::FloatLit => Literal::Float(curr_token.lexeme.clone()),
+            _ => panic!("Expected a literal but got {:?}", curr_token),
+        };
+        let literal = Expr::Literal { value, span: Span::new(curr_token.line) };
+        push_fragment(AstFragment::Expr(literal), vec![], semantic_stack, tree);
+    }
+}
+
+pub struct DimsGather;
+
+impl SemanticAction for DimsGather {
+    fn take_action(
+        &self,
+        semantic_stack: &mut Vec<SemanticNode>,
+        tree: &mut SemanticTree,
+        _curr_token: Option<&Token>
+    ) {
+        let mut reader = StackReader::new(semantic_stack);
+        let dims = reader.pop_until_marker().into_iter()
+            .map(|fragment| match fragment {
+                AstFragment::Dim(dim) => dim,
+                other => panic!("Expected an array size but got {:?}", other),
+            }).collect();
+        push_fragment(AstFragment::Dims(dims), reader.into_consumed(), semantic_stack, tree);
+    }
+}
+
+pub struct ArgsGather;
+
+impl SemanticAction for ArgsGather {
+    fn take_action(
+        &self,
+        semantic_stack: &mut Vec<SemanticNode>,
+        tree: &mut SemanticTree,
+        _curr_token: Option<&Token>
+    ) {
+        let mut reader = StackReader::new(semantic_stack);
+        let args = reader.pop_until_marker().into_iter()
+            .map(AstFragment::into_expr).collect();
+        push_fragment(AstFragment::Args(args), reader.into_consumed(), semantic_stack, tree);
+    }
+}
+
+pub struct BlockGather;
+
+impl SemanticAction for BlockGather {
+    fn take_action(
+        &self,
+        semantic_stack: &mut Vec<SemanticNode>,
+        tree: &mut SemanticTree,
+        _curr_token: Option<&Token>
+    ) {
+        let mut reader = StackReader::new(semantic_stack);
+        let statements = reader.pop_until_marker().into_iter()
+            .map(|fragment| match fragment {
+                AstFragment::Stmt(statement) => statement,
+                other => panic!("Expected a statement but got {:?}", other),
+            }).collect();
+        push_fragment(AstFragment::Block(statements), reader.into_consumed(), semantic_stack, tree);
+    }
+}
+
+// Stack: Location, Name, TypeName, Dims
+pub struct VarDeclGather;
+
+impl SemanticAction for VarDeclGather {
+    fn take_action(
+        &self,
+        semantic_stack: &mut Vec<SemanticNode>,
+        tree: &mut SemanticTree,
+        _curr_token: Option<&Token>
+    ) {
+        let mut reader = StackReader::new(semantic_stack);
+        let dims = reader.pop().into_dims();
+        let type_name = reader.pop().into_type_name();
+        let (name, _) = reader.pop().into_name();
+        let span = reader.pop().into_span();
+        let var = VarDecl { name, type_name, dims, constructor_args: None, span };
+        push_fragment(AstFragment::VarDecl(var), reader.into_consumed(), semantic_stack, tree);
+    }
+}
+
+// Stack: Location, Name, TypeName, Dims or Args
+pub struct LocalVarStmtGather;
+
+impl SemanticAction for LocalVarStmtGather {
+    fn take_action(
+        &self,
+        semantic_stack: &mut Vec<SemanticNode>,
+        tree: &mut SemanticTree,
+        _curr_token: Option<&Token>
+    ) {
+        let mut reader = StackReader::new(semantic_stack);
+        let (dims, constructor_args) = match reader.pop() {
+            AstFragment::Dims(dims) => (dims, None),
+            AstFragment::Args(args) => (vec![], Some(args)),
+            other => panic!("Expected array dimensions or constructor arguments but got {:?}", other),
+        };
+        let type_name = reader.pop().into_type_name();
+        let (name, _) = reader.pop().into_name();
+        let span = reader.pop().into_span();
+        let var = VarDecl { name, type_name, dims, constructor_args, span };
+        push_fragment(AstFragment::Stmt(Stmt::Local { var, span }), reader.into_consumed(), semantic_stack, tree);
+    }
+}
+
+fn pop_params(reader: &mut StackReader) -> Vec<VarDecl> {
+    reader.pop_until_marker().into_iter()
+        .map(AstFragment::into_var_decl).collect()
+}
+
+// Stack: Location, Name, Marker, params..., TypeName
+pub struct MemberFuncSigGather;
+
+impl SemanticAction for MemberFuncSigGather {
+    fn take_action(
+        &self,
+        semantic_stack: &mut Vec<SemanticNode>,
+        tree: &mut SemanticTree,
+        _curr_token: Option<&Token>
+    ) {
+        let mut reader = StackReader::new(semantic_stack);
+        let return_type = reader.pop().into_type_name();
+        let params = pop_params(&mut reader);
+        let (name, _) = reader.pop().into_name();
+        let span = reader.pop().into_span();
+        let sig = FuncSig { name, params, return_type, is_constructor: false, span };
+        push_fragment(AstFragment::FuncSig(sig), reader.into_consumed(), semantic_stack, tree);
+    }
+}
+
+// Stack: Location, Marker, params...
+pub struct ConstructorSigGather;
+
+impl SemanticAction for ConstructorSigGather {
+    fn take_action(
+        &self,
+        semantic_stack: &mut Vec<SemanticNode>,
+        tree: &mut SemanticTree,
+        _curr_token: Option<&Token>
+    ) {
+        let mut reader = StackReader::new(semantic_stack);
+        let params = pop_params(&mut reader);
+        let span = reader.pop().into_span();
+        let sig = FuncSig { name: "constructor".to_string(), params, return_type: TypeName::Void, is_constructor: true, span };
+        push_fragment(AstFragment::FuncSig(sig), reader.into_consumed(), semantic_stack, tree);
+    }
+}
+
+// Stack: Visibility, VarDecl or FuncSig
+pub struct MemberDeclGather;
+
+impl SemanticAction for MemberDeclGather {
+    fn take_action(
+        &self,
+        semantic_stack: &mut Vec<SemanticNode>,
+        tree: &mut SemanticTree,
+        _curr_token: Option<&Token>
+    ) {
+        let mut reader = StackReader::new(semantic_stack);
+        let member = reader.pop();
+        let visibility = match reader.pop() {
+            AstFragment::Visibility(visibility) => visibility,
+            other => panic!("Expected a visibility but got {:?}", other),
+        };
+        let member_decl = match member {
+            AstFragment::VarDecl(var) => MemberDecl::Attribute { visibility, var },
+            AstFragment::FuncSig(sig) => MemberDecl::Function { visibility, sig },
+            other => panic!("Expected a member declaration but got {:?}", other),
+        };
+        push_fragment(AstFragment::MemberDecl(member_decl), reader.into_consumed(), semantic_stack, tree);
+    }
+}
+
+// Stack: Location, Name, Marker, parent names..., Marker, members...
+pub struct ClassDeclGather;
+
+impl SemanticAction for ClassDeclGather {
+    fn take_action(
+        &self,
+        semantic_stack: &mut Vec<SemanticNode>,
+        tree: &mut SemanticTree,
+        _curr_token: Option<&Token>
+    ) {
+        let mut reader = StackReader::new(semantic_stack);
+        let members = reader.pop_until_marker().into_iter()
+            .map(|fragment| match fragment {
+                AstFragment::MemberDecl(member) => member,
+                other => panic!("Expected a member declaration but got {:?}", other),
+            }).collect();
+        let parents = reader.pop_until_marker().into_iter()
+            .map(|fragment| fragment.into_name().0).collect();
+        let (name, _) = reader.pop().into_name();
+        let span = reader.pop().into_span();
+        let class = ClassDecl { name, parents, members, span };
+        push_fragment(AstFragment::ClassDecl(class), reader.into_consumed(), semantic_stack, tree);
+    }
+}
+
+// Stack: Location, Name, Marker, params..., TypeName
+pub struct FreeFuncHeadGather;
+
+impl SemanticAction for FreeFuncHeadGather {
+    fn take_action(
+        &self,
+        semantic_stack: &mut Vec<SemanticNode>,
+        tree: &mut SemanticTree,
+        _curr_token: Option<&Token>
+    ) {
+        let mut reader = StackReader::new(semantic_stack);
+        let return_type = reader.pop().into_type_name();
+        let params = pop_params(&mut reader);
+        let (name, _) = reader.pop().into_name();
+        let span = reader.pop().into_span();
+        let sig = FuncSig { name, params, return_type, is_constructor: false, span };
+        push_fragment(AstFragment::FuncHead { class_name: None, sig }, reader.into_consumed(), semantic_stack, tree);
+    }
+}
+
+// Stack: Location, class Name, function Name, Marker, params..., TypeName
+pub struct MemberFuncHeadGather;
+
+impl SemanticAction for MemberFuncHeadGather {
+    fn take_action(
+        &self,
+        semantic_stack: &mut Vec<SemanticNode>,
+        tree: &mut SemanticTree,
+        _curr_token: Option<&Token>
+    ) {
+        let mut reader = StackReader::new(semantic_stack);
+        let return_type = reader.pop().into_type_name();
+        let params = pop_params(&mut reader);
+        let (name, _) = reader.pop().into_name();
+        let (class_name, _) = reader.pop().into_name();
+        let span = reader.pop().into_span();
+        let sig = FuncSig { name, params, return_type, is_constructor: false, span };
+        push_fragment(AstFragment::FuncHead { class_name: Some(class_name), sig }, reader.into_consumed(), semantic_stack, tree);
+    }
+}
+
+// Stack: Location, class Name, Marker, params...
+pub struct ConstructorHeadGather;
+
+impl SemanticAction for ConstructorHeadGather {
+    fn take_action(
+        &self,
+        semantic_stack: &mut Vec<SemanticNode>,
+        tree: &mut SemanticTree,
+        _curr_token: Option<&Token>
+    ) {
+        let mut reader = StackReader::new(semantic_stack);
+        let params = pop_params(&mut reader);
+        let (class_name, _) = reader.pop().into_name();
+        let span = reader.pop().into_span();
+        let sig = FuncSig { name: "constructor".to_string(), params, return_type: TypeName::Void, is_constructor: true, span };
+        push_fragment(AstFragment::FuncHead { class_name: Some(class_name), sig }, reader.into_consumed(), semantic_stack, tree);
+    }
+}
+
+// Stack: FuncHead, Block
+pub struct FuncDefGather;
+
+impl SemanticAction for FuncDefGather {
+    fn take_action(
+        &self,
+        semantic_stack: &mut Vec<SemanticNode>,
+        tree: &mut SemanticTree,
+        _curr_token: Option<&Token>
+    ) {
+        let mut reader = StackReader::new(semantic_stack);
+        let body = reader.pop().into_block();
+        let function = match reader.pop() {
+            AstFragment::FuncHead { class_name, sig } => {
+                let span = sig.span;
+                FuncDef { class_name, sig, body, span }
+            }
+            other => panic!("Expected a function head but got {:?}", other),
+        };
+        push_fragment(AstFragment::FuncDef(function), reader.into_consumed(), semantic_stack, tree);
+    }
+}
+
+// Stack: Marker, class declarations and function definitions...
+pub struct ProgramItemsGather;
+
+impl SemanticAction for ProgramItemsGather {
+    fn take_action(
+        &self,
+        semantic_stack: &mut Vec<SemanticNode>,
+        tree: &mut SemanticTree,
+        _curr_token: Option<&Token>
+    ) {
+        let mut reader = StackReader::new(semantic_stack);
+        let mut program = Program { classes: vec![], functions: vec![], span: Span::new(1) };
+        for item in reader.pop_until_marker() {
+            match item {
+                AstFragment::ClassDecl(class) => program.classes.push(class),
+                AstFragment::FuncDef(function) => program.functions.push(function),
+                other => panic!("Expected a class or a function but got {:?}", other),
+            }
+        }
+        push_fragment(AstFragment::Program(program), reader.into_consumed(), semantic_stack, tree);
+    }
+}
+
+// Stack: base Expr, Name
+pub struct MemberAccessGather;
+
+impl SemanticAction for MemberAccessGather {
+    fn take_action(
+        &self,
+        semantic_stack: &mut Vec<SemanticNode>,
+        tree: &mut SemanticTree,
+        _curr_token: Option<&Token>
+    ) {
+        let mut reader = StackReader::new(semantic_stack);
+        let (name, span) = reader.pop().into_name();
+        let base = reader.pop().into_expr();
+        let member = Expr::Member { base: Box::new(base), name, span };
+        push_fragment(AstFragment::Expr(member), reader.into_consumed(), semantic_stack, tree);
+    }
+}
+
+// Stack: base Expr, index Expr
+pub struct IndexGather;
+
+impl SemanticAction for IndexGather {
+    fn take_action(
+        &self,
+        semantic_stack: &mut Vec<SemanticNode>,
+        tree: &mut SemanticTree,
+        _curr_token: Option<&Token>
+    ) {
+        let mut reader = StackReader::new(semantic_stack);
+        let index = reader.pop().into_expr();
+        let base = reader.pop().into_expr();
+        let span = base.span();
+        let indexed = Expr::Index { base: Box::new(base), index: Box::new(index), span };
+        push_fragment(AstFragment::Expr(indexed), reader.into_consumed(), semantic_stack, tree);
+    }
+}
+
+// Stack: lhs Expr, BinaryOp, rhs Expr. Gathering right after each operand keeps the
+// operators left associative.
+pub struct BinaryGather;
+
+impl SemanticAction for BinaryGather {
+    fn take_action(
+        &self,
+        semantic_stack: &mut Vec<SemanticNode>,
+        tree: &mut SemanticTree,
+        _curr_token: Option<&Token>
+    ) {
+        let mut reader = StackReader::new(semantic_stack);
+        let rhs = reader.pop().into_expr();
+        let (op, span) = match reader.pop() {
+            AstFragment::BinaryOp { op, span } => (op, span),
+            other => panic!("Expected a binary operator but got {:?}", other),
+        };
+        let lhs = reader.pop().into_expr();
+        let binary = Expr::Binary { op, lhs: Box::new(lhs), rhs: Box::new(rhs), span };
+        push_fragment(AstFragment::Expr(binary), reader.into_consumed(), semantic_stack, tree);
+    }
+}
+
+// Stack: UnaryOp, operand Expr
+pub struct UnaryGather;
+
+impl SemanticAction for UnaryGather {
+    fn take_action(
+        &self,
+        semantic_stack: &mut Vec<SemanticNode>,
+        tree: &mut SemanticTree,
+        _curr_token: Option<&Token>
+    ) {
+        let mut reader = StackReader::new(semantic_stack);
+        let operand = reader.pop().into_expr();
+        let (op, span) = match reader.pop() {
+            AstFragment::UnaryOp { op, span } => (op, span),
+            other => panic!("Expected a unary operator but got {:?}", other),
+        };
+        let unary = Expr::Unary { op, operand: Box::new(operand), span };
+        push_fragment(AstFragment::Expr(unary), reader.into_consumed(), semantic_stack, tree);
+    }
+}
+
+// `a.b.f` is parsed as member accesses before we know it is called, so the last
+// member access becomes the called function and its base the receiver.
+fn make_call(callee: Expr, args: Vec<Expr>) -> Expr {
+    match callee {
+        Expr::Var { name, span } => Expr::Call { receiver: None, name, args, span },
+        Expr::Member { base, name, span } => Expr::Call { receiver: Some(base), name, args, span },
+        other => panic!("Cannot call {:?}", other),
+    }
+}
+
+// Stack: callee Expr, Args
+pub struct CallExprGather;
+
+impl SemanticAction for CallExprGather {
+    fn take_action(
+        &self,
+        semantic_stack: &mut Vec<SemanticNode>,
+        tree: &mut SemanticTree,
+        _curr_token: Option<&Token>
+    ) {
+        let mut reader = StackReader::new(semantic_stack);
+        let args = reader.pop().into_args();
+        let callee = reader.pop().into_expr();
+        push_fragment(AstFragment::Expr(make_call(callee, args)), reader.into_consumed(), semantic_stack, tree);
+    }
+}
+
+// Stack: Location, callee Expr, Args
+pub struct CallStmtGather;
+
+impl SemanticAction for CallStmtGather {
+    fn take_action(
+        &self,
+        semantic_stack: &mut Vec<SemanticNode>,
+        tree: &mut SemanticTree,
+        _curr_token: Option<&Token>
+    ) {
+        let mut reader = StackReader::new(semantic_stack);
+        let args = reader.pop().into_args();
+        let callee = reader.pop().into_expr();
+        let span = reader.pop().into_span();
+        let statement = Stmt::Call { call: make_call(callee, args), span };
+        push_fragment(AstFragment::Stmt(statement), reader.into_consumed(), semantic_stack, tree);
+    }
+}
+
+// Stack: Location, target Expr, value Expr
+pub struct AssignGather;
+
+impl SemanticAction for AssignGather {
+    fn take_action(
+        &self,
+        semantic_stack: &mut Vec<SemanticNode>,
+        tree: &mut SemanticTree,
+        _curr_token: Option<&Token>
+    ) {
+        let mut reader = StackReader::new(semantic_stack);
+        let value = reader.pop().into_expr();
+        let target = reader.pop().into_expr();
+        let span = reader.pop().into_span();
+        push_fragment(AstFragment::Stmt(Stmt::Assign { target, value, span }), reader.into_consumed(), semantic_stack, tree);
+    }
+}
+
+// Stack: Location, condition Expr, then Block, else Block
+pub struct IfGather;
+
+impl SemanticAction for IfGather {
+    fn take_action(
+        &self,
+        semantic_stack: &mut Vec<SemanticNode>,
+        tree: &mut SemanticTree,
+        _curr_token: Option<&Token>
+    ) {
+        let mut reader = StackReader::new(semantic_stack);
+        let else_block = reader.pop().into_block();
+        let then_block = reader.pop().into_block();
+        let condition = reader.pop().into_expr();
+        let span = reader.pop().into_span();
+        let statement = Stmt::If { condition, then_block, else_block, span };
+        push_fragment(AstFragment::Stmt(statement), reader.into_consumed(), semantic_stack, tree);
+    }
+}
+
+// Stack: Location, condition Expr, body Block
+pub struct WhileGather;
+
+impl SemanticAction for WhileGather {
+    fn take_action(
+        &self,
+        semantic_stack: &mut Vec<SemanticNode>,
+        tree: &mut SemanticTree,
+        _curr_token: Option<&Token>
+    ) {
+        let mut reader = StackReader::new(semantic_stack);
+        let body = reader.pop().into_block();
+        let condition = reader.pop().into_expr();
+        let span = reader.pop().into_span();
+        push_fragment(AstFragment::Stmt(Stmt::While { condition, body, span }), reader.into_consumed(), semantic_stack, tree);
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum SingleExprStatement {
+    Read,
+    Write,
+    Return,
+}
+
+// Stack: Location, Expr
+pub struct SingleExprStatementGather {
+    pub statement: SingleExprStatement,
+}
+
+impl SemanticAction for SingleExprStatementGather {
+    fn take_action(
+        &self,
+        semantic_stack: &mut Vec<SemanticNode>,
+        tree: &mut SemanticTree,
+        _curr_token: Option<&Token>
+    ) {
+        let mut reader = StackReader::new(semantic_stack);
+        let expr = reader.pop().into_expr();
+        let span = reader.pop().into_span();
+        let statement = match self.statement {
+            SingleExprStatement::Read => Stmt::Read { target: expr, span },
+            SingleExprStatement::Write => Stmt::Write { value: expr, span },
+            SingleExprStatement::Return => Stmt::Return { value: expr, span },
+        };
+        push_fragment(AstFragment::Stmt(statement), reader.into_consumed(), semantic_stack, tree);
+    }
+}
+
+// Semantic actions for the productions of the full grammar (grammars/full_table.json).
+pub fn get_ast_production_elements(production_string: &str) -> Vec<ProductionElement> {
+    match production_string {
+        //
+        // Program
+        //
+        "START → REPTSTART0 eof" => {
+            vec![
+                SemanticElement(Box::new(MarkListBegin)),
+                SyntaxElement("REPTSTART0".into()),
+                SyntaxElement("eof".into()),
+                SemanticElement(Box::new(ProgramItemsGather)),
+            ]
+        }
+        "REPTSTART0 → CLASSDECLORFUNCDEF REPTSTART0" |
+        "REPTSTART0 → &epsilon" |
+        "CLASSDECLORFUNCDEF → CLASSDECL" |
+        "CLASSDECLORFUNCDEF → FUNCDEF" => {
+            get_only_syntax_elements(production_string)
+        }
+        //
+        // Classes
+        //
+        "CLASSDECL → class id OPTCLASSINHERITANCE lcurbr REPTCLASSDECL4 rcurbr semi" => {
+            vec![
+                SemanticElement(Box::new(PushLocation)),
+                SyntaxElement("class".into()),
+                SemanticElement(Box::new(PushName)),
+                SyntaxElement("id".into()),
+                SemanticElement(Box::new(MarkListBegin)),
+                SyntaxElement("OPTCLASSINHERITANCE".into()),
+                SyntaxElement("lcurbr".into()),
+                SemanticElement(Box::new(MarkListBegin)),
+                SyntaxElement("REPTCLASSDECL4".into()),
+                SyntaxElement("rcurbr".into()),
+                SyntaxElement("semi".into()),
+                SemanticElement(Box::new(ClassDeclGather)),
+            ]
+        }
+        "OPTCLASSINHERITANCE → isa id REPTOPTCLASSINHERITANCE2" => {
+            vec![
+                SyntaxElement("isa".into()),
+                SemanticElement(Box::new(PushName)),
+                SyntaxElement("id".into()),
+                SyntaxElement("REPTOPTCLASSINHERITANCE2".into()),
+            ]
+        }
+        "REPTOPTCLASSINHERITANCE2 → comma id REPTOPTCLASSINHERITANCE2" => {
+            vec![
+                SyntaxElement("comma".into()),
+                SemanticElement(Box::new(PushName)),
+                SyntaxElement("id".into()),
+                SyntaxElement("REPTOPTCLASSINHERITANCE2".into()),
+            ]
+        }
+        "OPTCLASSINHERITANCE → &epsilon" |
+        "REPTOPTCLASSINHERITANCE2 → &epsilon" |
+        "REPTCLASSDECL4 → &epsilon" |
+        "MEMBERDECL → MEMBERFUNCDECL" |
+        "MEMBERDECL → MEMBERVARDECL" => {
+            get_only_syntax_elements(production_string)
+        }
+        "REPTCLASSDECL4 → VISIBILITY MEMBERDECL REPTCLASSDECL4" => {
+            vec![
+                SyntaxElement("VISIBILITY".into()),
+                SyntaxElement("MEMBERDECL".into()),
+                SemanticElement(Box::new(MemberDeclGather)),
+                SyntaxElement("REPTCLASSDECL4".into()),
+            ]
+        }
+        "VISIBILITY → public" => {
+            vec![
+                SemanticElement(Box::new(PushVisibility { visibility: Visibility::Public })),
+                SyntaxElement("public".into()),
+            ]
+        }
+        "VISIBILITY → private" => {
+            vec![
+                SemanticElement(Box::new(PushVisibility { visibility: Visibility::Private })),
+                SyntaxElement("private".into()),
+            ]
+        }
+        "VISIBILITY → &epsilon" => {
+            vec![
+                SemanticElement(Box::new(PushVisibility { visibility: Visibility::Public })),
+                SyntaxElement("&epsilon".into()),
+            ]
+        }
+        "MEMBERFUNCDECL → function id colon lpar FPARAMS rpar arrow RETURNTYPE semi" => {
+            vec![
+                SemanticElement(Box::new(PushLocation)),
+                SyntaxElement("function".into()),
+                SemanticElement(Box::new(PushName)),
+                SyntaxElement("id".into()),
+                SyntaxElement("colon".into()),
+                SyntaxElement("lpar".into()),
+                SemanticElement(Box::new(MarkListBegin)),
+                SyntaxElement("FPARAMS".into()),
+                SyntaxElement("rpar".into()),
+                SyntaxElement("arrow".into()),
+                SyntaxElement("RETURNTYPE".into()),
+                SyntaxElement("semi".into()),
+                SemanticElement(Box::new(MemberFuncSigGather)),
+            ]
+        }
+        "MEMBERFUNCDECL → constructor colon lpar FPARAMS rpar semi" => {
+            vec![
+                SemanticElement(Box::new(PushLocation)),
+                SyntaxElement("constructor".into()),
+                SyntaxElement("colon".into()),
+                SyntaxElement("lpar".into()),
+                SemanticElement(Box::new(MarkListBegin)),
+                SyntaxElement("FPARAMS".into()),
+                SyntaxElement("rpar".into()),
+                SyntaxElement("semi".into()),
+                SemanticElement(Box::new(ConstructorSigGather)),
+            ]
+        }
+        "MEMBERVARDECL → attribute id colon TYPE REPTMEMBERVARDECL4 semi" => {
+            vec![
+                SemanticElement(Box::new(PushLocation)),
+                SyntaxElement("attribute".into()),
+                SemanticElement(Box::new(PushName)),
+                SyntaxElement("id".into()),
+                SyntaxElement("colon".into()),
+                SyntaxElement("TYPE".into()),
+                SemanticElement(Box::new(MarkListBegin)),
+                SyntaxElement("REPTMEMBERVARDECL4".into()),
+                SemanticElement(Box::new(DimsGather)),
+                SyntaxElement("semi".into()),
+                SemanticElement(Box::new(VarDeclGather)),
+            ]
+        }
+        //
+        // Types and array sizes
+        //
+        "TYPE → integer" => {
+            vec![
+                SemanticElement(Box::new(PushTypeName)),
+                SyntaxElement("integer".into()),
+            ]
+        }
+        "TYPE → float" => {
+            vec![
+                SemanticElement(Box::new(PushTypeName)),
+                SyntaxElement("float".into()),
+            ]
+        }
+        "TYPE → id" => {
+            vec![
+                SemanticElement(Box::new(PushTypeName)),
+                SyntaxElement("id".into()),
+            ]
+        }
+        "RETURNTYPE → void" => {
+            vec![
+                SemanticElement(Box::new(PushTypeName)),
+                SyntaxElement("void".into()),
+            ]
+        }
+        "RETURNTYPE → TYPE" |
+        "ARRAYSIZE → lsqbr ARRAYSIZELEFTAMBI" |
+        "REPTMEMBERVARDECL4 → ARRAYSIZE REPTMEMBERVARDECL4" |
+        "REPTMEMBERVARDECL4 → &epsilon" |
+        "REPTLOCALVARDECL4 → ARRAYSIZE REPTLOCALVARDECL4" |
+        "REPTLOCALVARDECL4 → &epsilon" |
+        "REPTFPARAMS3 → ARRAYSIZE REPTFPARAMS3" |
+        "REPTFPARAMS3 → &epsilon" |
+        "REPTFPARAMSTAIL4 → ARRAYSIZE REPTFPARAMSTAIL4" |
+        "REPTFPARAMSTAIL4 → &epsilon" => {
+            get_only_syntax_elements(production_string)
+        }
+        "ARRAYSIZELEFTAMBI → intlit rsqbr" => {
+            vec![
+                SemanticElement(Box::new(PushDim)),
+                SyntaxElement("intlit".into()),
+                SyntaxElement("rsqbr".into()),
+            ]
+        }
+        "ARRAYSIZELEFTAMBI → rsqbr" => {
+            vec![
+                SemanticElement(Box::new(PushDim)),
+                SyntaxElement("rsqbr".into()),
+            ]
+        }
+        //
+        // Function parameters
+        //
+        "FPARAMS → id colon TYPE REPTFPARAMS3 REPTFPARAMS4" => {
+            vec![
+                SemanticElement(Box::new(PushLocation)),
+                SemanticElement(Box::new(PushName)),
+                SyntaxElement("id".into()),
+                SyntaxElement("colon".into()),
+                SyntaxElement("TYPE".into()),
+                SemanticElement(Box::new(MarkListBegin)),
+                SyntaxElement("REPTFPARAMS3".into()),
+                SemanticElement(Box::new(DimsGather)),
+                SemanticElement(Box::new(VarDeclGather)),
+                SyntaxElement("REPTFPARAMS4".into()),
+            ]
+        }
+        "FPARAMSTAIL → comma id colon TYPE REPTFPARAMSTAIL4" => {
+            vec![
+                SyntaxElement("comma".into()),
+                SemanticElement(Box::new(PushLocation)),
+                SemanticElement(Box::new(PushName)),
+                SyntaxElement("id".into()),
+                SyntaxElement("colon".into()),
+                SyntaxElement("TYPE".into()),
+                SemanticElement(Box::new(MarkListBegin)),
+                SyntaxElement("REPTFPARAMSTAIL4".into()),
+                SemanticElement(Box::new(DimsGather)),
+                SemanticElement(Box::new(VarDeclGather)),
+            ]
+        }
+        "FPARAMS → &epsilon" |
+        "REPTFPARAMS4 → FPARAMSTAIL REPTFPARAMS4" |
+        "REPTFPARAMS4 → &epsilon" => {
+            get_only_syntax_elements(production_string)
+        }
+        //
+        // Function definitions
+        //
+        "FUNCDEF → FUNCHEAD FUNCBODY" => {
+            vec![
+                SyntaxElement("FUNCHEAD".into()),
+                SyntaxElement("FUNCBODY".into()),
+                SemanticElement(Box::new(FuncDefGather)),
+            ]
+        }
+        "FUNCHEAD → function id FUNCHEADOPTION" => {
+            vec![
+                SemanticElement(Box::new(PushLocation)),
+                SyntaxElement("function".into()),
+                SemanticElement(Box::new(PushName)),
+                SyntaxElement("id".into()),
+                SyntaxElement("FUNCHEADOPTION".into()),
+            ]
+        }
+        "FUNCHEADOPTION → lpar FPARAMS rpar arrow RETURNTYPE" => {
+            vec![
+                SyntaxElement("lpar".into()),
+                SemanticElement(Box::new(MarkListBegin)),
+                SyntaxElement("FPARAMS".into()),
+                SyntaxElement("rpar".into()),
+                SyntaxElement("arrow".into()),
+                SyntaxElement("RETURNTYPE".into()),
+                SemanticElement(Box::new(FreeFuncHeadGather)),
+            ]
+        }
+        "FUNCHEADOPTION → sr SROPTION" => {
+            get_only_syntax_elements(production_string)
+        }
+        "SROPTION → id lpar FPARAMS rpar arrow RETURNTYPE" => {
+            vec![
+                SemanticElement(Box::new(PushName)),
+                SyntaxElement("id".into()),
+                SyntaxElement("lpar".into()),
+                SemanticElement(Box::new(MarkListBegin)),
+                SyntaxElement("FPARAMS".into()),
+                SyntaxElement("rpar".into()),
+                SyntaxElement("arrow".into()),
+                SyntaxElement("RETURNTYPE".into()),
+                SemanticElement(Box::new(MemberFuncHeadGather)),
+            ]
+        }
+        "SROPTION → constructor lpar FPARAMS rpar" => {
+            vec![
+                SyntaxElement("constructor".into()),
+                SyntaxElement("lpar".into()),
+                SemanticElement(Box::new(MarkListBegin)),
+                SyntaxElement("FPARAMS".into()),
+                SyntaxElement("rpar".into()),
+                SemanticElement(Box::new(ConstructorHeadGather)),
+            ]
+        }
+        "FUNCBODY → lcurbr REPTFUNCBODY1 rcurbr" => {
+            vec![
+                SyntaxElement("lcurbr".into()),
+                SemanticElement(Box::new(MarkListBegin)),
+                SyntaxElement("REPTFUNCBODY1".into()),
+                SyntaxElement("rcurbr".into()),
+                SemanticElement(Box::new(BlockGather)),
+            ]
+        }
+        "REPTFUNCBODY1 → LOCALVARDECLORSTMT REPTFUNCBODY1" |
+        "REPTFUNCBODY1 → &epsilon" |
+        "LOCALVARDECLORSTMT → LOCALVARDECL" |
+        "LOCALVARDECLORSTMT → STATEMENT" => {
+            get_only_syntax_elements(production_string)
+        }
+        "LOCALVARDECL → localvar id colon TYPE LOCALVARDECLOPTION" => {
+            vec![
+                SemanticElement(Box::new(PushLocation)),
+                SyntaxElement("localvar".into()),
+                SemanticElement(Box::new(PushName)),
+                SyntaxElement("id".into()),
+                SyntaxElement("colon".into()),
+                SyntaxElement("TYPE".into()),
+                SyntaxElement("LOCALVARDECLOPTION".into()),
+                SemanticElement(Box::new(LocalVarStmtGather)),
+            ]
+        }
+        "LOCALVARDECLOPTION → REPTLOCALVARDECL4 semi" => {
+            vec![
+                SemanticElement(Box::new(MarkListBegin)),
+                SyntaxElement("REPTLOCALVARDECL4".into()),
+                SemanticElement(Box::new(DimsGather)),
+                SyntaxElement("semi".into()),
+            ]
+        }
+        "LOCALVARDECLOPTION → lpar APARAMS rpar semi" => {
+            vec![
+                SyntaxElement("lpar".into()),
+                SemanticElement(Box::new(MarkListBegin)),
+                SyntaxElement("APARAMS".into()),
+                SemanticElement(Box::new(ArgsGather)),
+                SyntaxElement("rpar".into()),
+                SyntaxElement("semi".into()),
+            ]
+        }
+        //
+        // Statements
+        //
+        "STATEMENT → id DOTTEDIDS STATEMENTOPTION" => {
+            vec![
+                SemanticElement(Box::new(PushLocation)),
+                SemanticElement(Box::new(PushVariable)),
+                SyntaxElement("id".into()),
+                SyntaxElement("DOTTEDIDS".into()),
+                SyntaxElement("STATEMENTOPTION".into()),
+            ]
+        }
+        "STATEMENTOPTION → lpar APARAMS rpar semi" => {
+            vec![
+                SyntaxElement("lpar".into()),
+                SemanticElement(Box::new(MarkListBegin)),
+                SyntaxElement("APARAMS".into()),
+                SemanticElement(Box::new(ArgsGather)),
+                SyntaxElement("rpar".into()),
+                SyntaxElement("semi".into()),
+                SemanticElement(Box::new(CallStmtGather)),
+            ]
+        }
+        "STATEMENTOPTION → REPTVARIABLE2 ASSIGNOP EXPR semi" => {
+            vec![
+                SyntaxElement("REPTVARIABLE2".into()),
+                SyntaxElement("ASSIGNOP".into()),
+                SyntaxElement("EXPR".into()),
+                SyntaxElement("semi".into()),
+                SemanticElement(Box::new(AssignGather)),
+            ]
+        }
+        "ASSIGNSTAT → VARIABLE ASSIGNOP EXPR" => {
+            vec![
+                SemanticElement(Box::new(PushLocation)),
+                SyntaxElement("VARIABLE".into()),
+                SyntaxElement("ASSIGNOP".into()),
+                SyntaxElement("EXPR".into()),
+                SemanticElement(Box::new(AssignGather)),
+            ]
+        }
+        "STATEMENT → if lpar RELEXPR rpar then STATBLOCK else STATBLOCK semi" => {
+            vec![
+                SemanticElement(Box::new(PushLocation)),
+                SyntaxElement("if".into()),
+                SyntaxElement("lpar".into()),
+                SyntaxElement("RELEXPR".into()),
+                SyntaxElement("rpar".into()),
+                SyntaxElement("then".into()),
+                SyntaxElement("STATBLOCK".into()),
+                SyntaxElement("else".into()),
+                SyntaxElement("STATBLOCK".into()),
+                SyntaxElement("semi".into()),
+                SemanticElement(Box::new(IfGather)),
+            ]
+        }
+        "STATEMENT → while lpar RELEXPR rpar STATBLOCK semi" => {
+            vec![
+                SemanticElement(Box::new(PushLocation)),
+                SyntaxElement("while".into()),
+                SyntaxElement("lpar".into()),
+                SyntaxElement("RELEXPR".into()),
+                SyntaxElement("rpar".into()),
+                SyntaxElement("STATBLOCK".into()),
+                SyntaxElement("semi".into()),
+                SemanticElement(Box::new(WhileGather)),
+            ]
+        }
+        "STATEMENT → read lpar VARIABLE rpar semi" => {
+            vec![
+                SemanticElement(Box::new(PushLocation)),
+                SyntaxElement("read".into()),
+                SyntaxElement("lpar".into()),
+                SyntaxElement("VARIABLE".into()),
+                SyntaxElement("rpar".into()),
+                SyntaxElement("semi".into()),
+                SemanticElement(Box::new(SingleExprStatementGather { statement: SingleExprStatement::Read })),
+            ]
+        }
+        "STATEMENT → write lpar EXPR rpar semi" => {
+            vec![
+                SemanticElement(Box::new(PushLocation)),
+                SyntaxElement("write".into()),
+                SyntaxElement("lpar".into()),
+                SyntaxElement("EXPR".into()),
+                SyntaxElement("rpar".into()),
+                SyntaxElement("semi".into()),
+                SemanticElement(Box::new(SingleExprStatementGather { statement: SingleExprStatement::Write })),
+            ]
+        }
+        "STATEMENT → return lpar EXPR rpar semi" => {
+            vec![
+                SemanticElement(Box::new(PushLocation)),
+                SyntaxElement("return".into()),
+                SyntaxElement("lpar".into()),
+                SyntaxElement("EXPR".into()),
+                SyntaxElement("rpar".into()),
+                SyntaxElement("semi".into()),
+                SemanticElement(Box::new(SingleExprStatementGather { statement: SingleExprStatement::Return })),
+            ]
+        }
+        "STATBLOCK → lcurbr REPTSTATBLOCK1 rcurbr" => {
+            vec![
+                SyntaxElement("lcurbr".into()),
+                SemanticElement(Box::new(MarkListBegin)),
+                SyntaxElement("REPTSTATBLOCK1".into()),
+                SyntaxElement("rcurbr".into()),
+                SemanticElement(Box::new(BlockGather)),
+            ]
+        }
+        "STATBLOCK → STATEMENT" => {
+            vec![
+                SemanticElement(Box::new(MarkListBegin)),
+                SyntaxElement("STATEMENT".into()),
+                SemanticElement(Box::new(BlockGather)),
+            ]
+        }
+        "STATBLOCK → &epsilon" => {
+            vec![
+                SemanticElement(Box::new(MarkListBegin)),
+                SemanticElement(Box::new(BlockGather)),
+                SyntaxElement("&epsilon".into()),
+            ]
+        }
+        "REPTSTATBLOCK1 → STATEMENT REPTSTATBLOCK1" |
+        "REPTSTATBLOCK1 → &epsilon" |
+        "ASSIGNOP → equal" => {
+            get_only_syntax_elements(production_string)
+        }
+        //
+        // Variables
+        //
+        "VARIABLE → id REPTVARIABLE2" => {
+            vec![
+                SemanticElement(Box::new(PushVariable)),
+                SyntaxElement("id".into()),
+                SyntaxElement("REPTVARIABLE2".into()),
+            ]
+        }
+        "DOTTEDIDS → dot id DOTTEDIDS" => {
+            vec![
+                SyntaxElement("dot".into()),
+                SemanticElement(Box::new(PushName)),
+                SyntaxElement("id".into()),
+                SemanticElement(Box::new(MemberAccessGather)),
+                SyntaxElement("DOTTEDIDS".into()),
+            ]
+        }
+        "INDICE → lsqbr ARITHEXPR rsqbr" => {
+            vec![
+                SyntaxElement("lsqbr".into()),
+                SyntaxElement("ARITHEXPR".into()),
+                SyntaxElement("rsqbr".into()),
+                SemanticElement(Box::new(IndexGather)),
+            ]
+        }
+        "DOTTEDIDS → &epsilon" |
+        "REPTVARIABLE2 → INDICE REPTVARIABLE2" |
+        "REPTVARIABLE2 → &epsilon" => {
+            get_only_syntax_elements(production_string)
+        }
+        //
+        // Expressions
+        //
+        "RELEXPR → ARITHEXPR RELOP ARITHEXPR" => {
+            vec![
+                SyntaxElement("ARITHEXPR".into()),
+                SyntaxElement("RELOP".into()),
+                SyntaxElement("ARITHEXPR".into()),
+                SemanticElement(Box::new(BinaryGather)),
+            ]
+        }
+        "EXPRLEFTAMBI → RELOP ARITHEXPR" => {
+            vec![
+                SyntaxElement("RELOP".into()),
+                SyntaxElement("ARITHEXPR".into()),
+                SemanticElement(Box::new(BinaryGather)),
+            ]
+        }
+        "RIGHTRECARITHEXPR → ADDOP TERM RIGHTRECARITHEXPR" => {
+            vec![
+                SyntaxElement("ADDOP".into()),
+                SyntaxElement("TERM".into()),
+                SemanticElement(Box::new(BinaryGather)),
+                SyntaxElement("RIGHTRECARITHEXPR".into()),
+            ]
+        }
+        "RIGHTRECTERM → MULTOP FACTOR RIGHTRECTERM" => {
+            vec![
+                SyntaxElement("MULTOP".into()),
+                SyntaxElement("FACTOR".into()),
+                SemanticElement(Box::new(BinaryGather)),
+                SyntaxElement("RIGHTRECTERM".into()),
+            ]
+        }
+        "EXPR → ARITHEXPR EXPRLEFTAMBI" |
+        "EXPRLEFTAMBI → &epsilon" |
+        "ARITHEXPR → TERM RIGHTRECARITHEXPR" |
+        "RIGHTRECARITHEXPR → &epsilon" |
+        "TERM → FACTOR RIGHTRECTERM" |
+        "RIGHTRECTERM → &epsilon" |
+        "FACTOR → lpar ARITHEXPR rpar" |
+        "FACTOROPTION → REPTVARIABLE2" |
+        "APARAMS → EXPR REPTAPARAMS1" |
+        "APARAMS → &epsilon" |
+        "REPTAPARAMS1 → APARAMSTAIL REPTAPARAMS1" |
+        "REPTAPARAMS1 → &epsilon" |
+        "APARAMSTAIL → comma EXPR" => {
+            get_only_syntax_elements(production_string)
+        }
+        "ADDOP → plus" => binary_operator_elements(BinaryOp::Add, "plus"),
+        "ADDOP → minus" => binary_operator_elements(BinaryOp::Sub, "minus"),
+        "ADDOP → or" => binary_operator_elements(BinaryOp::Or, "or"),
+        "MULTOP → mult" => binary_operator_elements(BinaryOp::Mul, "mult"),
+        "MULTOP → div" => binary_operator_elements(BinaryOp::Div, "div"),
+        "MULTOP → and" => binary_operator_elements(BinaryOp::And, "and"),
+        "RELOP → eq" => binary_operator_elements(BinaryOp::Eq, "eq"),
+        "RELOP → neq" => binary_operator_elements(BinaryOp::Neq, "neq"),
+        "RELOP → lt" => binary_operator_elements(BinaryOp::Lt, "lt"),
+        "RELOP → gt" => binary_operator_elements(BinaryOp::Gt, "gt"),
+        "RELOP → leq" => binary_operator_elements(BinaryOp::Leq, "leq"),
+        "RELOP → geq" => binary_operator_elements(BinaryOp::Geq, "geq"),
+        "SIGN → plus" => {
+            vec![
+                SemanticElement(Box::new(PushUnaryOp { op: UnaryOp::Plus })),
+                SyntaxElement("plus".into()),
+            ]
+        }
+        "SIGN → minus" => {
+            vec![
+                SemanticElement(Box::new(PushUnaryOp { op: UnaryOp::Neg })),
+                SyntaxElement("minus".into()),
+            ]
+        }
+        "FACTOR → intlit" => {
+            vec![
+                SemanticElement(Box::new(PushLiteral)),
+                SyntaxElement("intlit".into()),
+            ]
+        }
+        "FACTOR → floatlit" => {
+            vec![
+                SemanticElement(Box::new(PushLiteral)),
+                SyntaxElement("floatlit".into()),
+            ]
+        }
+        "FACTOR → not FACTOR" => {
+            vec![
+                SemanticElement(Box::new(PushUnaryOp { op: UnaryOp::Not })),
+                SyntaxElement("not".into()),
+                SyntaxElement("FACTOR".into()),
+                SemanticElement(Box::new(UnaryGather)),
+            ]
+        }
+        "FACTOR → SIGN FACTOR" => {
+            vec![
+                SyntaxElement("SIGN".into()),
+                SyntaxElement("FACTOR".into()),
+                SemanticElement(Box::new(UnaryGather)),
+            ]
+        }
+        "FACTOR → id DOTTEDIDS FACTOROPTION" => {
+            vec![
+                SemanticElement(Box::new(PushVariable)),
+                SyntaxElement("id".into()),
+                SyntaxElement("DOTTEDIDS".into()),
+                SyntaxElement("FACTOROPTION".into()),
+            ]
+        }
+        "FACTOROPTION → lpar APARAMS rpar" => {
+            vec![
+                SyntaxElement("lpar".into()),
+                SemanticElement(Box::new(MarkListBegin)),
+                SyntaxElement("APARAMS".into()),
+                SemanticElement(Box::new(ArgsGather)),
+                SyntaxElement("rpar".into()),
+                SemanticElement(Box::new(CallExprGather)),
+            ]
+        }
+        _ => panic!("Can't build an AST for ({}) at the moment", production_string)
+    }
+}
+
+fn binary_operator_elements(op: BinaryOp, terminal: &str) -> Vec<ProductionElement> {
+    vec![
+        SemanticElement(Box::new(PushBinaryOp { op })),
+        SyntaxElement(terminal.into()),
+    ]
+}
@@ -0,0 +1,759 @@
+// C99 back end, from the type-checked AST. Classes become structs that start with
+// the parts of their parents, in `isa` order, or with the address of their virtual
+// function table for classes without parents, as in memory_layout.rs. Member
+// functions and constructors take the object as a first parameter, `self`, and
+// member function calls go through a dispatch function per member function that
+// looks it up in the table of the object, built like the ones of vtables.rs.
+// Integers are `int32_t` and wrap around, floats are `double` like in the
+// interpreter, `read` and `write` use `scanf` and `printf`. C leaves the order of
+// operands unspecified, so calls are made in statements of their own, after the
+// operands on their left. Names from the source that C or the generated code
+// could use get a `_` appended.
+
+use std::collections::BTreeSet;
+use crate::ast::*;
+use crate::symbol_table::*;
+use crate::synthesis::mangled_name;
+use crate::type_checker::FunctionScope;
+use crate::types::Type;
+use crate::vtables::{slot_index, vtable_slots};
+
+// C keywords and names declared by the included headers
+const RESERVED_NAMES: &[&str] = &[
+    "auto", "break", "case", "char", "const", "continue", "default", "do", "double", "else", "enum", "extern",
+    "float", "for", "goto", "if", "inline", "int", "long", "register", "restrict", "return", "short", "signed",
+    "sizeof", "static", "struct", "switch", "typedef", "union", "unsigned", "void", "volatile", "while",
+    "abort", "abs", "atexit", "atof", "atoi", "atol", "bsearch", "calloc", "clearerr", "div", "exit", "fail",
+    "fclose", "feof", "ferror", "fflush", "fgetc", "fgetpos", "fgets", "fopen", "fprintf", "fputc", "fputs",
+    "fread", "free", "freopen", "fscanf", "fseek", "fsetpos", "ftell", "fwrite", "getc", "getchar", "getenv",
+    "gets", "labs", "ldiv", "malloc", "mblen", "mbstowcs", "mbtowc", "perror", "printf", "putc", "putchar",
+    "puts", "qsort", "rand", "realloc", "remove", "rename", "rewind", "scanf", "setbuf", "setvbuf", "snprintf",
+    "sprintf", "srand", "sscanf", "stderr", "stdin", "stdout", "strtod", "strtol", "strtoul", "system",
+    "tmpfile", "tmpnam", "ungetc", "vfprintf", "vprintf", "vsprintf", "wcstombs", "wctomb",
+    "BUFSIZ", "EOF", "EXIT_FAILURE", "EXIT_SUCCESS", "FILE", "NULL", "RAND_MAX", "offsetof",
+    "object", "self", "vptr",
+];
+
+// prefixes of the names the generated code declares
+const GENERATED_PREFIXES: &[&str] = &["base_", "float_", "init_", "integer_", "read_", "virtual_", "vtable_", "write_"];
+
+// `name` from the source as a C identifier. Generated temporaries end with `_`,
+// so names from the source that do get one more.
+pub fn c_name(name: &str) -> String {
+    let generated = GENERATED_PREFIXES.iter().any(|prefix| name.starts_with(prefix));
+    if generated || RESERVED_NAMES.contains(&name) || name.ends_with('_') || name.ends_with("_t") {
+        return format!("{}_", name);
+    }
+    name.to_string()
+}
+
+fn scalar_type(type_name: &TypeName) -> String {
+    match type_name {
+        TypeName::Integer => "int32_t".to_string(),
+        TypeName::Float => "double".to_string(),
+        TypeName::Void => "void".to_string(),
+        TypeName::Class(class_name) => format!("struct {}", c_name(class_name)),
+    }
+}
+
+// Declaration of `name`, which is empty in the parameter types of function
+// pointers.
+fn declaration(type_name: &TypeName, dims: &[Option<usize>], name: &str) -> String {
+    let dims: String = dims.iter()
+        .map(|dim| match dim {
+            Some(size) => format!("[{}]", size),
+            None => "[]".to_string(),
+        })
+        .collect();
+    format!("{} {}{}", scalar_type(type_name), name, dims).trim_end().to_string()
+}
+
+// Arrays and objects are passed by address.
+fn param_declaration(param: &VarDecl, name: &str) -> String {
+    match &param.type_name {
+        TypeName::Class(_) if param.dims.is_empty() => format!("{} *{}", scalar_type(&param.type_name), name),
+        type_name => declaration(type_name, &param.dims, name),
+    }
+}
+
+fn base_field(class_name: &str) -> String {
+    format!("base_{}", class_name)
+}
+
+// Fields leading from an object of class `class_name` to its `ancestor` part,
+// following the first path in `isa` order like `base_offset`.
+fn base_path(global: &SymbolTable, class_name: &str, ancestor: &str) -> Option<Vec<String>> {
+    if class_name == ancestor {
+        return Some(vec![]);
+    }
+    global.parents(class_name).iter().find_map(|parent| {
+        let mut path = base_path(global, parent, ancestor)?;
+        path.insert(0, base_field(parent));
+        Some(path)
+    })
+}
+
+// Fields leading to the table address of an object of class `class_name`, at the
+// start of its first parent part, recursively.
+fn vptr_path(global: &SymbolTable, class_name: &str) -> Vec<String> {
+    let mut path = vec![];
+    let mut current = class_name.to_string();
+    while let Some(parent) = global.parents(&current).first() {
+        path.push(base_field(parent));
+        current = parent.clone();
+    }
+    path.push("vptr".to_string());
+    path
+}
+
+// Path and class of the parts of an object of class `class_name` that have a table
+// of their own: the object itself and every parent but the first, recursively.
+fn table_parts(global: &SymbolTable, class_name: &str) -> Vec<(Vec<String>, String)> {
+    fn visit(global: &SymbolTable, class_name: &str, path: &[String], parts: &mut Vec<(Vec<String>, String)>) {
+        for (index, parent) in global.parents(class_name).iter().enumerate() {
+            let mut parent_path = path.to_vec();
+            parent_path.push(base_field(parent));
+            if index > 0 {
+                parts.push((parent_path.clone(), parent.clone()));
+            }
+            visit(global, parent, &parent_path, parts);
+        }
+    }
+    let mut parts = vec![(vec![], class_name.to_string())];
+    visit(global, class_name, &[], &mut parts);
+    parts
+}
+
+fn vtable_name(class_name: &str, part: usize) -> String {
+    match part {
+        0 => format!("vtable_{}", class_name),
+        _ => format!("vtable_{}_{}", class_name, part),
+    }
+}
+
+// Distance in bytes from the part at `from` to the part at `to` in a `struct_type`.
+fn offset_difference(struct_type: &str, to: &[String], from: &[String]) -> String {
+    let offset = |path: &[String]| format!("(ptrdiff_t) offsetof({}, {})", struct_type, path.join("."));
+    match (to.is_empty(), from.is_empty()) {
+        _ if to == from => "0".to_string(),
+        (false, true) => offset(to),
+        (true, false) => format!("-{}", offset(from)),
+        _ => format!("{} - {}", offset(to), offset(from)),
+    }
+}
+
+// Classes ordered so that every struct comes after the ones it contains.
+fn class_order(global: &SymbolTable) -> Vec<String> {
+    fn visit(global: &SymbolTable, class_name: &str, visited: &mut BTreeSet<String>, order: &mut Vec<String>) {
+        if !visited.insert(class_name.to_string()) {
+            return;
+        }
+        if let Some(class_table) = global.class_table(class_name) {
+            for entry in &class_table.entries {
+                match (&entry.kind, &entry.type_name) {
+                    (SymbolKind::Inherit, _) => visit(global, &entry.name, visited, order),
+                    (SymbolKind::Attribute, TypeName::Class(member_class)) => visit(global, member_class, visited, order),
+                    _ => {}
+                }
+            }
+        }
+        order.push(class_name.to_string());
+    }
+    let mut visited = BTreeSet::new();
+    let mut order = vec![];
+    for class in global.entries.iter().filter(|entry| entry.kind == SymbolKind::Class) {
+        visit(global, &class.name, &mut visited, &mut order);
+    }
+    order
+}
+
+fn struct_definition(global: &SymbolTable, class_name: &str) -> String {
+    let parents = global.parents(class_name);
+    let mut output = format!("struct {} {{\n", c_name(class_name));
+    if parents.is_empty() {
+        output.push_str("    const struct vtable_entry *vptr;\n");
+    }
+    for parent in &parents {
+        output.push_str(&format!("    struct {} {};\n", c_name(parent), base_field(parent)));
+    }
+    let class_table = global.class_table(class_name).unwrap();
+    for entry in class_table.entries.iter().filter(|entry| entry.kind == SymbolKind::Attribute) {
+        output.push_str(&format!("    {};\n", declaration(&entry.type_name, &entry.dims, &c_name(&entry.name))));
+    }
+    output.push_str("};\n");
+    output
+}
+
+fn vtable_definitions(global: &SymbolTable, class_name: &str) -> String {
+    let struct_type = format!("struct {}", c_name(class_name));
+    let mut tables = vec![];
+    for (part, (path, part_class)) in table_parts(global, class_name).iter().enumerate() {
+        let slots = vtable_slots(global, part_class);
+        if slots.is_empty() {
+            continue;
+        }
+        let mut output = format!("static const struct vtable_entry {}[] = {{\n", vtable_name(class_name, part));
+        for slot in slots {
+            let owner = global.find_override(class_name, &slot.name, &slot.params).unwrap();
+            let owner_path = base_path(global, class_name, &owner).unwrap();
+            let function = c_name(&mangled_name(Some(&owner), &slot.name, &slot.params));
+            let adjustment = offset_difference(&struct_type, &owner_path, path);
+            output.push_str(&format!("    {{ (void (*)(void)) {}, {} }},\n", function, adjustment));
+        }
+        output.push_str("};\n");
+        tables.push(output);
+    }
+    tables.join("\n")
+}
+
+// Stores the table addresses of `count` objects of class `class_name`, including
+// the ones of their data members.
+fn init_definition(global: &SymbolTable, class_name: &str) -> String {
+    let mut output = format!("static void init_{}(struct {} *object, size_t count) {{\n", class_name, c_name(class_name));
+    output.push_str("    for (size_t index = 0; index < count; index++) {\n");
+    for (part, (path, part_class)) in table_parts(global, class_name).iter().enumerate() {
+        let mut fields = path.clone();
+        fields.extend(vptr_path(global, part_class));
+        let vtable = match vtable_slots(global, part_class).is_empty() {
+            true => "NULL".to_string(),
+            false => vtable_name(class_name, part),
+        };
+        output.push_str(&format!("        object[index].{} = {};\n", fields.join("."), vtable));
+    }
+    // data members of every part
+    let mut parts = vec![(vec![], class_name.to_string())];
+    while let Some((path, part_class)) = parts.pop() {
+        let class_table = match global.class_table(&part_class) {
+            Some(class_table) => class_table,
+            None => continue,
+        };
+        for entry in class_table.entries.iter().rev() {
+            let mut fields = path.clone();
+            match (&entry.kind, &entry.type_name) {
+                (SymbolKind::Inherit, _) => {
+                    fields.push(base_field(&entry.name));
+                    parts.push((fields, entry.name.clone()));
+                }
+                (SymbolKind::Attribute, TypeName::Class(member_class)) => {
+                    fields.push(c_name(&entry.name));
+                    let count = Type::of_entry(entry).element_count().unwrap_or(0);
+                    let first = match entry.dims.is_empty() {
+                        true => format!("&object[index].{}", fields.join(".")),
+                        false => format!("(struct {} *) object[index].{}", c_name(member_class), fields.join(".")),
+                    };
+                    output.push_str(&format!("        init_{}({}, {});\n", member_class, first, count));
+                }
+                _ => {}
+            }
+        }
+    }
+    output.push_str("    }\n}\n");
+    output
+}
+
+// Calls the member function `function` declared by `owner` on the object at
+// `self`, which is an `owner` part.
+fn dispatcher_definition(global: &SymbolTable, owner: &str, function: &SymbolEntry) -> String {
+    let slot = slot_index(global, owner, &function.name, &function.params);
+    let return_type = scalar_type(&function.type_name);
+    let mut params = vec![format!("struct {} *self", c_name(owner))];
+    let mut param_types = vec!["void *".to_string()];
+    let mut args = vec!["(char *) self + entry->adjustment".to_string()];
+    for (index, param) in function.params.iter().enumerate() {
+        params.push(param_declaration(param, &format!("p{}", index + 1)));
+        param_types.push(param_declaration(param, ""));
+        args.push(format!("p{}", index + 1));
+    }
+    let name = format!("virtual_{}", mangled_name(Some(owner), &function.name, &function.params));
+    let mut output = format!("static {} {}({}) {{\n", return_type, name, params.join(", "));
+    output.push_str(&format!("    const struct vtable_entry *entry = &self->{}[{}];\n", vptr_path(global, owner).join("."), slot));
+    let call = format!("(({} (*)({})) entry->function)({})", return_type, param_types.join(", "), args.join(", "));
+    match function.type_name {
+        TypeName::Void => output.push_str(&format!("    {};\n", call)),
+        _ => output.push_str(&format!("    return {};\n", call)),
+    }
+    output.push_str("}\n");
+    output
+}
+
+fn helper_definition(name: &str) -> &'static str {
+    match name {
+        "fail" => "static void fail(const char *message) {\n    fprintf(stderr, \"%s\\n\", message);\n    exit(1);\n}\n",
+        "float_div" => "static double float_div(double lhs, double rhs) {\n    if (rhs == 0.0) {\n        fail(\"division by zero\");\n    }\n    return lhs / rhs;\n}\n",
+        "integer_add" => "static int32_t integer_add(int32_t lhs, int32_t rhs) {\n    return (int32_t) ((uint32_t) lhs + (uint32_t) rhs);\n}\n",
+        "integer_div" => "static int32_t integer_div(int32_t lhs, int32_t rhs) {\n    if (rhs == 0) {\n        fail(\"division by zero\");\n    }\n    if (lhs == INT32_MIN && rhs == -1) {\n        return INT32_MIN;\n    }\n    return lhs / rhs;\n}\n",
+        "integer_mul" => "static int32_t integer_mul(int32_t lhs, int32_t rhs) {\n    return (int32_t) ((uint32_t) lhs * (uint32_t) rhs);\n}\n",
+        "integer_neg" => "static int32_t integer_neg(int32_t value) {\n    return (int32_t) (0u - (uint32_t) value);\n}\n",
+        "integer_sub" => "static int32_t integer_sub(int32_t lhs, int32_t rhs) {\n    return (int32_t) ((uint32_t) lhs - (uint32_t) rhs);\n}\n",
+        "read_float" => "static double read_float(void) {\n    double value;\n    if (scanf(\"%lf\", &value) != 1) {\n        fail(\"invalid or missing number in input\");\n    }\n    return value;\n}\n",
+        "read_integer" => "static int32_t read_integer(void) {\n    int32_t value;\n    if (scanf(\"%\" SCNd32, &value) != 1) {\n        fail(\"invalid or missing number in input\");\n    }\n    return value;\n}\n",
+        "write_float" => "static void write_float(double value) {\n    printf(\"%.2f\\n\", value);\n}\n",
+        "write_integer" => "static void write_integer(int32_t value) {\n    printf(\"%\" PRId32 \"\\n\", value);\n}\n",
+        _ => panic!("no helper named {}", name),
+    }
+}
+
+fn int_literal(value: i32) -> String {
+    match value {
+        i32::MIN => "INT32_MIN".to_string(),
+        _ if value < 0 => format!("({})", value),
+        _ => value.to_string(),
+    }
+}
+
+fn has_call(expr: &Expr) -> bool {
+    match expr {
+        Expr::Call { .. } => true,
+        Expr::Binary { lhs, rhs, .. } => has_call(lhs) || has_call(rhs),
+        Expr::Unary { operand, .. } => has_call(operand),
+        Expr::Index { base, index, .. } => has_call(base) || has_call(index),
+        Expr::Member { base, .. } => has_call(base),
+        Expr::Literal { .. } | Expr::Var { .. } => false,
+    }
+}
+
+// `object` when `place` is `(*object)`, for objects reached through a pointer.
+fn pointer_of(place: &str) -> Option<&str> {
+    let pointer = place.strip_prefix("(*")?.strip_suffix(')')?;
+    pointer.chars().all(|c| c.is_ascii_alphanumeric() || c == '_').then_some(pointer)
+}
+
+fn member(place: &str, field: &str) -> String {
+    match pointer_of(place) {
+        Some(pointer) => format!("{}->{}", pointer, field),
+        None => format!("{}.{}", place, field),
+    }
+}
+
+fn address(place: &str) -> String {
+    match pointer_of(place) {
+        Some(pointer) => pointer.to_string(),
+        None => format!("&{}", place),
+    }
+}
+
+// `value` in parentheses, unless it already is.
+fn parenthesized(value: &str) -> String {
+    let mut depth = 0;
+    for (index, c) in value.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 {
+            if index == value.len() - 1 && value.starts_with('(') {
+                return value.to_string();
+            }
+            break;
+        }
+    }
+    format!("({})", value)
+}
+
+fn is_temporary(value: &str) -> bool {
+    match value.strip_prefix('t').and_then(|rest| rest.strip_suffix('_')) {
+        Some(number) => !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()),
+        None => false,
+    }
+}
+
+struct FunctionWriter<'a, 'b> {
+    scope: FunctionScope<'a>,
+    helpers: &'b mut BTreeSet<&'static str>,
+    // indentation depth and text
+    lines: Vec<(usize, String)>,
+    depth: usize,
+    temp_count: usize,
+}
+
+impl<'a, 'b> FunctionWriter<'a, 'b> {
+    fn line(&mut self, text: String) {
+        self.lines.push((self.depth, text));
+    }
+
+    fn helper(&mut self, name: &'static str) -> &'static str {
+        if matches!(name, "float_div" | "integer_div" | "read_float" | "read_integer") {
+            self.helpers.insert("fail");
+        }
+        self.helpers.insert(name);
+        name
+    }
+
+    fn expr_type(&mut self, expr: &Expr) -> Type {
+        self.scope.expr_type(expr).expect("expressions are type checked before code generation")
+    }
+
+    fn is_integer(&mut self, expr: &Expr) -> bool {
+        self.expr_type(expr).type_name == TypeName::Integer
+    }
+
+    // `value` stored in a new temporary of type `type_name`, unless it is a
+    // constant or a temporary already.
+    fn hoisted(&mut self, value: String, type_name: &TypeName) -> String {
+        if value.parse::<f64>().is_ok() || is_temporary(&value) {
+            return value;
+        }
+        self.temp_count += 1;
+        let temp = format!("t{}_", self.temp_count);
+        self.line(format!("{} = {};", declaration(type_name, &[], &temp), value));
+        temp
+    }
+
+    fn value(&mut self, expr: &Expr) -> String {
+        match expr {
+            Expr::Literal { value: Literal::Int(value), .. } => int_literal(*value as i32),
+            Expr::Literal { value, .. } => format!("{:?}", value.float_value()),
+            Expr::Unary { op: UnaryOp::Plus, operand, .. } => self.value(operand),
+            Expr::Unary { op: UnaryOp::Neg, operand, .. } if self.is_integer(operand) => {
+                let operand = self.value(operand);
+                format!("{}({})", self.helper("integer_neg"), operand)
+            }
+            Expr::Unary { op: UnaryOp::Neg, operand, .. } => format!("(-{})", self.value(operand)),
+            Expr::Unary { op: UnaryOp::Not, operand, .. } => format!("(!{})", self.value(operand)),
+            Expr::Binary { op, lhs, rhs, .. } => {
+                let integer = self.is_integer(lhs) && self.is_integer(rhs);
+                let mut lhs_value = self.value(lhs);
+                if has_call(rhs) {
+                    let lhs_type = self.expr_type(lhs).type_name;
+                    lhs_value = self.hoisted(lhs_value, &lhs_type);
+                }
+                let rhs_value = self.value(rhs);
+                let helper = match (op, integer) {
+                    (BinaryOp::Add, true) => Some("integer_add"),
+                    (BinaryOp::Sub, true) => Some("integer_sub"),
+                    (BinaryOp::Mul, true) => Some("integer_mul"),
+                    (BinaryOp::Div, true) => Some("integer_div"),
+                    (BinaryOp::Div, false) => Some("float_div"),
+                    _ => None,
+                };
+                if let Some(helper) = helper {
+                    return format!("{}({}, {})", self.helper(helper), lhs_value, rhs_value);
+                }
+                // both operands are evaluated, as on Moon
+                let symbol = match op {
+                    BinaryOp::And => return format!("(({} != 0) & ({} != 0))", lhs_value, rhs_value),
+                    BinaryOp::Or => return format!("(({} != 0) | ({} != 0))", lhs_value, rhs_value),
+                    BinaryOp::Neq => "!=".to_string(),
+                    op => op.as_string(),
+                };
+                format!("({} {} {})", lhs_value, symbol, rhs_value)
+            }
+            Expr::Call { .. } => {
+                let (call, return_type) = self.call(expr);
+                self.hoisted(call, &return_type)
+            }
+            _ => self.place(expr),
+        }
+    }
+
+    // Data member `name` of the object of class `class_name` at `place`.
+    fn attribute(&self, place: &str, class_name: &str, name: &str) -> String {
+        let (owner, _) = self.scope.global.find_member(class_name, name).expect("data members are declared before code generation");
+        let mut place = place.to_string();
+        for field in base_path(self.scope.global, class_name, &owner).unwrap() {
+            place = member(&place, &field);
+        }
+        member(&place, &c_name(name))
+    }
+
+    // Address of the `ancestor` part of the object of class `class_name` at `place`.
+    fn part_address(&self, place: &str, class_name: &str, ancestor: &str) -> String {
+        let mut place = place.to_string();
+        for field in base_path(self.scope.global, class_name, ancestor).expect("ancestors are declared before code generation") {
+            place = member(&place, &field);
+        }
+        address(&place)
+    }
+
+    fn place(&mut self, expr: &Expr) -> String {
+        match expr {
+            Expr::Var { name, .. } if name == "self" && self.scope.class_name.is_some() => "(*self)".to_string(),
+            Expr::Var { name, .. } => match self.scope.lookup_variable(name) {
+                Some(entry) if entry.kind == SymbolKind::Attribute => {
+                    let class_name = self.scope.class_name.clone().unwrap();
+                    self.attribute("(*self)", &class_name, name)
+                }
+                Some(entry) if entry.kind == SymbolKind::Parameter && entry.dims.is_empty() && matches!(entry.type_name, TypeName::Class(_)) => {
+                    format!("(*{})", c_name(name))
+                }
+                _ => c_name(name),
+            },
+            Expr::Index { base, index, .. } => {
+                let base = self.place(base);
+                let index = self.value(index);
+                format!("{}[{}]", base, index)
+            }
+            Expr::Member { base, name, .. } => {
+                let class_name = self.expr_type(base).type_name.as_string();
+                let place = self.place(base);
+                self.attribute(&place, &class_name, name)
+            }
+            // objects are returned by value
+            Expr::Call { .. } => {
+                let (call, return_type) = self.call(expr);
+                self.hoisted(call, &return_type)
+            }
+            _ => unreachable!("only variables, array elements, data members and calls have a place"),
+        }
+    }
+
+    // Arguments for `params`, passed as `FunctionLowering::lower_args` passes them.
+    fn args(&mut self, params: &[VarDecl], args: &[Expr]) -> Vec<String> {
+        let mut values = vec![];
+        for (index, (param, arg)) in params.iter().zip(args).enumerate() {
+            if Type::of_var(param).is_numeric() {
+                let value = self.value(arg);
+                match args[index + 1..].iter().any(has_call) {
+                    true => values.push(self.hoisted(value, &param.type_name)),
+                    false => values.push(value),
+                }
+                continue;
+            }
+            let arg_type = self.expr_type(arg);
+            let place = self.place(arg);
+            match (&param.type_name, &arg_type.type_name) {
+                (TypeName::Class(param_class), TypeName::Class(arg_class)) if param.dims.is_empty() => {
+                    values.push(self.part_address(&place, arg_class, param_class));
+                }
+                _ => values.push(place),
+            }
+        }
+        values
+    }
+
+    // Call expression and the type it returns.
+    fn call(&mut self, expr: &Expr) -> (String, TypeName) {
+        let (receiver, name, args, span) = match expr {
+            Expr::Call { receiver, name, args, span } => (receiver, name, args, *span),
+            _ => unreachable!(),
+        };
+        let receiver_class = receiver.as_ref().map(|receiver| self.expr_type(receiver).type_name.as_string());
+        let (owner, candidates) = self.scope.call_candidates(receiver_class.as_deref(), name);
+        let function = self.scope.resolve_call(&candidates, name, args, span).expect("calls are resolved before code generation");
+        let mut values = vec![];
+        if let Some(owner) = &owner {
+            let object = match receiver {
+                Some(receiver) => {
+                    let place = self.place(receiver);
+                    self.part_address(&place, receiver_class.as_ref().unwrap(), owner)
+                }
+                None => {
+                    let class_name = self.scope.class_name.clone().unwrap();
+                    self.part_address("(*self)", &class_name, owner)
+                }
+            };
+            values.push(object);
+        }
+        values.extend(self.args(&function.params, args));
+        let mangled = mangled_name(owner.as_deref(), name, &function.params);
+        let callee = match owner {
+            Some(_) => format!("virtual_{}", mangled),
+            None => c_name(&mangled),
+        };
+        (format!("{}({})", callee, values.join(", ")), function.type_name)
+    }
+
+    fn constructor_call(&mut self, var: &VarDecl, args: &[Expr], span: Span) {
+        let class_name = var.type_name.as_string();
+        let constructors: Vec<SymbolEntry> = self.scope.global.class_table(&class_name).unwrap().entries.iter()
+            .filter(|entry| entry.kind == SymbolKind::Constructor)
+            .cloned()
+            .collect();
+        let constructor = self.scope.resolve_call(&constructors, &format!("{}::constructor", class_name), args, span)
+            .expect("constructor calls are resolved before code generation");
+        let mut values = vec![format!("&{}", c_name(&var.name))];
+        values.extend(self.args(&constructor.params, args));
+        let function = c_name(&mangled_name(Some(&class_name), &constructor.name, &constructor.params));
+        self.line(format!("{}({});", function, values.join(", ")));
+    }
+
+    fn block(&mut self, stmts: &[Stmt]) {
+        self.depth += 1;
+        for stmt in stmts {
+            self.stmt(stmt);
+        }
+        self.depth -= 1;
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Local { var, span } => {
+                let name = c_name(&var.name);
+                let zero = match Type::of_var(var).is_numeric() {
+                    true => "0",
+                    false => "{0}",
+                };
+                self.line(format!("{} = {};", declaration(&var.type_name, &var.dims, &name), zero));
+                if let TypeName::Class(class_name) = &var.type_name {
+                    let count = Type::of_var(var).element_count().unwrap_or(0);
+                    let first = match var.dims.is_empty() {
+                        true => format!("&{}", name),
+                        false => format!("(struct {} *) {}", c_name(class_name), name),
+                    };
+                    self.line(format!("init_{}({}, {});", class_name, first, count));
+                }
+                if let Some(args) = &var.constructor_args {
+                    self.constructor_call(var, args, *span);
+                }
+            }
+            Stmt::Assign { target, value, .. } => {
+                let target_type = self.expr_type(target);
+                let target = self.place(target);
+                let value = match target_type.is_numeric() {
+                    true => self.value(value),
+                    false => self.place(value),
+                };
+                self.line(format!("{} = {};", target, value));
+            }
+            Stmt::If { condition, then_block, else_block, .. } => {
+                let condition = self.value(condition);
+                self.line(format!("if {} {{", parenthesized(&condition)));
+                self.block(then_block);
+                if !else_block.is_empty() {
+                    self.line("} else {".to_string());
+                    self.block(else_block);
+                }
+                self.line("}".to_string());
+            }
+            Stmt::While { condition, body, .. } => {
+                let start = self.lines.len();
+                let condition = self.value(condition);
+                if self.lines.len() == start {
+                    self.line(format!("while {} {{", parenthesized(&condition)));
+                    self.block(body);
+                    self.line("}".to_string());
+                    return;
+                }
+                // the calls of the condition are made on every iteration
+                let condition_lines = self.lines.split_off(start);
+                self.line("while (1) {".to_string());
+                self.lines.extend(condition_lines.into_iter().map(|(depth, text)| (depth + 1, text)));
+                self.depth += 1;
+                self.line(format!("if (!{}) {{", parenthesized(&condition)));
+                self.line("    break;".to_string());
+                self.line("}".to_string());
+                self.depth -= 1;
+                self.block(body);
+                self.line("}".to_string());
+            }
+            Stmt::Read { target, .. } => {
+                let helper = match self.is_integer(target) {
+                    true => "read_integer",
+                    false => "read_float",
+                };
+                let target = self.place(target);
+                let helper = self.helper(helper);
+                self.line(format!("{} = {}();", target, helper));
+            }
+            Stmt::Write { value, .. } => {
+                let helper = match self.is_integer(value) {
+                    true => "write_integer",
+                    false => "write_float",
+                };
+                let value = self.value(value);
+                let helper = self.helper(helper);
+                self.line(format!("{}({});", helper, value));
+            }
+            Stmt::Return { value, .. } => {
+                let value = self.value(value);
+                self.line(format!("return {};", value));
+            }
+            Stmt::Call { call, .. } => {
+                let (call, _) = self.call(call);
+                self.line(format!("{};", call));
+            }
+        }
+    }
+}
+
+// Whether the C code `text` uses the identifier `name`.
+fn uses_name(text: &str, name: &str) -> bool {
+    text.split(|char: char| !char.is_ascii_alphanumeric() && char != '_').any(|word| word == name)
+}
+
+fn is_entry_point(function: &FuncDef) -> bool {
+    function.class_name.is_none() && function.sig.name == "main"
+}
+
+fn signature(function: &FuncDef) -> String {
+    if is_entry_point(function) {
+        return "int main(void)".to_string();
+    }
+    let mut params = vec![];
+    match &function.class_name {
+        Some(class_name) if function.sig.is_constructor => params.push(format!("struct {} *self", c_name(class_name))),
+        // called through tables, with a pointer to any part
+        Some(_) => params.push("void *object".to_string()),
+        None => {}
+    }
+    params.extend(function.sig.params.iter().map(|param| param_declaration(param, &c_name(&param.name))));
+    if params.is_empty() {
+        params.push("void".to_string());
+    }
+    let name = c_name(&mangled_name(function.class_name.as_deref(), &function.sig.name, &function.sig.params));
+    format!("static {} {}({})", scalar_type(&function.sig.return_type), name, params.join(", "))
+}
+
+fn function_definition(global: &SymbolTable, function: &FuncDef, helpers: &mut BTreeSet<&'static str>) -> String {
+    let scope = FunctionScope::new(global, function);
+    let mut writer = FunctionWriter { scope, helpers, lines: vec![], depth: 1, temp_count: 0 };
+    for stmt in &function.body {
+        writer.stmt(stmt);
+    }
+    // `self` is only declared when the body uses it, and parameters it doesn't
+    // use are cast to void, so that C compilers don't warn about them
+    let uses = |name: &str| writer.lines.iter().any(|(_, text)| uses_name(text, name));
+    let mut first_lines = vec![];
+    let mut unused: Vec<String> = function.sig.params.iter().map(|param| c_name(&param.name)).filter(|name| !uses(name)).collect();
+    match &function.class_name {
+        Some(class_name) if !function.sig.is_constructor && uses("self") => {
+            first_lines.push(format!("struct {} *self = object;", c_name(class_name)));
+        }
+        Some(_) if !function.sig.is_constructor => unused.insert(0, "object".to_string()),
+        Some(_) if !uses("self") => unused.insert(0, "self".to_string()),
+        _ => {}
+    }
+    first_lines.extend(unused.iter().map(|name| format!("(void) {};", name)));
+    writer.lines.splice(0..0, first_lines.into_iter().map(|text| (1, text)));
+    if is_entry_point(function) {
+        writer.line("return 0;".to_string());
+    }
+    let mut output = format!("{} {{\n", signature(function));
+    for (depth, text) in writer.lines {
+        output.push_str(&format!("{}{}\n", "    ".repeat(depth), text));
+    }
+    output.push_str("}\n");
+    output
+}
+
+// Standalone C99 program for `program`, which has been type checked.
+pub fn generate_c(program: &Program, global: &SymbolTable) -> String {
+    let classes = class_order(global);
+    let mut helpers = BTreeSet::new();
+    let definitions: Vec<String> = program.functions.iter()
+        .map(|function| function_definition(global, function, &mut helpers))
+        .collect();
+    let mut sections = vec![
+        "#include <inttypes.h>\n#include <stddef.h>\n#include <stdio.h>\n#include <stdlib.h>\n".to_string(),
+        "struct vtable_entry {\n    void (*function)(void);\n    ptrdiff_t adjustment;\n};\n".to_string(),
+    ];
+    sections.extend(helpers.iter().map(|name| helper_definition(name).to_string()));
+    sections.extend(classes.iter().map(|class_name| struct_definition(global, class_name)));
+    let prototypes: String = program.functions.iter()
+        .filter(|function| !is_entry_point(function))
+        .map(|function| format!("{};\n", signature(function)))
+        .collect();
+    sections.push(prototypes);
+    sections.extend(classes.iter().map(|class_name| vtable_definitions(global, class_name)));
+    sections.extend(classes.iter().map(|class_name| init_definition(global, class_name)));
+    for class_name in &classes {
+        let class_table = global.class_table(class_name).unwrap();
+        for function in class_table.entries.iter().filter(|entry| entry.kind == SymbolKind::Function) {
+            sections.push(dispatcher_definition(global, class_name, function));
+        }
+    }
+    sections.extend(definitions);
+    sections.retain(|section| !section.is_empty());
+    sections.join("\n")
+}
@@ -0,0 +1,161 @@
+// Control flow graphs of IR functions. A basic block starts at the first
+// instruction, at every label and after every jump, branch or return, and holds
+// the instructions up to the next start. Two empty blocks stand for the entry and
+// the exit of the function: the entry goes to the first block, and the blocks
+// that return or fall off the end go to the exit. Blocks nothing jumps to stay in
+// the graph, without predecessors. Graphs render to DOT with one box per block.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::Write;
+use crate::ir::*;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BasicBlock {
+    pub name: String,
+    // position of the first instruction in the function body
+    pub start: usize,
+    pub instrs: Vec<Instr>,
+    pub successors: Vec<usize>,
+    pub predecessors: Vec<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ControlFlowGraph {
+    pub function: String,
+    pub blocks: Vec<BasicBlock>,
+    pub entry: usize,
+    pub exit: usize,
+}
+
+// Edges out of a branch are labelled with the outcome that takes them.
+pub type CfgEdge = (usize, usize, Option<bool>);
+
+impl ControlFlowGraph {
+    pub fn build(function: &IrFunction) -> Self {
+        let body = &function.body;
+        let mut starts: Vec<usize> = vec![];
+        for (position, instr) in body.iter().enumerate() {
+            let after_terminator = position > 0 && body[position - 1].is_terminator();
+            if position == 0 || after_terminator || matches!(instr, Instr::Label(_)) {
+                starts.push(position);
+            }
+        }
+        let empty_block = |name: &str| BasicBlock {
+            name: name.to_string(),
+            start: body.len(),
+            instrs: vec![],
+            successors: vec![],
+            predecessors: vec![],
+        };
+        let mut blocks = vec![empty_block("entry")];
+        for (index, start) in starts.iter().enumerate() {
+            let end = starts.get(index + 1).copied().unwrap_or(body.len());
+            let name = match &body[*start] {
+                Instr::Label(label) => label.clone(),
+                _ => format!("B{}", index + 1),
+            };
+            blocks.push(BasicBlock { name, start: *start, instrs: body[*start..end].to_vec(), successors: vec![], predecessors: vec![] });
+        }
+        let exit = blocks.len();
+        blocks.push(empty_block("exit"));
+        let labels: HashMap<String, usize> = blocks.iter().enumerate()
+            .filter_map(|(index, block)| match block.instrs.first() {
+                Some(Instr::Label(label)) => Some((label.clone(), index)),
+                _ => None,
+            })
+            .collect();
+        let mut graph = ControlFlowGraph { function: function.name.clone(), blocks, entry: 0, exit };
+        graph.add_edge(0, 1);
+        for index in 1..exit {
+            let targets = match graph.blocks[index].instrs.last() {
+                Some(Instr::Jump(label)) => vec![labels[label]],
+                Some(Instr::Branch { if_true, if_false, .. }) => vec![labels[if_true], labels[if_false]],
+                Some(Instr::Return(_)) => vec![exit],
+                _ => vec![index + 1],
+            };
+            for target in targets {
+                graph.add_edge(index, target);
+            }
+        }
+        graph
+    }
+
+    fn add_edge(&mut self, from: usize, to: usize) {
+        if !self.blocks[from].successors.contains(&to) {
+            self.blocks[from].successors.push(to);
+            self.blocks[to].predecessors.push(from);
+        }
+    }
+
+    pub fn edges(&self) -> Vec<CfgEdge> {
+        let mut edges = vec![];
+        for (index, block) in self.blocks.iter().enumerate() {
+            let outcomes = match block.instrs.last() {
+                Some(Instr::Branch { if_true, if_false, .. }) if if_true != if_false => vec![Some(true), Some(false)],
+                _ => vec![None; block.successors.len()],
+            };
+            edges.extend(block.successors.iter().zip(outcomes).map(|(successor, outcome)| (index, *successor, outcome)));
+        }
+        edges
+    }
+
+    // Blocks in the order of the function body, entry first and exit last.
+    pub fn block_names(&self) -> Vec<&str> {
+        self.blocks.iter().map(|block| block.name.as_str()).collect()
+    }
+
+    // Instructions of the blocks back in one body, in block order.
+    pub fn body(&self) -> Vec<Instr> {
+        self.blocks.iter().flat_map(|block| block.instrs.iter().cloned()).collect()
+    }
+
+    pub fn render<W: Write>(&self, output: &mut W) -> std::io::Result<()> {
+        dot::render(self, output)
+    }
+}
+
+impl<'a> dot::Labeller<'a, usize, CfgEdge> for ControlFlowGraph {
+    fn graph_id(&'a self) -> dot::Id<'a> {
+        dot::Id::new(format!("cfg_{}", self.function)).unwrap()
+    }
+
+    fn node_id(&'a self, n: &usize) -> dot::Id<'a> {
+        dot::Id::new(format!("block{}", n)).unwrap()
+    }
+
+    fn node_shape(&'a self, _node: &usize) -> Option<dot::LabelText<'a>> {
+        Some(dot::LabelText::label("box"))
+    }
+
+    // The block name, then one left aligned line per instruction.
+    fn node_label(&'a self, n: &usize) -> dot::LabelText<'a> {
+        let block = &self.blocks[*n];
+        let mut text = format!("{}\\l", block.name);
+        for instr in block.instrs.iter().filter(|instr| !matches!(instr, Instr::Label(_))) {
+            text.push_str(&format!("    {}\\l", instr.as_string()));
+        }
+        dot::LabelText::escaped(text)
+    }
+
+    fn edge_label(&'a self, edge: &CfgEdge) -> dot::LabelText<'a> {
+        match edge.2 {
+            Some(outcome) => dot::LabelText::label(outcome.to_string()),
+            None => dot::LabelText::label(""),
+        }
+    }
+}
+
+impl<'a> dot::GraphWalk<'a, usize, CfgEdge> for ControlFlowGraph {
+    fn nodes(&'a self) -> dot::Nodes<'a, usize> {
+        Cow::Owned((0..self.blocks.len()).collect())
+    }
+
+    fn edges(&'a self) -> dot::Edges<'a, CfgEdge> {
+        Cow::Owned(ControlFlowGraph::edges(self))
+    }
+
+    fn source(&self, edge: &CfgEdge) -> usize { edge.0 }
+
+    fn target(&self, edge: &CfgEdge) -> usize { edge.1 }
+}
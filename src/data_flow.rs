@@ -0,0 +1,326 @@
+// Data-flow analyses over control flow graphs. An analysis gives the direction
+// facts flow in, the fact at the boundary (the entry going forward, the exit going
+// backward), the top of its lattice, which blocks start from, the meet of the
+// facts flowing into a block and the effect of one instruction. The solver keeps
+// a worklist of blocks whose input changed until the facts are stable, then
+// gives the facts at the start and end of every block and around every
+// instruction, always in program order: `before` holds before the instruction
+// runs, whatever the direction.
+
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::fmt::Debug;
+use crate::ast::Program;
+use crate::cfg::ControlFlowGraph;
+use crate::ir::*;
+use crate::passes::{Pass, PassContext};
+use crate::symbol_table::SymbolKind;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Forward,
+    Backward,
+}
+
+pub trait DataFlowAnalysis {
+    type Fact: Clone + PartialEq + Debug;
+
+    fn direction(&self) -> Direction;
+    fn boundary(&self) -> Self::Fact;
+    fn top(&self) -> Self::Fact;
+    fn meet(&self, lhs: &Self::Fact, rhs: &Self::Fact) -> Self::Fact;
+    // Fact on the other side of the instruction at `position` in the body.
+    fn transfer(&self, position: usize, instr: &Instr, fact: &Self::Fact) -> Self::Fact;
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DataFlowResult<F> {
+    // by block
+    pub block_in: Vec<F>,
+    pub block_out: Vec<F>,
+    // by position in the function body
+    pub before: Vec<F>,
+    pub after: Vec<F>,
+}
+
+pub fn solve<A: DataFlowAnalysis>(cfg: &ControlFlowGraph, analysis: &A) -> DataFlowResult<A::Fact> {
+    let forward = analysis.direction() == Direction::Forward;
+    let count = cfg.blocks.len();
+    // facts flowing into and out of each block, in the direction of the analysis
+    let mut inputs = vec![analysis.top(); count];
+    let mut outputs = vec![analysis.top(); count];
+    let mut worklist: VecDeque<usize> = if forward { (0..count).collect() } else { (0..count).rev().collect() };
+    let mut queued = vec![true; count];
+    while let Some(block) = worklist.pop_front() {
+        queued[block] = false;
+        let (sources, targets) = match forward {
+            true => (&cfg.blocks[block].predecessors, &cfg.blocks[block].successors),
+            false => (&cfg.blocks[block].successors, &cfg.blocks[block].predecessors),
+        };
+        let boundary = if forward { cfg.entry } else { cfg.exit };
+        inputs[block] = match block == boundary {
+            true => analysis.boundary(),
+            false => sources.iter()
+                .map(|source| &outputs[*source])
+                .fold(None, |met: Option<A::Fact>, fact| Some(match met {
+                    Some(met) => analysis.meet(&met, fact),
+                    None => fact.clone(),
+                }))
+                .unwrap_or_else(|| analysis.top()),
+        };
+        let output = transfer_block(cfg, analysis, block, &inputs[block], &mut |_, _| {});
+        if output != outputs[block] {
+            outputs[block] = output;
+            for target in targets {
+                if !queued[*target] {
+                    queued[*target] = true;
+                    worklist.push_back(*target);
+                }
+            }
+        }
+    }
+    let length = cfg.blocks.iter().map(|block| block.instrs.len()).sum();
+    let mut before = vec![analysis.top(); length];
+    let mut after = vec![analysis.top(); length];
+    for (block, input) in inputs.iter().enumerate() {
+        transfer_block(cfg, analysis, block, input, &mut |position, (fact_in, fact_out)| {
+            let (fact_before, fact_after) = if forward { (fact_in, fact_out) } else { (fact_out, fact_in) };
+            before[position] = fact_before;
+            after[position] = fact_after;
+        });
+    }
+    let (block_in, block_out) = if forward { (inputs, outputs) } else { (outputs, inputs) };
+    DataFlowResult { block_in, block_out, before, after }
+}
+
+// Runs the instructions of `block` in the direction of the analysis, calling
+// `visit` with the facts on both sides of each.
+fn transfer_block<A: DataFlowAnalysis>(
+    cfg: &ControlFlowGraph,
+    analysis: &A,
+    block: usize,
+    input: &A::Fact,
+    visit: &mut dyn FnMut(usize, (A::Fact, A::Fact)),
+) -> A::Fact {
+    let block = &cfg.blocks[block];
+    let mut positions: Vec<usize> = (0..block.instrs.len()).collect();
+    if analysis.direction() == Direction::Backward {
+        positions.reverse();
+    }
+    let mut fact = input.clone();
+    for index in positions {
+        let next = analysis.transfer(block.start + index, &block.instrs[index], &fact);
+        visit(block.start + index, (fact, next.clone()));
+        fact = next;
+    }
+    fact
+}
+
+// A named variable or a temporary, as the analyses track them.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Variable {
+    Named(String),
+    Temp(usize),
+}
+
+impl Variable {
+    pub fn of(operand: &Operand) -> Option<Variable> {
+        match operand {
+            Operand::Var(name) => Some(Variable::Named(name.clone())),
+            Operand::Temp(number) => Some(Variable::Temp(*number)),
+            _ => None,
+        }
+    }
+
+    pub fn operand(&self) -> Operand {
+        match self {
+            Variable::Named(name) => Operand::Var(name.clone()),
+            Variable::Temp(number) => Operand::Temp(*number),
+        }
+    }
+
+    pub fn as_string(&self) -> String {
+        self.operand().as_string()
+    }
+}
+
+// Variables whose value may still be read, going backward from the exit. Reads
+// through addresses are not seen.
+pub struct Liveness;
+
+impl DataFlowAnalysis for Liveness {
+    type Fact = BTreeSet<Variable>;
+
+    fn direction(&self) -> Direction {
+        Direction::Backward
+    }
+
+    fn boundary(&self) -> Self::Fact {
+        BTreeSet::new()
+    }
+
+    fn top(&self) -> Self::Fact {
+        BTreeSet::new()
+    }
+
+    fn meet(&self, lhs: &Self::Fact, rhs: &Self::Fact) -> Self::Fact {
+        lhs.union(rhs).cloned().collect()
+    }
+
+    fn transfer(&self, _position: usize, instr: &Instr, fact: &Self::Fact) -> Self::Fact {
+        let mut live = fact.clone();
+        if let Some(dest) = instr.dest().and_then(Variable::of) {
+            live.remove(&dest);
+        }
+        live.extend(instr.uses().into_iter().filter_map(Variable::of));
+        live
+    }
+}
+
+// Positions of the instructions whose assignment may still hold.
+pub struct ReachingDefinitions {
+    definitions: HashMap<Variable, Vec<usize>>,
+}
+
+impl ReachingDefinitions {
+    pub fn new(function: &IrFunction) -> Self {
+        let mut definitions: HashMap<Variable, Vec<usize>> = HashMap::new();
+        for (position, instr) in function.body.iter().enumerate() {
+            if let Some(dest) = instr.dest().and_then(Variable::of) {
+                definitions.entry(dest).or_default().push(position);
+            }
+        }
+        ReachingDefinitions { definitions }
+    }
+}
+
+impl DataFlowAnalysis for ReachingDefinitions {
+    type Fact = BTreeSet<usize>;
+
+    fn direction(&self) -> Direction {
+        Direction::Forward
+    }
+
+    fn boundary(&self) -> Self::Fact {
+        BTreeSet::new()
+    }
+
+    fn top(&self) -> Self::Fact {
+        BTreeSet::new()
+    }
+
+    fn meet(&self, lhs: &Self::Fact, rhs: &Self::Fact) -> Self::Fact {
+        lhs.union(rhs).cloned().collect()
+    }
+
+    fn transfer(&self, position: usize, instr: &Instr, fact: &Self::Fact) -> Self::Fact {
+        let mut reaching = fact.clone();
+        if let Some(dest) = instr.dest().and_then(Variable::of) {
+            for definition in &self.definitions[&dest] {
+                reaching.remove(definition);
+            }
+            reaching.insert(position);
+        }
+        reaching
+    }
+}
+
+// Scalar local variables assigned on every path from the entry. Taking the
+// address of a variable counts as assigning it, since it can be assigned through
+// the address.
+pub struct DefiniteAssignment {
+    locals: BTreeSet<String>,
+}
+
+impl DefiniteAssignment {
+    pub fn new(function: &IrFunction) -> Self {
+        let locals = function.frame.entries.iter()
+            .filter(|entry| entry.kind == SymbolKind::Local && !function.is_aggregate(&entry.name))
+            .map(|entry| entry.name.clone())
+            .collect();
+        DefiniteAssignment { locals }
+    }
+}
+
+impl DataFlowAnalysis for DefiniteAssignment {
+    type Fact = BTreeSet<String>;
+
+    fn direction(&self) -> Direction {
+        Direction::Forward
+    }
+
+    fn boundary(&self) -> Self::Fact {
+        BTreeSet::new()
+    }
+
+    fn top(&self) -> Self::Fact {
+        self.locals.clone()
+    }
+
+    fn meet(&self, lhs: &Self::Fact, rhs: &Self::Fact) -> Self::Fact {
+        lhs.intersection(rhs).cloned().collect()
+    }
+
+    fn transfer(&self, _position: usize, instr: &Instr, fact: &Self::Fact) -> Self::Fact {
+        let mut assigned = fact.clone();
+        match instr {
+            Instr::AddressOf { var, .. } => {
+                assigned.insert(var.clone());
+            }
+            _ => {
+                if let Some(Operand::Var(name)) = instr.dest() {
+                    assigned.insert(name.clone());
+                }
+            }
+        }
+        assigned.retain(|name| self.locals.contains(name));
+        assigned
+    }
+}
+
+// Scalar local variables of `function` that some path reads before assigning,
+// in the order of their first such read.
+pub fn unassigned_reads(function: &IrFunction) -> Vec<String> {
+    let analysis = DefiniteAssignment::new(function);
+    let result = solve(&ControlFlowGraph::build(function), &analysis);
+    let mut names: Vec<String> = vec![];
+    for (position, instr) in function.body.iter().enumerate() {
+        for operand in instr.uses() {
+            if let Operand::Var(name) = operand {
+                if analysis.locals.contains(name) && !result.before[position].contains(name) && !names.contains(name) {
+                    names.push(name.clone());
+                }
+            }
+        }
+    }
+    names
+}
+
+// Warns about local variables read before being assigned. The IR has no lines,
+// so the warning points at the declaration. Runs after the IR generation pass.
+pub struct DefiniteAssignmentPass;
+
+impl Pass for DefiniteAssignmentPass {
+    fn name(&self) -> String {
+        "definite-assignment".to_string()
+    }
+
+    fn run(&mut self, _program: &mut Program, context: &mut PassContext) {
+        let ir = match &context.ir {
+            Some(ir) => ir,
+            None => return,
+        };
+        let mut warnings = vec![];
+        for function in &ir.functions {
+            for name in unassigned_reads(function) {
+                let entry = function.variable(&name).unwrap();
+                warnings.push((
+                    format!("local variable '{}' in function '{}' may be read before being assigned", name, function.frame.name),
+                    entry.span,
+                ));
+            }
+        }
+        for (message, span) in warnings {
+            context.warning(message, span);
+        }
+    }
+}
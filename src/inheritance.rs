@@ -0,0 +1,144 @@
+// Checks on the class hierarchy: `isa` cycles, classes that contain an instance of
+// themselves through their attributes, and members inherited under the same name
+// from more than one parent.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use crate::ast::*;
+use crate::passes::{Diagnostic, Pass, PassContext};
+use crate::symbol_table::*;
+
+type ClassGraph = HashMap<String, Vec<String>>;
+
+// Edges from each class to its parents and, when `with_attributes` is set, to the
+// classes of its attributes (arrays of objects hold the objects themselves).
+fn class_graph(program: &Program, with_attributes: bool) -> ClassGraph {
+    let mut graph = ClassGraph::new();
+    for class in &program.classes {
+        if graph.contains_key(&class.name) {
+            continue;
+        }
+        let mut edges = class.parents.clone();
+        if with_attributes {
+            for member in &class.members {
+                if let MemberDecl::Attribute { var: VarDecl { type_name: TypeName::Class(class_name), .. }, .. } = member {
+                    edges.push(class_name.clone());
+                }
+            }
+        }
+        graph.insert(class.name.clone(), edges);
+    }
+    graph
+}
+
+// A path `start -> ... -> start`, if there is one.
+fn find_cycle(graph: &ClassGraph, start: &str) -> Option<Vec<String>> {
+    let mut visited = HashSet::new();
+    let mut path = vec![start.to_string()];
+    if find_cycle_helper(graph, start, &mut visited, &mut path) {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+fn find_cycle_helper(graph: &ClassGraph, start: &str, visited: &mut HashSet<String>, path: &mut Vec<String>) -> bool {
+    let current = path.last().unwrap().clone();
+    for next in graph.get(&current).into_iter().flatten() {
+        path.push(next.clone());
+        if next == start {
+            return true;
+        }
+        if visited.insert(next.clone()) && find_cycle_helper(graph, start, visited, path) {
+            return true;
+        }
+        path.pop();
+    }
+    false
+}
+
+fn check_cycles(program: &Program, diagnostics: &mut Vec<Diagnostic>) {
+    let inheritance_graph = class_graph(program, false);
+    let containment_graph = class_graph(program, true);
+    let mut reported: HashSet<BTreeSet<String>> = HashSet::new();
+    for class in &program.classes {
+        if let Some(cycle) = find_cycle(&inheritance_graph, &class.name) {
+            if reported.insert(cycle.iter().cloned().collect()) {
+                diagnostics.push(Diagnostic::error(format!("circular inheritance: {}", cycle.join(" isa ")), class.span));
+            }
+        } else if let Some(cycle) = find_cycle(&containment_graph, &class.name) {
+            if reported.insert(cycle.iter().cloned().collect()) {
+                diagnostics.push(Diagnostic::error(format!("class '{}' contains itself: {}", class.name, cycle.join(" -> ")), class.span));
+            }
+        }
+    }
+}
+
+// Names of the members a class inherits through `parent`, with the class that
+// declares each of them.
+fn members_through(global: &SymbolTable, parent: &str) -> Vec<(String, String)> {
+    let mut classes = vec![parent.to_string()];
+    classes.extend(global.ancestors(parent));
+    let mut members: Vec<(String, String)> = vec![];
+    for class_name in classes {
+        let class_table = match global.class_table(&class_name) {
+            Some(class_table) => class_table,
+            None => continue,
+        };
+        for entry in &class_table.entries {
+            let is_inherited = entry.kind != SymbolKind::Inherit && entry.kind != SymbolKind::Constructor;
+            if is_inherited && !members.iter().any(|(name, _)| name == &entry.name) {
+                members.push((entry.name.clone(), class_name.clone()));
+            }
+        }
+    }
+    members
+}
+
+fn check_inherited_conflicts(program: &Program, global: &SymbolTable, diagnostics: &mut Vec<Diagnostic>) {
+    for class in program.classes.iter().filter(|class| class.parents.len() > 1) {
+        let own_table = global.class_table(&class.name);
+        let mut seen: Vec<(String, String)> = vec![];
+        for parent in &class.parents {
+            for (name, owner) in members_through(global, parent) {
+                if own_table.is_some_and(|table| table.entries.iter().any(|entry| entry.name == name && entry.kind != SymbolKind::Inherit)) {
+                    continue;
+                }
+                match seen.iter().find(|(seen_name, _)| seen_name == &name) {
+                    Some((_, first_owner)) if first_owner != &owner => {
+                        diagnostics.push(Diagnostic::warning(format!(
+                            "member '{}' of class '{}' is inherited from both '{}' and '{}', '{}' is used",
+                            name, class.name, first_owner, owner, first_owner
+                        ), class.span));
+                    }
+                    Some(_) => {}
+                    None => seen.push((name, owner)),
+                }
+            }
+        }
+    }
+}
+
+pub fn check_inheritance(program: &Program, global: &SymbolTable) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    check_cycles(program, &mut diagnostics);
+    check_inherited_conflicts(program, global, &mut diagnostics);
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span.line);
+    diagnostics
+}
+
+pub struct InheritancePass;
+
+impl Pass for InheritancePass {
+    fn name(&self) -> String {
+        "inheritance".to_string()
+    }
+
+    fn needs_valid_program(&self) -> bool {
+        false
+    }
+
+    fn run(&mut self, program: &mut Program, context: &mut PassContext) {
+        let diagnostics = check_inheritance(program, context.symbol_table());
+        context.report(diagnostics);
+    }
+}
@@ -0,0 +1,458 @@
+// Tree-walking interpreter over the type-checked AST, for running programs without
+// the code generator. Integers are 32-bit and wrap around like on Moon, `write`
+// prints floats with two decimals. Arrays and objects are passed by reference: a
+// parameter refers to part of a variable of the caller, as a path from the
+// variable's value. Member functions are looked up in the class of the object at
+// run time, so overrides are dispatched dynamically. Index out of bounds and
+// division by zero stop the program with an error at the line of the expression.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::rc::Rc;
+use crate::ast::*;
+use crate::passes::Diagnostic;
+use crate::symbol_table::*;
+use crate::type_checker::FunctionScope;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Field {
+    pub owner: String,
+    pub name: String,
+    pub value: Value,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Int(i32),
+    Float(f64),
+    Array(Vec<Value>),
+    // attributes of the parents first, in `isa` order, then the class's own
+    Object { class_name: String, fields: Vec<Field> },
+}
+
+pub fn format_float(value: f64) -> String {
+    format!("{:.2}", value)
+}
+
+impl Value {
+    pub fn as_string(&self) -> String {
+        match self {
+            Value::Int(value) => value.to_string(),
+            Value::Float(value) => format_float(*value),
+            Value::Array(values) => {
+                let values: Vec<String> = values.iter().map(|value| value.as_string()).collect();
+                format!("[{}]", values.join(", "))
+            }
+            Value::Object { class_name, .. } => class_name.clone(),
+        }
+    }
+
+    fn as_float(&self) -> f64 {
+        match self {
+            Value::Int(value) => *value as f64,
+            Value::Float(value) => *value,
+            _ => panic!("{} is not a number", self.as_string()),
+        }
+    }
+
+    // `self` converted for storing where `slot` is: integers become floats in
+    // float variables.
+    fn converted_for(self, slot: &Value) -> Value {
+        match (self, slot) {
+            (Value::Int(value), Value::Float(_)) => Value::Float(value as f64),
+            (value, _) => value,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Step {
+    Index(usize),
+    Field(usize),
+}
+
+// Part of a variable's value.
+#[derive(Clone, Debug)]
+struct Reference {
+    root: Rc<RefCell<Value>>,
+    path: Vec<Step>,
+}
+
+impl Reference {
+    fn new(value: Value) -> Self {
+        Reference { root: Rc::new(RefCell::new(value)), path: vec![] }
+    }
+
+    fn child(&self, step: Step) -> Self {
+        let mut path = self.path.clone();
+        path.push(step);
+        Reference { root: self.root.clone(), path }
+    }
+
+    fn with<T>(&self, action: impl FnOnce(&mut Value) -> T) -> T {
+        let mut root = self.root.borrow_mut();
+        let mut value = &mut *root;
+        for step in &self.path {
+            value = match (step, value) {
+                (Step::Index(index), Value::Array(values)) => &mut values[*index],
+                (Step::Field(index), Value::Object { fields, .. }) => &mut fields[*index].value,
+                _ => unreachable!(),
+            };
+        }
+        action(value)
+    }
+
+    fn get(&self) -> Value {
+        self.with(|value| value.clone())
+    }
+}
+
+enum Flow {
+    Next,
+    Return(Value),
+}
+
+type RuntimeResult<T> = Result<T, Diagnostic>;
+
+struct Frame<'a> {
+    scope: FunctionScope<'a>,
+    variables: HashMap<String, Reference>,
+}
+
+pub struct Interpreter<'a, R: BufRead, W: Write> {
+    program: &'a Program,
+    global: &'a SymbolTable,
+    input: R,
+    output: W,
+}
+
+impl<'a, R: BufRead, W: Write> Interpreter<'a, R, W> {
+    pub fn new(program: &'a Program, global: &'a SymbolTable, input: R, output: W) -> Self {
+        Interpreter { program, global, input, output }
+    }
+
+    // Runs `main`.
+    pub fn run(&mut self) -> RuntimeResult<()> {
+        let program = self.program;
+        let main = program.functions.iter()
+            .find(|function| function.class_name.is_none() && function.sig.name == "main" && function.sig.params.is_empty())
+            .ok_or_else(|| Diagnostic::error("no main function".to_string(), program.span))?;
+        self.call_function(main, None, vec![])?;
+        Ok(())
+    }
+
+    fn default_value(&self, type_name: &TypeName, dims: &[Option<usize>]) -> Value {
+        if let Some(size) = dims.first() {
+            let element = self.default_value(type_name, &dims[1..]);
+            return Value::Array(vec![element; size.unwrap_or(0)]);
+        }
+        match type_name {
+            TypeName::Float => Value::Float(0.0),
+            TypeName::Class(class_name) => Value::Object { class_name: class_name.clone(), fields: self.fields(class_name) },
+            _ => Value::Int(0),
+        }
+    }
+
+    fn fields(&self, class_name: &str) -> Vec<Field> {
+        let mut fields = vec![];
+        let class_table = match self.global.class_table(class_name) {
+            Some(class_table) => class_table,
+            None => return fields,
+        };
+        for entry in &class_table.entries {
+            match entry.kind {
+                SymbolKind::Inherit => fields.extend(self.fields(&entry.name)),
+                SymbolKind::Attribute => fields.push(Field {
+                    owner: class_name.to_string(),
+                    name: entry.name.clone(),
+                    value: self.default_value(&entry.type_name, &entry.dims),
+                }),
+                _ => {}
+            }
+        }
+        fields
+    }
+
+    fn static_class(frame: &mut Frame, expr: &Expr) -> String {
+        let expr_type = frame.scope.expr_type(expr).expect("expressions are type checked before running");
+        expr_type.type_name.as_string()
+    }
+
+    // Attribute `name` of `object`, seen as an object of class `class_name`.
+    fn field(&self, object: &Reference, class_name: &str, name: &str) -> Reference {
+        let (owner, _) = self.global.find_member(class_name, name).expect("attributes are checked before running");
+        let index = object.with(|value| match value {
+            Value::Object { fields, .. } => fields.iter().position(|field| field.owner == owner && field.name == name),
+            _ => None,
+        });
+        object.child(Step::Field(index.expect("objects have a field for every attribute")))
+    }
+
+    fn eval_place(&mut self, frame: &mut Frame, expr: &Expr) -> RuntimeResult<Reference> {
+        match expr {
+            Expr::Var { name, .. } => match frame.variables.get(name) {
+                Some(reference) => Ok(reference.clone()),
+                None => {
+                    let object = frame.variables["self"].clone();
+                    let class_name = frame.scope.class_name.clone().unwrap();
+                    Ok(self.field(&object, &class_name, name))
+                }
+            },
+            Expr::Index { base, index, .. } => {
+                let array = self.eval_place(frame, base)?;
+                let position = self.eval_int(frame, index)?;
+                let size = array.with(|value| match value {
+                    Value::Array(values) => values.len(),
+                    _ => unreachable!(),
+                });
+                if position < 0 || position as usize >= size {
+                    let message = format!("array index {} is out of bounds for a dimension of size {}", position, size);
+                    return Err(Diagnostic::error(message, index.span()));
+                }
+                Ok(array.child(Step::Index(position as usize)))
+            }
+            Expr::Member { base, name, .. } => {
+                let class_name = Self::static_class(frame, base);
+                let object = self.eval_place(frame, base)?;
+                Ok(self.field(&object, &class_name, name))
+            }
+            _ => Ok(Reference::new(self.eval(frame, expr)?)),
+        }
+    }
+
+    fn eval_int(&mut self, frame: &mut Frame, expr: &Expr) -> RuntimeResult<i32> {
+        match self.eval(frame, expr)? {
+            Value::Int(value) => Ok(value),
+            value => panic!("{} is not an integer", value.as_string()),
+        }
+    }
+
+    fn eval(&mut self, frame: &mut Frame, expr: &Expr) -> RuntimeResult<Value> {
+        match expr {
+            Expr::Literal { value: Literal::Int(value), .. } => Ok(Value::Int(*value as i32)),
+            Expr::Literal { value, .. } => Ok(Value::Float(value.float_value())),
+            Expr::Unary { op, operand, .. } => {
+                let value = self.eval(frame, operand)?;
+                Ok(match (op, value) {
+                    (UnaryOp::Plus, value) => value,
+                    (UnaryOp::Neg, Value::Int(value)) => Value::Int(value.wrapping_neg()),
+                    (UnaryOp::Neg, value) => Value::Float(-value.as_float()),
+                    (UnaryOp::Not, value) => Value::Int((value == Value::Int(0)) as i32),
+                })
+            }
+            Expr::Binary { op, lhs, rhs, span } => {
+                let lhs = self.eval(frame, lhs)?;
+                let rhs = self.eval(frame, rhs)?;
+                binary(*op, lhs, rhs, *span)
+            }
+            // checked programs never read the value of a void call
+            Expr::Call { .. } => Ok(self.call(frame, expr)?.unwrap_or(Value::Int(0))),
+            _ => Ok(self.eval_place(frame, expr)?.get()),
+        }
+    }
+
+    fn definition(&self, class_name: Option<&str>, name: &str, params: &[VarDecl], is_constructor: bool) -> &'a FuncDef {
+        let program = self.program;
+        program.functions.iter()
+            .find(|function| function.class_name.as_deref() == class_name
+                && function.sig.name == name
+                && function.sig.is_constructor == is_constructor
+                && same_param_types(&function.sig.params, params))
+            .expect("declared functions are defined")
+    }
+
+    // Scalars by value, converted to the type of the parameter, arrays and objects
+    // by reference.
+    fn eval_args(&mut self, frame: &mut Frame, params: &[VarDecl], args: &[Expr]) -> RuntimeResult<Vec<Reference>> {
+        let mut references = vec![];
+        for (param, arg) in params.iter().zip(args) {
+            if param.dims.is_empty() && matches!(param.type_name, TypeName::Integer | TypeName::Float) {
+                let value = self.eval(frame, arg)?.converted_for(&self.default_value(&param.type_name, &[]));
+                references.push(Reference::new(value));
+            } else {
+                references.push(self.eval_place(frame, arg)?);
+            }
+        }
+        Ok(references)
+    }
+
+    fn call(&mut self, frame: &mut Frame, expr: &Expr) -> RuntimeResult<Option<Value>> {
+        let (receiver, name, args, span) = match expr {
+            Expr::Call { receiver, name, args, span } => (receiver, name, args, *span),
+            _ => unreachable!(),
+        };
+        let receiver_class = receiver.as_ref().map(|receiver| Self::static_class(frame, receiver));
+        let (owner, candidates) = frame.scope.call_candidates(receiver_class.as_deref(), name);
+        let function = frame.scope.resolve_call(&candidates, name, args, span).expect("calls are resolved before running");
+        let object = match (receiver, owner) {
+            (Some(receiver), _) => Some(self.eval_place(frame, receiver)?),
+            (None, Some(_)) => Some(frame.variables["self"].clone()),
+            (None, None) => None,
+        };
+        let class_name = object.as_ref().map(|object| object.with(|value| match value {
+            Value::Object { class_name, .. } => class_name.clone(),
+            _ => unreachable!(),
+        }));
+        let class_name = class_name.map(|class_name| self.global.find_override(&class_name, name, &function.params).unwrap());
+        let definition = self.definition(class_name.as_deref(), name, &function.params, false);
+        let args = self.eval_args(frame, &function.params, args)?;
+        self.call_function(definition, object, args)
+    }
+
+    fn call_function(&mut self, function: &FuncDef, object: Option<Reference>, args: Vec<Reference>) -> RuntimeResult<Option<Value>> {
+        let mut frame = Frame { scope: FunctionScope::new(self.global, function), variables: HashMap::new() };
+        if let Some(object) = object {
+            frame.variables.insert("self".to_string(), object);
+        }
+        for (param, arg) in function.sig.params.iter().zip(args) {
+            frame.variables.insert(param.name.clone(), arg);
+        }
+        match self.exec_block(&mut frame, &function.body)? {
+            Flow::Return(value) => Ok(Some(value)),
+            Flow::Next => Ok(None),
+        }
+    }
+
+    fn exec_block(&mut self, frame: &mut Frame, stmts: &[Stmt]) -> RuntimeResult<Flow> {
+        for stmt in stmts {
+            if let Flow::Return(value) = self.exec_stmt(frame, stmt)? {
+                return Ok(Flow::Return(value));
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    fn read_token(&mut self, span: Span) -> RuntimeResult<String> {
+        let mut token = String::new();
+        let mut byte = [0u8];
+        loop {
+            let count = self.input.read(&mut byte).map_err(|error| Diagnostic::error(format!("cannot read input: {}", error), span))?;
+            if count == 0 {
+                break;
+            }
+            match byte[0] {
+                byte if byte.is_ascii_whitespace() => {
+                    if !token.is_empty() {
+                        break;
+                    }
+                }
+                byte => token.push(byte as char),
+            }
+        }
+        if token.is_empty() {
+            return Err(Diagnostic::error("unexpected end of input".to_string(), span));
+        }
+        Ok(token)
+    }
+
+    fn exec_stmt(&mut self, frame: &mut Frame, stmt: &Stmt) -> RuntimeResult<Flow> {
+        match stmt {
+            Stmt::Local { var, span } => {
+                let object = Reference::new(self.default_value(&var.type_name, &var.dims));
+                frame.variables.insert(var.name.clone(), object.clone());
+                if let Some(args) = &var.constructor_args {
+                    let class_name = var.type_name.as_string();
+                    let constructors: Vec<SymbolEntry> = self.global.class_table(&class_name).unwrap().entries.iter()
+                        .filter(|entry| entry.kind == SymbolKind::Constructor)
+                        .cloned()
+                        .collect();
+                    let constructor = frame.scope.resolve_call(&constructors, &format!("{}::constructor", class_name), args, *span)
+                        .expect("constructor calls are resolved before running");
+                    let definition = self.definition(Some(&class_name), &constructor.name, &constructor.params, true);
+                    let args = self.eval_args(frame, &constructor.params, args)?;
+                    self.call_function(definition, Some(object), args)?;
+                }
+            }
+            Stmt::Assign { target, value, .. } => {
+                let target = self.eval_place(frame, target)?;
+                let value = self.eval(frame, value)?;
+                target.with(|slot| *slot = value.converted_for(slot));
+            }
+            Stmt::If { condition, then_block, else_block, .. } => {
+                let block = if self.eval_int(frame, condition)? != 0 { then_block } else { else_block };
+                return self.exec_block(frame, block);
+            }
+            Stmt::While { condition, body, .. } => {
+                while self.eval_int(frame, condition)? != 0 {
+                    if let Flow::Return(value) = self.exec_block(frame, body)? {
+                        return Ok(Flow::Return(value));
+                    }
+                }
+            }
+            Stmt::Read { target, span } => {
+                let target = self.eval_place(frame, target)?;
+                let token = self.read_token(*span)?;
+                let is_float = target.with(|slot| matches!(slot, Value::Float(_)));
+                let value = if is_float {
+                    token.parse().map(Value::Float).ok()
+                } else {
+                    token.parse().map(Value::Int).ok()
+                };
+                let value = value.ok_or_else(|| Diagnostic::error(format!("invalid number '{}' in input", token), *span))?;
+                target.with(|slot| *slot = value);
+            }
+            Stmt::Write { value, span } => {
+                let value = self.eval(frame, value)?;
+                writeln!(self.output, "{}", value.as_string())
+                    .map_err(|error| Diagnostic::error(format!("cannot write output: {}", error), *span))?;
+            }
+            Stmt::Return { value, .. } => {
+                let value = self.eval(frame, value)?;
+                let return_type = self.default_value(&frame.scope.return_type, &[]);
+                return Ok(Flow::Return(value.converted_for(&return_type)));
+            }
+            Stmt::Call { call, .. } => {
+                self.call(frame, call)?;
+            }
+        }
+        Ok(Flow::Next)
+    }
+}
+
+fn binary(op: BinaryOp, lhs: Value, rhs: Value, span: Span) -> RuntimeResult<Value> {
+    let division_by_zero = || Err(Diagnostic::error("division by zero".to_string(), span));
+    if let (Value::Int(lhs), Value::Int(rhs)) = (&lhs, &rhs) {
+        let (lhs, rhs) = (*lhs, *rhs);
+        let value = match op {
+            BinaryOp::Add => lhs.wrapping_add(rhs),
+            BinaryOp::Sub => lhs.wrapping_sub(rhs),
+            BinaryOp::Mul => lhs.wrapping_mul(rhs),
+            BinaryOp::Div if rhs == 0 => return division_by_zero(),
+            BinaryOp::Div => lhs.wrapping_div(rhs),
+            BinaryOp::And => (lhs != 0 && rhs != 0) as i32,
+            BinaryOp::Or => (lhs != 0 || rhs != 0) as i32,
+            BinaryOp::Eq => (lhs == rhs) as i32,
+            BinaryOp::Neq => (lhs != rhs) as i32,
+            BinaryOp::Lt => (lhs < rhs) as i32,
+            BinaryOp::Gt => (lhs > rhs) as i32,
+            BinaryOp::Leq => (lhs <= rhs) as i32,
+            BinaryOp::Geq => (lhs >= rhs) as i32,
+        };
+        return Ok(Value::Int(value));
+    }
+    let (lhs, rhs) = (lhs.as_float(), rhs.as_float());
+    let value = match op {
+        BinaryOp::Add => lhs + rhs,
+        BinaryOp::Sub => lhs - rhs,
+        BinaryOp::Mul => lhs * rhs,
+        BinaryOp::Div if rhs == 0.0 => return division_by_zero(),
+        BinaryOp::Div => lhs / rhs,
+        BinaryOp::Eq => return Ok(Value::Int((lhs == rhs) as i32)),
+        BinaryOp::Neq => return Ok(Value::Int((lhs != rhs) as i32)),
+        BinaryOp::Lt => return Ok(Value::Int((lhs < rhs) as i32)),
+        BinaryOp::Gt => return Ok(Value::Int((lhs > rhs) as i32)),
+        BinaryOp::Leq => return Ok(Value::Int((lhs <= rhs) as i32)),
+        BinaryOp::Geq => return Ok(Value::Int((lhs >= rhs) as i32)),
+        BinaryOp::And | BinaryOp::Or => panic!("'{}' applied to floats", op.as_string()),
+    };
+    Ok(Value::Float(value))
+}
+
+// Output of `program` run with `input`, along with the runtime error that stopped
+// it, if any.
+pub fn interpret(program: &Program, global: &SymbolTable, input: &str) -> (String, Option<Diagnostic>) {
+    let mut output = vec![];
+    let result = Interpreter::new(program, global, input.as_bytes(), &mut output).run();
+    (String::from_utf8_lossy(&output).to_string(), result.err())
+}
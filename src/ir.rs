@@ -0,0 +1,349 @@
+// Three-address intermediate representation, the common input of the optimizers
+// and back ends. A function is a list of instructions over its scalar variables
+// (parameters and local variables, by name), numbered temporaries and constants.
+// Arrays and objects are only reached through addresses: `AddressOf` gives the
+// address of one stored in the frame, and array or object parameters hold one.
+// Integers and addresses are `Int` values, floats are `Float` values. Member
+// functions are called through the virtual function tables of vtables.rs.
+
+use crate::ast::{BinaryOp, TypeName, UnaryOp};
+use crate::types::{FLOAT_SIZE, INTEGER_SIZE};
+use crate::symbol_table::{SymbolEntry, SymbolKind, SymbolTable};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IrType {
+    Int,
+    Float,
+}
+
+impl IrType {
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            IrType::Int => "i",
+            IrType::Float => "f",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Operand {
+    Var(String),
+    // `Temp(n)` is stored in the frame entry named `tn`
+    Temp(usize),
+    Int(i64),
+    Float(f64),
+}
+
+impl Operand {
+    pub fn is_constant(&self) -> bool {
+        matches!(self, Operand::Int(_) | Operand::Float(_))
+    }
+
+    pub fn as_string(&self) -> String {
+        match self {
+            Operand::Var(name) => name.clone(),
+            Operand::Temp(number) => format!("t{}", number),
+            Operand::Int(value) => value.to_string(),
+            Operand::Float(value) => format!("{:?}", value),
+        }
+    }
+}
+
+// `dest` operands are always variables or temporaries. Relational operators give
+// 1 or 0, `and`, `or` and `not` treat any non-zero value as true.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Instr {
+    Label(String),
+    Copy { dest: Operand, src: Operand },
+    // `ty` is the type of the operands
+    Binary { dest: Operand, op: BinaryOp, ty: IrType, lhs: Operand, rhs: Operand },
+    // `op` is never `UnaryOp::Plus`
+    Unary { dest: Operand, op: UnaryOp, ty: IrType, operand: Operand },
+    IntToFloat { dest: Operand, src: Operand },
+    AddressOf { dest: Operand, var: String },
+    Load { dest: Operand, ty: IrType, address: Operand, offset: usize },
+    Store { ty: IrType, address: Operand, offset: usize, value: Operand },
+    Jump(String),
+    // jumps to `if_true` when `condition` is not 0
+    Branch { condition: Operand, if_true: String, if_false: String },
+    Call { dest: Option<Operand>, function: String, args: Vec<Operand> },
+    // calls the function in slot `slot` of the table of the object `args[0]`,
+    // with `args[0]` moved by the adjustment of the slot; `function` is the one
+    // the call resolves to statically, which has the same parameters
+    CallVirtual { dest: Option<Operand>, function: String, slot: usize, args: Vec<Operand> },
+    VtableAddress { dest: Operand, vtable: String },
+    Return(Option<Operand>),
+    Read { dest: Operand, ty: IrType },
+    Write { value: Operand, ty: IrType },
+}
+
+fn binary_symbol(op: BinaryOp, ty: IrType) -> String {
+    match ty {
+        _ if op.is_logical() => op.as_string(),
+        IrType::Int => op.as_string(),
+        IrType::Float => format!("{}f", op.as_string()),
+    }
+}
+
+fn address_string(address: &Operand, offset: usize) -> String {
+    match offset {
+        0 => format!("[{}]", address.as_string()),
+        _ => format!("[{} + {}]", address.as_string(), offset),
+    }
+}
+
+impl Instr {
+    // Variable or temporary written by the instruction.
+    pub fn dest(&self) -> Option<&Operand> {
+        match self {
+            Instr::Copy { dest, .. }
+            | Instr::Binary { dest, .. }
+            | Instr::Unary { dest, .. }
+            | Instr::IntToFloat { dest, .. }
+            | Instr::AddressOf { dest, .. }
+            | Instr::Load { dest, .. }
+            | Instr::VtableAddress { dest, .. }
+            | Instr::Read { dest, .. } => Some(dest),
+            Instr::Call { dest, .. } | Instr::CallVirtual { dest, .. } => dest.as_ref(),
+            _ => None,
+        }
+    }
+
+    // Operands read by the instruction, in evaluation order.
+    pub fn uses(&self) -> Vec<&Operand> {
+        match self {
+            Instr::Copy { src, .. } | Instr::IntToFloat { src, .. } => vec![src],
+            Instr::Binary { lhs, rhs, .. } => vec![lhs, rhs],
+            Instr::Unary { operand, .. } => vec![operand],
+            Instr::Load { address, .. } => vec![address],
+            Instr::Store { address, value, .. } => vec![address, value],
+            Instr::Branch { condition, .. } => vec![condition],
+            Instr::Call { args, .. } | Instr::CallVirtual { args, .. } => args.iter().collect(),
+            Instr::Return(value) => value.iter().collect(),
+            Instr::Write { value, .. } => vec![value],
+            _ => vec![],
+        }
+    }
+
+    pub fn uses_mut(&mut self) -> Vec<&mut Operand> {
+        match self {
+            Instr::Copy { src, .. } | Instr::IntToFloat { src, .. } => vec![src],
+            Instr::Binary { lhs, rhs, .. } => vec![lhs, rhs],
+            Instr::Unary { operand, .. } => vec![operand],
+            Instr::Load { address, .. } => vec![address],
+            Instr::Store { address, value, .. } => vec![address, value],
+            Instr::Branch { condition, .. } => vec![condition],
+            Instr::Call { args, .. } | Instr::CallVirtual { args, .. } => args.iter_mut().collect(),
+            Instr::Return(value) => value.iter_mut().collect(),
+            Instr::Write { value, .. } => vec![value],
+            _ => vec![],
+        }
+    }
+
+    pub fn dest_mut(&mut self) -> Option<&mut Operand> {
+        match self {
+            Instr::Copy { dest, .. }
+            | Instr::Binary { dest, .. }
+            | Instr::Unary { dest, .. }
+            | Instr::IntToFloat { dest, .. }
+            | Instr::AddressOf { dest, .. }
+            | Instr::Load { dest, .. }
+            | Instr::VtableAddress { dest, .. }
+            | Instr::Read { dest, .. } => Some(dest),
+            Instr::Call { dest, .. } | Instr::CallVirtual { dest, .. } => dest.as_mut(),
+            _ => None,
+        }
+    }
+
+    pub fn is_terminator(&self) -> bool {
+        matches!(self, Instr::Jump(_) | Instr::Branch { .. } | Instr::Return(_))
+    }
+
+    pub fn as_string(&self) -> String {
+        match self {
+            Instr::Label(label) => format!("{}:", label),
+            Instr::Copy { dest, src } => format!("{} = {}", dest.as_string(), src.as_string()),
+            Instr::Binary { dest, op, ty, lhs, rhs } => {
+                format!("{} = {} {} {}", dest.as_string(), lhs.as_string(), binary_symbol(*op, *ty), rhs.as_string())
+            }
+            Instr::Unary { dest, op, ty, operand } => {
+                let symbol = match (op, ty) {
+                    (UnaryOp::Not, _) => "not ".to_string(),
+                    (_, IrType::Int) => op.as_string(),
+                    (_, IrType::Float) => format!("{}f ", op.as_string()),
+                };
+                format!("{} = {}{}", dest.as_string(), symbol, operand.as_string())
+            }
+            Instr::IntToFloat { dest, src } => format!("{} = itof {}", dest.as_string(), src.as_string()),
+            Instr::AddressOf { dest, var } => format!("{} = &{}", dest.as_string(), var),
+            Instr::Load { dest, ty, address, offset } => {
+                format!("{} = load.{} {}", dest.as_string(), ty.suffix(), address_string(address, *offset))
+            }
+            Instr::Store { ty, address, offset, value } => {
+                format!("store.{} {}, {}", ty.suffix(), address_string(address, *offset), value.as_string())
+            }
+            Instr::Jump(label) => format!("goto {}", label),
+            Instr::Branch { condition, if_true, if_false } => {
+                format!("if {} goto {} else {}", condition.as_string(), if_true, if_false)
+            }
+            Instr::Call { dest, function, args } => {
+                let args: Vec<String> = args.iter().map(|arg| arg.as_string()).collect();
+                let call = format!("call {}({})", function, args.join(", "));
+                match dest {
+                    Some(dest) => format!("{} = {}", dest.as_string(), call),
+                    None => call,
+                }
+            }
+            Instr::CallVirtual { dest, function, slot, args } => {
+                let args: Vec<String> = args.iter().map(|arg| arg.as_string()).collect();
+                let call = format!("vcall {}#{}({})", function, slot, args.join(", "));
+                match dest {
+                    Some(dest) => format!("{} = {}", dest.as_string(), call),
+                    None => call,
+                }
+            }
+            Instr::VtableAddress { dest, vtable } => format!("{} = &{}", dest.as_string(), vtable),
+            Instr::Return(Some(value)) => format!("return {}", value.as_string()),
+            Instr::Return(None) => "return".to_string(),
+            Instr::Read { dest, ty } => format!("read.{} {}", ty.suffix(), dest.as_string()),
+            Instr::Write { value, ty } => format!("write.{} {}", ty.suffix(), value.as_string()),
+        }
+    }
+}
+
+// `frame` is the function's table from the memory layout pass, with one
+// `Temporary` entry per temporary and one `Local` entry per object returned by a
+// call. `params` lists the parameters in call order, `self` first for member
+// functions, then `value` for functions returning an object, which is the
+// address of the caller's slot to copy it to.
+#[derive(Clone, Debug)]
+pub struct IrFunction {
+    pub name: String,
+    pub params: Vec<String>,
+    pub return_type: Option<IrType>,
+    pub frame: SymbolTable,
+    pub body: Vec<Instr>,
+}
+
+pub fn ir_type(type_name: &TypeName) -> IrType {
+    match type_name {
+        TypeName::Float => IrType::Float,
+        _ => IrType::Int,
+    }
+}
+
+impl IrFunction {
+    // Frame entry of a parameter, local variable or return slot named `name`.
+    pub fn variable(&self, name: &str) -> Option<&SymbolEntry> {
+        self.frame.entries.iter().find(|entry| entry.name == name && entry.kind != SymbolKind::Temporary)
+    }
+
+    pub fn temporary(&self, number: usize) -> Option<&SymbolEntry> {
+        let name = format!("t{}", number);
+        self.frame.entries.iter().find(|entry| entry.name == name && entry.kind == SymbolKind::Temporary)
+    }
+
+    // Adds a temporary to the frame, which needs its memory layout.
+    pub fn add_temporary(&mut self, ty: IrType) -> Operand {
+        let (type_name, size) = match ty {
+            IrType::Int => (TypeName::Integer, INTEGER_SIZE),
+            IrType::Float => (TypeName::Float, FLOAT_SIZE),
+        };
+        let (name, _) = self.frame.add_temporary(type_name, size);
+        Operand::Temp(name[1..].parse().unwrap())
+    }
+
+    pub fn temporary_count(&self) -> usize {
+        self.frame.entries.iter().filter(|entry| entry.kind == SymbolKind::Temporary).count()
+    }
+
+    // Arrays and objects stored in the frame, as opposed to the ones passed by
+    // address.
+    pub fn is_aggregate(&self, name: &str) -> bool {
+        match self.variable(name) {
+            Some(entry) => entry.kind == SymbolKind::Local && (!entry.dims.is_empty() || matches!(entry.type_name, TypeName::Class(_))),
+            None => false,
+        }
+    }
+
+    pub fn value_type(&self, operand: &Operand) -> IrType {
+        let entry = match operand {
+            Operand::Int(_) => return IrType::Int,
+            Operand::Float(_) => return IrType::Float,
+            Operand::Var(name) => self.variable(name),
+            Operand::Temp(number) => self.temporary(*number),
+        };
+        match entry {
+            Some(entry) if entry.dims.is_empty() => ir_type(&entry.type_name),
+            Some(_) => IrType::Int,
+            None => panic!("{} is not in the frame of {}", operand.as_string(), self.name),
+        }
+    }
+
+    pub fn as_string(&self) -> String {
+        let return_type = match self.return_type {
+            Some(IrType::Int) => "integer",
+            Some(IrType::Float) => "float",
+            None => "void",
+        };
+        let mut output = format!("function {}({}) => {}\n", self.name, self.params.join(", "), return_type);
+        for instr in &self.body {
+            match instr {
+                Instr::Label(_) => output.push_str(&format!("{}\n", instr.as_string())),
+                _ => output.push_str(&format!("    {}\n", instr.as_string())),
+            }
+        }
+        output
+    }
+}
+
+// `adjustment` is added to the object address passed as `self`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VtableEntry {
+    pub function: String,
+    pub adjustment: i64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Vtable {
+    pub name: String,
+    pub entries: Vec<VtableEntry>,
+}
+
+impl Vtable {
+    pub fn as_string(&self) -> String {
+        let entries: Vec<String> = self.entries.iter()
+            .map(|entry| match entry.adjustment {
+                0 => entry.function.clone(),
+                adjustment => format!("{}({:+})", entry.function, adjustment),
+            })
+            .collect();
+        format!("{} = [{}]\n", self.name, entries.join(", "))
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct IrProgram {
+    pub vtables: Vec<Vtable>,
+    pub functions: Vec<IrFunction>,
+}
+
+impl IrProgram {
+    pub fn function(&self, name: &str) -> Option<&IrFunction> {
+        self.functions.iter().find(|function| function.name == name)
+    }
+
+    pub fn vtable(&self, name: &str) -> Option<&Vtable> {
+        self.vtables.iter().find(|vtable| vtable.name == name)
+    }
+
+    // Tables, if any, then functions, separated by blank lines.
+    pub fn as_string(&self) -> String {
+        let mut sections: Vec<String> = vec![];
+        if !self.vtables.is_empty() {
+            sections.push(self.vtables.iter().map(|vtable| vtable.as_string()).collect());
+        }
+        sections.extend(self.functions.iter().map(|function| function.as_string()));
+        sections.join("\n")
+    }
+}
@@ -102,6 +102,7 @@ pub enum TokenType {
     LocalVar,
     // self
     WhiteSpace, // represents whitespaces
+    Comment, // represents // and /* */ comments
     // EndOfFile
     EndOfFile
 }
@@ -115,18 +116,31 @@ lazy_static! {
     static ref PUNCTUATION_REGEX: Regex = Regex::new(r"^(::|=>|\(|\)|\{|\}|\[|\]|;|,|\.|:)").unwrap();
     static ref RESERVED_WORD_REGEX: Regex = Regex::new(r"^(integer|float|void|class|isa|while|if|then|else|read|write|return|localvar|constructor|attribute|function|public|private)(\W|$)").unwrap();
     static ref WHITESPACE_REGEX: Regex = Regex::new(r"^(\s+)(\S|$)").unwrap();
+    static ref INLINE_COMMENT_REGEX: Regex = Regex::new(r"^(//[^\n]*)").unwrap();
+    static ref BLOCK_COMMENT_REGEX: Regex = Regex::new(r"^(/\*[\s\S]*?\*/)").unwrap();
 }
 
 
-#[derive(PartialEq, Eq, Debug)]
+#[derive(Eq, Debug)]
 pub struct Token {
     pub token_type: TokenType,
     pub lexeme: String,
+    // line (1-based) of the first character of the lexeme
+    pub line: usize,
+}
+
+// Tokens compare by type and lexeme only, the line is just location information.
+impl PartialEq for Token {
+    fn eq(&self, other: &Self) -> bool {
+        self.token_type == other.token_type && self.lexeme == other.lexeme
+    }
 }
 
 pub struct Scanner {
     pub source_text: String,
     pub current_location: usize,
+    // line (1-based) of the character at `current_location`
+    pub current_line: usize,
 }
 
 impl Token {
@@ -134,9 +148,18 @@ impl Token {
         return Self {
             token_type,
             lexeme,
+            line: 1,
         };
     }
 
+    pub fn at_line(token_type: TokenType, lexeme: String, line: usize) -> Self {
+        Self {
+            token_type,
+            lexeme,
+            line,
+        }
+    }
+
 
     pub fn to_calgary(&self) -> String {
         match self.token_type {
@@ -182,6 +205,12 @@ impl Token {
             TokenType::LessThanOrEq => String::from("leq"),
             TokenType::GreaterThanOrEq => String::from("geq"),
             TokenType::EndOfFile => String::from("eof"),
+            TokenType::ForwardSlash => String::from("div"),
+            TokenType::And => String::from("and"),
+            TokenType::Or => String::from("or"),
+            TokenType::Not => String::from("not"),
+            TokenType::Eq => String::from("eq"),
+            TokenType::NotEqual => String::from("neq"),
             _ => panic!("This token type has not been mapped")
         }
     }
@@ -192,6 +221,7 @@ impl Scanner {
         return Self {
             source_text: input_source,
             current_location: 0,
+            current_line: 1,
         };
     }
 
@@ -218,6 +248,7 @@ impl Scanner {
                 get_integer_token(concerned_slice.clone()),
                 get_punctuation_token(concerned_slice.clone()),
                 get_reserved_word_token(concerned_slice.clone()),
+                get_comment_token(concerned_slice.clone()),
             ];
             let token_candidates: Vec<Option<Token>> = token_candidates.into_iter().filter(|x| x.is_some()).collect();
             if token_candidates.is_empty() {
@@ -237,8 +268,11 @@ impl Scanner {
                     }
                 }
             }
-            self.current_location += longest_token.as_ref().unwrap().lexeme.len();
-            return longest_token;
+            let mut longest_token = longest_token.unwrap();
+            longest_token.line = self.current_line;
+            self.current_location += longest_token.lexeme.len();
+            self.current_line += longest_token.lexeme.matches('\n').count();
+            return Some(longest_token);
         }
     }
 
@@ -247,8 +281,8 @@ impl Scanner {
         loop {
             match self.next_token() {
                 Some(token) => {
-                    if token.token_type != TokenType::WhiteSpace {
-                        // ignore whitespaces
+                    if token.token_type != TokenType::WhiteSpace && token.token_type != TokenType::Comment {
+                        // ignore whitespaces and comments
                         ret.push(token)
                     }
                 }
@@ -262,7 +296,7 @@ impl Scanner {
 
 fn get_token_if_valid(lexeme_string: Option<String>, token_type: TokenType) -> Option<Token> {
     return if lexeme_string.is_some() {
-        Some(Token { token_type: token_type, lexeme: lexeme_string.unwrap() })
+        Some(Token::from(token_type, lexeme_string.unwrap()))
     } else {
         None
     }
@@ -302,20 +336,20 @@ pub fn get_operator_token(source_code_string: String) -> Option<Token> {
     } else {
         let operator_string = operator_token_string.unwrap();
         match operator_string.as_str() {
-            "==" => return Some(Token { token_type: TokenType::Eq, lexeme: operator_string }),
-            "<>" => return Some(Token { token_type: TokenType::NotEqual, lexeme: operator_string }),
-            "<=" => return Some(Token { token_type: TokenType::LessThanOrEq, lexeme: operator_string }),
-            ">=" => return Some(Token { token_type: TokenType::GreaterThanOrEq, lexeme: operator_string }),
-            ">" => return Some(Token { token_type: TokenType::GreaterThan, lexeme: operator_string }),
-            "<" => return Some(Token { token_type: TokenType::LessThan, lexeme: operator_string }),
-            "+" => return Some(Token { token_type: TokenType::Plus, lexeme: operator_string }),
-            "-" => return Some(Token { token_type: TokenType::Minus, lexeme: operator_string }),
-            "*" => return Some(Token { token_type: TokenType::Asterix, lexeme: operator_string }),
-            "/" => return Some(Token { token_type: TokenType::ForwardSlash, lexeme: operator_string }),
-            "=" => return Some(Token { token_type: TokenType::EqualsSymbol, lexeme: operator_string }),
-            "and" => return Some(Token { token_type: TokenType::And, lexeme: operator_string }),
-            "or" => return Some(Token { token_type: TokenType::Or, lexeme: operator_string }),
-            "not" => return Some(Token { token_type: TokenType::Not, lexeme: operator_string }),
+            "==" => return Some(Token::from(TokenType::Eq, operator_string)),
+            "<>" => return Some(Token::from(TokenType::NotEqual, operator_string)),
+            "<=" => return Some(Token::from(TokenType::LessThanOrEq, operator_string)),
+            ">=" => return Some(Token::from(TokenType::GreaterThanOrEq, operator_string)),
+            ">" => return Some(Token::from(TokenType::GreaterThan, operator_string)),
+            "<" => return Some(Token::from(TokenType::LessThan, operator_string)),
+            "+" => return Some(Token::from(TokenType::Plus, operator_string)),
+            "-" => return Some(Token::from(TokenType::Minus, operator_string)),
+            "*" => return Some(Token::from(TokenType::Asterix, operator_string)),
+            "/" => return Some(Token::from(TokenType::ForwardSlash, operator_string)),
+            "=" => return Some(Token::from(TokenType::EqualsSymbol, operator_string)),
+            "and" => return Some(Token::from(TokenType::And, operator_string)),
+            "or" => return Some(Token::from(TokenType::Or, operator_string)),
+            "not" => return Some(Token::from(TokenType::Not, operator_string)),
             _ => panic!("{} is not an operator", operator_string.as_str())
         }
     }
@@ -333,18 +367,18 @@ pub fn get_punctuation_token(source_code_string: String) -> Option<Token> {
     } else {
         let punctuation_string = punctuation_string.unwrap();
         match punctuation_string.as_str() {
-            "::" => return Some(Token { token_type: TokenType::Sr, lexeme: punctuation_string }),
-            "=>" => return Some(Token { token_type: TokenType::Arrow, lexeme: punctuation_string }),
-            "(" => return Some(Token { token_type: TokenType::OpenParenthesis, lexeme: punctuation_string }),
-            ")" => return Some(Token { token_type: TokenType::CloseParenthesis, lexeme: punctuation_string }),
-            "{" => return Some(Token { token_type: TokenType::OpenCurly, lexeme: punctuation_string }),
-            "}" => return Some(Token { token_type: TokenType::CloseCurly, lexeme: punctuation_string }),
-            "[" => return Some(Token { token_type: TokenType::OpenSquareBracket, lexeme: punctuation_string }),
-            "]" => return Some(Token { token_type: TokenType::CloseSquareBracket, lexeme: punctuation_string }),
-            ";" => return Some(Token { token_type: TokenType::SemiColon, lexeme: punctuation_string }),
-            "," => return Some(Token { token_type: TokenType::Comma, lexeme: punctuation_string }),
-            "." => return Some(Token { token_type: TokenType::Period, lexeme: punctuation_string }),
-            ":" => return Some(Token { token_type: TokenType::Colon, lexeme: punctuation_string }),
+            "::" => return Some(Token::from(TokenType::Sr, punctuation_string)),
+            "=>" => return Some(Token::from(TokenType::Arrow, punctuation_string)),
+            "(" => return Some(Token::from(TokenType::OpenParenthesis, punctuation_string)),
+            ")" => return Some(Token::from(TokenType::CloseParenthesis, punctuation_string)),
+            "{" => return Some(Token::from(TokenType::OpenCurly, punctuation_string)),
+            "}" => return Some(Token::from(TokenType::CloseCurly, punctuation_string)),
+            "[" => return Some(Token::from(TokenType::OpenSquareBracket, punctuation_string)),
+            "]" => return Some(Token::from(TokenType::CloseSquareBracket, punctuation_string)),
+            ";" => return Some(Token::from(TokenType::SemiColon, punctuation_string)),
+            "," => return Some(Token::from(TokenType::Comma, punctuation_string)),
+            "." => return Some(Token::from(TokenType::Period, punctuation_string)),
+            ":" => return Some(Token::from(TokenType::Colon, punctuation_string)),
             _ => panic!("{} is not a punctuation", punctuation_string.as_str())
         }
     }
@@ -363,24 +397,24 @@ pub fn get_reserved_word_token(source_code_string: String) -> Option<Token> {
     } else {
         let reserved_keyword_string = reserved_keyword_string.unwrap();
         match reserved_keyword_string.as_str() {
-            "integer" => return Some(Token { token_type: TokenType::IntegerKeyword, lexeme: reserved_keyword_string }),
-            "float" => return Some(Token { token_type: TokenType::FloatKeyword, lexeme: reserved_keyword_string }),
-            "void" => return Some(Token { token_type: TokenType::Void, lexeme: reserved_keyword_string }),
-            "class" => return Some(Token { token_type: TokenType::Class, lexeme: reserved_keyword_string }),
-            "isa" => return Some(Token { token_type: TokenType::IsA, lexeme: reserved_keyword_string }),
-            "while" => return Some(Token { token_type: TokenType::While, lexeme: reserved_keyword_string }),
-            "if" => return Some(Token { token_type: TokenType::If, lexeme: reserved_keyword_string }),
-            "then" => return Some(Token { token_type: TokenType::Then, lexeme: reserved_keyword_string }),
-            "else" => return Some(Token { token_type: TokenType::Else, lexeme: reserved_keyword_string }),
-            "read" => return Some(Token { token_type: TokenType::Read, lexeme: reserved_keyword_string }),
-            "write" => return Some(Token { token_type: TokenType::Write, lexeme: reserved_keyword_string }),
-            "return" => return Some(Token { token_type: TokenType::Return, lexeme: reserved_keyword_string }),
-            "localvar" => return Some(Token { token_type: TokenType::LocalVar, lexeme: reserved_keyword_string }),
-            "constructor" => return Some(Token { token_type: TokenType::Constructor, lexeme: reserved_keyword_string }),
-            "attribute" => return Some(Token { token_type: TokenType::Attribute, lexeme: reserved_keyword_string }),
-            "function" => return Some(Token { token_type: TokenType::Function, lexeme: reserved_keyword_string }),
-            "public" => return Some(Token { token_type: TokenType::Public, lexeme: reserved_keyword_string }),
-            "private" => return Some(Token { token_type: TokenType::Private, lexeme: reserved_keyword_string }),
+            "integer" => return Some(Token::from(TokenType::IntegerKeyword, reserved_keyword_string)),
+            "float" => return Some(Token::from(TokenType::FloatKeyword, reserved_keyword_string)),
+            "void" => return Some(Token::from(TokenType::Void, reserved_keyword_string)),
+            "class" => return Some(Token::from(TokenType::Class, reserved_keyword_string)),
+            "isa" => return Some(Token::from(TokenType::IsA, reserved_keyword_string)),
+            "while" => return Some(Token::from(TokenType::While, reserved_keyword_string)),
+            "if" => return Some(Token::from(TokenType::If, reserved_keyword_string)),
+            "then" => return Some(Token::from(TokenType::Then, reserved_keyword_string)),
+            "else" => return Some(Token::from(TokenType::Else, reserved_keyword_string)),
+            "read" => return Some(Token::from(TokenType::Read, reserved_keyword_string)),
+            "write" => return Some(Token::from(TokenType::Write, reserved_keyword_string)),
+            "return" => return Some(Token::from(TokenType::Return, reserved_keyword_string)),
+            "localvar" => return Some(Token::from(TokenType::LocalVar, reserved_keyword_string)),
+            "constructor" => return Some(Token::from(TokenType::Constructor, reserved_keyword_string)),
+            "attribute" => return Some(Token::from(TokenType::Attribute, reserved_keyword_string)),
+            "function" => return Some(Token::from(TokenType::Function, reserved_keyword_string)),
+            "public" => return Some(Token::from(TokenType::Public, reserved_keyword_string)),
+            "private" => return Some(Token::from(TokenType::Private, reserved_keyword_string)),
             _ => panic!("{} is not a reserved keyword", reserved_keyword_string.as_str())
         }
     }
@@ -401,6 +435,15 @@ pub fn get_whitespaces_string(source_code_string: String) -> Option<String> {
     return get_string_from_captures(captures);
 }
 
+pub fn get_comment_token(source_code_string: String) -> Option<Token> {
+    get_token_if_valid(get_comment_string(source_code_string), TokenType::Comment)
+}
+
+pub fn get_comment_string(source_code_string: String) -> Option<String> {
+    let inline_comment = get_string_from_captures(INLINE_COMMENT_REGEX.captures(&source_code_string));
+    inline_comment.or_else(|| get_string_from_captures(BLOCK_COMMENT_REGEX.captures(&source_code_string)))
+}
+
 pub fn get_token_using_regex(regex: String, source_code_string: String) -> Option<String> {
     let compiled_regex_obj = Regex::new(&regex).unwrap();
     let captures = compiled_regex_obj.captures(&source_code_string);
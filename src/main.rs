@@ -1,6 +1,8 @@
 #![allow(dead_code)]
 
 mod lexical_analysis;
+mod ast;
+mod ast_actions;
 mod synthesis;
 mod tests_lexical_analysis;
 mod semantic_analysis;
@@ -8,6 +10,49 @@ mod syntactic_analysis;
 mod tests_syntactic_analysis;
 mod semantic_graph;
 mod tests_semantic_analysis;
+mod tests_ast;
+mod visitor;
+mod passes;
+mod tests_passes;
+mod symbol_table;
+mod tests_symbol_table;
+mod semantic_checks;
+mod tests_semantic_checks;
+mod types;
+mod tests_types;
+mod type_checker;
+mod tests_type_checker;
+mod inheritance;
+mod tests_inheritance;
+mod memory_layout;
+mod tests_memory_layout;
+mod ir;
+mod tests_ir;
+mod moon_codegen;
+mod tests_moon_codegen;
+mod moon_vm;
+mod tests_moon_vm;
+mod interpreter;
+mod tests_interpreter;
+mod vtables;
+mod tests_vtables;
+mod register_allocation;
+mod tests_register_allocation;
+mod optimization;
+mod tests_optimization;
+mod cfg;
+mod tests_cfg;
+mod data_flow;
+mod tests_data_flow;
+mod ssa;
+mod tests_ssa;
+mod c_codegen;
+mod tests_c_codegen;
+mod x86_64_codegen;
+mod tests_x86_64_codegen;
+mod wasm_codegen;
+mod tests_wasm_codegen;
+mod test_support;
 
 use lexical_analysis::read_source_file;
 use lexical_analysis::Scanner;
@@ -16,6 +61,19 @@ use std::io::prelude::*;
 use crate::lexical_analysis::Token;
 use std::env;
 use regex::{Captures, Regex};
+use std::path::Path;
+use crate::passes::{PassContext, PassManager};
+use crate::moon_codegen::generate_moon;
+use crate::moon_vm::profile_moon;
+use crate::optimization::{OptimizationPass, Optimizations};
+use crate::cfg::ControlFlowGraph;
+use crate::data_flow::DefiniteAssignmentPass;
+use crate::ssa::to_ssa;
+use crate::c_codegen::generate_c;
+use crate::x86_64_codegen::generate_x86_64;
+use crate::wasm_codegen::generate_wat;
+use crate::interpreter::Interpreter;
+use crate::syntactic_analysis::parse_source;
 
 
 fn write_tokens_to_file(tokens: Vec<Token>, output_file_path: String) -> std::io::Result<()>  {
@@ -27,8 +85,179 @@ fn write_tokens_to_file(tokens: Vec<Token>, output_file_path: String) -> std::io
     Ok(())
 }
 
+fn write_symbol_tables_to_file(context: &PassContext, output_file_path: &Path) -> std::io::Result<()> {
+    let mut output_file = File::create(output_file_path)?;
+    if let Some(symbol_table) = &context.symbol_table {
+        output_file.write_all(symbol_table.as_string().as_bytes())?;
+    }
+    Ok(())
+}
+
+// Errors and warnings of every pass, ordered by line.
+fn write_diagnostics_to_file(context: &PassContext, output_file_path: &Path) -> std::io::Result<()> {
+    let mut output_file = File::create(output_file_path)?;
+    let mut diagnostics = context.diagnostics.clone();
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span.line);
+    for diagnostic in diagnostics {
+        output_file.write_all(format!("{}\n", diagnostic.as_string()).as_bytes())?;
+    }
+    Ok(())
+}
+
+fn write_ir_to_file(context: &PassContext, output_file_path: &Path) -> std::io::Result<()> {
+    let mut output_file = File::create(output_file_path)?;
+    if let Some(ir) = &context.ir {
+        output_file.write_all(ir.as_string().as_bytes())?;
+    }
+    Ok(())
+}
+
+// Every function in SSA form, with its phi nodes.
+fn write_ssa_to_file(context: &PassContext, output_file_path: &Path) -> std::io::Result<()> {
+    let mut output_file = File::create(output_file_path)?;
+    if let Some(ir) = &context.ir {
+        let functions: Vec<String> = ir.functions.iter().map(|function| to_ssa(function).as_string()).collect();
+        output_file.write_all(functions.join("\n").as_bytes())?;
+    }
+    Ok(())
+}
+
+// One DOT file per function, named after the source file and the function.
+fn write_cfgs_to_files(context: &PassContext, source_path: &Path) -> std::io::Result<()> {
+    if let Some(ir) = &context.ir {
+        for function in &ir.functions {
+            let cfg_path = source_path.with_extension(format!("{}.dot", function.name));
+            ControlFlowGraph::build(function).render(&mut File::create(&cfg_path)?)?;
+            println!("successfully generated {}", cfg_path.display());
+        }
+    }
+    Ok(())
+}
+
+// Runs every pass over the source file and writes the outputs next to it.
+fn compile_source_file(source_file_path: String, optimizations: Optimizations) -> std::io::Result<()> {
+    let source_path = Path::new(&source_file_path).to_path_buf();
+    let mut program = parse_source(read_source_file(source_file_path));
+    let mut pass_manager = PassManager::code_generation();
+    pass_manager.add_pass(Box::new(DefiniteAssignmentPass));
+    // the Moon code is generated from the same IR
+    pass_manager.add_pass(Box::new(OptimizationPass { optimizations: optimizations.for_moon() }));
+    let context = pass_manager.run(&mut program);
+    let symbol_tables_path = source_path.with_extension("outsymboltables");
+    write_symbol_tables_to_file(&context, &symbol_tables_path)?;
+    println!("successfully generated {}", symbol_tables_path.display());
+    let semantic_errors_path = source_path.with_extension("outsemanticerrors");
+    write_diagnostics_to_file(&context, &semantic_errors_path)?;
+    println!("successfully generated {}", semantic_errors_path.display());
+    let ir_path = source_path.with_extension("outir");
+    write_ir_to_file(&context, &ir_path)?;
+    println!("successfully generated {}", ir_path.display());
+    let ssa_path = source_path.with_extension("outssa");
+    write_ssa_to_file(&context, &ssa_path)?;
+    println!("successfully generated {}", ssa_path.display());
+    write_cfgs_to_files(&context, &source_path)?;
+    if let (false, Some(ir)) = (context.has_errors(), &context.ir) {
+        let moon_path = source_path.with_extension("m");
+        File::create(&moon_path)?.write_all(generate_moon(ir, true).as_bytes())?;
+        println!("successfully generated {}", moon_path.display());
+        let c_path = source_path.with_extension("c");
+        File::create(&c_path)?.write_all(generate_c(&program, context.symbol_table()).as_bytes())?;
+        println!("successfully generated {}", c_path.display());
+        let x86_64_path = source_path.with_extension("s");
+        File::create(&x86_64_path)?.write_all(generate_x86_64(ir).as_bytes())?;
+        println!("successfully generated {}", x86_64_path.display());
+        let wat_path = source_path.with_extension("wat");
+        File::create(&wat_path)?.write_all(generate_wat(ir).as_bytes())?;
+        println!("successfully generated {}", wat_path.display());
+    }
+    Ok(())
+}
+
+// Checks the source file and runs it with the interpreter on stdin and stdout.
+fn run_source_file(source_file_path: String) -> std::io::Result<()> {
+    let mut program = parse_source(read_source_file(source_file_path));
+    let mut pass_manager = PassManager::front_end();
+    let context = pass_manager.run(&mut program);
+    if context.has_errors() {
+        for diagnostic in context.errors() {
+            eprintln!("{}", diagnostic.as_string());
+        }
+        std::process::exit(1);
+    }
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    let mut interpreter = Interpreter::new(&program, context.symbol_table(), stdin.lock(), stdout.lock());
+    if let Err(diagnostic) = interpreter.run() {
+        eprintln!("runtime {}", diagnostic.as_string());
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+// Runs the Moon code generated for the source file with and without register
+// allocation on stdin, and reports the size and execution time of both.
+fn compare_source_file(source_file_path: String, optimizations: Optimizations) -> std::io::Result<()> {
+    let mut program = parse_source(read_source_file(source_file_path));
+    let mut pass_manager = PassManager::code_generation();
+    pass_manager.add_pass(Box::new(OptimizationPass { optimizations: optimizations.for_moon() }));
+    let context = pass_manager.run(&mut program);
+    if context.has_errors() {
+        for diagnostic in context.errors() {
+            eprintln!("{}", diagnostic.as_string());
+        }
+        std::process::exit(1);
+    }
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input)?;
+    let ir = context.ir.as_ref().unwrap();
+    println!("{:<20} {:>12} {:>12} {:>12}", "", "instructions", "executed", "cycles");
+    let mut outputs = vec![];
+    for (name, allocate) in [("without allocation", false), ("with allocation", true)] {
+        match profile_moon(&generate_moon(ir, allocate), &input) {
+            Ok(profile) => {
+                println!("{:<20} {:>12} {:>12} {:>12}", name, profile.instructions, profile.steps, profile.cycles);
+                outputs.push(profile.output);
+            }
+            Err(message) => {
+                eprintln!("runtime error {}: {}", name, message);
+                std::process::exit(1);
+            }
+        }
+    }
+    if outputs[0] != outputs[1] {
+        eprintln!("the outputs differ");
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
 fn main() -> std::io::Result<()> {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+    // `-O0` (the default) to `-O2` select the IR optimizations
+    let mut optimizations = Optimizations::level(0);
+    if let Some(position) = args.iter().position(|arg| arg.starts_with("-O")) {
+        let flag = args.remove(position);
+        let level = match flag.as_str() {
+            "-O0" => 0,
+            "-O1" => 1,
+            "-O2" => 2,
+            _ => {
+                eprintln!("invalid optimization level '{}', expected -O0, -O1 or -O2", flag);
+                eprintln!("usage: compiler [-O0|-O1|-O2] [run|compare] file.src");
+                std::process::exit(1);
+            }
+        };
+        optimizations = Optimizations::level(level);
+    }
+    if args.len() == 3 && args[1] == "run" {
+        return run_source_file(args[2].clone());
+    }
+    if args.len() == 3 && args[1] == "compare" {
+        return compare_source_file(args[2].clone(), optimizations);
+    }
+    if args.len() == 2 {
+        return compile_source_file(args[1].clone(), optimizations);
+    }
     assert_eq!(args.len(), 3, "missing arguments");
     let source_file_path = args[1].clone();
     let output_tokens_file_path = args[2].clone();
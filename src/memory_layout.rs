@@ -0,0 +1,195 @@
+// Sizes and offsets for code generation. Objects lay out the attributes of their
+// parents first, in `isa` order, then their own. Every object starts with the
+// address of its virtual function table, hidden in the part of its first parent
+// if it has one, so the part of every ancestor starts with one too. A stack
+// frame holds, from offset 0: the return address, the return value (if any),
+// `self` for member functions and constructors, the parameters, the local
+// variables and then the temporaries added by code generation. Arrays and
+// objects are passed by address, and a function returning an object gets the
+// address of one provided by the caller in its return value slot.
+
+use std::collections::HashMap;
+use crate::ast::*;
+use crate::passes::{Pass, PassContext};
+use crate::symbol_table::*;
+use crate::types::*;
+
+pub struct ClassSizes {
+    sizes: HashMap<String, usize>,
+}
+
+impl ClassSizes {
+    pub fn new(global: &SymbolTable) -> Self {
+        let mut class_sizes = ClassSizes { sizes: HashMap::new() };
+        for entry in global.entries.iter().filter(|entry| entry.kind == SymbolKind::Class) {
+            class_sizes.compute(global, &entry.name, &mut vec![]);
+        }
+        class_sizes
+    }
+
+    // `pending` guards against the cycles reported by the inheritance pass.
+    fn compute(&mut self, global: &SymbolTable, class_name: &str, pending: &mut Vec<String>) -> usize {
+        if let Some(size) = self.sizes.get(class_name) {
+            return *size;
+        }
+        if pending.iter().any(|name| name == class_name) {
+            return 0;
+        }
+        let class_table = match global.class_table(class_name) {
+            Some(class_table) => class_table,
+            None => return 0,
+        };
+        pending.push(class_name.to_string());
+        let mut size = vtable_pointer_size(class_table);
+        for entry in &class_table.entries {
+            size += match entry.kind {
+                SymbolKind::Inherit => self.compute(global, &entry.name, pending),
+                SymbolKind::Attribute => {
+                    let element_size = self.element_size(global, &entry.type_name, pending);
+                    Type::of_entry(entry).size(element_size).unwrap_or(0)
+                }
+                _ => 0,
+            };
+        }
+        pending.pop();
+        self.sizes.insert(class_name.to_string(), size);
+        size
+    }
+
+    fn element_size(&mut self, global: &SymbolTable, type_name: &TypeName, pending: &mut Vec<String>) -> usize {
+        match type_name {
+            TypeName::Integer => INTEGER_SIZE,
+            TypeName::Float => FLOAT_SIZE,
+            TypeName::Void => 0,
+            TypeName::Class(class_name) => self.compute(global, class_name, pending),
+        }
+    }
+
+    pub fn type_name_size(&self, type_name: &TypeName) -> usize {
+        match type_name {
+            TypeName::Integer => INTEGER_SIZE,
+            TypeName::Float => FLOAT_SIZE,
+            TypeName::Void => 0,
+            TypeName::Class(class_name) => self.sizes.get(class_name).copied().unwrap_or(0),
+        }
+    }
+
+    // Size of a variable of type `var_type`; unsized arrays only exist as parameters.
+    pub fn type_size(&self, var_type: &Type) -> usize {
+        var_type.size(self.type_name_size(&var_type.type_name)).unwrap_or(ADDRESS_SIZE)
+    }
+
+    // Size of the slot holding a parameter of type `param_type`.
+    pub fn param_size(&self, param_type: &Type) -> usize {
+        match param_type.type_name {
+            _ if param_type.is_array() => ADDRESS_SIZE,
+            TypeName::Class(_) => ADDRESS_SIZE,
+            _ => self.type_size(param_type),
+        }
+    }
+}
+
+// Offset of the `ancestor` part in an object of class `class_name`, following the
+// first path in `isa` order like `SymbolTable::find_member`.
+pub fn base_offset(global: &SymbolTable, class_name: &str, ancestor: &str) -> Option<usize> {
+    if class_name == ancestor {
+        return Some(0);
+    }
+    global.class_table(class_name)?.entries.iter()
+        .filter(|entry| entry.kind == SymbolKind::Inherit)
+        .find_map(|parent| Some(parent.offset? + base_offset(global, &parent.name, ancestor)?))
+}
+
+// Offset of the data member `name`, declared or inherited, in an object of class
+// `class_name`.
+pub fn attribute_offset(global: &SymbolTable, class_name: &str, name: &str) -> Option<usize> {
+    let (owner, entries) = global.find_member(class_name, name)?;
+    let attribute = entries.into_iter().find(|entry| entry.kind == SymbolKind::Attribute)?;
+    Some(base_offset(global, class_name, &owner)? + attribute.offset?)
+}
+
+// Classes without parents start with the address of their virtual function table.
+fn vtable_pointer_size(class_table: &SymbolTable) -> usize {
+    match class_table.entries.iter().any(|entry| entry.kind == SymbolKind::Inherit) {
+        true => 0,
+        false => ADDRESS_SIZE,
+    }
+}
+
+fn lay_out_class(class_table: &mut SymbolTable, class_sizes: &ClassSizes) {
+    let mut offset = vtable_pointer_size(class_table);
+    for entry in class_table.entries.iter_mut() {
+        entry.size = match entry.kind {
+            SymbolKind::Inherit => class_sizes.type_name_size(&entry.type_name),
+            SymbolKind::Attribute => class_sizes.type_size(&Type::of_entry(entry)),
+            _ => continue,
+        };
+        entry.offset = Some(offset);
+        offset += entry.size;
+    }
+    class_table.size = Some(offset);
+}
+
+fn lay_out_frame(function_table: &mut SymbolTable, class_name: Option<&str>, return_type: &TypeName, class_sizes: &ClassSizes) {
+    let declared = std::mem::take(&mut function_table.entries);
+    function_table.size = Some(0);
+    function_table.add_slot("return".to_string(), SymbolKind::ReturnAddress, TypeName::Integer, ADDRESS_SIZE);
+    if *return_type != TypeName::Void {
+        let size = match return_type {
+            TypeName::Class(_) => ADDRESS_SIZE,
+            _ => class_sizes.type_name_size(return_type),
+        };
+        function_table.add_slot("value".to_string(), SymbolKind::ReturnValue, return_type.clone(), size);
+    }
+    if let Some(class_name) = class_name {
+        function_table.add_slot("self".to_string(), SymbolKind::Parameter, TypeName::Class(class_name.to_string()), ADDRESS_SIZE);
+    }
+    for mut entry in declared {
+        let var_type = Type::of_entry(&entry);
+        entry.size = match entry.kind {
+            SymbolKind::Parameter => class_sizes.param_size(&var_type),
+            _ => class_sizes.type_size(&var_type),
+        };
+        let offset = function_table.size.unwrap();
+        entry.offset = Some(offset);
+        function_table.size = Some(offset + entry.size);
+        function_table.entries.push(entry);
+    }
+}
+
+pub fn compute_memory_layout(global: &mut SymbolTable) {
+    let class_sizes = ClassSizes::new(global);
+    for entry in global.entries.iter_mut() {
+        let link = match entry.link.as_mut() {
+            Some(link) => link,
+            None => continue,
+        };
+        match entry.kind {
+            SymbolKind::Class => {
+                lay_out_class(link, &class_sizes);
+                for member in link.entries.iter_mut().filter(|member| member.kind.is_function()) {
+                    if let Some(member_table) = member.link.as_mut() {
+                        lay_out_frame(member_table, Some(&entry.name), &member.type_name, &class_sizes);
+                        member.size = member_table.size.unwrap();
+                    }
+                }
+            }
+            SymbolKind::Function => lay_out_frame(link, None, &entry.type_name, &class_sizes),
+            _ => continue,
+        }
+        entry.size = link.size.unwrap();
+    }
+}
+
+pub struct MemoryLayoutPass;
+
+impl Pass for MemoryLayoutPass {
+    fn name(&self) -> String {
+        "memory-layout".to_string()
+    }
+
+    fn run(&mut self, _program: &mut Program, context: &mut PassContext) {
+        let global = context.symbol_table.as_mut().expect("symbol table pass should run first");
+        compute_memory_layout(global);
+    }
+}
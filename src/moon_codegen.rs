@@ -0,0 +1,610 @@
+// Moon assembly back end. Every variable and temporary lives in its slot of the
+// function's frame, whose layout comes from the memory layout pass: each IR
+// instruction loads its operands into scratch registers, computes, and stores the
+// result back. `r14` points to the base of the current frame, and frames are
+// stacked upwards from the `stack` label at the end of the program. A call stores
+// the arguments in the parameter slots of the callee's frame, which starts right
+// after the caller's, and reads the result from its return value slot. `read` and
+// `write` call the `rtgetint` and `rtputint` routines emitted with the program.
+// Virtual function tables are emitted as data, two words per slot: the address
+// of the function and the adjustment of `self`.
+//
+// Moon has no floating point instructions, so floats are 16.16 fixed-point
+// numbers kept in the first word of their 8-byte slot: addition, subtraction and
+// comparisons are the integer instructions, while multiplication, division and
+// float input and output call the `rtfmul`, `rtfdiv`, `rtgetflt` and `rtputflt`
+// routines. Their range is about -32768 to 32767, with a precision of 1/65536.
+
+use crate::ast::{BinaryOp, UnaryOp};
+use crate::ir::*;
+use crate::register_allocation::*;
+use crate::symbol_table::SymbolKind;
+
+// r0 is always 0 and r1 to r7 are scratch registers, for the generated code and
+// the runtime routines. The frame pointer is also the stack pointer, since the
+// frame of a callee starts right after the caller's.
+pub const FRAME_POINTER: &str = "r14";
+pub const RETURN_ADDRESS: &str = "r15";
+pub const ALLOCATABLE_REGISTERS: [&str; 6] = ["r8", "r9", "r10", "r11", "r12", "r13"];
+
+// Moon immediate operands are 16-bit signed values.
+fn fits_immediate(value: i64) -> bool {
+    (-32768..=32767).contains(&value)
+}
+
+// 16.16 fixed-point representation of a float, saturated to the word range.
+pub fn fixed_point(value: f64) -> i32 {
+    (value * 65536.0).round() as i32
+}
+
+// Assembly text with labels in the first column, as Moon expects.
+#[derive(Default)]
+pub struct MoonWriter {
+    lines: Vec<String>,
+    pending_label: Option<String>,
+}
+
+impl MoonWriter {
+    pub fn label(&mut self, label: &str) {
+        if let Some(pending_label) = self.pending_label.take() {
+            self.lines.push(format!("{:<9} nop", pending_label));
+        }
+        self.pending_label = Some(label.to_string());
+    }
+
+    pub fn instr(&mut self, text: &str) {
+        let label = self.pending_label.take().unwrap_or_default();
+        self.lines.push(format!("{:<9} {}", label, text));
+    }
+
+    pub fn comment(&mut self, text: &str) {
+        self.lines.push(format!("{:<9} % {}", "", text));
+    }
+
+    pub fn load_constant(&mut self, register: &str, value: i64) {
+        if fits_immediate(value) {
+            self.instr(&format!("addi {}, r0, {}", register, value));
+            return;
+        }
+        let value = value as i32;
+        self.instr(&format!("addi {}, r0, {}", register, value >> 16));
+        self.instr(&format!("sl {}, 16", register));
+        self.instr(&format!("ori {}, {}, {}", register, register, value & 0xffff));
+    }
+
+    pub fn text(mut self) -> String {
+        if let Some(pending_label) = self.pending_label.take() {
+            self.lines.push(format!("{:<9} nop", pending_label));
+        }
+        let mut text = self.lines.join("\n");
+        text.push('\n');
+        text
+    }
+}
+
+fn int_opcode(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Add => "add",
+        BinaryOp::Sub => "sub",
+        BinaryOp::Mul => "mul",
+        BinaryOp::Div => "div",
+        BinaryOp::And => "and",
+        BinaryOp::Or => "or",
+        BinaryOp::Eq => "ceq",
+        BinaryOp::Neq => "cne",
+        BinaryOp::Lt => "clt",
+        BinaryOp::Gt => "cgt",
+        BinaryOp::Leq => "cle",
+        BinaryOp::Geq => "cge",
+    }
+}
+
+struct FunctionCodegen<'a> {
+    program: &'a IrProgram,
+    function: &'a IrFunction,
+    allocation: Allocation,
+    writer: &'a mut MoonWriter,
+}
+
+impl<'a> FunctionCodegen<'a> {
+    fn label(&self, label: &str) -> String {
+        format!("{}_{}", self.function.name, label)
+    }
+
+    fn frame_size(&self) -> usize {
+        self.function.frame.size.unwrap()
+    }
+
+    fn slot(&self, operand: &Operand) -> usize {
+        let entry = match operand {
+            Operand::Var(name) => self.function.variable(name),
+            Operand::Temp(number) => self.function.temporary(*number),
+            _ => None,
+        };
+        entry.and_then(|entry| entry.offset)
+            .unwrap_or_else(|| panic!("{} has no slot in {}", operand.as_string(), self.function.name))
+    }
+
+    fn load(&mut self, register: &str, operand: &Operand) {
+        match operand {
+            Operand::Int(value) => self.writer.load_constant(register, *value),
+            Operand::Float(value) => self.writer.load_constant(register, fixed_point(*value) as i64),
+            _ => match self.allocation.register(operand) {
+                Some(own_register) => self.writer.instr(&format!("add {}, {}, r0", register, own_register)),
+                None => {
+                    let slot = self.slot(operand);
+                    self.writer.instr(&format!("lw {}, {}({})", register, slot, FRAME_POINTER));
+                }
+            },
+        }
+    }
+
+    // Register holding the value of `operand`: its own, or `scratch` once loaded.
+    fn operand(&mut self, scratch: &str, operand: &Operand) -> String {
+        match self.allocation.register(operand) {
+            Some(register) => register.to_string(),
+            None => {
+                self.load(scratch, operand);
+                scratch.to_string()
+            }
+        }
+    }
+
+    // Register to compute `dest` in: its own, or `scratch` to be stored.
+    fn dest_register(&self, dest: &Operand, scratch: &str) -> String {
+        self.allocation.register(dest).unwrap_or(scratch).to_string()
+    }
+
+    fn store(&mut self, dest: &Operand, register: &str) {
+        match self.allocation.register(dest) {
+            Some(own_register) if own_register == register => {}
+            Some(own_register) => {
+                let own_register = own_register.to_string();
+                self.writer.instr(&format!("add {}, {}, r0", own_register, register));
+            }
+            None => {
+                let slot = self.slot(dest);
+                self.writer.instr(&format!("sw {}({}), {}", slot, FRAME_POINTER, register));
+            }
+        }
+    }
+
+    fn generate(&mut self) {
+        self.writer.label(&self.function.name);
+        self.writer.instr(&format!("sw 0({}), {}", FRAME_POINTER, RETURN_ADDRESS));
+        for (position, instr) in self.function.body.iter().enumerate() {
+            if !matches!(instr, Instr::Label(_)) {
+                self.writer.comment(&instr.as_string());
+            }
+            self.generate_instr(position, instr);
+        }
+    }
+
+    fn generate_instr(&mut self, position: usize, instr: &Instr) {
+        match instr {
+            Instr::Label(label) => {
+                let label = self.label(label);
+                self.writer.label(&label);
+            }
+            Instr::Copy { dest, src } => {
+                let dest_register = self.dest_register(dest, "r1");
+                self.load(&dest_register, src);
+                self.store(dest, &dest_register);
+            }
+            Instr::Binary { dest, op, ty, lhs, rhs } => {
+                let routine = match (ty, op) {
+                    (IrType::Float, BinaryOp::Mul) => Some("rtfmul"),
+                    (IrType::Float, BinaryOp::Div) => Some("rtfdiv"),
+                    _ => None,
+                };
+                if let Some(routine) = routine {
+                    self.load("r1", lhs);
+                    self.load("r2", rhs);
+                    self.writer.instr(&format!("jl {}, {}", RETURN_ADDRESS, routine));
+                    self.store(dest, "r1");
+                    return;
+                }
+                let mut lhs = self.operand("r1", lhs);
+                let mut rhs = self.operand("r2", rhs);
+                if op.is_logical() {
+                    self.writer.instr(&format!("cne r1, {}, r0", lhs));
+                    self.writer.instr(&format!("cne r2, {}, r0", rhs));
+                    (lhs, rhs) = ("r1".to_string(), "r2".to_string());
+                }
+                let dest_register = self.dest_register(dest, "r3");
+                self.writer.instr(&format!("{} {}, {}, {}", int_opcode(*op), dest_register, lhs, rhs));
+                self.store(dest, &dest_register);
+            }
+            Instr::Unary { dest, op, operand, .. } => {
+                let operand = self.operand("r1", operand);
+                let dest_register = self.dest_register(dest, "r1");
+                match op {
+                    UnaryOp::Not => self.writer.instr(&format!("ceq {}, {}, r0", dest_register, operand)),
+                    _ => self.writer.instr(&format!("sub {}, r0, {}", dest_register, operand)),
+                }
+                self.store(dest, &dest_register);
+            }
+            Instr::IntToFloat { dest, src } => {
+                let dest_register = self.dest_register(dest, "r1");
+                self.load(&dest_register, src);
+                self.writer.instr(&format!("sl {}, 16", dest_register));
+                self.store(dest, &dest_register);
+            }
+            Instr::AddressOf { dest, var } => {
+                let slot = self.slot(&Operand::Var(var.clone()));
+                let dest_register = self.dest_register(dest, "r1");
+                self.writer.instr(&format!("addi {}, {}, {}", dest_register, FRAME_POINTER, slot));
+                self.store(dest, &dest_register);
+            }
+            Instr::Load { dest, address, offset, .. } => {
+                let address = self.operand("r1", address);
+                let dest_register = self.dest_register(dest, "r2");
+                self.writer.instr(&format!("lw {}, {}({})", dest_register, offset, address));
+                self.store(dest, &dest_register);
+            }
+            Instr::Store { address, offset, value, .. } => {
+                let address = self.operand("r1", address);
+                let value = self.operand("r2", value);
+                self.writer.instr(&format!("sw {}({}), {}", offset, address, value));
+            }
+            Instr::Jump(label) => {
+                let label = self.label(label);
+                self.writer.instr(&format!("j {}", label));
+            }
+            Instr::Branch { condition, if_true, if_false } => {
+                let condition = self.operand("r1", condition);
+                let (if_true, if_false) = (self.label(if_true), self.label(if_false));
+                self.writer.instr(&format!("bz {}, {}", condition, if_false));
+                self.writer.instr(&format!("j {}", if_true));
+            }
+            Instr::Call { dest, function, args } => self.generate_call(position, dest.as_ref(), function, None, args),
+            Instr::CallVirtual { dest, function, slot, args } => self.generate_call(position, dest.as_ref(), function, Some(*slot), args),
+            Instr::VtableAddress { dest, vtable } => {
+                let dest_register = self.dest_register(dest, "r1");
+                self.writer.instr(&format!("addi {}, r0, {}", dest_register, vtable));
+                self.store(dest, &dest_register);
+            }
+            Instr::Return(value) => {
+                if let Some(value) = value {
+                    let value = self.operand("r1", value);
+                    let slot = self.function.frame.lookup_kind("value", SymbolKind::ReturnValue).unwrap().offset.unwrap();
+                    self.writer.instr(&format!("sw {}({}), {}", slot, FRAME_POINTER, value));
+                }
+                self.writer.instr(&format!("lw {}, 0({})", RETURN_ADDRESS, FRAME_POINTER));
+                self.writer.instr(&format!("jr {}", RETURN_ADDRESS));
+            }
+            Instr::Read { dest, ty } => {
+                let routine = match ty {
+                    IrType::Int => "rtgetint",
+                    IrType::Float => "rtgetflt",
+                };
+                self.writer.instr(&format!("jl {}, {}", RETURN_ADDRESS, routine));
+                self.store(dest, "r1");
+            }
+            Instr::Write { value, ty } => {
+                let routine = match ty {
+                    IrType::Int => "rtputint",
+                    IrType::Float => "rtputflt",
+                };
+                self.load("r1", value);
+                self.writer.instr(&format!("jl {}, {}", RETURN_ADDRESS, routine));
+            }
+        }
+    }
+
+    // With a `slot`, `name` only gives the layout of the callee's frame, and the
+    // function is taken from the table of the object passed first. Registers
+    // live across the call are saved in the slots of their temporaries.
+    fn generate_call(&mut self, position: usize, dest: Option<&Operand>, name: &str, slot: Option<usize>, args: &[Operand]) {
+        let callee = self.program.function(name).unwrap_or_else(|| panic!("call to unknown function {}", name));
+        let frame_size = self.frame_size();
+        let saved: Vec<(usize, String)> = self.allocation.live_across(position).into_iter()
+            .map(|(temp, register)| (temp, register.to_string()))
+            .collect();
+        for (temp, register) in &saved {
+            let slot = self.slot(&Operand::Temp(*temp));
+            self.writer.instr(&format!("sw {}({}), {}", slot, FRAME_POINTER, register));
+        }
+        for (param, arg) in callee.params.iter().zip(args) {
+            let offset = callee.variable(param).unwrap().offset.unwrap();
+            self.load("r1", arg);
+            if let (Some(slot), "self") = (slot, param.as_str()) {
+                self.writer.instr("lw r4, 0(r1)");
+                self.writer.instr(&format!("lw r2, {}(r4)", 8 * slot + 4));
+                self.writer.instr("add r1, r1, r2");
+                self.writer.instr(&format!("lw r4, {}(r4)", 8 * slot));
+            }
+            self.writer.instr(&format!("sw {}({}), r1", frame_size + offset, FRAME_POINTER));
+        }
+        self.writer.instr(&format!("addi {}, {}, {}", FRAME_POINTER, FRAME_POINTER, frame_size));
+        match slot {
+            Some(_) => self.writer.instr(&format!("jlr {}, r4", RETURN_ADDRESS)),
+            None => self.writer.instr(&format!("jl {}, {}", RETURN_ADDRESS, name)),
+        }
+        self.writer.instr(&format!("subi {}, {}, {}", FRAME_POINTER, FRAME_POINTER, frame_size));
+        for (temp, register) in &saved {
+            let slot = self.slot(&Operand::Temp(*temp));
+            self.writer.instr(&format!("lw {}, {}({})", register, slot, FRAME_POINTER));
+        }
+        if let Some(dest) = dest {
+            let slot = callee.frame.lookup_kind("value", SymbolKind::ReturnValue).unwrap().offset.unwrap();
+            self.writer.instr(&format!("lw r1, {}({})", frame_size + slot, FRAME_POINTER));
+            self.store(dest, "r1");
+        }
+    }
+}
+
+// `rtputint` writes r1 and a newline, `rtgetint` reads an integer into r1, or 0
+// at the end of the input (`getc` gives 0), leaving the character after it in
+// r2 and 1 in r3 if it was negative. `rtputflt` and `rtgetflt` do the same for
+// fixed-point floats, written with two decimals rounded half to even like the
+// interpreter, and read with an optional exponent. `rtfmul` and `rtfdiv` compute r1 * r2
+// and r1 / r2 into r1. The routines only use r1 to r7.
+const RUNTIME: &str = "\
+          % runtime: integer output and input
+rtputint  add r2, r1, r0
+          cge r4, r2, r0
+          bnz r4, rtputint2
+          addi r4, r0, 45
+          putc r4
+          % the last digit apart, since -2147483648 cannot be negated
+          modi r6, r2, 10
+          sub r6, r0, r6
+          divi r2, r2, 10
+          sub r2, r0, r2
+          bz r2, rtputint1
+          jl r5, rtdigits
+rtputint1 addi r6, r6, 48
+          putc r6
+          j rtputint3
+rtputint2 jl r5, rtdigits
+rtputint3 addi r4, r0, 10
+          putc r4
+          jr r15
+          % writes the digits of r2 >= 0 and returns to r5
+rtdigits  addi r3, r0, 0
+rtdigits1 modi r4, r2, 10
+          addi r4, r4, 48
+          sw rtbuffer(r3), r4
+          addi r3, r3, 4
+          divi r2, r2, 10
+          bnz r2, rtdigits1
+rtdigits2 subi r3, r3, 4
+          lw r4, rtbuffer(r3)
+          putc r4
+          bnz r3, rtdigits2
+          jr r5
+rtgetint  addi r1, r0, 0
+          addi r3, r0, 0
+rtgetint1 getc r2
+          bz r2, rtgetint3
+          clei r4, r2, 32
+          bnz r4, rtgetint1
+          cnei r4, r2, 45
+          bnz r4, rtgetint2
+          addi r3, r0, 1
+          getc r2
+rtgetint2 clti r4, r2, 48
+          bnz r4, rtgetint3
+          cgti r4, r2, 57
+          bnz r4, rtgetint3
+          muli r1, r1, 10
+          subi r2, r2, 48
+          add r1, r1, r2
+          getc r2
+          j rtgetint2
+rtgetint3 bz r3, rtgetint4
+          sub r1, r0, r1
+rtgetint4 jr r15
+rtbuffer  res 48
+          % runtime: fixed-point floats
+rtfmul    addi r6, r0, 0
+          cge r3, r1, r0
+          bnz r3, rtfmul1
+          sub r1, r0, r1
+          addi r6, r0, 1
+rtfmul1   cge r3, r2, r0
+          bnz r3, rtfmul2
+          sub r2, r0, r2
+          ceqi r6, r6, 0
+          % |r1| * |r2| from the 16-bit halves, shifted right by 16
+rtfmul2   add r3, r1, r0
+          sr r3, 16
+          add r4, r3, r0
+          sl r4, 16
+          sub r4, r1, r4
+          add r5, r2, r0
+          sr r5, 16
+          add r7, r5, r0
+          sl r7, 16
+          sub r2, r2, r7
+          mul r1, r3, r5
+          sl r1, 16
+          mul r7, r3, r2
+          add r1, r1, r7
+          mul r7, r4, r5
+          add r1, r1, r7
+          mul r7, r4, r2
+          sr r7, 16
+          add r1, r1, r7
+          bz r6, rtfmul3
+          sub r1, r0, r1
+rtfmul3   jr r15
+rtfdiv    addi r6, r0, 0
+          cge r3, r1, r0
+          bnz r3, rtfdiv1
+          sub r1, r0, r1
+          addi r6, r0, 1
+rtfdiv1   cge r3, r2, r0
+          bnz r3, rtfdiv2
+          sub r2, r0, r2
+          ceqi r6, r6, 0
+          % integer quotient, then 16 bits of long division
+rtfdiv2   div r3, r1, r2
+          mod r4, r1, r2
+          addi r5, r0, 16
+rtfdiv3   sl r3, 1
+          sl r4, 1
+          clt r7, r4, r2
+          bnz r7, rtfdiv4
+          sub r4, r4, r2
+          addi r3, r3, 1
+rtfdiv4   subi r5, r5, 1
+          bnz r5, rtfdiv3
+          add r1, r3, r0
+          bz r6, rtfdiv5
+          sub r1, r0, r1
+rtfdiv5   jr r15
+rtputflt  cge r3, r1, r0
+          bnz r3, rtputflt1
+          addi r3, r0, 45
+          putc r3
+          sub r1, r0, r1
+rtputflt1 add r2, r1, r0
+          sr r2, 16
+          add r3, r2, r0
+          sl r3, 16
+          sub r1, r1, r3
+          muli r1, r1, 100
+          add r3, r1, r0
+          sr r1, 16
+          add r4, r1, r0
+          sl r4, 16
+          sub r3, r3, r4
+          % r1 hundredths, r3 remainder in 1/65536 of a hundredth
+          add r4, r3, r0
+          sr r4, 15
+          bz r4, rtputflt2
+          sl r4, 15
+          cne r4, r3, r4
+          modi r3, r1, 2
+          or r4, r4, r3
+          add r1, r1, r4
+          cnei r4, r1, 100
+          bnz r4, rtputflt2
+          addi r1, r0, 0
+          addi r2, r2, 1
+rtputflt2 jl r5, rtdigits
+          addi r3, r0, 46
+          putc r3
+          divi r2, r1, 10
+          addi r2, r2, 48
+          putc r2
+          modi r2, r1, 10
+          addi r2, r2, 48
+          putc r2
+          addi r3, r0, 10
+          putc r3
+          jr r15
+rtgetflt  add r6, r15, r0
+          jl r15, rtgetint
+          add r15, r6, r0
+          sl r1, 16
+          cnei r4, r2, 46
+          bnz r4, rtgetflt4
+          addi r4, r0, 0
+          addi r5, r0, 1
+          % digits after the fourth decimal are ignored
+rtgetflt1 getc r2
+          clti r7, r2, 48
+          bnz r7, rtgetflt2
+          cgti r7, r2, 57
+          bnz r7, rtgetflt2
+          cgei r7, r5, 10000
+          bnz r7, rtgetflt1
+          muli r4, r4, 10
+          subi r2, r2, 48
+          add r4, r4, r2
+          muli r5, r5, 10
+          j rtgetflt1
+rtgetflt2 sl r4, 16
+          divi r7, r5, 2
+          add r4, r4, r7
+          div r4, r4, r5
+          bz r3, rtgetflt3
+          sub r4, r0, r4
+rtgetflt3 add r1, r1, r4
+rtgetflt4 ceqi r4, r2, 101
+          ceqi r5, r2, 69
+          or r4, r4, r5
+          bnz r4, rtexp
+          jr r15
+          % exponent into r4, up to 100, with r3 1 if it is negative
+rtexp     addi r3, r0, 0
+          getc r2
+          ceqi r4, r2, 43
+          bnz r4, rtexp1
+          cnei r4, r2, 45
+          bnz r4, rtexp2
+          addi r3, r0, 1
+rtexp1    getc r2
+rtexp2    addi r4, r0, 0
+rtexp3    clti r7, r2, 48
+          bnz r7, rtexp5
+          cgti r7, r2, 57
+          bnz r7, rtexp5
+          cgei r7, r4, 100
+          bnz r7, rtexp4
+          muli r4, r4, 10
+          subi r7, r2, 48
+          add r4, r4, r7
+rtexp4    getc r2
+          j rtexp3
+rtexp5    bnz r3, rtscale2
+rtscale1  bz r4, rtscale7
+          muli r1, r1, 10
+          subi r4, r4, 1
+          j rtscale1
+          % r1 / 10^r4 rounded half away from zero, which is 0 past 10^9
+rtscale2  cgti r7, r4, 9
+          bz r7, rtscale3
+          addi r1, r0, 0
+          jr r15
+rtscale3  addi r5, r0, 1
+rtscale4  bz r4, rtscale5
+          muli r5, r5, 10
+          subi r4, r4, 1
+          j rtscale4
+rtscale5  cge r6, r1, r0
+          bnz r6, rtscale6
+          sub r1, r0, r1
+rtscale6  mod r7, r1, r5
+          div r1, r1, r5
+          add r7, r7, r7
+          cge r7, r7, r5
+          add r1, r1, r7
+          bnz r6, rtscale7
+          sub r1, r0, r1
+rtscale7  jr r15
+";
+
+// Moon program for `ir`, which must contain a `main` function. Without register
+// allocation every temporary lives in its frame slot.
+pub fn generate_moon(ir: &IrProgram, allocate: bool) -> String {
+    let mut writer = MoonWriter::default();
+    writer.instr("entry");
+    writer.instr(&format!("addi {}, r0, stack", FRAME_POINTER));
+    writer.instr(&format!("jl {}, main", RETURN_ADDRESS));
+    writer.instr("hlt");
+    for function in &ir.functions {
+        let allocation = match allocate {
+            true => allocate_registers(function, &ALLOCATABLE_REGISTERS),
+            false => Allocation::default(),
+        };
+        FunctionCodegen { program: ir, function, allocation, writer: &mut writer }.generate();
+    }
+    for vtable in &ir.vtables {
+        let words: Vec<String> = vtable.entries.iter()
+            .map(|entry| format!("{}, {}", entry.function, entry.adjustment))
+            .collect();
+        writer.label(&vtable.name);
+        match words.is_empty() {
+            true => writer.instr("res 0"),
+            false => writer.instr(&format!("dw {}", words.join(", "))),
+        }
+    }
+    let mut text = writer.text();
+    text.push_str(RUNTIME);
+    text.push_str(&format!("{:<9} align\n{:<9} res 0\n", "", "stack"));
+    text
+}
@@ -0,0 +1,553 @@
+// Emulator for the Moon processor, to run generated programs in tests. `load`
+// assembles Moon assembly text: a symbol in the first column of a line labels it,
+// `%` starts a comment, and the directives are `entry`, `align`, `org K`,
+// `dw K, ...`, `db K or "text", ...` and `res K`. `topaddr` is the address just
+// past the end of memory. Memory is a vector of 32-bit words addressed by bytes:
+// `lw` and `sw` need word-aligned addresses, `lb` and `sb` pick a byte of a word,
+// most significant first. Instructions take 4 bytes each and are kept apart from
+// the data. `getc` gives 0 once the input is exhausted. Execution time is counted
+// in cycles: one per instruction, plus `MEMORY_ACCESS_CYCLES` for a load or store.
+
+use std::collections::HashMap;
+
+pub const MEMORY_SIZE: usize = 1 << 20;
+pub const DEFAULT_STEP_LIMIT: usize = 10_000_000;
+pub const MEMORY_ACCESS_CYCLES: usize = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArithOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    And,
+    Or,
+    Ceq,
+    Cne,
+    Clt,
+    Cle,
+    Cgt,
+    Cge,
+}
+
+impl ArithOp {
+    fn from_mnemonic(mnemonic: &str) -> Option<ArithOp> {
+        let op = match mnemonic {
+            "add" => ArithOp::Add,
+            "sub" => ArithOp::Sub,
+            "mul" => ArithOp::Mul,
+            "div" => ArithOp::Div,
+            "mod" => ArithOp::Mod,
+            "and" => ArithOp::And,
+            "or" => ArithOp::Or,
+            "ceq" => ArithOp::Ceq,
+            "cne" => ArithOp::Cne,
+            "clt" => ArithOp::Clt,
+            "cle" => ArithOp::Cle,
+            "cgt" => ArithOp::Cgt,
+            "cge" => ArithOp::Cge,
+            _ => return None,
+        };
+        Some(op)
+    }
+
+    fn apply(&self, left: i32, right: i32) -> Result<i32, String> {
+        let value = match self {
+            ArithOp::Add => left.wrapping_add(right),
+            ArithOp::Sub => left.wrapping_sub(right),
+            ArithOp::Mul => left.wrapping_mul(right),
+            ArithOp::Div | ArithOp::Mod if right == 0 => return Err("division by zero".to_string()),
+            ArithOp::Div => left.wrapping_div(right),
+            ArithOp::Mod => left.wrapping_rem(right),
+            ArithOp::And => left & right,
+            ArithOp::Or => left | right,
+            ArithOp::Ceq => (left == right) as i32,
+            ArithOp::Cne => (left != right) as i32,
+            ArithOp::Clt => (left < right) as i32,
+            ArithOp::Cle => (left <= right) as i32,
+            ArithOp::Cgt => (left > right) as i32,
+            ArithOp::Cge => (left >= right) as i32,
+        };
+        Ok(value)
+    }
+}
+
+// Registers are numbers from 0 to 15, `k` operands are resolved addresses or
+// constants.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    Lw { ri: usize, k: i32, rj: usize },
+    Lb { ri: usize, k: i32, rj: usize },
+    Sw { k: i32, rj: usize, ri: usize },
+    Sb { k: i32, rj: usize, ri: usize },
+    Arith { op: ArithOp, ri: usize, rj: usize, rk: usize },
+    Immediate { op: ArithOp, ri: usize, rj: usize, k: i32 },
+    Not { ri: usize, rj: usize },
+    Sl { ri: usize, k: i32 },
+    Sr { ri: usize, k: i32 },
+    Getc { ri: usize },
+    Putc { ri: usize },
+    Bz { ri: usize, k: i32 },
+    Bnz { ri: usize, k: i32 },
+    J { k: i32 },
+    Jr { ri: usize },
+    Jl { ri: usize, k: i32 },
+    Jlr { ri: usize, rj: usize },
+    Nop,
+    Hlt,
+}
+
+impl Instruction {
+    pub fn accesses_memory(&self) -> bool {
+        matches!(self, Instruction::Lw { .. } | Instruction::Lb { .. } | Instruction::Sw { .. } | Instruction::Sb { .. })
+    }
+}
+
+// A source line split into its label, mnemonic and operands.
+struct Line {
+    number: usize,
+    label: Option<String>,
+    mnemonic: Option<String>,
+    operands: Vec<String>,
+}
+
+fn strip_comment(text: &str) -> &str {
+    let mut in_string = false;
+    for (position, character) in text.char_indices() {
+        match character {
+            '"' => in_string = !in_string,
+            '%' if !in_string => return &text[..position],
+            _ => {}
+        }
+    }
+    text
+}
+
+fn split_operands(text: &str) -> Vec<String> {
+    let mut operands = vec![];
+    let mut current = String::new();
+    let mut in_string = false;
+    for character in text.chars() {
+        match character {
+            '"' => {
+                in_string = !in_string;
+                current.push(character);
+            }
+            ',' if !in_string => operands.push(std::mem::take(&mut current).trim().to_string()),
+            _ => current.push(character),
+        }
+    }
+    if !current.trim().is_empty() {
+        operands.push(current.trim().to_string());
+    }
+    operands
+}
+
+fn parse_line(number: usize, text: &str) -> Line {
+    let text = strip_comment(text);
+    let mut rest = text.trim_start();
+    let mut label = None;
+    if !text.starts_with(char::is_whitespace) && !rest.is_empty() {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        label = Some(rest[..end].to_string());
+        rest = rest[end..].trim_start();
+    }
+    let mut mnemonic = None;
+    let mut operands = vec![];
+    if !rest.is_empty() {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        mnemonic = Some(rest[..end].to_lowercase());
+        operands = split_operands(&rest[end..]);
+    }
+    Line { number, label, mnemonic, operands }
+}
+
+fn align(address: usize) -> usize {
+    address.div_ceil(4) * 4
+}
+
+struct Assembler {
+    symbols: HashMap<String, i32>,
+}
+
+impl Assembler {
+    fn error(line: &Line, message: String) -> String {
+        format!("line {}: {}", line.number, message)
+    }
+
+    fn register(line: &Line, text: &str) -> Result<usize, String> {
+        let number = text.strip_prefix('r').or_else(|| text.strip_prefix('R')).and_then(|number| number.parse::<usize>().ok());
+        match number {
+            Some(number) if number < 16 => Ok(number),
+            _ => Err(Self::error(line, format!("invalid register '{}'", text))),
+        }
+    }
+
+    // A number, a symbol, or a symbol plus or minus a number.
+    fn constant(&self, line: &Line, text: &str) -> Result<i32, String> {
+        let text = text.trim();
+        if let Ok(value) = text.parse::<i64>() {
+            return Ok(value as i32);
+        }
+        if let Some(position) = text.rfind(['+', '-']).filter(|position| *position > 0) {
+            let base = self.constant(line, &text[..position])?;
+            let offset = self.constant(line, &text[position + 1..])?;
+            return Ok(if text[position..].starts_with('+') { base.wrapping_add(offset) } else { base.wrapping_sub(offset) });
+        }
+        self.symbols.get(text).copied().ok_or_else(|| Self::error(line, format!("undefined symbol '{}'", text)))
+    }
+
+    // `K(Rj)` memory operands.
+    fn indexed(&self, line: &Line, text: &str) -> Result<(i32, usize), String> {
+        let open = text.find('(').ok_or_else(|| Self::error(line, format!("expected K(Rj), found '{}'", text)))?;
+        let close = text.rfind(')').ok_or_else(|| Self::error(line, format!("expected K(Rj), found '{}'", text)))?;
+        let k = if text[..open].trim().is_empty() { 0 } else { self.constant(line, &text[..open])? };
+        Ok((k, Self::register(line, text[open + 1..close].trim())?))
+    }
+
+    fn operand_count(line: &Line, count: usize) -> Result<(), String> {
+        if line.operands.len() != count {
+            return Err(Self::error(line, format!("'{}' takes {} operands", line.mnemonic.as_ref().unwrap(), count)));
+        }
+        Ok(())
+    }
+
+    fn instruction(&self, line: &Line) -> Result<Instruction, String> {
+        let mnemonic = line.mnemonic.as_deref().unwrap();
+        let operands = &line.operands;
+        let register = |index: usize| Self::register(line, &operands[index]);
+        let constant = |index: usize| self.constant(line, &operands[index]);
+        let count = match mnemonic {
+            "nop" | "hlt" => 0,
+            "getc" | "putc" | "j" | "jr" => 1,
+            "lw" | "lb" | "sw" | "sb" | "not" | "sl" | "sr" | "bz" | "bnz" | "jl" | "jlr" => 2,
+            _ => 3,
+        };
+        Self::operand_count(line, count)?;
+        let instruction = match mnemonic {
+            "lw" | "lb" => {
+                let (k, rj) = self.indexed(line, &operands[1])?;
+                let ri = register(0)?;
+                if mnemonic == "lw" { Instruction::Lw { ri, k, rj } } else { Instruction::Lb { ri, k, rj } }
+            }
+            "sw" | "sb" => {
+                let (k, rj) = self.indexed(line, &operands[0])?;
+                let ri = register(1)?;
+                if mnemonic == "sw" { Instruction::Sw { k, rj, ri } } else { Instruction::Sb { k, rj, ri } }
+            }
+            "not" => Instruction::Not { ri: register(0)?, rj: register(1)? },
+            "sl" => Instruction::Sl { ri: register(0)?, k: constant(1)? },
+            "sr" => Instruction::Sr { ri: register(0)?, k: constant(1)? },
+            "getc" => Instruction::Getc { ri: register(0)? },
+            "putc" => Instruction::Putc { ri: register(0)? },
+            "bz" => Instruction::Bz { ri: register(0)?, k: constant(1)? },
+            "bnz" => Instruction::Bnz { ri: register(0)?, k: constant(1)? },
+            "j" => Instruction::J { k: constant(0)? },
+            "jr" => Instruction::Jr { ri: register(0)? },
+            "jl" => Instruction::Jl { ri: register(0)?, k: constant(1)? },
+            "jlr" => Instruction::Jlr { ri: register(0)?, rj: register(1)? },
+            "nop" => Instruction::Nop,
+            "hlt" => Instruction::Hlt,
+            _ => {
+                if let Some(op) = ArithOp::from_mnemonic(mnemonic) {
+                    Instruction::Arith { op, ri: register(0)?, rj: register(1)?, rk: register(2)? }
+                } else if let Some(op) = mnemonic.strip_suffix('i').and_then(ArithOp::from_mnemonic) {
+                    Instruction::Immediate { op, ri: register(0)?, rj: register(1)?, k: constant(2)? }
+                } else {
+                    return Err(Self::error(line, format!("unknown instruction '{}'", mnemonic)));
+                }
+            }
+        };
+        Ok(instruction)
+    }
+}
+
+fn is_directive(mnemonic: &str) -> bool {
+    matches!(mnemonic, "entry" | "align" | "org" | "dw" | "db" | "res")
+}
+
+fn data_size(operand: &str) -> usize {
+    match operand.strip_prefix('"').and_then(|operand| operand.strip_suffix('"')) {
+        Some(text) => text.len(),
+        None => 1,
+    }
+}
+
+// Bytes of a `db` directive.
+fn data_bytes(assembler: &Assembler, line: &Line) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![];
+    for operand in &line.operands {
+        match operand.strip_prefix('"').and_then(|operand| operand.strip_suffix('"')) {
+            Some(text) => bytes.extend(text.bytes()),
+            None => bytes.push(assembler.constant(line, operand)? as u8),
+        }
+    }
+    Ok(bytes)
+}
+
+pub struct MoonVm {
+    pub registers: [i32; 16],
+    pub pc: usize,
+    memory: Vec<i32>,
+    instructions: HashMap<usize, Instruction>,
+    input: Vec<u8>,
+    input_position: usize,
+    output: Vec<u8>,
+    // instructions executed so far
+    pub steps: usize,
+    pub cycles: usize,
+    pub step_limit: usize,
+}
+
+impl MoonVm {
+    // Assembles `source`; errors give the line number.
+    pub fn load(source: &str) -> Result<MoonVm, String> {
+        let lines: Vec<Line> = source.lines().enumerate().map(|(index, text)| parse_line(index + 1, text)).collect();
+        let mut assembler = Assembler { symbols: HashMap::new() };
+        assembler.symbols.insert("topaddr".to_string(), MEMORY_SIZE as i32);
+        // first pass: addresses of the labels
+        let mut location = 0;
+        for line in &lines {
+            let mnemonic = line.mnemonic.as_deref().unwrap_or("");
+            if mnemonic == "dw" || (!mnemonic.is_empty() && !is_directive(mnemonic)) {
+                location = align(location);
+            }
+            if let Some(label) = &line.label {
+                if assembler.symbols.insert(label.clone(), location as i32).is_some() {
+                    return Err(Assembler::error(line, format!("multiply defined symbol '{}'", label)));
+                }
+            }
+            location = match mnemonic {
+                "" | "entry" => location,
+                "align" => align(location),
+                "org" => assembler.constant(line, &line.operands[0])? as usize,
+                "dw" => location + 4 * line.operands.len(),
+                "db" => location + line.operands.iter().map(|operand| data_size(operand)).sum::<usize>(),
+                "res" => location + assembler.constant(line, &line.operands[0])? as usize,
+                _ => location + 4,
+            };
+        }
+        // second pass: instructions and data
+        let mut vm = MoonVm {
+            registers: [0; 16],
+            pc: 0,
+            memory: vec![0; MEMORY_SIZE / 4],
+            instructions: HashMap::new(),
+            input: vec![],
+            input_position: 0,
+            output: vec![],
+            steps: 0,
+            cycles: 0,
+            step_limit: DEFAULT_STEP_LIMIT,
+        };
+        let mut location = 0;
+        let mut entry_pending = false;
+        for line in &lines {
+            let mnemonic = match line.mnemonic.as_deref() {
+                Some(mnemonic) => mnemonic,
+                None => continue,
+            };
+            match mnemonic {
+                "entry" => entry_pending = true,
+                "align" => location = align(location),
+                "org" => location = assembler.constant(line, &line.operands[0])? as usize,
+                "res" => location += assembler.constant(line, &line.operands[0])? as usize,
+                "dw" => {
+                    location = align(location);
+                    for operand in &line.operands {
+                        let value = assembler.constant(line, operand)?;
+                        vm.store_word(location, value).map_err(|message| Assembler::error(line, message))?;
+                        location += 4;
+                    }
+                }
+                "db" => {
+                    for byte in data_bytes(&assembler, line)? {
+                        vm.store_byte(location, byte as i32).map_err(|message| Assembler::error(line, message))?;
+                        location += 1;
+                    }
+                }
+                _ => {
+                    location = align(location);
+                    vm.instructions.insert(location, assembler.instruction(line)?);
+                    if entry_pending {
+                        vm.pc = location;
+                        entry_pending = false;
+                    }
+                    location += 4;
+                }
+            }
+        }
+        Ok(vm)
+    }
+
+    pub fn set_input(&mut self, input: &str) {
+        self.input = input.as_bytes().to_vec();
+        self.input_position = 0;
+    }
+
+    pub fn output(&self) -> String {
+        String::from_utf8_lossy(&self.output).to_string()
+    }
+
+    fn word_index(&self, address: i32) -> Result<usize, String> {
+        if address < 0 || address as usize >= MEMORY_SIZE {
+            return Err(format!("address {} is out of memory", address));
+        }
+        Ok(address as usize / 4)
+    }
+
+    pub fn load_word(&self, address: usize) -> Result<i32, String> {
+        if !address.is_multiple_of(4) {
+            return Err(format!("unaligned word access at address {}", address));
+        }
+        Ok(self.memory[self.word_index(address as i32)?])
+    }
+
+    pub fn store_word(&mut self, address: usize, value: i32) -> Result<(), String> {
+        if !address.is_multiple_of(4) {
+            return Err(format!("unaligned word access at address {}", address));
+        }
+        let index = self.word_index(address as i32)?;
+        self.memory[index] = value;
+        Ok(())
+    }
+
+    fn byte_shift(address: usize) -> u32 {
+        8 * (3 - (address % 4) as u32)
+    }
+
+    pub fn load_byte(&self, address: usize) -> Result<i32, String> {
+        let word = self.memory[self.word_index(address as i32)?];
+        Ok((word >> Self::byte_shift(address)) & 0xff)
+    }
+
+    pub fn store_byte(&mut self, address: usize, value: i32) -> Result<(), String> {
+        let index = self.word_index(address as i32)?;
+        let shift = Self::byte_shift(address);
+        self.memory[index] = (self.memory[index] & !(0xff << shift)) | ((value & 0xff) << shift);
+        Ok(())
+    }
+
+    fn set_register(&mut self, register: usize, value: i32) {
+        // r0 is always 0
+        if register != 0 {
+            self.registers[register] = value;
+        }
+    }
+
+    fn effective_address(&self, k: i32, rj: usize) -> Result<usize, String> {
+        let address = self.registers[rj].wrapping_add(k);
+        self.word_index(address)?;
+        Ok(address as usize)
+    }
+
+    // Executes one instruction; returns false after `hlt`.
+    pub fn step(&mut self) -> Result<bool, String> {
+        let instruction = *self.instructions.get(&self.pc)
+            .ok_or_else(|| format!("no instruction at address {}", self.pc))?;
+        self.steps += 1;
+        self.cycles += 1;
+        if instruction.accesses_memory() {
+            self.cycles += MEMORY_ACCESS_CYCLES;
+        }
+        let mut next_pc = self.pc + 4;
+        match instruction {
+            Instruction::Lw { ri, k, rj } => {
+                let value = self.load_word(self.effective_address(k, rj)?)?;
+                self.set_register(ri, value);
+            }
+            Instruction::Lb { ri, k, rj } => {
+                let value = self.load_byte(self.effective_address(k, rj)?)?;
+                self.set_register(ri, value);
+            }
+            Instruction::Sw { k, rj, ri } => self.store_word(self.effective_address(k, rj)?, self.registers[ri])?,
+            Instruction::Sb { k, rj, ri } => self.store_byte(self.effective_address(k, rj)?, self.registers[ri])?,
+            Instruction::Arith { op, ri, rj, rk } => {
+                let value = op.apply(self.registers[rj], self.registers[rk])?;
+                self.set_register(ri, value);
+            }
+            Instruction::Immediate { op, ri, rj, k } => {
+                let value = op.apply(self.registers[rj], k)?;
+                self.set_register(ri, value);
+            }
+            Instruction::Not { ri, rj } => self.set_register(ri, !self.registers[rj]),
+            Instruction::Sl { ri, k } => self.set_register(ri, self.registers[ri].wrapping_shl(k as u32)),
+            Instruction::Sr { ri, k } => self.set_register(ri, (self.registers[ri] as u32).wrapping_shr(k as u32) as i32),
+            Instruction::Getc { ri } => {
+                let value = self.input.get(self.input_position).map(|byte| *byte as i32).unwrap_or(0);
+                self.input_position += 1;
+                self.set_register(ri, value);
+            }
+            Instruction::Putc { ri } => self.output.push(self.registers[ri] as u8),
+            Instruction::Bz { ri, k } => {
+                if self.registers[ri] == 0 {
+                    next_pc = k as usize;
+                }
+            }
+            Instruction::Bnz { ri, k } => {
+                if self.registers[ri] != 0 {
+                    next_pc = k as usize;
+                }
+            }
+            Instruction::J { k } => next_pc = k as usize,
+            Instruction::Jr { ri } => next_pc = self.registers[ri] as usize,
+            Instruction::Jl { ri, k } => {
+                self.set_register(ri, next_pc as i32);
+                next_pc = k as usize;
+            }
+            Instruction::Jlr { ri, rj } => {
+                let target = self.registers[rj] as usize;
+                self.set_register(ri, next_pc as i32);
+                next_pc = target;
+            }
+            Instruction::Nop => {}
+            Instruction::Hlt => return Ok(false),
+        }
+        self.pc = next_pc;
+        Ok(true)
+    }
+
+    // Number of instructions in the program.
+    pub fn instruction_count(&self) -> usize {
+        self.instructions.len()
+    }
+
+    // Runs until `hlt`; errors give the address of the failing instruction.
+    pub fn run(&mut self) -> Result<(), String> {
+        loop {
+            if self.steps >= self.step_limit {
+                return Err(format!("step limit of {} exceeded", self.step_limit));
+            }
+            let pc = self.pc;
+            match self.step() {
+                Ok(true) => continue,
+                Ok(false) => return Ok(()),
+                Err(message) => return Err(format!("at address {}: {}", pc, message)),
+            }
+        }
+    }
+}
+
+// Output of the Moon program `source` run with `input` as its input.
+pub fn run_moon(source: &str, input: &str) -> Result<String, String> {
+    let mut vm = MoonVm::load(source)?;
+    vm.set_input(input);
+    vm.run()?;
+    Ok(vm.output())
+}
+
+pub struct MoonProfile {
+    pub output: String,
+    pub instructions: usize,
+    pub steps: usize,
+    pub cycles: usize,
+}
+
+// Runs the Moon program `source` like `run_moon`, measuring it.
+pub fn profile_moon(source: &str, input: &str) -> Result<MoonProfile, String> {
+    let mut vm = MoonVm::load(source)?;
+    vm.set_input(input);
+    vm.run()?;
+    Ok(MoonProfile { output: vm.output(), instructions: vm.instruction_count(), steps: vm.steps, cycles: vm.cycles })
+}
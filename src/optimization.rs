@@ -0,0 +1,406 @@
+// Machine independent optimizations of the IR. Each one can be turned on by
+// itself, and `-O1` and `-O2` pick the usual sets; they are repeated on every
+// function until none of them changes it. Named variables are frame slots that
+// only the function reaches, unless their address is taken (arrays, objects and
+// variables read by address), so facts about the others survive stores and calls.
+// Propagation and common subexpressions are found within basic blocks, where
+// only temporaries assigned once carry their constant across blocks; dead stores
+// come from liveness.
+// Arithmetic on constants follows the interpreter: integers wrap at 32 bits and
+// divisions by zero are left for the run to report. Floats are folded as
+// doubles, unless `fold_floats` is off for the Moon back end, whose floats are
+// fixed point.
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use crate::ast::{BinaryOp, Program, UnaryOp};
+use crate::cfg::ControlFlowGraph;
+use crate::data_flow::{solve, Liveness, Variable};
+use crate::ir::*;
+use crate::passes::{Pass, PassContext};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Optimizations {
+    pub fold_constants: bool,
+    // with `fold_constants`, also operations on floats and conversions to them
+    pub fold_floats: bool,
+    pub propagate_copies: bool,
+    pub eliminate_dead_stores: bool,
+    pub eliminate_unreachable_blocks: bool,
+    pub eliminate_common_subexpressions: bool,
+}
+
+impl Optimizations {
+    // Level 1 folds and propagates constants and drops unreachable code, level 2
+    // also removes dead stores and common subexpressions.
+    pub fn level(level: u32) -> Self {
+        Optimizations {
+            fold_constants: level >= 1,
+            fold_floats: level >= 1,
+            propagate_copies: level >= 1,
+            eliminate_unreachable_blocks: level >= 1,
+            eliminate_dead_stores: level >= 2,
+            eliminate_common_subexpressions: level >= 2,
+        }
+    }
+
+    // The same optimizations for Moon code, where folding floats as doubles
+    // would change the output.
+    pub fn for_moon(self) -> Self {
+        Optimizations { fold_floats: false, ..self }
+    }
+}
+
+fn compare(op: BinaryOp, ordering: Ordering) -> bool {
+    match op {
+        BinaryOp::Eq => ordering == Ordering::Equal,
+        BinaryOp::Neq => ordering != Ordering::Equal,
+        BinaryOp::Lt => ordering == Ordering::Less,
+        BinaryOp::Gt => ordering == Ordering::Greater,
+        BinaryOp::Leq => ordering != Ordering::Greater,
+        BinaryOp::Geq => ordering != Ordering::Less,
+        _ => panic!("'{}' is not relational", op.as_string()),
+    }
+}
+
+fn fold_binary(op: BinaryOp, lhs: &Operand, rhs: &Operand, fold_floats: bool) -> Option<Operand> {
+    match (lhs, rhs) {
+        (Operand::Int(lhs), Operand::Int(rhs)) => {
+            let (lhs, rhs) = (*lhs as i32, *rhs as i32);
+            let value = match op {
+                BinaryOp::Add => lhs.wrapping_add(rhs),
+                BinaryOp::Sub => lhs.wrapping_sub(rhs),
+                BinaryOp::Mul => lhs.wrapping_mul(rhs),
+                BinaryOp::Div if rhs == 0 => return None,
+                BinaryOp::Div => lhs.wrapping_div(rhs),
+                BinaryOp::And => (lhs != 0 && rhs != 0) as i32,
+                BinaryOp::Or => (lhs != 0 || rhs != 0) as i32,
+                _ => compare(op, lhs.cmp(&rhs)) as i32,
+            };
+            Some(Operand::Int(value as i64))
+        }
+        (Operand::Float(lhs), Operand::Float(rhs)) if fold_floats => match op {
+            BinaryOp::Add => Some(Operand::Float(lhs + rhs)),
+            BinaryOp::Sub => Some(Operand::Float(lhs - rhs)),
+            BinaryOp::Mul => Some(Operand::Float(lhs * rhs)),
+            BinaryOp::Div if *rhs == 0.0 => None,
+            BinaryOp::Div => Some(Operand::Float(lhs / rhs)),
+            BinaryOp::And | BinaryOp::Or => None,
+            _ => Some(Operand::Int(compare(op, lhs.partial_cmp(rhs)?) as i64)),
+        },
+        _ => None,
+    }
+}
+
+fn fold_unary(op: UnaryOp, operand: &Operand, fold_floats: bool) -> Option<Operand> {
+    match (op, operand) {
+        (UnaryOp::Neg, Operand::Int(value)) => Some(Operand::Int((*value as i32).wrapping_neg() as i64)),
+        (UnaryOp::Neg, Operand::Float(value)) if fold_floats => Some(Operand::Float(-value)),
+        (UnaryOp::Not, Operand::Int(value)) => Some(Operand::Int((*value == 0) as i64)),
+        _ => None,
+    }
+}
+
+// Replaces operations on constants by copies of their result, and branches on a
+// constant by jumps. Operations on floats are left alone unless `fold_floats`.
+pub fn fold_constants(function: &mut IrFunction, fold_floats: bool) -> bool {
+    let mut changed = false;
+    for instr in function.body.iter_mut() {
+        let folded = match instr {
+            Instr::Binary { dest, op, lhs, rhs, .. } => fold_binary(*op, lhs, rhs, fold_floats).map(|src| Instr::Copy { dest: dest.clone(), src }),
+            Instr::Unary { dest, op, operand, .. } => fold_unary(*op, operand, fold_floats).map(|src| Instr::Copy { dest: dest.clone(), src }),
+            Instr::IntToFloat { dest, src: Operand::Int(value) } if fold_floats => Some(Instr::Copy { dest: dest.clone(), src: Operand::Float(*value as f64) }),
+            Instr::Branch { condition: Operand::Int(value), if_true, if_false } => {
+                Some(Instr::Jump(if *value != 0 { if_true.clone() } else { if_false.clone() }))
+            }
+            _ => None,
+        };
+        if let Some(folded) = folded {
+            *instr = folded;
+            changed = true;
+        }
+    }
+    changed
+}
+
+// Variables that loads, stores and calls can reach through their address.
+fn address_taken(function: &IrFunction) -> HashSet<String> {
+    function.body.iter()
+        .filter_map(|instr| match instr {
+            Instr::AddressOf { var, .. } => Some(var.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn is_private(operand: &Operand, aliased: &HashSet<String>) -> bool {
+    match operand {
+        Operand::Var(name) => !aliased.contains(name),
+        _ => true,
+    }
+}
+
+// Whether the instruction does nothing but set its destination, so it can go
+// when the value is not needed.
+fn is_pure(instr: &Instr) -> bool {
+    match instr {
+        Instr::Binary { op: BinaryOp::Div, rhs, .. } => matches!(rhs, Operand::Int(value) if *value != 0)
+            || matches!(rhs, Operand::Float(value) if *value != 0.0),
+        Instr::Copy { .. }
+        | Instr::Binary { .. }
+        | Instr::Unary { .. }
+        | Instr::IntToFloat { .. }
+        | Instr::AddressOf { .. }
+        | Instr::Load { .. }
+        | Instr::VtableAddress { .. } => true,
+        _ => false,
+    }
+}
+
+// Replaces the uses of a variable or temporary copied from another one or from a
+// constant by that operand, as long as neither was assigned since.
+pub fn propagate_copies(function: &mut IrFunction) -> bool {
+    let aliased = address_taken(function);
+    let mut definitions: HashMap<usize, usize> = HashMap::new();
+    for instr in &function.body {
+        if let Some(Operand::Temp(number)) = instr.dest() {
+            *definitions.entry(*number).or_default() += 1;
+        }
+    }
+    let constant_temps: HashMap<usize, Operand> = function.body.iter()
+        .filter_map(|instr| match instr {
+            Instr::Copy { dest: Operand::Temp(number), src: src @ (Operand::Int(_) | Operand::Float(_)) }
+                if definitions[number] == 1 => Some((*number, src.clone())),
+            _ => None,
+        })
+        .collect();
+    let mut changed = false;
+    // (destination, source) of the copies still valid
+    let mut copies: Vec<(Operand, Operand)> = vec![];
+    for instr in function.body.iter_mut() {
+        if let Instr::Label(_) = instr {
+            copies.clear();
+        }
+        for operand in instr.uses_mut() {
+            let value = match operand {
+                Operand::Temp(number) if constant_temps.contains_key(number) => Some(constant_temps[number].clone()),
+                _ => copies.iter().find(|(dest, _)| dest == operand).map(|(_, src)| src.clone()),
+            };
+            if let Some(value) = value {
+                *operand = value;
+                changed = true;
+            }
+        }
+        if let Some(dest) = instr.dest().cloned() {
+            copies.retain(|(copy_dest, copy_src)| *copy_dest != dest && *copy_src != dest);
+            if let Instr::Copy { src, .. } = instr {
+                if *src != dest && is_private(&dest, &aliased) && is_private(src, &aliased) {
+                    copies.push((dest, src.clone()));
+                }
+            }
+        }
+        if instr.is_terminator() {
+            copies.clear();
+        }
+    }
+    changed
+}
+
+// Removes the pure instructions whose result is not live afterwards, and copies
+// of a variable to itself.
+pub fn eliminate_dead_stores(function: &mut IrFunction) -> bool {
+    let aliased = address_taken(function);
+    let liveness = solve(&ControlFlowGraph::build(function), &Liveness);
+    let dead: Vec<bool> = function.body.iter().enumerate()
+        .map(|(position, instr)| match instr.dest() {
+            Some(dest) if is_pure(instr) && is_private(dest, &aliased) => {
+                let self_copy = matches!(instr, Instr::Copy { src, .. } if src == dest);
+                self_copy || !Variable::of(dest).is_some_and(|dest| liveness.after[position].contains(&dest))
+            }
+            _ => false,
+        })
+        .collect();
+    let changed = dead.contains(&true);
+    let mut position = 0;
+    function.body.retain(|_| {
+        position += 1;
+        !dead[position - 1]
+    });
+    changed
+}
+
+// Removes the instructions that no path from the entry reaches, jumps to the next
+// instruction and labels nothing jumps to, which also merges the blocks around them.
+pub fn eliminate_unreachable_blocks(function: &mut IrFunction) -> bool {
+    let body = &function.body;
+    let labels: HashMap<&str, usize> = body.iter().enumerate()
+        .filter_map(|(position, instr)| match instr {
+            Instr::Label(label) => Some((label.as_str(), position)),
+            _ => None,
+        })
+        .collect();
+    let mut reachable = vec![false; body.len()];
+    let mut pending = vec![0];
+    while let Some(mut position) = pending.pop() {
+        while position < body.len() && !reachable[position] {
+            reachable[position] = true;
+            match &body[position] {
+                Instr::Jump(label) => pending.push(labels[label.as_str()]),
+                Instr::Branch { if_true, if_false, .. } => {
+                    pending.push(labels[if_true.as_str()]);
+                    pending.push(labels[if_false.as_str()]);
+                }
+                _ => {}
+            }
+            if body[position].is_terminator() {
+                break;
+            }
+            position += 1;
+        }
+    }
+    let old_length = body.len();
+    let mut position = 0;
+    function.body.retain(|_| {
+        position += 1;
+        reachable[position - 1]
+    });
+    let mut position = 0;
+    while position + 1 < function.body.len() {
+        match (&function.body[position], &function.body[position + 1]) {
+            (Instr::Jump(target), Instr::Label(label)) if target == label => {
+                function.body.remove(position);
+            }
+            _ => position += 1,
+        }
+    }
+    let targets: HashSet<String> = function.body.iter()
+        .flat_map(|instr| match instr {
+            Instr::Jump(label) => vec![label.clone()],
+            Instr::Branch { if_true, if_false, .. } => vec![if_true.clone(), if_false.clone()],
+            _ => vec![],
+        })
+        .collect();
+    function.body.retain(|instr| !matches!(instr, Instr::Label(label) if !targets.contains(label)));
+    function.body.len() != old_length
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Expression {
+    Binary(BinaryOp, IrType, Operand, Operand),
+    Unary(UnaryOp, IrType, Operand),
+    IntToFloat(Operand),
+}
+
+impl Expression {
+    fn of(instr: &Instr) -> Option<Expression> {
+        match instr {
+            Instr::Binary { op, ty, lhs, rhs, .. } => Some(Expression::Binary(*op, *ty, lhs.clone(), rhs.clone())),
+            Instr::Unary { op, ty, operand, .. } => Some(Expression::Unary(*op, *ty, operand.clone())),
+            Instr::IntToFloat { src, .. } => Some(Expression::IntToFloat(src.clone())),
+            _ => None,
+        }
+    }
+
+    fn operands(&self) -> Vec<&Operand> {
+        match self {
+            Expression::Binary(_, _, lhs, rhs) => vec![lhs, rhs],
+            Expression::Unary(_, _, operand) | Expression::IntToFloat(operand) => vec![operand],
+        }
+    }
+
+    fn matches(&self, other: &Expression) -> bool {
+        match (self, other) {
+            (Expression::Binary(op, ty, lhs, rhs), Expression::Binary(other_op, other_ty, other_lhs, other_rhs))
+                if op == other_op && ty == other_ty && is_commutative(*op) => {
+                (lhs == other_lhs && rhs == other_rhs) || (lhs == other_rhs && rhs == other_lhs)
+            }
+            _ => self == other,
+        }
+    }
+}
+
+fn is_commutative(op: BinaryOp) -> bool {
+    matches!(op, BinaryOp::Add | BinaryOp::Mul | BinaryOp::And | BinaryOp::Or | BinaryOp::Eq | BinaryOp::Neq)
+}
+
+// Replaces an operation already computed in the block, with operands unchanged
+// since, by a copy of the first result. Addresses cost a single instruction to
+// compute again, less than keeping them, across calls in particular.
+pub fn eliminate_common_subexpressions(function: &mut IrFunction) -> bool {
+    let aliased = address_taken(function);
+    let mut changed = false;
+    // expressions computed in the block and the operand still holding their value
+    let mut available: Vec<(Expression, Operand)> = vec![];
+    for instr in function.body.iter_mut() {
+        if let Instr::Label(_) = instr {
+            available.clear();
+        }
+        let expression = Expression::of(instr);
+        if let (Some(expression), Some(dest)) = (&expression, instr.dest().cloned()) {
+            if let Some((_, holder)) = available.iter().find(|(other, _)| other.matches(expression)) {
+                *instr = Instr::Copy { dest, src: holder.clone() };
+                changed = true;
+            }
+        }
+        if let Some(dest) = instr.dest().cloned() {
+            available.retain(|(other, holder)| *holder != dest && !other.operands().contains(&&dest));
+            if let Some(expression) = expression {
+                let private = expression.operands().into_iter().all(|operand| is_private(operand, &aliased));
+                if private && is_private(&dest, &aliased) && !expression.operands().contains(&&dest) {
+                    available.push((expression, dest));
+                }
+            }
+        }
+        if instr.is_terminator() {
+            available.clear();
+        }
+    }
+    changed
+}
+
+pub fn optimize_function(function: &mut IrFunction, optimizations: &Optimizations) {
+    loop {
+        let mut changed = false;
+        if optimizations.fold_constants {
+            changed |= fold_constants(function, optimizations.fold_floats);
+        }
+        if optimizations.propagate_copies {
+            changed |= propagate_copies(function);
+        }
+        if optimizations.eliminate_common_subexpressions {
+            changed |= eliminate_common_subexpressions(function);
+        }
+        if optimizations.eliminate_dead_stores {
+            changed |= eliminate_dead_stores(function);
+        }
+        if optimizations.eliminate_unreachable_blocks {
+            changed |= eliminate_unreachable_blocks(function);
+        }
+        if !changed {
+            break;
+        }
+    }
+}
+
+pub fn optimize(program: &mut IrProgram, optimizations: &Optimizations) {
+    for function in program.functions.iter_mut() {
+        optimize_function(function, optimizations);
+    }
+}
+
+// Runs after the IR generation pass.
+pub struct OptimizationPass {
+    pub optimizations: Optimizations,
+}
+
+impl Pass for OptimizationPass {
+    fn name(&self) -> String {
+        "optimization".to_string()
+    }
+
+    fn run(&mut self, _program: &mut Program, context: &mut PassContext) {
+        if let Some(ir) = context.ir.as_mut() {
+            optimize(ir, &self.optimizations);
+        }
+    }
+}
@@ -0,0 +1,161 @@
+// Pass manager for the phases that run after parsing. Passes run in the order they
+// were added and share a `PassContext`, which carries the diagnostics reported so
+// far along with whatever an earlier pass produced for the later ones.
+
+use crate::ast::{Program, Span};
+use crate::inheritance::InheritancePass;
+use crate::ir::IrProgram;
+use crate::memory_layout::MemoryLayoutPass;
+use crate::semantic_checks::SemanticCheckPass;
+use crate::symbol_table::{SymbolTable, SymbolTablePass};
+use crate::synthesis::IrGenerationPass;
+use crate::type_checker::TypeCheckPass;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub span: Span,
+}
+
+impl Diagnostic {
+    pub fn error(message: String, span: Span) -> Self {
+        Diagnostic { severity: Severity::Error, message, span }
+    }
+
+    pub fn warning(message: String, span: Span) -> Self {
+        Diagnostic { severity: Severity::Warning, message, span }
+    }
+
+    pub fn as_string(&self) -> String {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        format!("[{}] line {}: {}", severity, self.span.line, self.message)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct PassContext {
+    pub diagnostics: Vec<Diagnostic>,
+    // names of the passes that ran, in order
+    pub completed_passes: Vec<String>,
+    pub symbol_table: Option<SymbolTable>,
+    pub ir: Option<IrProgram>,
+}
+
+impl PassContext {
+    pub fn new() -> Self {
+        PassContext::default()
+    }
+
+    pub fn error(&mut self, message: String, span: Span) {
+        self.diagnostics.push(Diagnostic::error(message, span));
+    }
+
+    pub fn warning(&mut self, message: String, span: Span) {
+        self.diagnostics.push(Diagnostic::warning(message, span));
+    }
+
+    // Adds the diagnostics of a pass, leaving out those an earlier pass already
+    // reported, such as an undeclared variable found by two checks.
+    pub fn report(&mut self, diagnostics: Vec<Diagnostic>) {
+        for diagnostic in diagnostics {
+            if !self.diagnostics.contains(&diagnostic) {
+                self.diagnostics.push(diagnostic);
+            }
+        }
+    }
+
+    // Panics if the symbol table pass has not run yet.
+    pub fn symbol_table(&self) -> &SymbolTable {
+        self.symbol_table.as_ref().expect("symbol table pass should run first")
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+
+    pub fn errors(&self) -> Vec<&Diagnostic> {
+        self.diagnostics.iter().filter(|diagnostic| diagnostic.severity == Severity::Error).collect()
+    }
+
+    pub fn warnings(&self) -> Vec<&Diagnostic> {
+        self.diagnostics.iter().filter(|diagnostic| diagnostic.severity == Severity::Warning).collect()
+    }
+}
+
+pub trait Pass {
+    fn name(&self) -> String;
+    fn run(&mut self, program: &mut Program, context: &mut PassContext);
+
+    // Whether the pass assumes a program without errors. Passes that only check
+    // the program return false, so they still run after an error.
+    fn needs_valid_program(&self) -> bool {
+        true
+    }
+}
+
+// The usual pipeline is symbol table creation, type checking, memory sizing and
+// code generation. The checking passes all run so that every diagnostic is
+// reported; the pipeline stops before the first pass needing a valid program
+// once one of them has reported an error.
+#[derive(Default)]
+pub struct PassManager {
+    passes: Vec<Box<dyn Pass>>,
+}
+
+impl PassManager {
+    pub fn new() -> Self {
+        PassManager::default()
+    }
+
+    // The passes checking the program, which the interpreter can run afterwards.
+    pub fn front_end() -> Self {
+        let mut manager = PassManager::new();
+        manager.add_pass(Box::new(SymbolTablePass));
+        manager.add_pass(Box::new(InheritancePass));
+        manager.add_pass(Box::new(SemanticCheckPass));
+        manager.add_pass(Box::new(TypeCheckPass));
+        manager
+    }
+
+    // The front end, then the memory layout and the generation of the IR.
+    pub fn code_generation() -> Self {
+        let mut manager = PassManager::front_end();
+        manager.add_pass(Box::new(MemoryLayoutPass));
+        manager.add_pass(Box::new(IrGenerationPass));
+        manager
+    }
+
+    pub fn add_pass(&mut self, pass: Box<dyn Pass>) {
+        self.passes.push(pass);
+    }
+
+    pub fn pass_names(&self) -> Vec<String> {
+        self.passes.iter().map(|pass| pass.name()).collect()
+    }
+
+    pub fn run(&mut self, program: &mut Program) -> PassContext {
+        let mut context = PassContext::new();
+        self.run_with_context(program, &mut context);
+        context
+    }
+
+    pub fn run_with_context(&mut self, program: &mut Program, context: &mut PassContext) {
+        for pass in self.passes.iter_mut() {
+            if pass.needs_valid_program() && context.has_errors() {
+                break;
+            }
+            pass.run(program, context);
+            context.completed_passes.push(pass.name());
+        }
+    }
+}
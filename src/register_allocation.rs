@@ -0,0 +1,115 @@
+// Linear scan register allocation (Poletto and Sarkar) of the temporaries of an
+// IR function. A temporary lives from its first to its last position in the body
+// where liveness says it holds a value, so one live around a loop is kept alive
+// until the jump back. Intervals are visited by start, and when no register is
+// free the one ending last, either the new interval or an active one, is spilled:
+// it stays in its frame slot, like every named variable. The allocator only knows
+// register names, so every back end passes the registers it can spare.
+
+use std::collections::BTreeMap;
+use crate::cfg::ControlFlowGraph;
+use crate::data_flow::{solve, Liveness, Variable};
+use crate::ir::*;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LiveInterval {
+    pub temp: usize,
+    // positions of the first and last instruction using the temporary
+    pub start: usize,
+    pub end: usize,
+}
+
+impl LiveInterval {
+    // Whether the temporary holds a value needed after the instruction at `position`
+    // and set before it.
+    pub fn is_live_across(&self, position: usize) -> bool {
+        self.start < position && position < self.end
+    }
+}
+
+fn temp_number(operand: &Operand) -> Option<usize> {
+    match operand {
+        Operand::Temp(number) => Some(*number),
+        _ => None,
+    }
+}
+
+// Intervals of the temporaries of `function`, by temporary number: the positions
+// where a temporary is used, assigned or live afterwards.
+pub fn live_intervals(function: &IrFunction) -> Vec<LiveInterval> {
+    let liveness = solve(&ControlFlowGraph::build(function), &Liveness);
+    let mut intervals: BTreeMap<usize, LiveInterval> = BTreeMap::new();
+    for (position, instr) in function.body.iter().enumerate() {
+        let live_after = liveness.after[position].iter().filter_map(|variable| match variable {
+            Variable::Temp(number) => Some(*number),
+            _ => None,
+        });
+        let appearances = instr.uses().into_iter().chain(instr.dest()).filter_map(temp_number);
+        for temp in appearances.chain(live_after) {
+            intervals.entry(temp)
+                .and_modify(|interval| {
+                    interval.start = interval.start.min(position);
+                    interval.end = interval.end.max(position);
+                })
+                .or_insert(LiveInterval { temp, start: position, end: position });
+        }
+    }
+    intervals.into_values().collect()
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Allocation {
+    pub intervals: Vec<LiveInterval>,
+    pub registers: BTreeMap<usize, String>,
+    pub spilled: Vec<usize>,
+}
+
+impl Allocation {
+    pub fn register(&self, operand: &Operand) -> Option<&str> {
+        temp_number(operand).and_then(|temp| self.registers.get(&temp)).map(|register| register.as_str())
+    }
+
+    // Registers holding values needed after the instruction at `position`, which
+    // a call there must preserve.
+    pub fn live_across(&self, position: usize) -> Vec<(usize, &str)> {
+        self.intervals.iter()
+            .filter(|interval| interval.is_live_across(position))
+            .filter_map(|interval| self.registers.get(&interval.temp).map(|register| (interval.temp, register.as_str())))
+            .collect()
+    }
+}
+
+pub fn allocate_registers(function: &IrFunction, available: &[&str]) -> Allocation {
+    let mut intervals = live_intervals(function);
+    intervals.sort_by_key(|interval| (interval.start, interval.temp));
+    let mut allocation = Allocation::default();
+    let mut free: Vec<String> = available.iter().rev().map(|register| register.to_string()).collect();
+    // allocated intervals still live, by increasing end
+    let mut active: Vec<LiveInterval> = vec![];
+    for interval in &intervals {
+        while !active.is_empty() && active[0].end < interval.start {
+            let expired = active.remove(0);
+            free.push(allocation.registers[&expired.temp].clone());
+        }
+        let register = match free.pop() {
+            Some(register) => register,
+            None => match active.last() {
+                Some(last) if last.end > interval.end => {
+                    let spilled = active.pop().unwrap();
+                    allocation.spilled.push(spilled.temp);
+                    allocation.registers.remove(&spilled.temp).unwrap()
+                }
+                _ => {
+                    allocation.spilled.push(interval.temp);
+                    continue;
+                }
+            },
+        };
+        allocation.registers.insert(interval.temp, register);
+        let position = active.partition_point(|other| other.end <= interval.end);
+        active.insert(position, interval.clone());
+    }
+    allocation.spilled.sort();
+    allocation.intervals = intervals;
+    allocation
+}
@@ -1,7 +1,8 @@
 use crate::lexical_analysis::Token;
 use crate::lexical_analysis::TokenType;
 use crate::semantic_analysis::ProductionElement::{SemanticElement, SyntaxElement};
-use super::semantic_graph::Edge;
+use super::semantic_graph::SemanticTree;
+use crate::ast_actions::AstFragment;
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum SemanticNode {
@@ -9,7 +10,8 @@ pub enum SemanticNode {
     Type{id: usize, type_string: String},
     ArraySize{id: usize, size: usize},
     HigherLevelNode{id: usize, description: String},
-    Marker{id: usize}
+    Marker{id: usize},
+    Ast{id: usize, fragment: AstFragment}
 }
 
 impl SemanticNode {
@@ -30,6 +32,20 @@ impl SemanticNode {
             SemanticNode::Marker { id } => {
                 return format!("Marker_ID{}", id);
             }
+            SemanticNode::Ast { id, fragment } => {
+                return format!("{}_ID{}", fragment.label(), id);
+            }
+        }
+    }
+
+    pub fn id(&self) -> usize {
+        match self {
+            SemanticNode::Identifier { id, .. } => *id,
+            SemanticNode::Type { id, .. } => *id,
+            SemanticNode::ArraySize { id, .. } => *id,
+            SemanticNode::HigherLevelNode { id, .. } => *id,
+            SemanticNode::Marker { id } => *id,
+            SemanticNode::Ast { id, .. } => *id,
         }
     }
 
@@ -53,6 +69,10 @@ impl SemanticNode {
         SemanticNode::Marker { id: all_semantic_nodes.len() }     
     }
 
+    pub fn new_ast_node(all_semantic_nodes: &Vec<SemanticNode>, fragment: AstFragment) -> SemanticNode {
+        SemanticNode::Ast { id: all_semantic_nodes.len(), fragment }
+    }
+
     pub fn is_marker(&self) -> bool {
         matches!(self, SemanticNode::Marker { id })
     }
@@ -63,8 +83,7 @@ pub trait SemanticAction {
     fn take_action(
         &self,
         semantic_stack: &mut Vec<SemanticNode>,
-        all_semantic_nodes: &mut Vec<SemanticNode>,
-        edges: &mut Vec<(SemanticNode, SemanticNode)>,
+        tree: &mut SemanticTree,
         curr_token: Option<&Token>
     );
 }
@@ -75,32 +94,45 @@ impl SemanticAction for CheckStackOneNode {
     fn take_action(
         &self,
         semantic_stack: &mut Vec<SemanticNode>,
-        all_semantic_nodes: &mut Vec<SemanticNode>,
-        edges: &mut Vec<(SemanticNode, SemanticNode)>,
+        tree: &mut SemanticTree,
         curr_token: Option<&Token>
 
     ) {
         assert_eq!(semantic_stack.len(), 1);
         let root_node = semantic_stack.pop()
             .expect("Should be able to get the last remaining node.");
-        let start_node = SemanticNode::new_higher_level_node(all_semantic_nodes, "START".to_string());
-        edges.push((start_node, root_node));
+        let start_node = SemanticNode::new_higher_level_node(&tree.nodes, "START".to_string());
+        let start_id = tree.add_node(start_node);
+        tree.attach_children(start_id, vec![root_node.id()]);
     }
 }
 
-fn push_new_node(
+pub fn push_new_node(
     new_node: SemanticNode,
     semantic_stack: &mut Vec<SemanticNode>,
-    all_semantic_nodes: &mut Vec<SemanticNode>,
+    tree: &mut SemanticTree,
 ) {
     semantic_stack.push(
         new_node.clone()
     );
-    all_semantic_nodes.push(
-        new_node.clone()
+    tree.add_node(
+        new_node
     );
 }
 
+// Adds `new_node` to the tree with `children` (given in source order) and pushes it
+// on the semantic stack.
+pub fn push_parent_node(
+    new_node: SemanticNode,
+    children: Vec<SemanticNode>,
+    semantic_stack: &mut Vec<SemanticNode>,
+    tree: &mut SemanticTree,
+) {
+    let new_id = new_node.id();
+    push_new_node(new_node, semantic_stack, tree);
+    tree.attach_children(new_id, children.iter().map(|child| child.id()).collect());
+}
+
 pub struct PushHigherLevelNode {
     pub description: String,
 }
@@ -109,13 +141,12 @@ impl SemanticAction for PushHigherLevelNode {
     fn take_action(
         &self,
         semantic_stack: &mut Vec<SemanticNode>,
-        all_semantic_nodes: &mut Vec<SemanticNode>,
-        edges: &mut Vec<(SemanticNode, SemanticNode)>,
+        tree: &mut SemanticTree,
         curr_token: Option<&Token>
 
     ) {
-        let new_node = SemanticNode::new_higher_level_node(&all_semantic_nodes, self.description.to_string());
-        push_new_node(new_node, semantic_stack, all_semantic_nodes);
+        let new_node = SemanticNode::new_higher_level_node(&tree.nodes, self.description.to_string());
+        push_new_node(new_node, semantic_stack, tree);
     }
 }
 
@@ -126,14 +157,13 @@ impl SemanticAction for PushIdentifier {
     fn take_action(
         &self,
         semantic_stack: &mut Vec<SemanticNode>,
-        all_semantic_nodes: &mut Vec<SemanticNode>,
-        edges: &mut Vec<(SemanticNode, SemanticNode)>,
+        tree: &mut SemanticTree,
         curr_token: Option<&Token>
     ) {
         let curr_token = curr_token.expect("Expected an identifier token, but none available");
         if matches!(curr_token.token_type, TokenType::Identifier) {
-            let new_node = SemanticNode::new_identifier(&all_semantic_nodes, curr_token.lexeme.to_string());
-            push_new_node(new_node, semantic_stack, all_semantic_nodes);
+            let new_node = SemanticNode::new_identifier(&tree.nodes, curr_token.lexeme.to_string());
+            push_new_node(new_node, semantic_stack, tree);
         } else {
             panic!("Expected an identifier token but got {:?}", curr_token);
         }
@@ -149,12 +179,11 @@ impl SemanticAction for PushType {
     fn take_action(
         &self,
         semantic_stack: &mut Vec<SemanticNode>,
-        all_semantic_nodes: &mut Vec<SemanticNode>,
-        edges: &mut Vec<(SemanticNode, SemanticNode)>,
+        tree: &mut SemanticTree,
         curr_token: Option<&Token>
     ) {
-        let new_node = SemanticNode::new_type(&all_semantic_nodes, self.type_string.to_string());
-        push_new_node(new_node, semantic_stack, all_semantic_nodes);
+        let new_node = SemanticNode::new_type(&tree.nodes, self.type_string.to_string());
+        push_new_node(new_node, semantic_stack, tree);
     }
 }
 
@@ -167,13 +196,12 @@ impl SemanticAction for PushArraySize {
     fn take_action(
         &self,
         semantic_stack: &mut Vec<SemanticNode>,
-        all_semantic_nodes: &mut Vec<SemanticNode>,
-        edges: &mut Vec<(SemanticNode, SemanticNode)>,
+        tree: &mut SemanticTree,
         curr_token: Option<&Token>
 
     ) {
-        let new_node = SemanticNode::new_array_size(&all_semantic_nodes, self.size);
-        push_new_node(new_node, semantic_stack, all_semantic_nodes);
+        let new_node = SemanticNode::new_array_size(&tree.nodes, self.size);
+        push_new_node(new_node, semantic_stack, tree);
     }
 }
 
@@ -183,8 +211,7 @@ impl SemanticAction for PlusGather {
     fn take_action(
         &self,
         semantic_stack: &mut Vec<SemanticNode>,
-        all_semantic_nodes: &mut Vec<SemanticNode>,
-        edges: &mut Vec<(SemanticNode, SemanticNode)>,
+        tree: &mut SemanticTree,
         curr_token: Option<&Token>
 
     ) {
@@ -203,12 +230,11 @@ impl SemanticAction for PlusGather {
             (symbol == "Or") || (symbol == "Minus") || (symbol == "Plus"),
             "The second element needs to be mult,and,or div. stack {:?}", semantic_stack
         );
-        let operand1 = semantic_stack.pop().unwrap();
+        let right_operand = semantic_stack.pop().unwrap();
         let operator = semantic_stack.pop().unwrap();
-        let operand2 = semantic_stack.pop().unwrap();
-        edges.push((operator.clone(), operand1.clone()));
-        edges.push((operator.clone(), operand2.clone()));
-        push_new_node(operator, semantic_stack, all_semantic_nodes)
+        let left_operand = semantic_stack.pop().unwrap();
+        tree.attach_children(operator.id(), vec![left_operand.id(), right_operand.id()]);
+        semantic_stack.push(operator);
     }
 }
 
@@ -219,8 +245,7 @@ impl SemanticAction for LocalVarGather {
     fn take_action(
         &self,
         semantic_stack: &mut Vec<SemanticNode>,
-        all_semantic_nodes: &mut Vec<SemanticNode>,
-        edges: &mut Vec<(SemanticNode, SemanticNode)>,
+        tree: &mut SemanticTree,
         curr_token: Option<&Token>
 
     ) {
@@ -229,11 +254,8 @@ impl SemanticAction for LocalVarGather {
         let array_list_node = semantic_stack.pop().unwrap();
         let type_node = semantic_stack.pop().unwrap();
         let id_node = semantic_stack.pop().unwrap();
-        let local_var_node = SemanticNode::new_higher_level_node(all_semantic_nodes, "LocalVarDecl".into());
-        edges.push((local_var_node.clone(), id_node.clone()));
-        edges.push((local_var_node.clone(), type_node.clone()));
-        edges.push((local_var_node.clone(), array_list_node.clone()));
-        push_new_node(local_var_node, semantic_stack, all_semantic_nodes);
+        let local_var_node = SemanticNode::new_higher_level_node(&tree.nodes, "LocalVarDecl".into());
+        push_parent_node(local_var_node, vec![id_node, type_node, array_list_node], semantic_stack, tree);
     }
 }
 
@@ -244,8 +266,7 @@ impl SemanticAction for FunctionGather {
     fn take_action(
         &self,
         semantic_stack: &mut Vec<SemanticNode>,
-        all_semantic_nodes: &mut Vec<SemanticNode>,
-        edges: &mut Vec<(SemanticNode, SemanticNode)>,
+        tree: &mut SemanticTree,
         curr_token: Option<&Token>
 
     ) {
@@ -253,10 +274,8 @@ impl SemanticAction for FunctionGather {
                 "The semantic stack should have the operands and plus. Stack {:?}", semantic_stack);
         let local_var_decl_list_node = semantic_stack.pop().unwrap();
         let id_node = semantic_stack.pop().unwrap();
-        let function_node = SemanticNode::new_higher_level_node(all_semantic_nodes, "Function".into());
-        edges.push((function_node.clone(), id_node.clone()));
-        edges.push((function_node.clone(), local_var_decl_list_node.clone()));
-        push_new_node(function_node, semantic_stack, all_semantic_nodes)
+        let function_node = SemanticNode::new_higher_level_node(&tree.nodes, "Function".into());
+        push_parent_node(function_node, vec![id_node, local_var_decl_list_node], semantic_stack, tree)
     }
 }
 
@@ -267,8 +286,7 @@ impl SemanticAction for FunctionGatherFull {
     fn take_action(
         &self,
         semantic_stack: &mut Vec<SemanticNode>,
-        all_semantic_nodes: &mut Vec<SemanticNode>,
-        edges: &mut Vec<(SemanticNode, SemanticNode)>,
+        tree: &mut SemanticTree,
         curr_token: Option<&Token>
 
     ) {
@@ -277,11 +295,13 @@ impl SemanticAction for FunctionGatherFull {
         let assign_statement_list_node = semantic_stack.pop().unwrap();
         let local_var_decl_list_node = semantic_stack.pop().unwrap();
         let id_node = semantic_stack.pop().unwrap();
-        let function_node = SemanticNode::new_higher_level_node(all_semantic_nodes, "Function".into());
-        edges.push((function_node.clone(), id_node.clone()));
-        edges.push((function_node.clone(), local_var_decl_list_node.clone()));
-        edges.push((function_node.clone(), assign_statement_list_node.clone()));
-        push_new_node(function_node, semantic_stack, all_semantic_nodes)
+        let function_node = SemanticNode::new_higher_level_node(&tree.nodes, "Function".into());
+        push_parent_node(
+            function_node,
+            vec![id_node, local_var_decl_list_node, assign_statement_list_node],
+            semantic_stack,
+            tree
+        )
     }
 }
 
@@ -294,21 +314,18 @@ impl SemanticAction for Gather {
     fn take_action(
         &self,
         semantic_stack: &mut Vec<SemanticNode>,
-        all_semantic_nodes: &mut Vec<SemanticNode>,
-        edges: &mut Vec<(SemanticNode, SemanticNode)>,
+        tree: &mut SemanticTree,
         curr_token: Option<&Token>
 
     ) {
         let semantic_stack_initial_size = semantic_stack.len();
         assert!(semantic_stack.len() >= self.num_nodes_to_gather,
                 "The semantic stack doesn't have enough nodes for gather {:?} Stack {:?}", self.gather_type.as_str(), semantic_stack);
-        let new_node = SemanticNode::new_higher_level_node(all_semantic_nodes, self.gather_type.to_string());
-        for _ in 0..self.num_nodes_to_gather {
-            let node_to_collect = semantic_stack.pop().unwrap();
-            edges.push((new_node.clone(), node_to_collect.clone()));
-        }    
+        let new_node = SemanticNode::new_higher_level_node(&tree.nodes, self.gather_type.to_string());
+        // the stack holds the nodes in source order, so gather the top of the stack as is
+        let nodes_to_collect = semantic_stack.split_off(semantic_stack_initial_size - self.num_nodes_to_gather);
         assert_eq!(semantic_stack.len(), semantic_stack_initial_size - self.num_nodes_to_gather);
-        push_new_node(new_node, semantic_stack, all_semantic_nodes);
+        push_parent_node(new_node, nodes_to_collect, semantic_stack, tree);
     }
 }
 
@@ -318,16 +335,14 @@ impl SemanticAction for ProgramGather {
     fn take_action(
         &self,
         semantic_stack: &mut Vec<SemanticNode>,
-        all_semantic_nodes: &mut Vec<SemanticNode>,
-        edges: &mut Vec<(SemanticNode, SemanticNode)>,
+        tree: &mut SemanticTree,
         curr_token: Option<&Token>
     ) {
         assert!(semantic_stack.len() >= 1,
                 "The semantic stack should have the operands and plus. Stack {:?}", semantic_stack);
         let function_list_node = semantic_stack.pop().unwrap();
-        let program_node = SemanticNode::new_higher_level_node(all_semantic_nodes, "Program".into());
-        edges.push((program_node.clone(), function_list_node.clone()));
-        push_new_node(program_node, semantic_stack, all_semantic_nodes);
+        let program_node = SemanticNode::new_higher_level_node(&tree.nodes, "Program".into());
+        push_parent_node(program_node, vec![function_list_node], semantic_stack, tree);
     }
 }
 
@@ -338,8 +353,7 @@ impl SemanticAction for AssignStatementGather {
     fn take_action(
         &self,
         semantic_stack: &mut Vec<SemanticNode>,
-        all_semantic_nodes: &mut Vec<SemanticNode>,
-        edges: &mut Vec<(SemanticNode, SemanticNode)>,
+        tree: &mut SemanticTree,
         curr_token: Option<&Token>
 
     ) {
@@ -347,10 +361,8 @@ impl SemanticAction for AssignStatementGather {
                 "The semantic stack doesn't have enough elements. Stack {:?}", semantic_stack);
         let righthand_side = semantic_stack.pop().unwrap();
         let left_hand_side = semantic_stack.pop().unwrap();
-        let assign_statement_node = SemanticNode::new_higher_level_node(all_semantic_nodes, "AssignStatement".into());
-        edges.push((assign_statement_node.clone(), left_hand_side.clone()));
-        edges.push((assign_statement_node.clone(), righthand_side.clone()));
-        push_new_node(assign_statement_node, semantic_stack, all_semantic_nodes)
+        let assign_statement_node = SemanticNode::new_higher_level_node(&tree.nodes, "AssignStatement".into());
+        push_parent_node(assign_statement_node, vec![left_hand_side, righthand_side], semantic_stack, tree)
     }
 }
 
@@ -361,8 +373,7 @@ impl SemanticAction for MultGather {
     fn take_action(
         &self,
         semantic_stack: &mut Vec<SemanticNode>,
-        all_semantic_nodes: &mut Vec<SemanticNode>,
-        edges: &mut Vec<(SemanticNode, SemanticNode)>,
+        tree: &mut SemanticTree,
         curr_token: Option<&Token>
 
     ) {
@@ -381,11 +392,10 @@ impl SemanticAction for MultGather {
             (symbol == "And") || (symbol == "Div") || (symbol == "Mult"),
             "The second element needs to be mult,and,or div. stack {:?}", semantic_stack
         );
-        let operand1 = semantic_stack.pop().unwrap();
+        let right_operand = semantic_stack.pop().unwrap();
         let operator = semantic_stack.pop().unwrap();
-        let operand2 = semantic_stack.pop().unwrap();
-        edges.push((operator.clone(), operand1.clone()));
-        edges.push((operator.clone(), operand2.clone()));
+        let left_operand = semantic_stack.pop().unwrap();
+        tree.attach_children(operator.id(), vec![left_operand.id(), right_operand.id()]);
         semantic_stack.push(operator);
     }
 }
@@ -396,13 +406,12 @@ impl SemanticAction for MarkListBegin {
     fn take_action(
         &self,
         semantic_stack: &mut Vec<SemanticNode>,
-        all_semantic_nodes: &mut Vec<SemanticNode>,
-        edges: &mut Vec<(SemanticNode, SemanticNode)>,
+        tree: &mut SemanticTree,
         curr_token: Option<&Token>
 
     ) {
-        let new_marker_node = SemanticNode::new_marker_node(all_semantic_nodes);
-        push_new_node(new_marker_node, semantic_stack, all_semantic_nodes);
+        let new_marker_node = SemanticNode::new_marker_node(&tree.nodes);
+        push_new_node(new_marker_node, semantic_stack, tree);
     }
 }
 
@@ -414,8 +423,7 @@ impl SemanticAction for CollectList {
     fn take_action(
         &self,
         semantic_stack: &mut Vec<SemanticNode>,
-        all_semantic_nodes: &mut Vec<SemanticNode>,
-        edges: &mut Vec<(SemanticNode, SemanticNode)>,
+        tree: &mut SemanticTree,
         curr_token: Option<&Token>
 
     ) {
@@ -427,14 +435,13 @@ impl SemanticAction for CollectList {
             collected_elements.push(semantic_stack.pop().unwrap())
         }
         assert!(semantic_stack.pop().unwrap().is_marker());
+        // elements were popped last to first
+        collected_elements.reverse();
         let list_node = SemanticNode::new_higher_level_node(
-            all_semantic_nodes,
+            &tree.nodes,
             format!("{}", self.list_name)
         );
-        for collected_item in collected_elements {
-            edges.push((list_node.clone(), collected_item.clone()))
-        }
-        push_new_node(list_node, semantic_stack, all_semantic_nodes);
+        push_parent_node(list_node, collected_elements, semantic_stack, tree);
     }
 }
 
@@ -461,8 +468,8 @@ pub fn get_only_syntax_elements(production: &str) -> Vec<ProductionElement> {
     return production_parts[2..].into_iter().map(|x| SyntaxElement(x.to_string())).collect()
 }
 
-pub fn get_production_elements(production_string: &String) -> Vec<ProductionElement> {
-    match production_string.as_str() {
+pub fn get_production_elements(production_string: &str) -> Vec<ProductionElement> {
+    match production_string {
         "START → ARITHEXPR eof" => {
             return vec![
                 SyntaxElement("ARITHEXPR".to_string()),
@@ -757,6 +764,6 @@ pub fn get_production_elements(production_string: &String) -> Vec<ProductionElem
                 SyntaxElement("id".into()),
             ];
         }
-        _ => panic!("Can't add semantics to ({}) at the moment", production_string.as_str())
+        _ => panic!("Can't add semantics to ({}) at the moment", production_string)
     };
 }
@@ -0,0 +1,317 @@
+// Declaration checks that run on the symbol tables: undeclared and multiply
+// declared identifiers, member functions without a definition (or definitions
+// without a declaration), shadowed inherited members and overloads.
+
+use crate::ast::*;
+use crate::passes::{Diagnostic, Pass, PassContext};
+use crate::symbol_table::*;
+use crate::visitor::{walk_expr, Visitor};
+
+struct DeclarationChecker<'a> {
+    global: &'a SymbolTable,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl DeclarationChecker<'_> {
+    fn error(&mut self, message: String, span: Span) {
+        self.diagnostics.push(Diagnostic::error(message, span));
+    }
+
+    fn warning(&mut self, message: String, span: Span) {
+        self.diagnostics.push(Diagnostic::warning(message, span));
+    }
+
+    fn check_type(&mut self, type_name: &TypeName, span: Span) {
+        if let TypeName::Class(class_name) = type_name {
+            if self.global.lookup_kind(class_name, SymbolKind::Class).is_none() {
+                self.error(format!("undeclared class '{}'", class_name), span);
+            }
+        }
+    }
+
+    // Only parameters can have unsized dimensions, since the caller provides the array.
+    fn check_dims(&mut self, var: &VarDecl, allow_unsized: bool) {
+        if var.dims.contains(&Some(0)) {
+            self.error(format!("array '{}' has a dimension of size 0", var.name), var.span);
+        }
+        if var.dims.iter().any(|dim| dim.is_some_and(|size| size > i32::MAX as usize)) {
+            self.error(format!("array '{}' has a dimension larger than {}", var.name, i32::MAX), var.span);
+        }
+        if !allow_unsized && var.dims.contains(&None) {
+            self.error(format!("array '{}' needs a size for every dimension", var.name), var.span);
+        }
+    }
+
+    fn check_classes(&mut self, program: &Program) {
+        for (index, class) in program.classes.iter().enumerate() {
+            if program.classes[..index].iter().any(|other| other.name == class.name) {
+                self.error(format!("multiply declared class '{}'", class.name), class.span);
+            }
+            for parent in &class.parents {
+                self.check_type(&TypeName::Class(parent.clone()), class.span);
+            }
+            for (member_index, member) in class.members.iter().enumerate() {
+                let earlier = &class.members[..member_index];
+                match member {
+                    MemberDecl::Attribute { var, .. } => {
+                        self.check_type(&var.type_name, var.span);
+                        self.check_dims(var, false);
+                        let is_duplicate = earlier.iter()
+                            .any(|other| matches!(other, MemberDecl::Attribute { var: other, .. } if other.name == var.name));
+                        if is_duplicate {
+                            self.error(format!("multiply declared data member '{}' in class '{}'", var.name, class.name), var.span);
+                        }
+                    }
+                    MemberDecl::Function { sig, .. } => {
+                        self.check_sig(sig);
+                        let overloads: Vec<&FuncSig> = earlier.iter()
+                            .filter_map(|other| match other {
+                                MemberDecl::Function { sig: other, .. } if other.name == sig.name => Some(other),
+                                _ => None,
+                            })
+                            .collect();
+                        if overloads.iter().any(|other| same_param_types(&other.params, &sig.params)) {
+                            self.error(format!("multiply declared member function '{}' in class '{}'", sig.name, class.name), sig.span);
+                        } else if !overloads.is_empty() {
+                            self.warning(format!("overloaded member function '{}' in class '{}'", sig.name, class.name), sig.span);
+                        }
+                    }
+                }
+            }
+            self.check_shadowed_members(class);
+        }
+    }
+
+    fn check_shadowed_members(&mut self, class: &ClassDecl) {
+        let ancestors = self.global.ancestors(&class.name);
+        for member in &class.members {
+            let (name, span, is_function) = match member {
+                MemberDecl::Attribute { var, .. } => (&var.name, var.span, false),
+                MemberDecl::Function { sig, .. } if sig.is_constructor => continue,
+                MemberDecl::Function { sig, .. } => (&sig.name, sig.span, true),
+            };
+            let shadowed = ancestors.iter().find(|ancestor| {
+                self.global.class_table(ancestor).is_some_and(|table| table.entries.iter()
+                    .any(|entry| &entry.name == name && entry.kind.is_function() == is_function && entry.kind != SymbolKind::Inherit))
+            });
+            if let Some(ancestor) = shadowed {
+                let kind = if is_function { "member function" } else { "data member" };
+                self.warning(format!("{} '{}' in class '{}' shadows inherited member of class '{}'", kind, name, class.name, ancestor), span);
+            }
+            // calls are dispatched on the object, so an override must return what
+            // the member function it replaces returns
+            if let MemberDecl::Function { sig, .. } = member {
+                let overridden = ancestors.iter().find_map(|ancestor| {
+                    self.global.class_table(ancestor)?.lookup_signature(&sig.name, &sig.params)
+                        .filter(|entry| entry.type_name != sig.return_type)
+                        .map(|entry| (ancestor, entry.type_name.as_string()))
+                });
+                if let Some((ancestor, return_type)) = overridden {
+                    self.error(format!("member function '{}' in class '{}' overrides one of class '{}' returning {}", name, class.name, ancestor, return_type), span);
+                }
+            }
+        }
+    }
+
+    fn check_sig(&mut self, sig: &FuncSig) {
+        self.check_type(&sig.return_type, sig.span);
+        for (index, param) in sig.params.iter().enumerate() {
+            self.check_type(&param.type_name, param.span);
+            self.check_dims(param, true);
+            if sig.params[..index].iter().any(|other| other.name == param.name) {
+                self.error(format!("multiply declared parameter '{}' in function '{}'", param.name, sig.name), param.span);
+            }
+        }
+    }
+
+    fn check_member_definitions(&mut self, program: &Program) {
+        for class in &program.classes {
+            for member in &class.members {
+                if let MemberDecl::Function { sig, .. } = member {
+                    let is_defined = program.functions.iter().any(|function| {
+                        function.class_name.as_ref() == Some(&class.name)
+                            && function.sig.name == sig.name
+                            && same_param_types(&function.sig.params, &sig.params)
+                    });
+                    if !is_defined {
+                        self.error(format!("member function '{}::{}' is declared but not defined", class.name, sig.name), sig.span);
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_functions(&mut self, program: &Program) {
+        for (index, function) in program.functions.iter().enumerate() {
+            let earlier = &program.functions[..index];
+            if let Some(class_name) = &function.class_name {
+                if self.global.lookup_kind(class_name, SymbolKind::Class).is_none() {
+                    self.error(format!("undeclared class '{}'", class_name), function.span);
+                } else if self.global.class_table(class_name).unwrap().lookup_signature(&function.sig.name, &function.sig.params).is_none() {
+                    self.error(format!("definition of '{}' has no matching declaration", function.qualified_name()), function.span);
+                }
+            } else {
+                self.check_sig(&function.sig);
+            }
+            let overloads: Vec<&FuncDef> = earlier.iter()
+                .filter(|other| other.class_name == function.class_name && other.sig.name == function.sig.name)
+                .collect();
+            if overloads.iter().any(|other| same_param_types(&other.sig.params, &function.sig.params)) {
+                self.error(format!("multiply defined function '{}'", function.qualified_name()), function.span);
+            } else if !overloads.is_empty() && function.class_name.is_none() {
+                self.warning(format!("overloaded free function '{}'", function.sig.name), function.span);
+            }
+            self.check_body(function);
+        }
+    }
+
+    fn check_body(&mut self, function: &FuncDef) {
+        let table = match self.global.function_table(function) {
+            Some(table) => table.clone(),
+            None => function_table_from(function.qualified_name(), &function.sig, &function.body),
+        };
+        let mut locals_seen: Vec<&str> = function.sig.params.iter().map(|param| param.name.as_str()).collect();
+        for stmt in &function.body {
+            if let Stmt::Local { var, .. } = stmt {
+                self.check_type(&var.type_name, var.span);
+                self.check_dims(var, false);
+                if locals_seen.contains(&var.name.as_str()) {
+                    self.error(format!("multiply declared local variable '{}' in function '{}'", var.name, function.qualified_name()), var.span);
+                }
+                locals_seen.push(&var.name);
+            }
+        }
+        let mut body_checker = BodyChecker {
+            global: self.global,
+            table: &table,
+            class_name: function.class_name.clone(),
+            diagnostics: vec![],
+        };
+        body_checker.visit_func_def(function);
+        self.diagnostics.extend(body_checker.diagnostics);
+    }
+}
+
+// Resolves the identifiers used in one function body.
+struct BodyChecker<'a> {
+    global: &'a SymbolTable,
+    table: &'a SymbolTable,
+    class_name: Option<String>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl BodyChecker<'_> {
+    fn lookup_variable(&self, name: &str) -> Option<SymbolEntry> {
+        if let Some(entry) = self.table.entries.iter().find(|entry| entry.name == name) {
+            return Some(entry.clone());
+        }
+        let class_name = self.class_name.as_ref()?;
+        if name == "self" {
+            return Some(SymbolEntry::new(name.to_string(), SymbolKind::Local, TypeName::Class(class_name.clone()), Span::default()));
+        }
+        self.global.lookup_member(class_name, name).into_iter()
+            .find(|entry| entry.kind == SymbolKind::Attribute)
+            .cloned()
+    }
+
+    // Class of an expression whose type is a class (and not an array of them).
+    fn class_of(&self, expr: &Expr) -> Option<String> {
+        let (type_name, dims) = self.type_of(expr)?;
+        match type_name {
+            TypeName::Class(class_name) if dims == 0 => Some(class_name),
+            _ => None,
+        }
+    }
+
+    fn type_of(&self, expr: &Expr) -> Option<(TypeName, usize)> {
+        match expr {
+            Expr::Var { name, .. } => self.lookup_variable(name).map(|entry| (entry.type_name, entry.dims.len())),
+            Expr::Index { base, .. } => {
+                let (type_name, dims) = self.type_of(base)?;
+                if dims == 0 { None } else { Some((type_name, dims - 1)) }
+            }
+            Expr::Member { base, name, .. } => {
+                let class_name = self.class_of(base)?;
+                self.global.lookup_member(&class_name, name).into_iter()
+                    .find(|entry| entry.kind == SymbolKind::Attribute)
+                    .map(|entry| (entry.type_name.clone(), entry.dims.len()))
+            }
+            Expr::Call { receiver, name, .. } => {
+                let entries = match receiver {
+                    Some(receiver) => self.global.lookup_member(&self.class_of(receiver)?, name),
+                    None => self.global.lookup(name),
+                };
+                entries.into_iter()
+                    .find(|entry| entry.kind == SymbolKind::Function)
+                    .map(|entry| (entry.type_name.clone(), 0))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Visitor for BodyChecker<'_> {
+    fn visit_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Var { name, span } if self.lookup_variable(name).is_none() => {
+                self.diagnostics.push(Diagnostic::error(format!("undeclared local variable '{}'", name), *span));
+            }
+            Expr::Member { base, name, span } => {
+                if let Some(class_name) = self.class_of(base) {
+                    let is_declared = self.global.lookup_member(&class_name, name).iter()
+                        .any(|entry| entry.kind == SymbolKind::Attribute);
+                    if !is_declared {
+                        self.diagnostics.push(Diagnostic::error(format!("undeclared data member '{}' in class '{}'", name, class_name), *span));
+                    }
+                }
+            }
+            Expr::Call { receiver: Some(receiver), name, span, .. } => {
+                if let Some(class_name) = self.class_of(receiver) {
+                    let is_declared = self.global.lookup_member(&class_name, name).iter()
+                        .any(|entry| entry.kind == SymbolKind::Function);
+                    if !is_declared {
+                        self.diagnostics.push(Diagnostic::error(format!("undeclared member function '{}' in class '{}'", name, class_name), *span));
+                    }
+                }
+            }
+            Expr::Call { receiver: None, name, span, .. } => {
+                let is_free_function = self.global.lookup(name).iter()
+                    .any(|entry| entry.kind == SymbolKind::Function);
+                let is_member_function = self.class_name.as_ref().is_some_and(|class_name| {
+                    self.global.lookup_member(class_name, name).iter().any(|entry| entry.kind == SymbolKind::Function)
+                });
+                if !is_free_function && !is_member_function {
+                    self.diagnostics.push(Diagnostic::error(format!("undeclared free function '{}'", name), *span));
+                }
+            }
+            _ => {}
+        }
+        walk_expr(self, expr);
+    }
+}
+
+pub fn check_declarations(program: &Program, global: &SymbolTable) -> Vec<Diagnostic> {
+    let mut checker = DeclarationChecker { global, diagnostics: vec![] };
+    checker.check_classes(program);
+    checker.check_member_definitions(program);
+    checker.check_functions(program);
+    checker.diagnostics.sort_by_key(|diagnostic| diagnostic.span.line);
+    checker.diagnostics
+}
+
+pub struct SemanticCheckPass;
+
+impl Pass for SemanticCheckPass {
+    fn name(&self) -> String {
+        "semantic-check".to_string()
+    }
+
+    fn needs_valid_program(&self) -> bool {
+        false
+    }
+
+    fn run(&mut self, program: &mut Program, context: &mut PassContext) {
+        let diagnostics = check_declarations(program, context.symbol_table());
+        context.report(diagnostics);
+    }
+}
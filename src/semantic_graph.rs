@@ -1,6 +1,8 @@
 use std::borrow::Cow;
+use std::collections::HashSet;
 use std::io::Write;
 use std::process::Command;
+use crate::semantic_analysis::SemanticNode;
 
 pub type Nd = String;
 pub type Edge = (String, String);
@@ -24,13 +26,16 @@ impl<'a> dot::GraphWalk<'a, Nd, Edge> for Edges {
     fn nodes(&self) -> dot::Nodes<'a, Nd> {
         // (assumes that |N| \approxeq |E|)
         let &Edges(ref v) = self;
-        let mut nodes = Vec::with_capacity(v.len());
+        // keep the order in which nodes first appear so children stay in source order
+        let mut nodes: Vec<Nd> = Vec::with_capacity(v.len());
+        let mut seen: HashSet<&Nd> = HashSet::new();
         for (s, t) in v {
-            nodes.push(s.clone());
-            nodes.push(t.clone());
+            for n in [s, t] {
+                if seen.insert(n) {
+                    nodes.push(n.clone());
+                }
+            }
         }
-        nodes.sort();
-        nodes.dedup();
         Cow::Owned(nodes)
     }
 
@@ -43,3 +48,114 @@ impl<'a> dot::GraphWalk<'a, Nd, Edge> for Edges {
 
     fn target(&self, e: &Edge) -> Nd { e.1.clone() }
 }
+
+// Arena of semantic nodes built by the semantic stack. A node's index is its id, and
+// `children` keeps every node's children in source order.
+pub struct SemanticTree {
+    pub nodes: Vec<SemanticNode>,
+    pub children: Vec<Vec<usize>>,
+    pub parents: Vec<Option<usize>>,
+}
+
+impl SemanticTree {
+    pub fn new() -> Self {
+        SemanticTree { nodes: vec![], children: vec![], parents: vec![] }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn add_node(&mut self, node: SemanticNode) -> usize {
+        let id = self.nodes.len();
+        assert_eq!(node.id(), id, "Node ids should be their position in the tree. Node {:?}", node);
+        self.nodes.push(node);
+        self.children.push(vec![]);
+        self.parents.push(None);
+        id
+    }
+
+    // `children` must be given in source order.
+    pub fn attach_children(&mut self, parent: usize, children: Vec<usize>) {
+        for &child in &children {
+            assert!(self.parents[child].is_none(), "Node {} already has a parent", child);
+            assert_ne!(child, parent, "Node {} cannot be its own child", child);
+            self.parents[child] = Some(parent);
+        }
+        self.children[parent].extend(children);
+    }
+
+    pub fn node(&self, id: usize) -> &SemanticNode {
+        &self.nodes[id]
+    }
+
+    pub fn children(&self, id: usize) -> &[usize] {
+        &self.children[id]
+    }
+
+    pub fn parent(&self, id: usize) -> Option<usize> {
+        self.parents[id]
+    }
+
+    // Nodes without a parent that have at least one child, i.e. the trees that were built.
+    pub fn roots(&self) -> Vec<usize> {
+        (0..self.len())
+            .filter(|&id| self.parents[id].is_none() && !self.children[id].is_empty())
+            .collect()
+    }
+
+    pub fn walk_pre_order<F: FnMut(usize, usize)>(&self, root: usize, visitor: &mut F) {
+        self.walk_pre_order_at_depth(root, 0, visitor)
+    }
+
+    fn walk_pre_order_at_depth<F: FnMut(usize, usize)>(&self, id: usize, depth: usize, visitor: &mut F) {
+        visitor(id, depth);
+        for &child in &self.children[id] {
+            self.walk_pre_order_at_depth(child, depth + 1, visitor);
+        }
+    }
+
+    pub fn walk_post_order<F: FnMut(usize, usize)>(&self, root: usize, visitor: &mut F) {
+        self.walk_post_order_at_depth(root, 0, visitor)
+    }
+
+    fn walk_post_order_at_depth<F: FnMut(usize, usize)>(&self, id: usize, depth: usize, visitor: &mut F) {
+        for &child in &self.children[id] {
+            self.walk_post_order_at_depth(child, depth + 1, visitor);
+        }
+        visitor(id, depth);
+    }
+
+    pub fn pre_order(&self, root: usize) -> Vec<usize> {
+        let mut order = vec![];
+        self.walk_pre_order(root, &mut |id, _| order.push(id));
+        order
+    }
+
+    pub fn post_order(&self, root: usize) -> Vec<usize> {
+        let mut order = vec![];
+        self.walk_post_order(root, &mut |id, _| order.push(id));
+        order
+    }
+
+    // Parent to child edges of the subtree under `root`, in pre-order.
+    pub fn edges(&self, root: usize) -> Edges {
+        let mut edges = vec![];
+        for id in self.pre_order(root) {
+            for &child in &self.children[id] {
+                edges.push((self.nodes[id].as_string(), self.nodes[child].as_string()));
+            }
+        }
+        Edges(edges)
+    }
+}
+
+impl Default for SemanticTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
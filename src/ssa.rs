@@ -0,0 +1,453 @@
+// Static single assignment form of IR functions (Cytron et al.). Dominators come
+// from the iterative algorithm of Cooper, Harvey and Kennedy, and phi nodes go at
+// the iterated dominance frontier of the blocks assigning a variable, only where
+// the variable is live. Renaming walks the dominator tree and gives every
+// assignment of a temporary or of a variable that is not reached through its
+// address a new temporary, so the names of the original function stay for the
+// values they hold on entry: parameters, and locals read before being assigned.
+// Leaving SSA replaces each phi node by copies at the end of its predecessors,
+// on a new block when the predecessor also branches elsewhere; the copies of one
+// edge happen at once, so they are ordered to read every source before it is
+// overwritten. Unreachable blocks are dropped first.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use crate::cfg::ControlFlowGraph;
+use crate::data_flow::{solve, Liveness, Variable};
+use crate::ir::*;
+use crate::optimization::eliminate_unreachable_blocks;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DominatorTree {
+    // immediate dominator of each block, None for the entry and unreachable blocks
+    pub idom: Vec<Option<usize>>,
+    pub children: Vec<Vec<usize>>,
+    // blocks reachable from the entry, in reverse postorder
+    pub order: Vec<usize>,
+}
+
+impl DominatorTree {
+    pub fn build(cfg: &ControlFlowGraph) -> Self {
+        let count = cfg.blocks.len();
+        let mut visited = vec![false; count];
+        let mut postorder = vec![];
+        // (block, index of the next successor to visit)
+        let mut stack = vec![(cfg.entry, 0)];
+        visited[cfg.entry] = true;
+        while let Some((block, next)) = stack.pop() {
+            match cfg.blocks[block].successors.get(next) {
+                Some(successor) => {
+                    stack.push((block, next + 1));
+                    if !visited[*successor] {
+                        visited[*successor] = true;
+                        stack.push((*successor, 0));
+                    }
+                }
+                None => postorder.push(block),
+            }
+        }
+        let order: Vec<usize> = postorder.iter().rev().cloned().collect();
+        let mut rank = vec![usize::MAX; count];
+        for (index, block) in order.iter().enumerate() {
+            rank[*block] = index;
+        }
+        let mut idom: Vec<Option<usize>> = vec![None; count];
+        idom[cfg.entry] = Some(cfg.entry);
+        let intersect = |idom: &Vec<Option<usize>>, mut left: usize, mut right: usize| {
+            while left != right {
+                while rank[left] > rank[right] {
+                    left = idom[left].unwrap();
+                }
+                while rank[right] > rank[left] {
+                    right = idom[right].unwrap();
+                }
+            }
+            left
+        };
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for block in order.iter().skip(1) {
+                let mut new_idom: Option<usize> = None;
+                for predecessor in &cfg.blocks[*block].predecessors {
+                    if idom[*predecessor].is_none() {
+                        continue;
+                    }
+                    new_idom = Some(match new_idom {
+                        Some(current) => intersect(&idom, *predecessor, current),
+                        None => *predecessor,
+                    });
+                }
+                if new_idom != idom[*block] {
+                    idom[*block] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+        idom[cfg.entry] = None;
+        let mut children = vec![vec![]; count];
+        for (block, parent) in idom.iter().enumerate() {
+            if let Some(parent) = parent {
+                children[*parent].push(block);
+            }
+        }
+        DominatorTree { idom, children, order }
+    }
+
+    pub fn is_reachable(&self, block: usize) -> bool {
+        self.order.contains(&block)
+    }
+
+    pub fn dominates(&self, dominator: usize, block: usize) -> bool {
+        let mut current = Some(block);
+        while let Some(ancestor) = current {
+            if ancestor == dominator {
+                return true;
+            }
+            current = self.idom[ancestor];
+        }
+        false
+    }
+
+    // Blocks where the dominance of each block stops: successors of the blocks it
+    // dominates that it does not strictly dominate.
+    pub fn frontiers(&self, cfg: &ControlFlowGraph) -> Vec<BTreeSet<usize>> {
+        let mut frontiers = vec![BTreeSet::new(); cfg.blocks.len()];
+        for block in &self.order {
+            let predecessors = &cfg.blocks[*block].predecessors;
+            if predecessors.len() < 2 {
+                continue;
+            }
+            for predecessor in predecessors.iter().filter(|predecessor| self.is_reachable(**predecessor)) {
+                let mut runner = *predecessor;
+                while Some(runner) != self.idom[*block] {
+                    frontiers[runner].insert(*block);
+                    match self.idom[runner] {
+                        Some(parent) => runner = parent,
+                        None => break,
+                    }
+                }
+            }
+        }
+        frontiers
+    }
+}
+
+// `sources` has one operand per predecessor of the block, in the order of its
+// `predecessors`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Phi {
+    pub variable: Variable,
+    pub dest: Operand,
+    pub sources: Vec<Operand>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SsaFunction {
+    // the function the form was built from, with a frame holding the new temporaries
+    pub function: IrFunction,
+    pub cfg: ControlFlowGraph,
+    pub dominators: DominatorTree,
+    // phi nodes at the start of each block
+    pub phis: Vec<Vec<Phi>>,
+    // `x.2` for the second value of `x`, by new temporary
+    pub versions: BTreeMap<usize, String>,
+}
+
+// Variables assigned by value only, which renaming can give new names.
+fn renamable_variables(function: &IrFunction) -> BTreeSet<Variable> {
+    let aliased: HashSet<&String> = function.body.iter()
+        .filter_map(|instr| match instr {
+            Instr::AddressOf { var, .. } => Some(var),
+            _ => None,
+        })
+        .collect();
+    function.body.iter()
+        .filter_map(|instr| instr.dest())
+        .filter(|dest| !matches!(dest, Operand::Var(name) if aliased.contains(name)))
+        .filter_map(Variable::of)
+        .collect()
+}
+
+struct Renamer<'a> {
+    function: &'a mut IrFunction,
+    cfg: &'a mut ControlFlowGraph,
+    dominators: &'a DominatorTree,
+    phis: &'a mut Vec<Vec<Phi>>,
+    renamable: BTreeSet<Variable>,
+    // current value of each variable, innermost last
+    stacks: BTreeMap<Variable, Vec<Operand>>,
+    counts: BTreeMap<Variable, usize>,
+    versions: BTreeMap<usize, String>,
+}
+
+impl Renamer<'_> {
+    fn new_version(&mut self, variable: &Variable) -> Operand {
+        let ty = self.function.value_type(&variable.operand());
+        let temp = self.function.add_temporary(ty);
+        let count = self.counts.entry(variable.clone()).or_default();
+        *count += 1;
+        if let Operand::Temp(number) = temp {
+            self.versions.insert(number, format!("{}.{}", variable.as_string(), count));
+        }
+        self.stacks.entry(variable.clone()).or_default().push(temp.clone());
+        temp
+    }
+
+    fn current(&self, variable: &Variable) -> Operand {
+        self.stacks.get(variable).and_then(|stack| stack.last().cloned()).unwrap_or_else(|| variable.operand())
+    }
+
+    fn rename(&mut self, block: usize) {
+        let mut defined: Vec<Variable> = vec![];
+        for index in 0..self.phis[block].len() {
+            let variable = self.phis[block][index].variable.clone();
+            self.phis[block][index].dest = self.new_version(&variable);
+            defined.push(variable);
+        }
+        let mut instrs = std::mem::take(&mut self.cfg.blocks[block].instrs);
+        for instr in instrs.iter_mut() {
+            for operand in instr.uses_mut() {
+                if let Some(variable) = Variable::of(operand).filter(|variable| self.renamable.contains(variable)) {
+                    *operand = self.current(&variable);
+                }
+            }
+            if let Some(variable) = instr.dest().and_then(Variable::of).filter(|variable| self.renamable.contains(variable)) {
+                *instr.dest_mut().unwrap() = self.new_version(&variable);
+                defined.push(variable);
+            }
+        }
+        self.cfg.blocks[block].instrs = instrs;
+        for successor in self.cfg.blocks[block].successors.clone() {
+            let index = self.cfg.blocks[successor].predecessors.iter().position(|predecessor| *predecessor == block).unwrap();
+            for phi in 0..self.phis[successor].len() {
+                let value = self.current(&self.phis[successor][phi].variable);
+                self.phis[successor][phi].sources[index] = value;
+            }
+        }
+        for child in self.dominators.children[block].clone() {
+            self.rename(child);
+        }
+        for variable in defined {
+            self.stacks.get_mut(&variable).unwrap().pop();
+        }
+    }
+}
+
+pub fn to_ssa(function: &IrFunction) -> SsaFunction {
+    let mut function = function.clone();
+    eliminate_unreachable_blocks(&mut function);
+    let mut cfg = ControlFlowGraph::build(&function);
+    let dominators = DominatorTree::build(&cfg);
+    let frontiers = dominators.frontiers(&cfg);
+    let liveness = solve(&cfg, &Liveness);
+    let renamable = renamable_variables(&function);
+    let mut phis: Vec<Vec<Phi>> = vec![vec![]; cfg.blocks.len()];
+    for variable in &renamable {
+        let mut pending: Vec<usize> = cfg.blocks.iter().enumerate()
+            .filter(|(_, block)| block.instrs.iter().any(|instr| instr.dest().and_then(Variable::of).as_ref() == Some(variable)))
+            .map(|(index, _)| index)
+            .collect();
+        let mut has_phi: BTreeSet<usize> = BTreeSet::new();
+        while let Some(block) = pending.pop() {
+            for frontier in &frontiers[block] {
+                if has_phi.insert(*frontier) && liveness.block_in[*frontier].contains(variable) {
+                    let sources = vec![variable.operand(); cfg.blocks[*frontier].predecessors.len()];
+                    phis[*frontier].push(Phi { variable: variable.clone(), dest: variable.operand(), sources });
+                    pending.push(*frontier);
+                }
+            }
+        }
+    }
+    let entry = cfg.entry;
+    let mut renamer = Renamer {
+        function: &mut function,
+        cfg: &mut cfg,
+        dominators: &dominators,
+        phis: &mut phis,
+        renamable,
+        stacks: BTreeMap::new(),
+        counts: BTreeMap::new(),
+        versions: BTreeMap::new(),
+    };
+    renamer.rename(entry);
+    let versions = renamer.versions;
+    SsaFunction { function, cfg, dominators, phis, versions }
+}
+
+// Orders the copies of one edge, which all read their source before any writes
+// its destination, using a new temporary to break cycles.
+fn sequence_copies(function: &mut IrFunction, mut copies: Vec<(Operand, Operand)>) -> Vec<Instr> {
+    copies.retain(|(dest, src)| dest != src);
+    let mut instrs = vec![];
+    while !copies.is_empty() {
+        let ready = copies.iter().position(|(dest, _)| !copies.iter().any(|(_, src)| src == dest));
+        match ready {
+            Some(index) => {
+                let (dest, src) = copies.remove(index);
+                instrs.push(Instr::Copy { dest, src });
+            }
+            None => {
+                let src = copies[0].1.clone();
+                let saved = function.add_temporary(function.value_type(&src));
+                instrs.push(Instr::Copy { dest: saved.clone(), src: src.clone() });
+                for (_, other) in copies.iter_mut().filter(|(_, other)| *other == src) {
+                    *other = saved.clone();
+                }
+            }
+        }
+    }
+    instrs
+}
+
+pub fn from_ssa(ssa: &SsaFunction) -> IrFunction {
+    let mut function = ssa.function.clone();
+    let mut blocks: Vec<Vec<Instr>> = ssa.cfg.blocks.iter().map(|block| block.instrs.clone()).collect();
+    let mut edge_blocks: Vec<Vec<Instr>> = vec![];
+    for (block, phis) in ssa.phis.iter().enumerate() {
+        if phis.is_empty() {
+            continue;
+        }
+        for (index, predecessor) in ssa.cfg.blocks[block].predecessors.iter().enumerate() {
+            let copies = phis.iter().map(|phi| (phi.dest.clone(), phi.sources[index].clone())).collect();
+            let copies = sequence_copies(&mut function, copies);
+            let instrs = &mut blocks[*predecessor];
+            match instrs.last_mut() {
+                Some(Instr::Branch { if_true, if_false, .. }) => {
+                    let target = ssa.cfg.blocks[block].name.clone();
+                    let label = format!("S{}", edge_blocks.len() + 1);
+                    if *if_true == target {
+                        *if_true = label.clone();
+                    }
+                    if *if_false == target {
+                        *if_false = label.clone();
+                    }
+                    let mut edge_block = vec![Instr::Label(label)];
+                    edge_block.extend(copies);
+                    edge_block.push(Instr::Jump(target));
+                    edge_blocks.push(edge_block);
+                }
+                Some(last) if last.is_terminator() => {
+                    let terminator = instrs.pop().unwrap();
+                    instrs.extend(copies);
+                    instrs.push(terminator);
+                }
+                _ => instrs.extend(copies),
+            }
+        }
+    }
+    let mut body: Vec<Instr> = blocks.into_iter().flatten().collect();
+    if !edge_blocks.is_empty() && !body.last().is_some_and(|instr| instr.is_terminator()) {
+        body.push(Instr::Return(None));
+    }
+    body.extend(edge_blocks.into_iter().flatten());
+    function.body = body;
+    function
+}
+
+impl SsaFunction {
+    fn operand_string(&self, operand: &Operand) -> String {
+        match operand {
+            Operand::Temp(number) if self.versions.contains_key(number) => self.versions[number].clone(),
+            _ => operand.as_string(),
+        }
+    }
+
+    fn instr_string(&self, instr: &Instr) -> String {
+        let mut instr = instr.clone();
+        let named = |operand: &mut Operand| {
+            if let Operand::Temp(number) = operand {
+                if let Some(version) = self.versions.get(number) {
+                    *operand = Operand::Var(version.clone());
+                }
+            }
+        };
+        instr.uses_mut().into_iter().for_each(named);
+        instr.dest_mut().into_iter().for_each(named);
+        instr.as_string()
+    }
+
+    // The blocks in order with their phi nodes, naming values `x.2`.
+    pub fn as_string(&self) -> String {
+        let function = &self.function;
+        let return_type = match function.return_type {
+            Some(IrType::Int) => "integer",
+            Some(IrType::Float) => "float",
+            None => "void",
+        };
+        let mut output = format!("function {}({}) => {}\n", function.name, function.params.join(", "), return_type);
+        for (index, block) in self.cfg.blocks.iter().enumerate() {
+            if index == self.cfg.entry || index == self.cfg.exit {
+                continue;
+            }
+            output.push_str(&format!("{}:\n", block.name));
+            for phi in &self.phis[index] {
+                let sources: Vec<String> = block.predecessors.iter().zip(&phi.sources)
+                    .map(|(predecessor, source)| format!("{}: {}", self.cfg.blocks[*predecessor].name, self.operand_string(source)))
+                    .collect();
+                output.push_str(&format!("    {} = phi({})\n", self.operand_string(&phi.dest), sources.join(", ")));
+            }
+            for instr in block.instrs.iter().filter(|instr| !matches!(instr, Instr::Label(_))) {
+                output.push_str(&format!("    {}\n", self.instr_string(instr)));
+            }
+        }
+        output
+    }
+
+    // Broken invariants: values assigned more than once, renamed variables still
+    // assigned, phi nodes without one source per predecessor, and uses that the
+    // assignment of their value does not dominate.
+    pub fn verify(&self) -> Vec<String> {
+        let mut errors = vec![];
+        // block and position (phi nodes first) of the assignment of each value
+        let mut definitions: BTreeMap<Variable, (usize, usize)> = BTreeMap::new();
+        let renamed: BTreeSet<Variable> = self.versions.keys().map(|number| Variable::Temp(*number)).collect();
+        for (block, phis) in self.phis.iter().enumerate() {
+            for (index, phi) in phis.iter().enumerate() {
+                let dest = Variable::of(&phi.dest).unwrap();
+                if definitions.insert(dest, (block, index)).is_some() {
+                    errors.push(format!("{} is assigned more than once", self.operand_string(&phi.dest)));
+                }
+                if phi.sources.len() != self.cfg.blocks[block].predecessors.len() {
+                    errors.push(format!("phi of {} in {} has {} sources", self.operand_string(&phi.dest), self.cfg.blocks[block].name, phi.sources.len()));
+                }
+            }
+        }
+        let renamable = renamable_variables(&self.function);
+        for (block, basic_block) in self.cfg.blocks.iter().enumerate() {
+            for (index, instr) in basic_block.instrs.iter().enumerate() {
+                let dest = match instr.dest().and_then(Variable::of) {
+                    Some(dest) => dest,
+                    None => continue,
+                };
+                if !renamed.contains(&dest) && renamable.contains(&dest) {
+                    errors.push(format!("{} is assigned outside SSA form", dest.as_string()));
+                }
+                if definitions.insert(dest.clone(), (block, self.phis[block].len() + index)).is_some() {
+                    errors.push(format!("{} is assigned more than once", self.operand_string(&dest.operand())));
+                }
+            }
+        }
+        let dominated = |operand: &Operand, block: usize, position: usize| match Variable::of(operand).and_then(|variable| definitions.get(&variable)) {
+            Some((definition_block, definition_position)) if *definition_block == block => *definition_position < position,
+            Some((definition_block, _)) => self.dominators.dominates(*definition_block, block),
+            None => true,
+        };
+        for (block, basic_block) in self.cfg.blocks.iter().enumerate() {
+            for phi in &self.phis[block] {
+                for (predecessor, source) in basic_block.predecessors.iter().zip(&phi.sources) {
+                    if !dominated(source, *predecessor, usize::MAX) {
+                        errors.push(format!("{} does not dominate the end of {}", self.operand_string(source), self.cfg.blocks[*predecessor].name));
+                    }
+                }
+            }
+            for (index, instr) in basic_block.instrs.iter().enumerate() {
+                for operand in instr.uses() {
+                    if !dominated(operand, block, self.phis[block].len() + index) {
+                        errors.push(format!("{} does not dominate its use in {}", self.operand_string(operand), basic_block.name));
+                    }
+                }
+            }
+        }
+        errors
+    }
+}
@@ -0,0 +1,434 @@
+// Symbol tables built from the typed AST. The global table has one entry per class
+// and free function; class entries link to a table with the inherited classes,
+// attributes, member functions and constructors; function entries link to a table
+// with the parameters and local variables.
+
+use std::collections::HashSet;
+use crate::ast::*;
+use crate::passes::{Pass, PassContext};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SymbolKind {
+    Class,
+    Inherit,
+    Attribute,
+    Function,
+    Constructor,
+    Parameter,
+    Local,
+    // frame slots added by the memory layout pass
+    ReturnAddress,
+    ReturnValue,
+    Temporary,
+}
+
+impl SymbolKind {
+    pub fn as_string(&self) -> String {
+        let kind = match self {
+            SymbolKind::Class => "class",
+            SymbolKind::Inherit => "inherit",
+            SymbolKind::Attribute => "data",
+            SymbolKind::Function => "function",
+            SymbolKind::Constructor => "constructor",
+            SymbolKind::Parameter => "param",
+            SymbolKind::Local => "local",
+            SymbolKind::ReturnAddress => "retaddr",
+            SymbolKind::ReturnValue => "retval",
+            SymbolKind::Temporary => "temp",
+        };
+        kind.to_string()
+    }
+
+    pub fn is_function(&self) -> bool {
+        matches!(self, SymbolKind::Function | SymbolKind::Constructor)
+    }
+
+    pub fn is_variable(&self) -> bool {
+        matches!(self, SymbolKind::Attribute | SymbolKind::Parameter | SymbolKind::Local)
+    }
+}
+
+// `type_name` is the declared type of a variable, the return type of a function
+// and the class itself for class and inherit entries. `params` is only filled for
+// functions and constructors. `size` and `offset` (in bytes) are filled by the
+// memory layout pass; `offset` is only set for entries that occupy a slot of an
+// object or a stack frame.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SymbolEntry {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub type_name: TypeName,
+    pub dims: Vec<Option<usize>>,
+    pub params: Vec<VarDecl>,
+    pub visibility: Visibility,
+    pub span: Span,
+    pub link: Option<Box<SymbolTable>>,
+    pub size: usize,
+    pub offset: Option<usize>,
+}
+
+impl SymbolEntry {
+    pub fn new(name: String, kind: SymbolKind, type_name: TypeName, span: Span) -> Self {
+        SymbolEntry {
+            name,
+            kind,
+            type_name,
+            dims: vec![],
+            params: vec![],
+            visibility: Visibility::Public,
+            span,
+            link: None,
+            size: 0,
+            offset: None,
+        }
+    }
+
+    pub fn from_var(var: &VarDecl, kind: SymbolKind, visibility: Visibility) -> Self {
+        let mut entry = SymbolEntry::new(var.name.clone(), kind, var.type_name.clone(), var.span);
+        entry.dims = var.dims.clone();
+        entry.visibility = visibility;
+        entry
+    }
+
+    pub fn from_sig(sig: &FuncSig, visibility: Visibility) -> Self {
+        let kind = if sig.is_constructor { SymbolKind::Constructor } else { SymbolKind::Function };
+        let mut entry = SymbolEntry::new(sig.name.clone(), kind, sig.return_type.clone(), sig.span);
+        entry.params = sig.params.clone();
+        entry.visibility = visibility;
+        entry
+    }
+
+    // `integer[2][]` for variables, `(float, integer[]):float` for functions
+    pub fn type_string(&self) -> String {
+        if self.kind.is_function() {
+            let params: Vec<String> = self.params.iter()
+                .map(|param| type_string(&param.type_name, &param.dims))
+                .collect();
+            format!("({}):{}", params.join(", "), self.type_name.as_string())
+        } else {
+            type_string(&self.type_name, &self.dims)
+        }
+    }
+
+    // `f(integer, float[])`, which tells overloads apart
+    pub fn signature(&self) -> String {
+        let params: Vec<String> = self.params.iter()
+            .map(|param| type_string(&param.type_name, &param.dims))
+            .collect();
+        format!("{}({})", self.name, params.join(", "))
+    }
+
+    pub fn has_same_params(&self, params: &[VarDecl]) -> bool {
+        same_param_types(&self.params, params)
+    }
+}
+
+// Whether `entry` declares `function` and links to the table built from it.
+fn is_defined_by(entry: &SymbolEntry, function: &FuncDef) -> bool {
+    entry.kind.is_function()
+        && entry.name == function.sig.name
+        && entry.has_same_params(&function.sig.params)
+        && entry.link.as_ref().is_some_and(|table| table.span == function.sig.span)
+}
+
+pub fn type_string(type_name: &TypeName, dims: &[Option<usize>]) -> String {
+    let mut result = type_name.as_string();
+    for dim in dims {
+        match dim {
+            Some(size) => result.push_str(&format!("[{}]", size)),
+            None => result.push_str("[]"),
+        }
+    }
+    result
+}
+
+pub fn same_param_types(left: &[VarDecl], right: &[VarDecl]) -> bool {
+    left.len() == right.len() && left.iter().zip(right.iter())
+        .all(|(left, right)| left.type_name == right.type_name && left.dims == right.dims)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct SymbolTable {
+    pub name: String,
+    pub entries: Vec<SymbolEntry>,
+    // size of the object or stack frame, once the memory layout pass has run
+    pub size: Option<usize>,
+    // signature a function table was built from, which tells apart the tables of
+    // functions defined more than once
+    pub span: Span,
+}
+
+impl SymbolTable {
+    pub fn new(name: String) -> Self {
+        SymbolTable { name, entries: vec![], size: None, span: Span::default() }
+    }
+
+    pub fn add_entry(&mut self, entry: SymbolEntry) {
+        self.entries.push(entry);
+    }
+
+    // Adds a slot of `size` bytes at the end of the frame and returns its offset.
+    pub fn add_slot(&mut self, name: String, kind: SymbolKind, type_name: TypeName, size: usize) -> usize {
+        let offset = self.size.expect("memory layout should be computed before adding slots");
+        let mut entry = SymbolEntry::new(name, kind, type_name, Span::default());
+        entry.size = size;
+        entry.offset = Some(offset);
+        self.entries.push(entry);
+        self.size = Some(offset + size);
+        offset
+    }
+
+    pub fn add_temporary(&mut self, type_name: TypeName, size: usize) -> (String, usize) {
+        let count = self.entries.iter().filter(|entry| entry.kind == SymbolKind::Temporary).count();
+        let name = format!("t{}", count + 1);
+        let offset = self.add_slot(name.clone(), SymbolKind::Temporary, type_name, size);
+        (name, offset)
+    }
+
+    pub fn lookup(&self, name: &str) -> Vec<&SymbolEntry> {
+        self.entries.iter().filter(|entry| entry.name == name).collect()
+    }
+
+    pub fn lookup_kind(&self, name: &str, kind: SymbolKind) -> Option<&SymbolEntry> {
+        self.entries.iter().find(|entry| entry.name == name && entry.kind == kind)
+    }
+
+    pub fn class_table(&self, class_name: &str) -> Option<&SymbolTable> {
+        self.lookup_kind(class_name, SymbolKind::Class)
+            .and_then(|entry| entry.link.as_deref())
+    }
+
+    fn class_table_mut(&mut self, class_name: &str) -> Option<&mut SymbolTable> {
+        self.entries.iter_mut()
+            .find(|entry| entry.name == class_name && entry.kind == SymbolKind::Class)
+            .and_then(|entry| entry.link.as_deref_mut())
+    }
+
+    // The function or constructor `name` whose parameter types are `params`.
+    pub fn lookup_signature(&self, name: &str, params: &[VarDecl]) -> Option<&SymbolEntry> {
+        self.entries.iter().find(|entry| entry.kind.is_function() && entry.name == name && entry.has_same_params(params))
+    }
+
+    // Members named `name` of the class or, if it has none, of the closest ancestor
+    // that has some. Ancestors are searched depth first in `isa` order, so with
+    // multiple inheritance the first parent listed wins.
+    pub fn lookup_member(&self, class_name: &str, name: &str) -> Vec<&SymbolEntry> {
+        self.find_member(class_name, name).map(|(_, entries)| entries).unwrap_or_default()
+    }
+
+    // Same as `lookup_member`, along with the class that declares the members.
+    pub fn find_member(&self, class_name: &str, name: &str) -> Option<(String, Vec<&SymbolEntry>)> {
+        let mut visited = HashSet::new();
+        self.find_member_helper(class_name, name, &mut visited)
+    }
+
+    fn find_member_helper(&self, class_name: &str, name: &str, visited: &mut HashSet<String>) -> Option<(String, Vec<&SymbolEntry>)> {
+        if !visited.insert(class_name.to_string()) {
+            return None;
+        }
+        let class_table = self.class_table(class_name)?;
+        let found: Vec<&SymbolEntry> = class_table.entries.iter()
+            .filter(|entry| entry.name == name && entry.kind != SymbolKind::Inherit)
+            .collect();
+        if !found.is_empty() {
+            return Some((class_name.to_string(), found));
+        }
+        class_table.entries.iter()
+            .filter(|entry| entry.kind == SymbolKind::Inherit)
+            .find_map(|parent| self.find_member_helper(&parent.name, name, visited))
+    }
+
+    // Class declaring the member function `name` with parameters `params` that a
+    // call on an object of class `class_name` runs: the class itself or the first
+    // ancestor declaring it, searched like `find_member`.
+    pub fn find_override(&self, class_name: &str, name: &str, params: &[VarDecl]) -> Option<String> {
+        let mut visited = HashSet::new();
+        self.find_override_helper(class_name, name, params, &mut visited)
+    }
+
+    fn find_override_helper(&self, class_name: &str, name: &str, params: &[VarDecl], visited: &mut HashSet<String>) -> Option<String> {
+        if !visited.insert(class_name.to_string()) {
+            return None;
+        }
+        let class_table = self.class_table(class_name)?;
+        let declares = class_table.entries.iter()
+            .any(|entry| entry.name == name && entry.kind.is_function() && entry.has_same_params(params));
+        if declares {
+            return Some(class_name.to_string());
+        }
+        class_table.entries.iter()
+            .filter(|entry| entry.kind == SymbolKind::Inherit)
+            .find_map(|parent| self.find_override_helper(&parent.name, name, params, visited))
+    }
+
+    pub fn parents(&self, class_name: &str) -> Vec<String> {
+        self.class_table(class_name)
+            .map(|class_table| class_table.entries.iter()
+                .filter(|entry| entry.kind == SymbolKind::Inherit)
+                .map(|entry| entry.name.clone())
+                .collect())
+            .unwrap_or_default()
+    }
+
+    // Every class reachable through `isa` links, closest first, without `class_name`.
+    pub fn ancestors(&self, class_name: &str) -> Vec<String> {
+        let mut ancestors: Vec<String> = vec![];
+        let mut current = class_name.to_string();
+        let mut next = 0;
+        loop {
+            if let Some(class_table) = self.class_table(&current) {
+                for parent in class_table.entries.iter().filter(|entry| entry.kind == SymbolKind::Inherit) {
+                    if parent.name != class_name && !ancestors.contains(&parent.name) {
+                        ancestors.push(parent.name.clone());
+                    }
+                }
+            }
+            if next == ancestors.len() {
+                return ancestors;
+            }
+            current = ancestors[next].clone();
+            next += 1;
+        }
+    }
+
+    // Table of the function defined by `function`, found through its declaration.
+    // A definition repeating an earlier one has none, since the table of a member
+    // function is built from its first definition.
+    pub fn function_table(&self, function: &FuncDef) -> Option<&SymbolTable> {
+        let scope = match &function.class_name {
+            Some(class_name) => self.class_table(class_name)?,
+            None => self,
+        };
+        scope.entries.iter()
+            .find(|entry| is_defined_by(entry, function))
+            .and_then(|entry| entry.link.as_deref())
+    }
+
+    // Replaces the table of the function defined by `function` with its stack
+    // frame once code generation has added the temporaries.
+    pub fn set_frame(&mut self, function: &FuncDef, frame: SymbolTable) {
+        let scope = match &function.class_name {
+            Some(class_name) => match self.class_table_mut(class_name) {
+                Some(class_table) => class_table,
+                None => return,
+            },
+            None => self,
+        };
+        if let Some(entry) = scope.entries.iter_mut().find(|entry| is_defined_by(entry, function)) {
+            entry.size = frame.size.unwrap_or(entry.size);
+            entry.link = Some(Box::new(frame));
+        }
+    }
+
+    pub fn as_string(&self) -> String {
+        let mut output = String::new();
+        self.write_table(0, &mut output);
+        output
+    }
+
+    fn write_table(&self, depth: usize, output: &mut String) {
+        let indent = "|    ".repeat(depth);
+        let rule = "=".repeat(70 - 5 * depth.min(10));
+        output.push_str(&format!("{}{}\n", indent, rule));
+        match self.size {
+            Some(size) => output.push_str(&format!("{}| table: {} | size: {}\n", indent, self.name, size)),
+            None => output.push_str(&format!("{}| table: {}\n", indent, self.name)),
+        }
+        output.push_str(&format!("{}{}\n", indent, rule));
+        for entry in &self.entries {
+            let mut line = format!("{}| {:<11} | {:<12} | {}", indent, entry.kind.as_string(), entry.name, entry.type_string());
+            if entry.kind.is_variable() || entry.kind.is_function() {
+                line.push_str(&format!(" | {}", entry.visibility.as_string()));
+            }
+            let is_sized = self.size.is_some() || entry.link.as_ref().is_some_and(|link| link.size.is_some());
+            if is_sized {
+                line.push_str(&format!(" | size: {}", entry.size));
+            }
+            if let Some(offset) = entry.offset {
+                line.push_str(&format!(" | offset: {}", offset));
+            }
+            output.push_str(line.trim_end());
+            output.push('\n');
+            if let Some(link) = &entry.link {
+                link.write_table(depth + 1, output);
+            }
+        }
+        output.push_str(&format!("{}{}\n", indent, rule));
+    }
+}
+
+pub fn function_table_from(name: String, sig: &FuncSig, body: &[Stmt]) -> SymbolTable {
+    let mut table = SymbolTable::new(name);
+    table.span = sig.span;
+    for param in &sig.params {
+        table.add_entry(SymbolEntry::from_var(param, SymbolKind::Parameter, Visibility::Public));
+    }
+    // the grammar only allows `localvar` directly in a function body
+    for stmt in body {
+        if let Stmt::Local { var, .. } = stmt {
+            table.add_entry(SymbolEntry::from_var(var, SymbolKind::Local, Visibility::Public));
+        }
+    }
+    table
+}
+
+fn class_table(class: &ClassDecl, program: &Program) -> SymbolTable {
+    let mut table = SymbolTable::new(class.name.clone());
+    for parent in &class.parents {
+        table.add_entry(SymbolEntry::new(parent.clone(), SymbolKind::Inherit, TypeName::Class(parent.clone()), class.span));
+    }
+    for member in &class.members {
+        match member {
+            MemberDecl::Attribute { visibility, var } => {
+                table.add_entry(SymbolEntry::from_var(var, SymbolKind::Attribute, *visibility));
+            }
+            MemberDecl::Function { visibility, sig } => {
+                let mut entry = SymbolEntry::from_sig(sig, *visibility);
+                let name = format!("{}::{}", class.name, sig.name);
+                let definition = program.functions.iter().find(|function| {
+                    function.class_name.as_ref() == Some(&class.name)
+                        && function.sig.name == sig.name
+                        && same_param_types(&function.sig.params, &sig.params)
+                });
+                let table_for_function = match definition {
+                    Some(definition) => function_table_from(name, &definition.sig, &definition.body),
+                    None => function_table_from(name, sig, &[]),
+                };
+                entry.link = Some(Box::new(table_for_function));
+                table.add_entry(entry);
+            }
+        }
+    }
+    table
+}
+
+pub fn build_symbol_tables(program: &Program) -> SymbolTable {
+    let mut global = SymbolTable::new("global".to_string());
+    for class in &program.classes {
+        let mut entry = SymbolEntry::new(class.name.clone(), SymbolKind::Class, TypeName::Class(class.name.clone()), class.span);
+        entry.link = Some(Box::new(class_table(class, program)));
+        global.add_entry(entry);
+    }
+    for function in program.functions.iter().filter(|function| function.class_name.is_none()) {
+        let mut entry = SymbolEntry::from_sig(&function.sig, Visibility::Public);
+        entry.link = Some(Box::new(function_table_from(function.qualified_name(), &function.sig, &function.body)));
+        global.add_entry(entry);
+    }
+    global
+}
+
+pub struct SymbolTablePass;
+
+impl Pass for SymbolTablePass {
+    fn name(&self) -> String {
+        "symbol-table".to_string()
+    }
+
+    fn needs_valid_program(&self) -> bool {
+        false
+    }
+
+    fn run(&mut self, program: &mut Program, context: &mut PassContext) {
+        context.symbol_table = Some(build_symbol_tables(program));
+    }
+}
@@ -1,8 +1,11 @@
 use std::collections::HashMap;
-use crate::lexical_analysis::Token;
+use crate::lexical_analysis::{Scanner, Token};
 use super::lexical_analysis::TokenType;
 use super::semantic_graph::*;
 use super::semantic_analysis::*;
+use crate::ast::Program;
+use crate::ast_actions::get_ast_production_elements;
+use std::collections::HashSet;
 use std::fs::File;
 use std::{fs, io};
 use std::io::{BufRead, Write};
@@ -14,7 +17,7 @@ pub fn write_production(
     focus_idx: usize,
     head: &Vec<String>,
     tail: &Vec<String>,
-    mut output_file: &File
+    output_file: &mut dyn Write
 ) {
     let mut ret = String::from("START -> ");
     ret.push_str(&head.join(" "));
@@ -37,10 +40,10 @@ pub fn parser_helper(
     head: Vec<String>,
     tail: Vec<String>,
     terminal_list: &Vec<String>,
-    output_file: &File,
+    output_file: &mut dyn Write,
     semantic_stack: &mut Vec<SemanticNode>,
-    all_semantic_nodes: &mut Vec<SemanticNode>,
-    edges: &mut Vec<(SemanticNode, SemanticNode)>,
+    tree: &mut SemanticTree,
+    production_semantics: fn(&str) -> Vec<ProductionElement>,
 )
     -> Vec<String>
 {
@@ -55,7 +58,7 @@ pub fn parser_helper(
     assert_eq!(production_parts[1], "→");
     assert!(production_parts[0].chars().all(is_uppercase_or_number),
             "Non terminals should be UPPERCASE OR NUMERIC {}", production_parts[0]);
-    let production_elements = get_production_elements(production);
+    let production_elements = production_semantics(production);
     let only_syntax_elements: Vec<String> = production_elements.iter()
         .filter(|x| x.is_syntax())
         .map(|x| {
@@ -72,10 +75,10 @@ pub fn parser_helper(
     let mut derived_parts: Vec<String> = vec![];
     let mut focus_idx = 0;
     for production_element_obj in production_elements {
-        assert!(semantic_stack.len() <= all_semantic_nodes.len());
+        assert!(semantic_stack.len() <= tree.len());
         match production_element_obj {
             ProductionElement::SemanticElement(handler) =>  {
-                handler.take_action(semantic_stack, all_semantic_nodes, edges, tokens.get(0))
+                handler.take_action(semantic_stack, tree, tokens.get(0))
             }
             ProductionElement::SyntaxElement(syntax_element) =>  {
                 write_production(
@@ -109,8 +112,8 @@ pub fn parser_helper(
                         terminal_list,
                         output_file,
                         semantic_stack,
-                        all_semantic_nodes,
-                        edges
+                        tree,
+                        production_semantics
                     );
                     for terminal in &derivation {
                         assert!(terminal_list.contains(terminal),
@@ -149,11 +152,10 @@ pub fn parse(
     mut output_file: &File,
     output_graph_path: &str
 ) {
-    tokens.push(Token { token_type: TokenType::EndOfFile, lexeme: "eof".to_string() });
+    tokens.push(Token::from(TokenType::EndOfFile, "eof".to_string()));
     let terminal_list = get_terminal_list();
     let mut semantic_stack: Vec<SemanticNode> = vec![];
-    let mut all_semantic_nodes: Vec<SemanticNode> = vec![];
-    let mut edges: Vec<(SemanticNode, SemanticNode)> = vec![];
+    let mut tree = SemanticTree::new();
     parser_helper(
         table_dict,
         tokens,
@@ -161,15 +163,73 @@ pub fn parse(
         vec![],
         vec![],
         &terminal_list,
-        output_file,
+        &mut output_file,
         &mut semantic_stack,
-        &mut all_semantic_nodes,
-        &mut edges
+        &mut tree,
+        get_production_elements
     );
     output_file.write_all("\n Parsed Succesfully".as_bytes())
         .expect("Failed to write");
     let mut file = File::create(output_graph_path)
         .expect("Unable to create graph file");
-    let edges_as_strings: Vec<(String, String)> = edges.iter().map(|e| (e.0.as_string(), e.1.as_string())).collect();
-    render_to(&mut file, Edges(edges_as_strings))
+    let roots = tree.roots();
+    assert_eq!(roots.len(), 1, "The semantic tree should have a single root, found {:?}", roots);
+    render_to(&mut file, tree.edges(roots[0]))
+}
+
+
+pub fn get_full_table_dict() -> HashMap<String, HashMap<String, String>> {
+    let table_dict_string = include_str!("grammars/full_table.json");
+    serde_json::from_str(table_dict_string).expect("Cannot parse the full grammar table")
+}
+
+// Every lowercase symbol used by the table, either as a lookahead or inside a production.
+pub fn get_table_terminal_list(table_dict: &HashMap<String, HashMap<String, String>>) -> Vec<String> {
+    let mut terminals: HashSet<String> = HashSet::new();
+    for productions in table_dict.values() {
+        for (lookahead, production) in productions {
+            terminals.insert(lookahead.clone());
+            for part in production.split_whitespace().skip(2) {
+                if part.chars().all(char::is_lowercase) {
+                    terminals.insert(part.to_string());
+                }
+            }
+        }
+    }
+    let mut terminal_list: Vec<String> = terminals.into_iter().collect();
+    terminal_list.sort();
+    terminal_list
+}
+
+// Parses a whole program with the full grammar and returns its typed AST along with
+// the semantic tree it was built from. The derivation is written to `derivation_output`.
+pub fn parse_program(tokens: &mut Vec<Token>, derivation_output: &mut dyn Write) -> (Program, SemanticTree) {
+    let eof_line = tokens.last().map_or(1, |token| token.line);
+    tokens.push(Token::at_line(TokenType::EndOfFile, "eof".to_string(), eof_line));
+    let table_dict = get_full_table_dict();
+    let terminal_list = get_table_terminal_list(&table_dict);
+    let mut semantic_stack: Vec<SemanticNode> = vec![];
+    let mut tree = SemanticTree::new();
+    parser_helper(
+        &table_dict,
+        tokens,
+        "START".to_string(),
+        vec![],
+        vec![],
+        &terminal_list,
+        derivation_output,
+        &mut semantic_stack,
+        &mut tree,
+        get_ast_production_elements
+    );
+    assert_eq!(semantic_stack.len(), 1, "Only the program should be left on the semantic stack {:?}", semantic_stack);
+    match semantic_stack.pop().unwrap() {
+        SemanticNode::Ast { fragment, .. } => (fragment.into_program(), tree),
+        other => panic!("Expected the program on the semantic stack but got {:?}", other),
+    }
+}
+
+pub fn parse_source(source: String) -> Program {
+    let mut tokens = Scanner::from(source).get_all_tokens();
+    parse_program(&mut tokens, &mut io::sink()).0
 }
@@ -1,3 +1,509 @@
-pub fn hello() {
-    println!("hello from synthesis!");
-}
\ No newline at end of file
+// Lowering of the type-checked AST to the three-address IR of ir.rs. The types of
+// expressions and the overload each call resolves to come from the type checker's
+// `FunctionScope`, sizes and offsets from the memory layout pass. Every function
+// gets its own temporaries and labels; `integer` values are converted with
+// `IntToFloat` wherever a float is expected. Member functions and constructors
+// take the address of their object as a first parameter, `self`, and data members
+// are reached through it. Member function calls go through the virtual function
+// tables, whose addresses are stored in local objects on entry to the function.
+// A function returning an object copies it to a slot of the caller's frame, whose
+// address the caller passes after `self`.
+
+use crate::ast::*;
+use crate::ir::*;
+use crate::memory_layout::{attribute_offset, base_offset, ClassSizes};
+use crate::passes::{Diagnostic, Pass, PassContext};
+use crate::symbol_table::*;
+use crate::type_checker::FunctionScope;
+use crate::types::*;
+use crate::vtables::*;
+
+// Name of the IR function for `name` with parameters `params`, distinct for every
+// overload: `mix(integer, float[])` gives `mix_i_fa`.
+pub fn mangled_name(class_name: Option<&str>, name: &str, params: &[VarDecl]) -> String {
+    let mut mangled = match class_name {
+        Some(class_name) => format!("{}_{}", class_name, name),
+        None => name.to_string(),
+    };
+    for param in params {
+        let code = match &param.type_name {
+            TypeName::Integer => "i".to_string(),
+            TypeName::Float => "f".to_string(),
+            TypeName::Void => "v".to_string(),
+            TypeName::Class(class_name) => class_name.clone(),
+        };
+        mangled.push_str(&format!("_{}{}", code, "a".repeat(param.dims.len())));
+    }
+    mangled
+}
+
+// Where an assigned or read value goes.
+enum Place {
+    Var(String),
+    Memory { address: Operand, offset: usize },
+}
+
+struct FunctionLowering<'a> {
+    scope: FunctionScope<'a>,
+    class_sizes: &'a ClassSizes,
+    frame: SymbolTable,
+    body: Vec<Instr>,
+    label_count: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> FunctionLowering<'a> {
+    fn unsupported(&mut self, feature: &str, span: Span) {
+        self.diagnostics.push(Diagnostic::error(format!("code generation does not support {} yet", feature), span));
+    }
+
+    fn expr_type(&mut self, expr: &Expr) -> Type {
+        self.scope.expr_type(expr).expect("expressions are type checked before code generation")
+    }
+
+    fn value_type(&mut self, expr: &Expr) -> IrType {
+        ir_type(&self.expr_type(expr).type_name)
+    }
+
+    fn new_temp(&mut self, ty: IrType) -> Operand {
+        let (type_name, size) = match ty {
+            IrType::Int => (TypeName::Integer, INTEGER_SIZE),
+            IrType::Float => (TypeName::Float, FLOAT_SIZE),
+        };
+        let (name, _) = self.frame.add_temporary(type_name, size);
+        Operand::Temp(name[1..].parse().unwrap())
+    }
+
+    fn new_label(&mut self) -> String {
+        self.label_count += 1;
+        format!("L{}", self.label_count)
+    }
+
+    fn emit(&mut self, instr: Instr) {
+        self.body.push(instr);
+    }
+
+    fn lower_value(&mut self, expr: &Expr) -> Operand {
+        match expr {
+            Expr::Literal { value: Literal::Int(value), .. } => Operand::Int(*value),
+            Expr::Literal { value, .. } => Operand::Float(value.float_value()),
+            Expr::Unary { op: UnaryOp::Plus, operand, .. } => self.lower_value(operand),
+            Expr::Unary { op, operand, .. } => {
+                let ty = self.value_type(operand);
+                let operand = self.lower_value(operand);
+                let dest = self.new_temp(ty);
+                self.emit(Instr::Unary { dest: dest.clone(), op: *op, ty, operand });
+                dest
+            }
+            Expr::Binary { op, lhs, rhs, .. } => {
+                let ty = match (self.value_type(lhs), self.value_type(rhs)) {
+                    (IrType::Int, IrType::Int) => IrType::Int,
+                    _ => IrType::Float,
+                };
+                let lhs = self.lower_converted(lhs, ty);
+                let rhs = self.lower_converted(rhs, ty);
+                let result_type = if op.is_relational() || op.is_logical() { IrType::Int } else { ty };
+                let dest = self.new_temp(result_type);
+                self.emit(Instr::Binary { dest: dest.clone(), op: *op, ty, lhs, rhs });
+                dest
+            }
+            // a void call used as a value is a type error, so the 0 is never read
+            Expr::Call { .. } => self.lower_call(expr).unwrap_or(Operand::Int(0)),
+            _ => match self.lower_place(expr) {
+                Place::Var(name) => Operand::Var(name),
+                Place::Memory { address, offset } => {
+                    let ty = self.value_type(expr);
+                    let dest = self.new_temp(ty);
+                    self.emit(Instr::Load { dest: dest.clone(), ty, address, offset });
+                    dest
+                }
+            },
+        }
+    }
+
+    // Value of `expr` as a `ty`, which can only differ from its own type for an
+    // integer used as a float.
+    fn lower_converted(&mut self, expr: &Expr, ty: IrType) -> Operand {
+        let expr_type = self.value_type(expr);
+        let value = self.lower_value(expr);
+        if expr_type == ty {
+            return value;
+        }
+        let dest = self.new_temp(IrType::Float);
+        self.emit(Instr::IntToFloat { dest: dest.clone(), src: value });
+        dest
+    }
+
+    fn is_attribute(&self, name: &str) -> bool {
+        matches!(self.scope.lookup_variable(name), Some(entry) if entry.kind == SymbolKind::Attribute)
+    }
+
+    fn lower_place(&mut self, expr: &Expr) -> Place {
+        match expr {
+            Expr::Var { name, .. } if !self.is_attribute(name) => Place::Var(name.clone()),
+            _ => {
+                let (address, offset) = self.lower_address(expr);
+                Place::Memory { address, offset }
+            }
+        }
+    }
+
+    fn is_aggregate_local(&self, name: &str) -> bool {
+        match self.frame.lookup_kind(name, SymbolKind::Local) {
+            Some(entry) => !entry.dims.is_empty() || matches!(entry.type_name, TypeName::Class(_)),
+            None => false,
+        }
+    }
+
+    fn attribute_offset(&self, class_name: &str, name: &str) -> usize {
+        attribute_offset(self.scope.global, class_name, name).expect("data members are declared before code generation")
+    }
+
+    // Address of the `ancestor` part of the object of class `class_name` at `address`.
+    fn base_address(&mut self, address: Operand, class_name: &str, ancestor: &str) -> Operand {
+        match base_offset(self.scope.global, class_name, ancestor).expect("ancestors are declared before code generation") {
+            0 => address,
+            offset => {
+                let dest = self.new_temp(IrType::Int);
+                self.emit(Instr::Binary { dest: dest.clone(), op: BinaryOp::Add, ty: IrType::Int, lhs: address, rhs: Operand::Int(offset as i64) });
+                dest
+            }
+        }
+    }
+
+    // Address of an array element, an attribute or an array or object variable, as
+    // an operand plus a constant offset.
+    fn lower_address(&mut self, expr: &Expr) -> (Operand, usize) {
+        match expr {
+            Expr::Var { name, .. } if self.is_aggregate_local(name) => {
+                let dest = self.new_temp(IrType::Int);
+                self.emit(Instr::AddressOf { dest: dest.clone(), var: name.clone() });
+                (dest, 0)
+            }
+            Expr::Var { name, .. } if self.is_attribute(name) => {
+                let class_name = self.scope.class_name.clone().unwrap();
+                (Operand::Var("self".to_string()), self.attribute_offset(&class_name, name))
+            }
+            // parameters hold the address of their array or object
+            Expr::Var { name, .. } => (Operand::Var(name.clone()), 0),
+            Expr::Index { base, index, span } => {
+                let base_type = self.expr_type(base);
+                let (address, offset) = self.lower_address(base);
+                let element_size = self.class_sizes.type_name_size(&base_type.type_name);
+                let stride = match base_type.strides()[0] {
+                    Some(stride) => stride * element_size,
+                    None => {
+                        self.unsupported("arrays with an unsized inner dimension", *span);
+                        return (address, offset);
+                    }
+                };
+                match self.lower_value(index) {
+                    Operand::Int(index) => (address, offset + index as usize * stride),
+                    index => {
+                        let distance = self.new_temp(IrType::Int);
+                        self.emit(Instr::Binary { dest: distance.clone(), op: BinaryOp::Mul, ty: IrType::Int, lhs: index, rhs: Operand::Int(stride as i64) });
+                        let element = self.new_temp(IrType::Int);
+                        self.emit(Instr::Binary { dest: element.clone(), op: BinaryOp::Add, ty: IrType::Int, lhs: address, rhs: distance });
+                        (element, offset)
+                    }
+                }
+            }
+            Expr::Member { base, name, .. } => {
+                let class_name = self.expr_type(base).type_name.as_string();
+                let (address, offset) = self.lower_address(base);
+                (address, offset + self.attribute_offset(&class_name, name))
+            }
+            Expr::Call { .. } => (self.lower_call(expr).expect("only calls returning an object have an address"), 0),
+            _ => unreachable!("only variables, array elements, data members and calls have an address"),
+        }
+    }
+
+    fn address_operand(&mut self, expr: &Expr) -> Operand {
+        match self.lower_address(expr) {
+            (address, 0) => address,
+            (address, offset) => {
+                let dest = self.new_temp(IrType::Int);
+                self.emit(Instr::Binary { dest: dest.clone(), op: BinaryOp::Add, ty: IrType::Int, lhs: address, rhs: Operand::Int(offset as i64) });
+                dest
+            }
+        }
+    }
+
+    // Arguments for `params`: scalars by value, arrays and objects by address,
+    // objects of a derived class by the address of the part of the parameter's class.
+    fn lower_args(&mut self, params: &[VarDecl], args: &[Expr]) -> Vec<Operand> {
+        let mut operands = vec![];
+        for (param, arg) in params.iter().zip(args) {
+            let param_type = Type::of_var(param);
+            if param_type.is_numeric() {
+                operands.push(self.lower_converted(arg, ir_type(&param.type_name)));
+                continue;
+            }
+            let arg_type = self.expr_type(arg);
+            let address = self.address_operand(arg);
+            match (&param_type.type_name, &arg_type.type_name) {
+                (TypeName::Class(param_class), TypeName::Class(arg_class)) if !param_type.is_array() => {
+                    operands.push(self.base_address(address, arg_class, param_class));
+                }
+                _ => operands.push(address),
+            }
+        }
+        operands
+    }
+
+    // A slot of the frame for an object returned by a call. Its name cannot be the
+    // one of a variable from the source.
+    fn result_slot(&mut self, class_name: &str) -> Operand {
+        let count = self.frame.entries.iter().filter(|entry| entry.name.starts_with("result.")).count();
+        let name = format!("result.{}", count + 1);
+        let type_name = TypeName::Class(class_name.to_string());
+        let size = self.class_sizes.type_name_size(&type_name);
+        self.frame.add_slot(name.clone(), SymbolKind::Local, type_name, size);
+        let address = self.new_temp(IrType::Int);
+        self.emit(Instr::AddressOf { dest: address.clone(), var: name });
+        address
+    }
+
+    // Result of the call, `None` for a void function and the address of the
+    // returned object for a function returning one.
+    fn lower_call(&mut self, expr: &Expr) -> Option<Operand> {
+        let (receiver, name, args, span) = match expr {
+            Expr::Call { receiver, name, args, span } => (receiver, name, args, *span),
+            _ => unreachable!(),
+        };
+        let receiver_class = receiver.as_ref().map(|receiver| self.expr_type(receiver).type_name.as_string());
+        let (owner, candidates) = self.scope.call_candidates(receiver_class.as_deref(), name);
+        let function = self.scope.resolve_call(&candidates, name, args, span).expect("calls are resolved before code generation");
+        // a member function gets the part of the receiver, or of `self`, declared
+        // by its class
+        let mut operands = vec![];
+        if let Some(owner) = &owner {
+            let (object, class_name) = match receiver {
+                Some(receiver) => (self.address_operand(receiver), receiver_class.unwrap()),
+                None => (Operand::Var("self".to_string()), self.scope.class_name.clone().unwrap()),
+            };
+            operands.push(self.base_address(object, &class_name, owner));
+        }
+        operands.extend(self.lower_args(&function.params, args));
+        let (dest, result) = match &function.type_name {
+            TypeName::Void => (None, None),
+            TypeName::Class(class_name) => {
+                let address = self.result_slot(class_name);
+                operands.insert(owner.is_some() as usize, address.clone());
+                (None, Some(address))
+            }
+            type_name => {
+                let dest = self.new_temp(ir_type(type_name));
+                (Some(dest.clone()), Some(dest))
+            }
+        };
+        let mangled = mangled_name(owner.as_deref(), name, &function.params);
+        match owner {
+            Some(owner) => {
+                let slot = slot_index(self.scope.global, &owner, name, &function.params);
+                self.emit(Instr::CallVirtual { dest, function: mangled, slot, args: operands });
+            }
+            None => self.emit(Instr::Call { dest, function: mangled, args: operands }),
+        }
+        result
+    }
+
+    // Stores the table addresses of the local objects, including the ones in arrays
+    // and data members.
+    fn initialize_vtable_pointers(&mut self) {
+        let locals: Vec<SymbolEntry> = self.frame.entries.iter().filter(|entry| entry.kind == SymbolKind::Local).cloned().collect();
+        for local in locals {
+            let pointers = vtable_pointers(self.scope.global, &Type::of_entry(&local));
+            if pointers.is_empty() {
+                continue;
+            }
+            let object = self.new_temp(IrType::Int);
+            self.emit(Instr::AddressOf { dest: object.clone(), var: local.name.clone() });
+            for (offset, vtable) in pointers {
+                let value = self.new_temp(IrType::Int);
+                self.emit(Instr::VtableAddress { dest: value.clone(), vtable });
+                self.emit(Instr::Store { ty: IrType::Int, address: object.clone(), offset, value });
+            }
+        }
+    }
+
+    fn lower_constructor_call(&mut self, var: &VarDecl, args: &[Expr], span: Span) {
+        let class_name = var.type_name.as_string();
+        let constructors: Vec<SymbolEntry> = self.scope.global.class_table(&class_name).unwrap().entries.iter()
+            .filter(|entry| entry.kind == SymbolKind::Constructor)
+            .cloned()
+            .collect();
+        let constructor = self.scope.resolve_call(&constructors, &format!("{}::constructor", class_name), args, span)
+            .expect("constructor calls are resolved before code generation");
+        let object = self.new_temp(IrType::Int);
+        self.emit(Instr::AddressOf { dest: object.clone(), var: var.name.clone() });
+        let mut operands = vec![object];
+        operands.extend(self.lower_args(&constructor.params, args));
+        let function = mangled_name(Some(&class_name), &constructor.name, &constructor.params);
+        self.emit(Instr::Call { dest: None, function, args: operands });
+    }
+
+    // Objects are copied word by word, floats included, between two addresses given
+    // as by `lower_address`.
+    fn lower_object_copy(&mut self, class_name: &str, to: (Operand, usize), from: (Operand, usize)) {
+        let ((to, to_offset), (from, from_offset)) = (to, from);
+        let size = self.class_sizes.type_name_size(&TypeName::Class(class_name.to_string()));
+        for word in (0..size).step_by(INTEGER_SIZE) {
+            let temp = self.new_temp(IrType::Int);
+            self.emit(Instr::Load { dest: temp.clone(), ty: IrType::Int, address: from.clone(), offset: from_offset + word });
+            self.emit(Instr::Store { ty: IrType::Int, address: to.clone(), offset: to_offset + word, value: temp });
+        }
+    }
+
+    fn store(&mut self, place: Place, ty: IrType, value: Operand) {
+        match place {
+            Place::Var(name) => self.emit(Instr::Copy { dest: Operand::Var(name), src: value }),
+            Place::Memory { address, offset } => self.emit(Instr::Store { ty, address, offset, value }),
+        }
+    }
+
+    fn lower_block(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts {
+            self.lower_stmt(stmt);
+        }
+    }
+
+    fn lower_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Local { var, span } => {
+                if let Some(args) = &var.constructor_args {
+                    self.lower_constructor_call(var, args, *span);
+                }
+            }
+            Stmt::Assign { target, value, .. } => {
+                if let TypeName::Class(class_name) = self.expr_type(target).type_name {
+                    let to = self.lower_address(target);
+                    let from = self.lower_address(value);
+                    self.lower_object_copy(&class_name, to, from);
+                    return;
+                }
+                let ty = self.value_type(target);
+                let place = self.lower_place(target);
+                let value = self.lower_converted(value, ty);
+                self.store(place, ty, value);
+            }
+            Stmt::If { condition, then_block, else_block, .. } => {
+                let condition = self.lower_value(condition);
+                let then_label = self.new_label();
+                let else_label = self.new_label();
+                let end_label = self.new_label();
+                let if_false = if else_block.is_empty() { end_label.clone() } else { else_label.clone() };
+                self.emit(Instr::Branch { condition, if_true: then_label.clone(), if_false });
+                self.emit(Instr::Label(then_label));
+                self.lower_block(then_block);
+                if !else_block.is_empty() {
+                    self.emit(Instr::Jump(end_label.clone()));
+                    self.emit(Instr::Label(else_label));
+                    self.lower_block(else_block);
+                }
+                self.emit(Instr::Label(end_label));
+            }
+            Stmt::While { condition, body, .. } => {
+                let condition_label = self.new_label();
+                let body_label = self.new_label();
+                let end_label = self.new_label();
+                self.emit(Instr::Label(condition_label.clone()));
+                let condition = self.lower_value(condition);
+                self.emit(Instr::Branch { condition, if_true: body_label.clone(), if_false: end_label.clone() });
+                self.emit(Instr::Label(body_label));
+                self.lower_block(body);
+                self.emit(Instr::Jump(condition_label));
+                self.emit(Instr::Label(end_label));
+            }
+            Stmt::Read { target, .. } => {
+                let ty = self.value_type(target);
+                match self.lower_place(target) {
+                    Place::Var(name) => self.emit(Instr::Read { dest: Operand::Var(name), ty }),
+                    place => {
+                        let value = self.new_temp(ty);
+                        self.emit(Instr::Read { dest: value.clone(), ty });
+                        self.store(place, ty, value);
+                    }
+                }
+            }
+            Stmt::Write { value, .. } => {
+                let ty = self.value_type(value);
+                let value = self.lower_value(value);
+                self.emit(Instr::Write { value, ty });
+            }
+            Stmt::Return { value, .. } => {
+                if let TypeName::Class(class_name) = self.scope.return_type.clone() {
+                    let from = self.lower_address(value);
+                    self.lower_object_copy(&class_name, (Operand::Var("value".to_string()), 0), from);
+                    self.emit(Instr::Return(None));
+                    return;
+                }
+                let ty = ir_type(&self.scope.return_type);
+                let value = self.lower_converted(value, ty);
+                self.emit(Instr::Return(Some(value)));
+            }
+            Stmt::Call { call, .. } => {
+                self.lower_call(call);
+            }
+        }
+    }
+}
+
+fn lower_function(global: &SymbolTable, class_sizes: &ClassSizes, function: &FuncDef) -> (IrFunction, Vec<Diagnostic>) {
+    let scope = FunctionScope::new(global, function);
+    let frame = scope.table.clone();
+    let mut lowering = FunctionLowering { scope, class_sizes, frame, body: vec![], label_count: 0, diagnostics: vec![] };
+    let return_type = match &function.sig.return_type {
+        TypeName::Void | TypeName::Class(_) => None,
+        return_type => Some(ir_type(return_type)),
+    };
+    lowering.initialize_vtable_pointers();
+    lowering.lower_block(&function.body);
+    if !matches!(lowering.body.last(), Some(Instr::Return(_))) {
+        lowering.emit(Instr::Return(None));
+    }
+    let mut params: Vec<String> = lowering.frame.entries.iter()
+        .filter(|entry| entry.kind == SymbolKind::Parameter)
+        .map(|entry| entry.name.clone())
+        .collect();
+    if let TypeName::Class(_) = function.sig.return_type {
+        params.insert(function.class_name.is_some() as usize, "value".to_string());
+    }
+    let name = mangled_name(function.class_name.as_deref(), &function.sig.name, &function.sig.params);
+    let ir_function = IrFunction { name, params, return_type, frame: lowering.frame, body: lowering.body };
+    (ir_function, lowering.diagnostics)
+}
+
+// Frames, offsets and class sizes are read from `global`, which should already
+// have its memory layout.
+pub fn lower_program(program: &Program, global: &SymbolTable) -> (IrProgram, Vec<Diagnostic>) {
+    let class_sizes = ClassSizes::new(global);
+    let mut ir = IrProgram { vtables: build_vtables(global), functions: vec![] };
+    let mut diagnostics = vec![];
+    for function in &program.functions {
+        let (ir_function, function_diagnostics) = lower_function(global, &class_sizes, function);
+        ir.functions.push(ir_function);
+        diagnostics.extend(function_diagnostics);
+    }
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span.line);
+    (ir, diagnostics)
+}
+
+pub struct IrGenerationPass;
+
+impl Pass for IrGenerationPass {
+    fn name(&self) -> String {
+        "ir-generation".to_string()
+    }
+
+    fn run(&mut self, program: &mut Program, context: &mut PassContext) {
+        let (ir, diagnostics) = lower_program(program, context.symbol_table());
+        context.diagnostics.extend(diagnostics);
+        // the printed symbol tables show the temporaries too
+        let global = context.symbol_table.as_mut().unwrap();
+        for (function, ir_function) in program.functions.iter().zip(&ir.functions) {
+            global.set_frame(function, ir_function.frame.clone());
+        }
+        context.ir = Some(ir);
+    }
+}
@@ -0,0 +1,42 @@
+#![cfg(test)]
+// Pipelines shared by the tests of every module.
+use super::ast::Program;
+use super::ir::IrProgram;
+use super::memory_layout::*;
+use super::passes::*;
+use super::symbol_table::*;
+use super::syntactic_analysis::*;
+
+// The program and the context left by `manager`, which should report no errors.
+pub fn run_manager(source: &str, mut manager: PassManager) -> (Program, PassContext) {
+    let mut program = parse_source(source.to_string());
+    let context = manager.run(&mut program);
+    assert!(!context.has_errors(), "{:?}", context.diagnostics);
+    (program, context)
+}
+
+pub fn run_front_end(source: &str) -> (Program, PassContext) {
+    run_manager(source, PassManager::front_end())
+}
+
+pub fn run_passes(source: &str) -> (Program, PassContext) {
+    run_manager(source, PassManager::code_generation())
+}
+
+pub fn lower(source: &str) -> IrProgram {
+    run_passes(source).1.ir.unwrap()
+}
+
+// Diagnostics of one checker, run on the symbol tables alone.
+pub fn check_source(source: &str, check: fn(&Program, &SymbolTable) -> Vec<Diagnostic>) -> Vec<String> {
+    let program = parse_source(source.to_string());
+    let global = build_symbol_tables(&program);
+    check(&program, &global).iter().map(|diagnostic| diagnostic.as_string()).collect()
+}
+
+pub fn layout_of(source: &str) -> SymbolTable {
+    let program = parse_source(source.to_string());
+    let mut global = build_symbol_tables(&program);
+    compute_memory_layout(&mut global);
+    global
+}
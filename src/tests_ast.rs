@@ -0,0 +1,145 @@
+#![cfg(test)]
+use super::ast::*;
+use super::lexical_analysis::*;
+use super::syntactic_analysis::*;
+
+fn parse_function_body(body: &str) -> Vec<Stmt> {
+    let source = format!("function main() => void\n{{\n{}\n}}", body);
+    let program = parse_source(source);
+    assert_eq!(program.functions.len(), 1);
+    program.functions[0].body.clone()
+}
+
+#[test]
+fn test_scanner_tracks_lines_and_skips_comments() {
+    let mut scanner = Scanner::from(String::from("x // first\n/* second\n line */ y\nz"));
+    let all_tokens = scanner.get_all_tokens();
+    let lines: Vec<usize> = all_tokens.iter().map(|token| token.line).collect();
+    assert_eq!(all_tokens.len(), 3);
+    assert_eq!(lines, vec![1, 3, 4]);
+}
+
+#[test]
+fn test_parse_polynomial_example() {
+    let source = read_source_file("assignment1.COMP442-6421.paquet.2023.4/example-polynomial.src".to_string())
+        .replace("localvar counter: integer = 1;", "localvar counter: integer;");
+    let program = parse_source(source);
+    let class_names: Vec<&str> = program.classes.iter().map(|class| class.name.as_str()).collect();
+    assert_eq!(class_names, vec!["POLYNOMIAL", "LINEAR", "QUADRATIC"]);
+    assert_eq!(program.classes[1].parents, vec!["POLYNOMIAL".to_string()]);
+    assert_eq!(program.classes[1].members.len(), 4);
+    match &program.classes[1].members[0] {
+        MemberDecl::Attribute { visibility, var } => {
+            assert_eq!(*visibility, Visibility::Private);
+            assert_eq!(var.name, "a");
+            assert_eq!(var.type_name, TypeName::Float);
+            assert_eq!(var.span.line, 7);
+        }
+        other => panic!("expected an attribute, got {:?}", other),
+    }
+    match &program.classes[1].members[2] {
+        MemberDecl::Function { visibility, sig } => {
+            assert_eq!(*visibility, Visibility::Public);
+            assert!(sig.is_constructor);
+            assert_eq!(sig.params.len(), 2);
+        }
+        other => panic!("expected a constructor, got {:?}", other),
+    }
+    let function_names: Vec<String> = program.functions.iter().map(|function| function.qualified_name()).collect();
+    assert_eq!(function_names, vec![
+        "POLYNOMIAL::evaluate", "QUADRATIC::evaluate", "QUADRATIC::constructor",
+        "LINEAR::constructor", "LINEAR::evaluate", "main",
+    ]);
+    let main_function = &program.functions[5];
+    assert_eq!(main_function.sig.return_type, TypeName::Void);
+    match &main_function.body[0] {
+        Stmt::Local { var, .. } => {
+            assert_eq!(var.type_name, TypeName::Class("LINEAR".to_string()));
+            assert_eq!(var.constructor_args.as_ref().unwrap().len(), 2);
+        }
+        other => panic!("expected a local variable, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_binary_operators_are_left_associative() {
+    let body = parse_function_body("x = 1 - 2 - 3 * 4;");
+    let expected_lhs = Expr::Binary {
+        op: BinaryOp::Sub,
+        lhs: Box::new(Expr::Literal { value: Literal::Int(1), span: Span::new(3) }),
+        rhs: Box::new(Expr::Literal { value: Literal::Int(2), span: Span::new(3) }),
+        span: Span::new(3),
+    };
+    match &body[0] {
+        Stmt::Assign { value: Expr::Binary { op, lhs, rhs, .. }, .. } => {
+            assert_eq!(*op, BinaryOp::Sub);
+            assert_eq!(**lhs, expected_lhs);
+            assert!(matches!(**rhs, Expr::Binary { op: BinaryOp::Mul, .. }));
+        }
+        other => panic!("expected an assignment, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_member_calls_and_indexing() {
+    let body = parse_function_body("a.b[i + 1][2] = f1.evaluate(x, -y);\nprint(a);");
+    match &body[0] {
+        Stmt::Assign { target, value, .. } => {
+            match target {
+                Expr::Index { base, index, .. } => {
+                    assert_eq!(**index, Expr::Literal { value: Literal::Int(2), span: Span::new(3) });
+                    assert!(matches!(&**base, Expr::Index { base, .. } if matches!(&**base, Expr::Member { name, .. } if name == "b")));
+                }
+                other => panic!("expected an index, got {:?}", other),
+            }
+            match value {
+                Expr::Call { receiver, name, args, .. } => {
+                    assert_eq!(name, "evaluate");
+                    assert_eq!(**receiver.as_ref().unwrap(), Expr::Var { name: "f1".to_string(), span: Span::new(3) });
+                    assert!(matches!(args[1], Expr::Unary { op: UnaryOp::Neg, .. }));
+                }
+                other => panic!("expected a call, got {:?}", other),
+            }
+        }
+        other => panic!("expected an assignment, got {:?}", other),
+    }
+    match &body[1] {
+        Stmt::Call { call: Expr::Call { receiver: None, name, args, .. }, span } => {
+            assert_eq!(name, "print");
+            assert_eq!(args.len(), 1);
+            assert_eq!(span.line, 4);
+        }
+        other => panic!("expected a call statement, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_control_flow_statements() {
+    let body = parse_function_body("while (i < n) { if (a[i] > 0) then write(a[i]); else ; read(x); };\nreturn (x);");
+    match &body[0] {
+        Stmt::While { condition, body, .. } => {
+            assert!(matches!(condition, Expr::Binary { op: BinaryOp::Lt, .. }));
+            assert_eq!(body.len(), 2);
+            match &body[0] {
+                Stmt::If { then_block, else_block, .. } => {
+                    assert!(matches!(then_block[..], [Stmt::Write { .. }]));
+                    assert!(else_block.is_empty());
+                }
+                other => panic!("expected an if, got {:?}", other),
+            }
+            assert!(matches!(body[1], Stmt::Read { .. }));
+        }
+        other => panic!("expected a while, got {:?}", other),
+    }
+    assert!(matches!(body[1], Stmt::Return { span: Span { line: 4 }, .. }));
+}
+
+#[test]
+fn test_array_dimensions() {
+    let program = parse_source("function f(a: integer[][3]) => void { localvar b: float[2][]; }".to_string());
+    assert_eq!(program.functions[0].sig.params[0].dims, vec![None, Some(3)]);
+    match &program.functions[0].body[0] {
+        Stmt::Local { var, .. } => assert_eq!(var.dims, vec![Some(2), None]),
+        other => panic!("expected a local variable, got {:?}", other),
+    }
+}
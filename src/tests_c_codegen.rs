@@ -0,0 +1,290 @@
+#![cfg(test)]
+use std::fs;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use super::c_codegen::*;
+use super::interpreter::*;
+use super::lexical_analysis::*;
+use super::moon_codegen::*;
+use super::moon_vm::*;
+use super::test_support::*;
+
+fn generate(source: &str) -> String {
+    let (program, context) = run_passes(source);
+    generate_c(&program, context.symbol_table())
+}
+
+// Output of the C program built with the system compiler, in a directory of its
+// own under the temporary directory.
+fn run_c(c_code: &str, name: &str, input: &str) -> String {
+    let directory = std::env::temp_dir().join(format!("c_codegen_{}_{}", std::process::id(), name));
+    fs::create_dir_all(&directory).unwrap();
+    let source_path = directory.join("program.c");
+    let executable_path = directory.join("program");
+    fs::write(&source_path, c_code).unwrap();
+    let build = Command::new("cc")
+        .args(["-std=c99", "-pedantic-errors", "-Wall", "-Wextra", "-Wno-unused-function", "-Werror", "-o"])
+        .arg(&executable_path)
+        .arg(&source_path)
+        .output()
+        .expect("cc should be installed");
+    assert!(build.status.success(), "{}\n{}", String::from_utf8_lossy(&build.stderr), c_code);
+    let mut child = Command::new(&executable_path).stdin(Stdio::piped()).stdout(Stdio::piped()).spawn().unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    fs::remove_dir_all(&directory).unwrap();
+    String::from_utf8(output.stdout).unwrap()
+}
+
+// Output of the native program, checked against the interpreter and Moon.
+fn run_source(source: &str, name: &str, input: &str) -> String {
+    let (program, context) = run_passes(source);
+    let output = run_c(&generate_c(&program, context.symbol_table()), name, input);
+    assert_eq!(output, interpret(&program, context.symbol_table(), input).0);
+    assert_eq!(output, run_moon(&generate_moon(context.ir.as_ref().unwrap(), true), input).unwrap());
+    output
+}
+
+#[test]
+fn test_classes_become_structs_with_their_parents_first() {
+    let c_code = generate("
+class SHAPE { public attribute scale: integer; public function area: () => integer; };
+class NAMED { public attribute id: integer; public function code: () => integer; };
+class SQUARE isa SHAPE, NAMED {
+  public attribute side: integer[2];
+  public attribute shape: SHAPE;
+  public function code: () => integer;
+  public function unit: (x: float) => integer;
+};
+function SHAPE::area() => integer { return (scale); }
+function NAMED::code() => integer { return (id); }
+function SQUARE::code() => integer { return (side[0] + area()); }
+function SQUARE::unit(x: float) => integer { return (1); }
+function main() => void { localvar square: SQUARE; write(square.code()); }
+");
+    assert!(c_code.contains("
+struct SQUARE {
+    struct SHAPE base_SHAPE;
+    struct NAMED base_NAMED;
+    int32_t side[2];
+    struct SHAPE shape;
+};
+"));
+    assert!(c_code.contains("\nstruct SHAPE {\n    const struct vtable_entry *vptr;\n    int32_t scale;\n};\n"));
+    // the NAMED part of a SQUARE has a table of its own, whose code goes to SQUARE
+    assert!(c_code.contains("
+static const struct vtable_entry vtable_SQUARE_1[] = {
+    { (void (*)(void)) SQUARE_code, -(ptrdiff_t) offsetof(struct SQUARE, base_NAMED) },
+};
+"));
+    assert!(c_code.contains("
+static int32_t SQUARE_code(void *object) {
+    struct SQUARE *self = object;
+    int32_t t1_ = self->side[0];
+    int32_t t2_ = virtual_SHAPE_area(&self->base_SHAPE);
+    return integer_add(t1_, t2_);
+}
+"));
+    // without `self`, nor warnings about unused parameters
+    assert!(c_code.contains("
+static int32_t SQUARE_unit_f(void *object, double x) {
+    (void) object;
+    (void) x;
+    return 1;
+}
+"), "{}", c_code);
+    assert!(c_code.contains("
+static int32_t virtual_NAMED_code(struct NAMED *self) {
+    const struct vtable_entry *entry = &self->vptr[0];
+    return ((int32_t (*)(void *)) entry->function)((char *) self + entry->adjustment);
+}
+"));
+    assert!(c_code.contains("    struct SQUARE square = {0};\n    init_SQUARE(&square, 1);\n"));
+}
+
+#[test]
+fn test_generated_programs_match_moon() {
+    let source = "
+function sum(values: integer[], count: integer) => integer {
+  localvar total: integer;
+  localvar i: integer;
+  total = 0;
+  i = 0;
+  while (i < count) { total = total + values[i]; i = i + 1; };
+  return (total);
+}
+function scale(values: float[][3], factor: float) => void {
+  localvar i: integer;
+  i = 0;
+  while (i < 3) { values[1][i] = values[0][i] * factor; i = i + 1; };
+}
+function main() => void {
+  localvar values: integer[4];
+  localvar grid: float[2][3];
+  localvar n: integer;
+  localvar x: float;
+  read(n);
+  read(x);
+  values[0] = n;
+  values[3] = n * 1000000;
+  write(sum(values, 4));
+  write(values[3] * 3000 / 7);
+  grid[0][0] = x;
+  grid[0][2] = 1 / 4;
+  grid[0][1] = -x / 3;
+  scale(grid, 2);
+  write(grid[1][0] + grid[1][1] + grid[1][2]);
+  if (n and not values[1] == 1) then write(1); else write(0);;
+}
+";
+    assert_eq!(run_source(source, "programs", "5 2.5"), "5000005\n302156873\n3.33\n1\n");
+}
+
+#[test]
+fn test_member_functions_are_dispatched_on_the_object() {
+    let source = "
+class SHAPE {
+  public attribute scale: integer;
+  public function area: () => integer;
+  public function describe: () => void;
+};
+class NAMED {
+  public attribute id: integer;
+  public function show: () => void;
+  public function code: () => integer;
+};
+class SQUARE isa SHAPE, NAMED {
+  public attribute side: integer;
+  public function area: () => integer;
+  public function code: () => integer;
+  public constructor: (side: integer);
+};
+class BOARD { public attribute squares: SQUARE[2]; public attribute size: integer; };
+function SHAPE::area() => integer { return (0); }
+function SHAPE::describe() => void { write(scale * area()); }
+function NAMED::show() => void { write(id * 1000 + code()); }
+function NAMED::code() => integer { return (1); }
+function SQUARE::area() => integer { return (side * side); }
+function SQUARE::code() => integer { return (side + id); }
+function SQUARE::constructor(side: integer) { self.side = side; scale = 10; id = 7; }
+function report(shape: SHAPE, named: NAMED) => void { shape.describe(); write(shape.area()); named.show(); }
+function main() => void {
+  localvar shape: SHAPE;
+  localvar square: SQUARE(3);
+  localvar board: BOARD;
+  localvar squares: SQUARE[2];
+  shape.scale = 2;
+  shape.describe();
+  square.describe();
+  square.show();
+  report(square, square);
+  board.size = 2;
+  squares[1] = square;
+  report(squares[1], square);
+  write(board.size);
+}
+";
+    assert_eq!(run_source(source, "dispatch", ""), "0\n90\n7010\n90\n9\n7010\n90\n9\n7010\n2\n");
+    let polynomial = read_source_file("assignment1.COMP442-6421.paquet.2023.4/example-polynomial.src".to_string())
+        .replace("localvar counter: integer = 1;", "localvar counter: integer;")
+        .replace("write(f2.evaluate(counter));", "write(f2.evaluate(counter)); counter = counter + 1;");
+    let output = run_source(&polynomial, "polynomial", "");
+    assert!(output.starts_with("0\n3.50\n0.00\n1\n5.50\n-1.00\n"));
+}
+
+#[test]
+fn test_calls_are_made_in_source_order() {
+    let source = "
+class COUNTER { public attribute count: integer; public function next: () => integer; };
+function COUNTER::next() => integer { count = count + 1; write(count); return (count); }
+function pair(a: integer, b: integer) => integer { return (a * 10 + b); }
+function main() => void {
+  localvar counter: COUNTER;
+  localvar values: integer[6];
+  write(counter.count - counter.next() * 100);
+  write(pair(counter.next(), counter.next()));
+  values[counter.next()] = counter.next();
+  write(values[3 + 1]);
+  while (counter.next() < 8) { write(0); };
+}
+";
+    let output = run_source(source, "order", "");
+    assert_eq!(output, "1\n-100\n2\n3\n23\n4\n5\n5\n6\n0\n7\n0\n8\n");
+}
+
+#[test]
+fn test_names_clashing_with_c_are_renamed() {
+    assert_eq!(c_name("total"), "total");
+    assert_eq!(c_name("int"), "int_");
+    assert_eq!(c_name("int_"), "int__");
+    assert_eq!(c_name("vtable_A"), "vtable_A_");
+    let source = "
+class char { public attribute printf: integer; public attribute vptr: float; };
+function exit(double: integer) => integer { return (double + 1); }
+function main() => void {
+  localvar int: char;
+  localvar t1_: integer;
+  localvar base_char: integer;
+  int.printf = exit(41);
+  t1_ = exit(int.printf) + exit(0);
+  base_char = t1_;
+  write(base_char);
+}
+";
+    assert_eq!(run_source(source, "names", ""), "44\n");
+}
+
+#[test]
+fn test_functions_returning_objects() {
+    let source = "
+class POINT {
+  public attribute x: integer;
+  public attribute y: float;
+  public function moved: (dx: integer) => POINT;
+  public function sum: () => float;
+};
+class PIXEL isa POINT {
+  public attribute color: integer;
+  public function sum: () => float;
+};
+function POINT::moved(dx: integer) => POINT {
+  localvar p: POINT;
+  p = self;
+  p.x = x + dx;
+  return (p);
+}
+function POINT::sum() => float { return (x + y); }
+function PIXEL::sum() => float { return (x + y + color); }
+function point(x: integer, y: float) => POINT {
+  localvar p: POINT;
+  p.x = x;
+  p.y = y;
+  return (p);
+}
+function pixel(x: integer, color: integer) => PIXEL {
+  localvar p: PIXEL;
+  p.x = x;
+  p.y = 0.5;
+  p.color = color;
+  return (p);
+}
+function twice(p: POINT) => POINT { return (p.moved(p.x)); }
+function total(p: POINT, q: POINT) => float { return (p.sum() + q.sum()); }
+function main() => void {
+  localvar a: POINT;
+  localvar b: PIXEL;
+  a = point(1, 2.5);
+  write(a.x);
+  write(a.y);
+  a = a.moved(10);
+  write(a.x);
+  write(a.sum());
+  b = pixel(2, 7);
+  write(b.sum());
+  a = twice(a);
+  write(a.sum());
+  write(total(point(1, 1.5), pixel(2, 3)));
+}
+";
+    assert_eq!(run_source(source, "objects", ""), "1\n2.50\n11\n13.50\n9.50\n24.50\n8.00\n");
+}
@@ -0,0 +1,76 @@
+#![cfg(test)]
+use super::cfg::*;
+use super::ir::*;
+use super::symbol_table::*;
+use super::test_support::*;
+
+fn successors(cfg: &ControlFlowGraph) -> Vec<Vec<usize>> {
+    cfg.blocks.iter().map(|block| block.successors.clone()).collect()
+}
+
+fn predecessors(cfg: &ControlFlowGraph) -> Vec<Vec<usize>> {
+    cfg.blocks.iter().map(|block| block.predecessors.clone()).collect()
+}
+
+const SOURCE: &str = "
+function sign(x: integer) => integer {
+  if (x < 0) then return (-1); else return (1);;
+  return (0);
+}
+function main() => void {
+  localvar i: integer;
+  read(i);
+  while (i > 0) {
+    if (i > 3) then { write(1); } else { };
+    i = i - sign(i);
+  };
+}
+";
+
+#[test]
+fn test_while_and_if_blocks_and_edges() {
+    let ir = lower(SOURCE);
+    let cfg = ControlFlowGraph::build(ir.function("main").unwrap());
+    assert_eq!(cfg.block_names(), ["entry", "B1", "L1", "L2", "L4", "L6", "L3", "exit"]);
+    assert_eq!((cfg.entry, cfg.exit), (0, 7));
+    assert_eq!(successors(&cfg), [vec![1], vec![2], vec![3, 6], vec![4, 5], vec![5], vec![2], vec![7], vec![]]);
+    assert_eq!(predecessors(&cfg), [vec![], vec![0], vec![1, 5], vec![2], vec![3], vec![3, 4], vec![2], vec![6]]);
+    assert_eq!(cfg.body(), ir.function("main").unwrap().body);
+}
+
+#[test]
+fn test_returns_go_to_the_exit_and_unreachable_blocks_stay() {
+    let ir = lower(SOURCE);
+    let cfg = ControlFlowGraph::build(ir.function("sign_i").unwrap());
+    assert_eq!(cfg.block_names(), ["entry", "B1", "L1", "B3", "L2", "L3", "exit"]);
+    assert_eq!(successors(&cfg), [vec![1], vec![2, 4], vec![6], vec![5], vec![6], vec![6], vec![]]);
+    assert!(cfg.blocks[3].predecessors.is_empty());
+    assert_eq!(cfg.blocks[5].predecessors, [3]);
+    assert_eq!(cfg.edges()[..3], [(0, 1, None), (1, 2, Some(true)), (1, 4, Some(false))]);
+}
+
+#[test]
+fn test_empty_functions_go_from_entry_to_exit() {
+    let function = IrFunction {
+        name: "empty".to_string(),
+        params: vec![],
+        return_type: None,
+        frame: SymbolTable::new("empty".to_string()),
+        body: vec![],
+    };
+    let cfg = ControlFlowGraph::build(&function);
+    assert_eq!(cfg.block_names(), ["entry", "exit"]);
+    assert_eq!(cfg.edges(), [(0, 1, None)]);
+}
+
+#[test]
+fn test_render_to_dot() {
+    let ir = lower(SOURCE);
+    let mut output = vec![];
+    ControlFlowGraph::build(ir.function("sign_i").unwrap()).render(&mut output).unwrap();
+    let dot = String::from_utf8(output).unwrap();
+    assert!(dot.starts_with("digraph cfg_sign_i {\n"));
+    assert!(dot.contains("    block2[label=\"L1\\l    t2 = -1\\l    return t2\\l\"][shape=\"box\"];\n"), "{}", dot);
+    assert!(dot.contains("    block1 -> block4[label=\"false\"];\n"), "{}", dot);
+    assert!(dot.contains("    block5 -> block6[label=\"\"];\n"), "{}", dot);
+}
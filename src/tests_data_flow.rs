@@ -0,0 +1,108 @@
+#![cfg(test)]
+use std::collections::BTreeSet;
+use super::cfg::*;
+use super::data_flow::*;
+use super::ir::*;
+use super::passes::*;
+use super::test_support::*;
+
+fn analyze(source: &str) -> PassContext {
+    let mut manager = PassManager::code_generation();
+    manager.add_pass(Box::new(DefiniteAssignmentPass));
+    run_manager(source, manager).1
+}
+
+fn names(variables: &BTreeSet<Variable>) -> Vec<String> {
+    variables.iter()
+        .map(|variable| match variable {
+            Variable::Named(name) => name.clone(),
+            Variable::Temp(number) => format!("t{}", number),
+        })
+        .collect()
+}
+
+const SOURCE: &str = "
+function main() => void {
+  localvar i: integer;
+  localvar total: integer;
+  localvar last: integer;
+  read(i);
+  total = 0;
+  while (i > 0) {
+    if (i > 5) then last = i; else total = total + i;;
+    i = i - 1;
+  };
+  write(total);
+  write(last);
+}
+";
+
+#[test]
+fn test_liveness_flows_backward_around_loops() {
+    let context = analyze(SOURCE);
+    let function = context.ir.as_ref().unwrap().function("main").unwrap();
+    let body: Vec<String> = function.body.iter().map(|instr| instr.as_string()).collect();
+    assert_eq!(body[..4], ["read.i i", "total = 0", "L1:", "t1 = i > 0"]);
+    let cfg = ControlFlowGraph::build(function);
+    let liveness = solve(&cfg, &Liveness);
+    assert_eq!(names(&liveness.before[0]), ["last"]);
+    assert_eq!(names(&liveness.after[0]), ["i", "last"]);
+    assert_eq!(names(&liveness.after[3]), ["i", "last", "total", "t1"]);
+    // nothing is live when the function returns
+    assert_eq!(names(&liveness.block_in[cfg.exit]), Vec::<String>::new());
+    assert_eq!(liveness.block_in[2], liveness.before[2]);
+    assert_eq!(liveness.block_out[1], liveness.after[1]);
+}
+
+#[test]
+fn test_definitions_reach_through_both_branches() {
+    let context = analyze(SOURCE);
+    let function = context.ir.as_ref().unwrap().function("main").unwrap();
+    let definitions = solve(&ControlFlowGraph::build(function), &ReachingDefinitions::new(function));
+    let position = function.body.iter().position(|instr| instr.as_string() == "write.i total").unwrap();
+    let reaching: Vec<String> = definitions.before[position].iter()
+        .map(|definition| &function.body[*definition])
+        .filter(|definition| matches!(definition.dest(), Some(Operand::Var(_))))
+        .map(|definition| definition.as_string())
+        .collect();
+    // the loop may run zero times
+    assert_eq!(reaching, ["read.i i", "total = 0", "last = i", "total = t3", "i = t4"]);
+}
+
+#[test]
+fn test_locals_read_before_being_assigned_are_reported() {
+    let context = analyze(&format!("
+class A {{
+  public function f: (x: integer) => integer;
+}};
+function A::f(x: integer) => integer {{
+  localvar y: integer;
+  localvar values: integer[2];
+  if (x > 0) then y = 1; else {{ }};
+  return (y + values[0]);
+}}
+{}", SOURCE));
+    let ir = context.ir.as_ref().unwrap();
+    assert_eq!(unassigned_reads(ir.function("A_f_i").unwrap()), ["y"]);
+    assert_eq!(unassigned_reads(ir.function("main").unwrap()), ["last"]);
+    let warnings: Vec<String> = context.warnings().iter().map(|warning| warning.as_string()).collect();
+    assert_eq!(warnings, [
+        "[warning] line 6: local variable 'y' in function 'A::f' may be read before being assigned",
+        "[warning] line 15: local variable 'last' in function 'main' may be read before being assigned",
+    ]);
+}
+
+#[test]
+fn test_read_and_address_assign_variables() {
+    let context = analyze("
+function fill(value: float[]) => void { read(value[0]); }
+function main() => void {
+  localvar x: float;
+  localvar values: float[1];
+  read(x);
+  fill(values);
+  write(x + values[0]);
+}
+");
+    assert!(context.warnings().is_empty(), "{:?}", context.warnings());
+}
@@ -0,0 +1,89 @@
+#![cfg(test)]
+use super::inheritance::*;
+use super::symbol_table::*;
+use super::syntactic_analysis::*;
+use super::test_support::*;
+use super::type_checker::*;
+
+#[test]
+fn test_circular_inheritance() {
+    let diagnostics = check_source("
+class A isa C { };
+class B isa A { };
+class C isa B { };
+class D isa D { };
+class E isa A { };
+function main() => void { }
+", check_inheritance);
+    assert_eq!(diagnostics, vec![
+        "[error] line 2: circular inheritance: A isa C isa B isa A",
+        "[error] line 5: circular inheritance: D isa D",
+    ]);
+}
+
+#[test]
+fn test_circular_attribute_dependencies() {
+    let diagnostics = check_source("
+class NODE { public attribute next: NODE; };
+class A { public attribute b: B[2]; };
+class B isa C { };
+class C { public attribute a: A; };
+class LIST { public attribute head: NODE; };
+function main() => void { }
+", check_inheritance);
+    assert_eq!(diagnostics, vec![
+        "[error] line 2: class 'NODE' contains itself: NODE -> NODE",
+        "[error] line 3: class 'A' contains itself: A -> B -> C -> A",
+    ]);
+}
+
+#[test]
+fn test_inherited_members_and_multiple_inheritance() {
+    let source = "
+class A { public attribute x: integer; public function f: () => integer; };
+class B { public attribute x: float; };
+class C isa A, B { };
+class D isa B, A { public attribute x: integer; };
+function A::f() => integer { return (x); }
+function main() => void { localvar c: C; localvar i: integer; i = c.x + c.f(); }
+";
+    assert_eq!(check_source(source, check_inheritance), vec![
+        "[warning] line 4: member 'x' of class 'C' is inherited from both 'A' and 'B', 'A' is used",
+    ]);
+    let program = parse_source(source.to_string());
+    let global = build_symbol_tables(&program);
+    assert_eq!(global.parents("D"), vec!["B", "A"]);
+    assert_eq!(global.find_member("C", "x").unwrap().0, "A");
+    assert_eq!(global.find_member("D", "x").unwrap().0, "D");
+    assert_eq!(check_types(&program, &global), vec![]);
+}
+
+#[test]
+fn test_private_members_are_only_accessible_in_their_class() {
+    let program = parse_source("
+class A {
+  private attribute secret: integer;
+  private function hidden: () => integer;
+  public function get: () => integer;
+  private constructor: ();
+};
+class B isa A { public function peek: () => integer; };
+function A::hidden() => integer { return (secret); }
+function A::get() => integer { localvar other: A; return (other.secret + hidden()); }
+function A::constructor() { }
+function B::peek() => integer { return (secret + get()); }
+function main() => void {
+  localvar a: A();
+  localvar i: integer;
+  i = a.secret + a.hidden() + a.get();
+}
+".to_string());
+    let global = build_symbol_tables(&program);
+    let diagnostics: Vec<String> = check_types(&program, &global).iter().map(|diagnostic| diagnostic.as_string()).collect();
+    assert_eq!(diagnostics, vec![
+        "[error] line 12: private data member 'secret' of class 'A' is not accessible here",
+        "[error] line 14: private constructor 'constructor' of class 'A' is not accessible here",
+        "[error] line 16: private data member 'secret' of class 'A' is not accessible here",
+        "[error] line 16: private member function 'hidden' of class 'A' is not accessible here",
+    ]);
+}
@@ -0,0 +1,97 @@
+#![cfg(test)]
+use super::interpreter::*;
+use super::lexical_analysis::*;
+use super::test_support::*;
+
+fn run(source: &str, input: &str) -> (String, Option<String>) {
+    let (program, context) = run_front_end(source);
+    let (output, error) = interpret(&program, context.symbol_table(), input);
+    (output, error.map(|error| error.as_string()))
+}
+
+#[test]
+fn test_polynomial_example() {
+    let source = read_source_file("assignment1.COMP442-6421.paquet.2023.4/example-polynomial.src".to_string())
+        .replace("localvar counter: integer = 1;", "localvar counter: integer;")
+        .replace("write(f2.evaluate(counter));", "write(f2.evaluate(counter)); counter = counter + 1;");
+    let (output, error) = run(&source, "");
+    assert_eq!(error, None);
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 33);
+    assert_eq!(lines[..6], ["0", "3.50", "0.00", "1", "5.50", "-1.00"]);
+    assert_eq!(lines[30..], ["10", "23.50", "-190.00"]);
+}
+
+#[test]
+fn test_inherited_and_overridden_member_functions() {
+    let (output, error) = run("
+class SHAPE {
+  public attribute scale: integer;
+  public function area: () => integer;
+  public function describe: () => void;
+};
+class SQUARE isa SHAPE {
+  public attribute side: integer;
+  public function area: () => integer;
+  public constructor: (side: integer);
+};
+function SHAPE::area() => integer { return (0); }
+function SHAPE::describe() => void { write(scale * area()); }
+function SQUARE::area() => integer { return (side * side); }
+function SQUARE::constructor(side: integer) { self.side = side; scale = 10; }
+function main() => void {
+  localvar shape: SHAPE;
+  localvar square: SQUARE(3);
+  shape.scale = 2;
+  shape.describe();
+  square.describe();
+  write(square.area() + square.scale);
+}
+", "");
+    assert_eq!(error, None);
+    assert_eq!(output, "0\n90\n19\n");
+}
+
+#[test]
+fn test_arrays_are_passed_by_reference_and_input_is_read() {
+    let (output, error) = run("
+function fill(values: float[], count: integer) => void {
+  localvar i: integer;
+  i = 0;
+  while (i < count) { read(values[i]); i = i + 1; };
+}
+function main() => void {
+  localvar values: float[3];
+  localvar n: integer;
+  read(n);
+  fill(values, n);
+  write(values[0] + values[1] * values[2]);
+  write(7 / 2);
+  write(-7 / 2.0);
+}
+", "3 1.5\n2 -0.25");
+    assert_eq!(error, None);
+    assert_eq!(output, "1.00\n3\n-3.50\n");
+}
+
+#[test]
+fn test_runtime_errors_have_lines() {
+    let source = "
+function main() => void {
+  localvar values: integer[2][3];
+  localvar i: integer;
+  i = 0;
+  while (i < 5) {
+    values[1][i] = 12 / (2 - i);
+    write(values[1][i]);
+    i = i + 1;
+  };
+}
+";
+    let (output, error) = run(source, "");
+    assert_eq!(output, "6\n12\n");
+    assert_eq!(error, Some("[error] line 7: division by zero".to_string()));
+    let (output, error) = run(&source.replace("12 / (2 - i)", "i"), "");
+    assert_eq!(output, "0\n1\n2\n");
+    assert_eq!(error, Some("[error] line 7: array index 3 is out of bounds for a dimension of size 3".to_string()));
+}
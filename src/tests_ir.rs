@@ -0,0 +1,157 @@
+#![cfg(test)]
+use super::ir::*;
+use super::test_support::*;
+
+#[test]
+fn test_control_flow_and_conversions() {
+    let ir = lower("
+function main() => void {
+  localvar i: integer;
+  localvar total: float;
+  i = 0;
+  total = 1;
+  while (i < 10) {
+    if (not i == 3) then { total = total * 1.5 + i; } else { };
+    i = i + 1;
+  };
+  write(total);
+}
+");
+    assert_eq!(ir.as_string(), "\
+function main() => void
+    i = 0
+    t1 = itof 1
+    total = t1
+L1:
+    t2 = i < 10
+    if t2 goto L2 else L3
+L2:
+    t3 = not i
+    t4 = t3 == 3
+    if t4 goto L4 else L6
+L4:
+    t5 = total *f 1.5
+    t6 = itof i
+    t7 = t5 +f t6
+    total = t7
+L6:
+    t8 = i + 1
+    i = t8
+    goto L1
+L3:
+    write.f total
+    return
+");
+    let main = ir.function("main").unwrap();
+    assert_eq!(main.temporary_count(), 8);
+    assert_eq!(main.value_type(&Operand::Temp(7)), IrType::Float);
+    assert_eq!(main.value_type(&Operand::Temp(4)), IrType::Int);
+}
+
+#[test]
+fn test_array_elements_and_attributes_are_addressed_by_offset() {
+    let ir = lower("
+class POINT { public attribute x: float; public attribute coords: float[3]; };
+function sum(values: integer[], count: integer) => integer {
+  localvar i: integer;
+  localvar total: integer;
+  i = 0;
+  total = 0;
+  while (i < count) { total = total + values[i]; i = i + 1; };
+  return (total);
+}
+function main() => void {
+  localvar grid: integer[3][4];
+  localvar point: POINT;
+  grid[1][2] = 7;
+  point.coords[2] = 2;
+  read(grid[2][3]);
+  write(sum(grid[1], 4));
+}
+");
+    assert_eq!(ir.function("main").unwrap().as_string(), "\
+function main() => void
+    t1 = &point
+    t2 = &vtable_POINT
+    store.i [t1], t2
+    t3 = &grid
+    store.i [t3 + 24], 7
+    t4 = &point
+    t5 = itof 2
+    store.f [t4 + 28], t5
+    t6 = &grid
+    read.i t7
+    store.i [t6 + 44], t7
+    t8 = &grid
+    t9 = t8 + 16
+    t10 = call sum_ia_i(t9, 4)
+    write.i t10
+    return
+");
+    let sum = ir.function("sum_ia_i").unwrap();
+    assert_eq!(sum.params, vec!["values", "count"]);
+    assert!(sum.as_string().contains("    t2 = i * 4\n    t3 = values + t2\n    t4 = load.i [t3]\n"));
+    assert!(!sum.is_aggregate("values"));
+}
+
+#[test]
+fn test_overloaded_functions_get_distinct_names() {
+    let ir = lower("
+function scale(x: integer) => integer { return (x * 2); }
+function scale(x: float) => float { return (x * 2); }
+function main() => void { write(scale(3)); write(scale(1.5)); write(scale(2) + 0.5); }
+");
+    let names: Vec<&str> = ir.functions.iter().map(|function| function.name.as_str()).collect();
+    assert_eq!(names, vec!["scale_i", "scale_f", "main"]);
+    assert_eq!(ir.function("scale_f").unwrap().as_string(), "\
+function scale_f(x) => float
+    t1 = itof 2
+    t2 = x *f t1
+    return t2
+");
+    assert!(ir.function("main").unwrap().as_string().contains("    t2 = call scale_f(1.5)\n"));
+}
+
+#[test]
+fn test_member_functions_take_their_object_first() {
+    let ir = lower("
+class A { public attribute n: integer; public function get: () => integer; };
+class B { public attribute x: float; };
+class C isa A, B {
+  public attribute m: integer;
+  public constructor: (m: integer);
+  public function sum: () => float;
+};
+function A::get() => integer { return (n); }
+function C::constructor(m: integer) { self.m = m; n = 2; }
+function C::sum() => float { return (x + get() + m); }
+function main() => void { localvar c: C(4); localvar d: C(5); d = c; write(d.sum() + d.get()); }
+");
+    // after the table address of the A part, the B part, and then m
+    assert_eq!(ir.vtable("vtable_C").unwrap().as_string(), "vtable_C = [A_get, C_sum]\n");
+    assert_eq!(ir.function("C_sum").unwrap().as_string(), "\
+function C_sum(self) => float
+    t1 = load.f [self + 12]
+    t2 = vcall A_get#0(self)
+    t3 = itof t2
+    t4 = t1 +f t3
+    t5 = load.i [self + 20]
+    t6 = itof t5
+    t7 = t4 +f t6
+    return t7
+");
+    assert!(ir.function("C_constructor_i").unwrap().as_string().contains("    store.i [self + 20], m\n    store.i [self + 4], 2\n"));
+    let main = ir.function("main").unwrap().as_string();
+    assert!(main.starts_with("\
+function main() => void
+    t1 = &c
+    t2 = &vtable_C
+    store.i [t1], t2
+    t3 = &vtable_C_8
+    store.i [t1 + 8], t3
+"));
+    assert!(main.contains("    t7 = &c\n    call C_constructor_i(t7, 4)\n"));
+    // objects are copied word by word
+    assert!(main.contains("    t9 = &d\n    t10 = &c\n    t11 = load.i [t10]\n    store.i [t9], t11\n"));
+    assert!(main.contains("    t16 = load.i [t10 + 20]\n    store.i [t9 + 20], t16\n    t17 = &d\n    t18 = vcall C_sum#1(t17)\n"));
+}
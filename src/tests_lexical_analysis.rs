@@ -37,6 +37,7 @@ fn test_create_token() {
     let some_token = Token {
         lexeme: String::from("+"),
         token_type: TokenType::Plus,
+        line: 1,
     };
     assert!(some_token.token_type == TokenType::Plus);
     assert_eq!(
@@ -51,6 +52,7 @@ fn test_create_scanner() {
     let scanner = Scanner {
         source_text: source,
         current_location: 10,
+        current_line: 1,
     };
     assert_eq!(scanner.source_text, String::from("123"));
     assert_eq!(scanner.current_location, 10);
@@ -61,6 +63,7 @@ fn test_scanner_create_using_method() {
     let scanner = Scanner::from(String::from("x=y+z"));
     assert_eq!(scanner.source_text, String::from("x=y+z"));
     assert_eq!(scanner.current_location, 0);
+    assert_eq!(scanner.current_line, 1);
 }
 
 #[test]
@@ -0,0 +1,94 @@
+#![cfg(test)]
+use super::ast::*;
+use super::memory_layout::*;
+use super::symbol_table::*;
+use super::syntactic_analysis::*;
+use super::test_support::*;
+
+fn slots(table: &SymbolTable) -> Vec<(String, usize, usize)> {
+    table.entries.iter()
+        .filter_map(|entry| entry.offset.map(|offset| (entry.name.clone(), entry.size, offset)))
+        .collect()
+}
+
+#[test]
+fn test_class_sizes_include_inherited_attributes() {
+    let global = layout_of("
+class POINT { public attribute x: float; public attribute y: float; };
+class PIXEL isa POINT { public attribute color: integer; public attribute history: POINT[3]; };
+class SPRITE { public attribute pixels: PIXEL[2][2]; public attribute id: integer; };
+function main() => void { }
+");
+    // the virtual function table address comes first
+    let point = global.class_table("POINT").unwrap();
+    assert_eq!(point.size, Some(20));
+    assert_eq!(slots(point), vec![("x".to_string(), 8, 4), ("y".to_string(), 8, 12)]);
+    // and is shared with the first parent
+    let pixel = global.class_table("PIXEL").unwrap();
+    assert_eq!(pixel.size, Some(84));
+    assert_eq!(slots(pixel), vec![
+        ("POINT".to_string(), 20, 0),
+        ("color".to_string(), 4, 20),
+        ("history".to_string(), 60, 24),
+    ]);
+    assert_eq!(global.class_table("SPRITE").unwrap().size, Some(344));
+    assert_eq!(global.lookup_kind("SPRITE", SymbolKind::Class).unwrap().size, 344);
+    assert_eq!(attribute_offset(&global, "PIXEL", "y"), Some(12));
+    assert_eq!(base_offset(&global, "PIXEL", "POINT"), Some(0));
+}
+
+#[test]
+fn test_stack_frame_offsets() {
+    let program = parse_source("
+class POINT {
+  public attribute x: float;
+  public function shift: (by: float, steps: integer[]) => float;
+};
+function POINT::shift(by: float, steps: integer[]) => float { localvar i: integer; return (x + by); }
+function main() => void { localvar p: POINT; localvar grid: integer[2][3]; localvar f: float; }
+".to_string());
+    let mut global = build_symbol_tables(&program);
+    compute_memory_layout(&mut global);
+    let shift = global.function_table(&program.functions[0]).unwrap();
+    assert_eq!(slots(shift), vec![
+        ("return".to_string(), 4, 0),
+        ("value".to_string(), 8, 4),
+        ("self".to_string(), 4, 12),
+        ("by".to_string(), 8, 16),
+        ("steps".to_string(), 4, 24),
+        ("i".to_string(), 4, 28),
+    ]);
+    assert_eq!(shift.size, Some(32));
+    let main_table = global.function_table(&program.functions[1]).unwrap();
+    assert_eq!(slots(main_table), vec![
+        ("return".to_string(), 4, 0),
+        ("p".to_string(), 12, 4),
+        ("grid".to_string(), 24, 16),
+        ("f".to_string(), 8, 40),
+    ]);
+    let mut main_table = main_table.clone();
+    assert_eq!(main_table.add_temporary(TypeName::Float, 8), ("t1".to_string(), 48));
+    assert_eq!(main_table.add_temporary(TypeName::Integer, 4), ("t2".to_string(), 56));
+    assert_eq!(main_table.size, Some(60));
+}
+
+#[test]
+fn test_augmented_symbol_table_output() {
+    let (_, context) = run_passes("
+class A { private attribute n: integer[3]; };
+function main() => void { localvar x: float; x = x * 2.0; }
+");
+    let output = context.symbol_table().as_string();
+    let lines: Vec<&str> = output.lines().filter(|line| !line.contains("====")).collect();
+    assert_eq!(lines, vec![
+        "| table: global",
+        "| class       | A            | A | size: 16",
+        "|    | table: A | size: 16",
+        "|    | data        | n            | integer[3] | private | size: 12 | offset: 4",
+        "| function    | main         | ():void | public | size: 20",
+        "|    | table: main | size: 20",
+        "|    | retaddr     | return       | integer | size: 4 | offset: 0",
+        "|    | local       | x            | float | public | size: 8 | offset: 4",
+        "|    | temp        | t1           | float | size: 8 | offset: 12",
+    ]);
+}
@@ -0,0 +1,80 @@
+#![cfg(test)]
+use super::moon_codegen::*;
+use super::test_support::*;
+
+fn compile(source: &str) -> String {
+    generate_moon(&lower(source), false)
+}
+
+fn code_lines(moon_code: &str) -> Vec<String> {
+    moon_code.lines()
+        .filter(|line| !line.trim_start().starts_with('%'))
+        .map(|line| line.to_string())
+        .collect()
+}
+
+#[test]
+fn test_calls_pass_arguments_through_the_callee_frame() {
+    let moon_code = compile("
+function twice(n: integer) => integer { return (n + n); }
+function main() => void { localvar x: integer; x = twice(21); write(x); }
+");
+    let lines = code_lines(&moon_code);
+    let main = lines.iter().position(|line| line.starts_with("main ")).unwrap();
+    // frame of main: return address, x and the temporary for the result
+    assert_eq!(lines[main..main + 10].to_vec(), vec![
+        "main      sw 0(r14), r15",
+        "          addi r1, r0, 21",
+        "          sw 20(r14), r1",
+        "          addi r14, r14, 12",
+        "          jl r15, twice_i",
+        "          subi r14, r14, 12",
+        "          lw r1, 16(r14)",
+        "          sw 8(r14), r1",
+        "          lw r1, 8(r14)",
+        "          sw 4(r14), r1",
+    ]);
+    assert_eq!(&lines[..4], &["          entry", "          addi r14, r0, stack", "          jl r15, main", "          hlt"]);
+    assert!(moon_code.contains("\nrtputint  add r2, r1, r0\n"));
+    assert!(moon_code.ends_with("stack     res 0\n"));
+}
+
+#[test]
+fn test_control_flow_and_array_elements() {
+    let moon_code = compile("
+function main() => void {
+  localvar values: integer[4];
+  localvar i: integer;
+  i = 0;
+  while (i < 4) { values[i] = i * 70000; i = i + 1; };
+}
+");
+    assert!(moon_code.contains("main_L1   lw r1, 20(r14)\n"));
+    assert!(moon_code.contains("          bz r1, main_L3\n          j main_L2\n"));
+    // 70000 does not fit in an immediate operand
+    assert!(moon_code.contains("          addi r2, r0, 1\n          sl r2, 16\n          ori r2, r2, 4464\n"));
+    assert!(moon_code.contains("main_L2   addi r1, r14, 4\n"));
+    assert!(moon_code.contains("          sw 0(r1), r2\n"));
+    assert!(code_lines(&moon_code).join("\n").contains("          j main_L1\nmain_L3   lw r15, 0(r14)\n"));
+}
+
+#[test]
+fn test_floats_are_fixed_point() {
+    let moon_code = compile("
+function main() => void {
+  localvar x: float;
+  localvar n: integer;
+  read(x);
+  n = 3;
+  x = x * 1.5 + n;
+  write(x);
+}
+");
+    assert_eq!(fixed_point(1.5), 98304);
+    assert_eq!(fixed_point(-0.25), -16384);
+    // 1.5 does not fit in an immediate operand
+    assert!(moon_code.contains("          addi r2, r0, 1\n          sl r2, 16\n          ori r2, r2, 32768\n          jl r15, rtfmul\n"));
+    assert!(moon_code.contains("          sl r1, 16\n"));
+    assert!(moon_code.contains("          jl r15, rtgetflt\n"));
+    assert!(moon_code.contains("          jl r15, rtputflt\n"));
+}
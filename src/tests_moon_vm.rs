@@ -0,0 +1,284 @@
+#![cfg(test)]
+use super::interpreter::*;
+use super::lexical_analysis::*;
+use super::moon_codegen::*;
+use super::moon_vm::*;
+use super::test_support::*;
+
+// Output of the generated program, checked against the interpreter and against
+// the program generated with register allocation.
+fn run_source(source: &str, input: &str) -> String {
+    let (program, context) = run_passes(source);
+    let ir = context.ir.as_ref().unwrap();
+    let output = run_moon(&generate_moon(ir, false), input).unwrap();
+    assert_eq!(output, interpret(&program, context.symbol_table(), input).0);
+    assert_eq!(run_moon(&generate_moon(ir, true), input).unwrap(), output);
+    output
+}
+
+#[test]
+fn test_instructions_and_directives() {
+    let output = run_moon("
+          entry
+          addi r1, r0, 6
+          muli r2, r1, 7        % 42
+          putc r2
+          lw r3, answer(r0)
+          subi r3, r3, 1
+          putc r3
+          addi r4, r0, text
+          lb r5, 1(r4)
+          putc r5
+          sb 0(r4), r2
+          lb r5, 0(r4)
+          putc r5
+          addi r6, r0, 1
+          sl r6, 6
+          sr r6, 1
+          ori r6, r6, 1         % 33
+          putc r6
+          jl r15, newline
+          hlt
+newline   addi r1, r0, 10
+          putc r1
+          jr r15
+answer    dw 66
+text      db \"hi\", 0
+", "").unwrap();
+    assert_eq!(output, "*Ai*!\n");
+}
+
+#[test]
+fn test_input_and_errors() {
+    let echo = "
+          entry
+loop      getc r1
+          bz r1, done
+          addi r1, r1, 1
+          putc r1
+          j loop
+done      hlt
+";
+    assert_eq!(run_moon(echo, "HAL").unwrap(), "IBM");
+    assert_eq!(run_moon("          addi r1, r0, 1\n          div r2, r1, r0\n", "").err().unwrap(), "at address 4: division by zero");
+    assert_eq!(MoonVm::load("          j nowhere\n").err().unwrap(), "line 1: undefined symbol 'nowhere'");
+    assert_eq!(MoonVm::load("          add r1, r2\n").err().unwrap(), "line 1: 'add' takes 3 operands");
+    let mut vm = MoonVm::load("loop      j loop\n").unwrap();
+    vm.step_limit = 100;
+    assert_eq!(vm.run().err().unwrap(), "step limit of 100 exceeded");
+    // r0 stays 0
+    let mut vm = MoonVm::load("          addi r0, r0, 5\n          hlt\n").unwrap();
+    vm.run().unwrap();
+    assert_eq!(vm.registers[0], 0);
+}
+
+#[test]
+fn test_generated_programs_run() {
+    let source = "
+function fact(n: integer) => integer {
+  if (n <= 1) then { return (1); } else { };
+  return (n * fact(n - 1));
+}
+function main() => void {
+  localvar values: integer[3];
+  localvar i: integer;
+  i = 0;
+  while (i < 3) { read(values[i]); i = i + 1; };
+  write(fact(values[0]));
+  write(values[1] * 100000 - values[2]);
+  write(-values[2] / 2);
+  write(values[2]);
+  write(values[2] * 10);
+  write(2147483647 + 1);
+  write(-2147483648);
+}
+";
+    assert_eq!(run_source(source, "5 3\n-7\n"), "120\n300007\n3\n-7\n-70\n-2147483648\n-2147483648\n");
+}
+
+#[test]
+fn test_generated_programs_with_arrays_and_attributes() {
+    let source = "
+class RANGE { public attribute low: integer; public attribute high: integer; public attribute values: integer[10]; };
+function fill(values: integer[], count: integer, step: integer) => void {
+  localvar i: integer;
+  i = 0;
+  while (i < count) { values[i] = i * step; i = i + 1; };
+}
+function sum(grid: integer[][2]) => integer {
+  return (grid[0][0] + grid[0][1] + grid[1][0] + grid[1][1]);
+}
+function main() => void {
+  localvar range: RANGE;
+  localvar grid: integer[2][2];
+  range.low = 2;
+  range.high = 5;
+  fill(range.values, 10, 3);
+  write(range.values[range.high] - range.values[range.low]);
+  grid[0][0] = 1; grid[0][1] = 2; grid[1][0] = 3; grid[1][1] = 4;
+  write(sum(grid));
+  if ((range.low and not range.high - 5) <> 0) then write(1); else write(0);;
+}
+";
+    assert_eq!(run_source(source, ""), "9\n10\n1\n");
+}
+
+#[test]
+fn test_generated_programs_with_floats() {
+    let source = "
+function average(values: float[], count: integer) => float {
+  localvar i: integer;
+  localvar total: float;
+  i = 0;
+  total = 0;
+  while (i < count) { total = total + values[i]; i = i + 1; };
+  return (total / count);
+}
+function main() => void {
+  localvar values: float[4];
+  localvar i: integer;
+  i = 0;
+  while (i < 4) { read(values[i]); i = i + 1; };
+  write(average(values, 4));
+  write(values[1] * values[2] - 0.125);
+  write(-values[3] / 0.5);
+  if (values[1] < values[2]) then write(1); else write(0);;
+  write(0.375);
+  write(1.999);
+  i = 0;
+  while (i < 4) { read(values[0]); write(values[0]); i = i + 1; };
+}
+";
+    let input = "2.5 -1.25\n12 .5\n1.5e2 -2.5E+1 12e-1 35E-3";
+    assert_eq!(run_source(source, input), "3.44\n-15.12\n-1.00\n1\n0.38\n2.00\n150.00\n-25.00\n1.20\n0.04\n");
+}
+
+#[test]
+fn test_generated_programs_with_objects() {
+    let source = read_source_file("assignment1.COMP442-6421.paquet.2023.4/example-polynomial.src".to_string())
+        .replace("localvar counter: integer = 1;", "localvar counter: integer;")
+        .replace("write(f2.evaluate(counter));", "write(f2.evaluate(counter)); counter = counter + 1;");
+    let output = run_source(&source, "");
+    assert!(output.starts_with("0\n3.50\n0.00\n1\n5.50\n-1.00\n"));
+    assert!(output.ends_with("10\n23.50\n-190.00\n"));
+    let output = run_source("
+class COUNTER { public attribute count: integer; public attribute step: float;
+  public constructor: (step: float); public function next: () => float; };
+class TIMER isa COUNTER { public attribute ticks: integer[3]; public constructor: (); };
+function COUNTER::constructor(step: float) { self.step = step; count = 0; }
+function COUNTER::next() => float { count = count + 1; return (count * step); }
+function TIMER::constructor() { step = 0.5; ticks[count] = 7; }
+function main() => void {
+  localvar timer: TIMER();
+  localvar copy: TIMER();
+  localvar counter: COUNTER(1.25);
+  write(timer.next() + counter.next());
+  copy = timer;
+  write(copy.next());
+  write(timer.next() + copy.ticks[0]);
+}
+", "");
+    assert_eq!(output, "1.75\n1.00\n8.00\n");
+}
+
+#[test]
+fn test_member_function_calls_are_dispatched_on_the_object() {
+    let source = "
+class SHAPE {
+  public attribute scale: integer;
+  public function area: () => integer;
+  public function describe: () => void;
+};
+class NAMED {
+  public attribute id: integer;
+  public function show: () => void;
+  public function code: () => integer;
+};
+class SQUARE isa SHAPE, NAMED {
+  public attribute side: integer;
+  public function area: () => integer;
+  public function code: () => integer;
+  public constructor: (side: integer);
+};
+class BOARD { public attribute squares: SQUARE[2]; public attribute size: integer; };
+function SHAPE::area() => integer { return (0); }
+function SHAPE::describe() => void { write(scale * area()); }
+function NAMED::show() => void { write(id * 1000 + code()); }
+function NAMED::code() => integer { return (1); }
+function SQUARE::area() => integer { return (side * side); }
+function SQUARE::code() => integer { return (side + id); }
+function SQUARE::constructor(side: integer) { self.side = side; scale = 10; id = 7; }
+function report(shape: SHAPE, named: NAMED) => void { shape.describe(); write(shape.area()); named.show(); }
+function main() => void {
+  localvar shape: SHAPE;
+  localvar square: SQUARE(3);
+  localvar board: BOARD;
+  localvar squares: SQUARE[2];
+  shape.scale = 2;
+  shape.describe();
+  square.describe();
+  square.show();
+  report(square, square);
+  board.size = 2;
+  squares[1] = square;
+  report(squares[1], square);
+  write(board.size);
+}
+";
+    assert_eq!(run_source(source, ""), "0\n90\n7010\n90\n9\n7010\n90\n9\n7010\n2\n");
+}
+
+#[test]
+fn test_functions_returning_objects() {
+    let source = "
+class POINT {
+  public attribute x: integer;
+  public attribute y: float;
+  public function moved: (dx: integer) => POINT;
+  public function sum: () => float;
+};
+class PIXEL isa POINT {
+  public attribute color: integer;
+  public function sum: () => float;
+};
+function POINT::moved(dx: integer) => POINT {
+  localvar p: POINT;
+  p = self;
+  p.x = x + dx;
+  return (p);
+}
+function POINT::sum() => float { return (x + y); }
+function PIXEL::sum() => float { return (x + y + color); }
+function point(x: integer, y: float) => POINT {
+  localvar p: POINT;
+  p.x = x;
+  p.y = y;
+  return (p);
+}
+function pixel(x: integer, color: integer) => PIXEL {
+  localvar p: PIXEL;
+  p.x = x;
+  p.y = 0.5;
+  p.color = color;
+  return (p);
+}
+function twice(p: POINT) => POINT { return (p.moved(p.x)); }
+function total(p: POINT, q: POINT) => float { return (p.sum() + q.sum()); }
+function main() => void {
+  localvar a: POINT;
+  localvar b: PIXEL;
+  a = point(1, 2.5);
+  write(a.x);
+  write(a.y);
+  a = a.moved(10);
+  write(a.x);
+  write(a.sum());
+  b = pixel(2, 7);
+  write(b.sum());
+  a = twice(a);
+  write(a.sum());
+  write(total(point(1, 1.5), pixel(2, 3)));
+}
+";
+    assert_eq!(run_source(source, ""), "1\n2.50\n11\n13.50\n9.50\n24.50\n8.00\n");
+}
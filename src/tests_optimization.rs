@@ -0,0 +1,227 @@
+#![cfg(test)]
+use super::ast::BinaryOp;
+use super::interpreter::*;
+use super::ir::*;
+use super::lexical_analysis::*;
+use super::moon_codegen::*;
+use super::moon_vm::*;
+use super::optimization::*;
+use super::passes::*;
+use super::symbol_table::*;
+use super::test_support::*;
+
+fn function(body: Vec<Instr>) -> IrFunction {
+    IrFunction {
+        name: "main".to_string(),
+        params: vec![],
+        return_type: None,
+        frame: SymbolTable::new("main".to_string()),
+        body,
+    }
+}
+
+fn binary(dest: Operand, op: BinaryOp, ty: IrType, lhs: Operand, rhs: Operand) -> Instr {
+    Instr::Binary { dest, op, ty, lhs, rhs }
+}
+
+fn var(name: &str) -> Operand {
+    Operand::Var(name.to_string())
+}
+
+fn copy(dest: Operand, src: Operand) -> Instr {
+    Instr::Copy { dest, src }
+}
+
+fn write(value: Operand) -> Instr {
+    Instr::Write { value, ty: IrType::Int }
+}
+
+fn body_lines(function: &IrFunction) -> Vec<String> {
+    function.body.iter().map(|instr| instr.as_string()).collect()
+}
+
+#[test]
+fn test_integer_float_and_relational_constants_are_folded() {
+    let mut function = function(vec![
+        binary(Operand::Temp(1), BinaryOp::Mul, IrType::Int, Operand::Int(65536), Operand::Int(65536)),
+        binary(Operand::Temp(2), BinaryOp::Div, IrType::Int, Operand::Int(-7), Operand::Int(2)),
+        binary(Operand::Temp(3), BinaryOp::Div, IrType::Int, Operand::Int(1), Operand::Int(0)),
+        binary(Operand::Temp(4), BinaryOp::Sub, IrType::Float, Operand::Float(1.5), Operand::Float(0.25)),
+        binary(Operand::Temp(5), BinaryOp::Geq, IrType::Float, Operand::Float(1.5), Operand::Float(1.5)),
+        Instr::IntToFloat { dest: Operand::Temp(6), src: Operand::Int(3) },
+        Instr::Branch { condition: Operand::Int(0), if_true: "L1".to_string(), if_false: "L2".to_string() },
+    ]);
+    assert!(fold_constants(&mut function, true));
+    assert_eq!(body_lines(&function), [
+        "t1 = 0",
+        "t2 = -3",
+        "t3 = 1 / 0",
+        "t4 = 1.25",
+        "t5 = 1",
+        "t6 = 3.0",
+        "goto L2",
+    ]);
+    assert!(!fold_constants(&mut function, true));
+}
+
+#[test]
+fn test_copies_are_propagated_until_either_side_changes() {
+    let mut function = function(vec![
+        copy(var("a"), Operand::Int(4)),
+        copy(var("b"), var("c")),
+        binary(Operand::Temp(1), BinaryOp::Add, IrType::Int, var("a"), var("b")),
+        copy(var("c"), Operand::Temp(1)),
+        write(var("b")),
+        Instr::AddressOf { dest: Operand::Temp(2), var: "d".to_string() },
+        copy(var("d"), Operand::Int(1)),
+        write(var("d")),
+        Instr::Label("L1".to_string()),
+        write(var("a")),
+    ]);
+    assert!(propagate_copies(&mut function));
+    assert_eq!(body_lines(&function)[2..], [
+        "t1 = 4 + c",
+        "c = t1",
+        "write.i b",
+        "t2 = &d",
+        "d = 1",
+        // `d` can change through its address and `a` on the way to the label
+        "write.i d",
+        "L1:",
+        "write.i a",
+    ]);
+}
+
+#[test]
+fn test_dead_stores_and_unreachable_blocks_are_removed() {
+    let mut function = function(vec![
+        copy(var("a"), Operand::Int(1)),
+        copy(var("unused"), Operand::Int(2)),
+        binary(Operand::Temp(1), BinaryOp::Div, IrType::Int, var("a"), var("b")),
+        copy(var("a"), Operand::Int(3)),
+        Instr::Read { dest: var("b"), ty: IrType::Int },
+        Instr::Jump("L2".to_string()),
+        Instr::Label("L1".to_string()),
+        write(var("b")),
+        Instr::Label("L2".to_string()),
+        write(var("a")),
+        Instr::Return(None),
+    ]);
+    assert!(eliminate_dead_stores(&mut function));
+    assert!(eliminate_unreachable_blocks(&mut function));
+    // the division is kept for its division by zero and `b` for the input it reads
+    assert_eq!(body_lines(&function), [
+        "a = 1",
+        "t1 = a / b",
+        "a = 3",
+        "read.i b",
+        "write.i a",
+        "return",
+    ]);
+}
+
+#[test]
+fn test_common_subexpressions_are_computed_once_per_block() {
+    let mut function = function(vec![
+        binary(Operand::Temp(1), BinaryOp::Mul, IrType::Int, var("a"), var("b")),
+        binary(Operand::Temp(2), BinaryOp::Mul, IrType::Int, var("b"), var("a")),
+        binary(Operand::Temp(3), BinaryOp::Sub, IrType::Int, var("b"), var("a")),
+        copy(var("a"), Operand::Temp(3)),
+        binary(Operand::Temp(4), BinaryOp::Mul, IrType::Int, var("a"), var("b")),
+        binary(Operand::Temp(5), BinaryOp::Sub, IrType::Int, var("b"), var("a")),
+        write(Operand::Temp(5)),
+    ]);
+    assert!(eliminate_common_subexpressions(&mut function));
+    assert_eq!(body_lines(&function), [
+        "t1 = a * b",
+        "t2 = t1",
+        "t3 = b - a",
+        "a = t3",
+        "t4 = a * b",
+        "t5 = b - a",
+        "write.i t5",
+    ]);
+}
+
+// Output of the Moon program generated with the given optimizations.
+fn moon_output(source: &str, input: &str, optimizations: Optimizations) -> String {
+    let mut manager = PassManager::code_generation();
+    manager.add_pass(Box::new(OptimizationPass { optimizations }));
+    let (_, context) = run_manager(source, manager);
+    let ir = context.ir.as_ref().unwrap();
+    run_moon(&generate_moon(ir, true), input).unwrap()
+}
+
+// The same, checked against the interpreter.
+fn run_optimized(source: &str, input: &str, optimizations: Optimizations) -> String {
+    let output = moon_output(source, input, optimizations);
+    let (program, context) = run_front_end(source);
+    assert_eq!(output, interpret(&program, context.symbol_table(), input).0);
+    output
+}
+
+#[test]
+fn test_program_output_is_unchanged() {
+    let polynomial = read_source_file("assignment1.COMP442-6421.paquet.2023.4/example-polynomial.src".to_string())
+        .replace("localvar counter: integer = 1;", "localvar counter: integer;")
+        .replace("write(f2.evaluate(counter));", "write(f2.evaluate(counter)); counter = counter + 1;");
+    let loops = "
+class COUNTER {
+  public attribute count: integer;
+  public function next: (step: integer) => integer;
+};
+function COUNTER::next(step: integer) => integer { count = count + step; return (count); }
+function main() => void {
+  localvar counter: COUNTER;
+  localvar values: integer[8];
+  localvar i: integer;
+  localvar unused: float;
+  localvar x: float;
+  read(x);
+  counter.count = 2 * 3 - 6;
+  i = 0;
+  while (i < 8) {
+    values[i] = counter.next(i * 2 + 1) * (i * 2 + 1) - (1 + 2) * 4;
+    unused = x * 2.5;
+    i = i + 1;
+  };
+  if (1 < 2) then write(values[7] / (8 - 2 * 4 + 3)); else write(0);;
+  if (2.5 > 3.0 * x) then write(x * 2.5 + 1.5 / 2.0); else write(x * 2.5 - 7);;
+  write(values[3]);
+}
+";
+    let mut options = vec![Optimizations::level(0), Optimizations::level(1), Optimizations::level(2)];
+    for index in 0..5 {
+        let mut optimizations = Optimizations::default();
+        *[
+            &mut optimizations.fold_constants,
+            &mut optimizations.propagate_copies,
+            &mut optimizations.eliminate_dead_stores,
+            &mut optimizations.eliminate_unreachable_blocks,
+            &mut optimizations.eliminate_common_subexpressions,
+        ][index] = true;
+        options.push(optimizations);
+    }
+    // fixed-point floats round differently from the interpreter, but not
+    // differently with optimizations
+    let fixed_point = "
+function main() => void {
+  localvar f: float;
+  f = 1.0 / 3.0;
+  write(f * 3.0 == 1.0);
+  write(f * 3.0 - 1.0 < 0.0);
+  write(-f + 40000);
+}
+";
+    let polynomial_output = moon_output(&polynomial, "", Optimizations::level(0));
+    let fixed_point_output = moon_output(fixed_point, "", Optimizations::level(0));
+    assert_eq!(fixed_point_output, "0\n1\n-25536.33\n");
+    for optimizations in options {
+        let optimizations = optimizations.for_moon();
+        assert_eq!(run_optimized(&polynomial, "", optimizations), polynomial_output);
+        assert_eq!(polynomial_output.lines().count(), 33);
+        assert_eq!(run_optimized(loops, "0.5", optimizations), "316\n2.00\n100\n");
+        assert_eq!(run_optimized(loops, "-2", optimizations), "316\n-4.25\n100\n");
+        assert_eq!(moon_output(fixed_point, "", optimizations), fixed_point_output);
+    }
+}
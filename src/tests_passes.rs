@@ -0,0 +1,139 @@
+#![cfg(test)]
+use super::ast::*;
+use super::passes::*;
+use super::syntactic_analysis::*;
+use super::visitor::*;
+
+struct CallCollector {
+    calls: Vec<String>,
+}
+
+impl Visitor for CallCollector {
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let Expr::Call { name, .. } = expr {
+            self.calls.push(name.clone());
+        }
+        walk_expr(self, expr);
+    }
+}
+
+// Reports a warning for every `write` and an error for every `read`.
+struct ReadWriteVisitor<'a> {
+    context: &'a mut PassContext,
+}
+
+impl Visitor for ReadWriteVisitor<'_> {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Read { span, .. } => self.context.error("read".to_string(), *span),
+            Stmt::Write { span, .. } => self.context.warning("write".to_string(), *span),
+            _ => {}
+        }
+        walk_stmt(self, stmt);
+    }
+}
+
+struct ReadWriteChecker;
+
+impl Pass for ReadWriteChecker {
+    fn name(&self) -> String {
+        "read-write-checker".to_string()
+    }
+
+    fn run(&mut self, program: &mut Program, context: &mut PassContext) {
+        ReadWriteVisitor { context }.visit_program(program);
+    }
+}
+
+// Doubles the integer on the left of every binary expression assigned at the top
+// of a function body.
+struct Doubling;
+
+impl Pass for Doubling {
+    fn name(&self) -> String {
+        "doubling".to_string()
+    }
+
+    fn run(&mut self, program: &mut Program, _context: &mut PassContext) {
+        for stmt in program.functions.iter_mut().flat_map(|function| function.body.iter_mut()) {
+            if let Stmt::Assign { value: Expr::Binary { lhs, .. }, .. } = stmt {
+                if let Expr::Literal { value: Literal::Int(value), .. } = lhs.as_mut() {
+                    *value *= 2;
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_visitor_reaches_nested_expressions() {
+    let program = parse_source(
+        "function main() => void { localvar p: POINT(f(1), 2); while (g(x) < 3) { write(h(a.k(1))); }; }".to_string()
+    );
+    let mut collector = CallCollector { calls: vec![] };
+    collector.visit_program(&program);
+    assert_eq!(collector.calls, vec!["f", "g", "h", "k"]);
+}
+
+#[test]
+fn test_pass_manager_runs_passes_in_order() {
+    let mut program = parse_source("function main() => void { x = 1 + 2; write(x); }".to_string());
+    let mut manager = PassManager::new();
+    manager.add_pass(Box::new(Doubling));
+    manager.add_pass(Box::new(ReadWriteChecker));
+    manager.add_pass(Box::new(Doubling));
+    let context = manager.run(&mut program);
+    assert_eq!(context.completed_passes, vec!["doubling", "read-write-checker", "doubling"]);
+    assert_eq!(context.warnings().len(), 1);
+    assert_eq!(context.warnings()[0].as_string(), "[warning] line 1: write");
+    match &program.functions[0].body[0] {
+        Stmt::Assign { value: Expr::Binary { lhs, .. }, .. } => {
+            assert!(matches!(**lhs, Expr::Literal { value: Literal::Int(4), .. }));
+        }
+        other => panic!("expected an assignment, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_pass_manager_stops_after_errors() {
+    let mut program = parse_source("function main() => void { read(x);\n read(y); }".to_string());
+    let mut manager = PassManager::new();
+    manager.add_pass(Box::new(ReadWriteChecker));
+    manager.add_pass(Box::new(Doubling));
+    let context = manager.run(&mut program);
+    assert_eq!(context.completed_passes, vec!["read-write-checker"]);
+    let lines: Vec<usize> = context.errors().iter().map(|error| error.span.line).collect();
+    assert_eq!(lines, vec![1, 2]);
+}
+
+#[test]
+fn test_standard_pipelines() {
+    assert_eq!(PassManager::front_end().pass_names(), vec!["symbol-table", "inheritance", "semantic-check", "type-check"]);
+    assert_eq!(PassManager::code_generation().pass_names()[4..], ["memory-layout", "ir-generation"]);
+    let mut program = parse_source("function main() => void { localvar x: integer; x = 1; write(x); }".to_string());
+    let context = PassManager::code_generation().run(&mut program);
+    assert_eq!(context.completed_passes.len(), 6);
+    assert!(context.ir.is_some());
+}
+
+#[test]
+fn test_checking_passes_all_run_after_errors() {
+    let mut program = parse_source("
+class A isa B { };
+class B isa A { };
+function main() => void {
+  localvar i: integer;
+  i = 1.5;
+  write(q);
+}
+".to_string());
+    let context = PassManager::code_generation().run(&mut program);
+    assert_eq!(context.completed_passes, PassManager::front_end().pass_names());
+    let errors: Vec<String> = context.errors().iter().map(|error| error.as_string()).collect();
+    assert_eq!(errors, vec![
+        "[error] line 2: circular inheritance: A isa B isa A",
+        "[error] line 7: undeclared local variable 'q'",
+        "[error] line 6: type mismatch in assignment: integer and float",
+    ]);
+    assert!(context.ir.is_none());
+}
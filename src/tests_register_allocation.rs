@@ -0,0 +1,108 @@
+#![cfg(test)]
+use super::ast::BinaryOp;
+use super::ir::*;
+use super::moon_codegen::*;
+use super::moon_vm::*;
+use super::register_allocation::*;
+use super::symbol_table::*;
+use super::test_support::*;
+
+fn function(body: Vec<Instr>) -> IrFunction {
+    IrFunction {
+        name: "main".to_string(),
+        params: vec![],
+        return_type: None,
+        frame: SymbolTable::new("main".to_string()),
+        body,
+    }
+}
+
+fn add(dest: Operand, lhs: Operand, rhs: Operand) -> Instr {
+    Instr::Binary { dest, op: BinaryOp::Add, ty: IrType::Int, lhs, rhs }
+}
+
+fn var(name: &str) -> Operand {
+    Operand::Var(name.to_string())
+}
+
+#[test]
+fn test_intervals_live_at_a_loop_start_last_until_the_jump_back() {
+    let function = function(vec![
+        add(Operand::Temp(1), var("n"), Operand::Int(1)),
+        Instr::Label("L1".to_string()),
+        add(Operand::Temp(2), var("i"), Operand::Temp(1)),
+        Instr::Copy { dest: var("i"), src: Operand::Temp(2) },
+        Instr::Branch { condition: var("i"), if_true: "L1".to_string(), if_false: "L2".to_string() },
+        Instr::Label("L2".to_string()),
+        Instr::Write { value: var("i"), ty: IrType::Int },
+    ]);
+    assert_eq!(live_intervals(&function), [
+        LiveInterval { temp: 1, start: 0, end: 4 },
+        LiveInterval { temp: 2, start: 2, end: 3 },
+    ]);
+}
+
+#[test]
+fn test_the_interval_ending_last_is_spilled() {
+    let function = function(vec![
+        add(Operand::Temp(1), var("a"), Operand::Int(1)),
+        add(Operand::Temp(2), var("b"), Operand::Int(2)),
+        add(Operand::Temp(3), Operand::Temp(2), Operand::Int(3)),
+        add(Operand::Temp(4), Operand::Temp(3), Operand::Temp(2)),
+        add(Operand::Temp(5), Operand::Temp(4), Operand::Temp(1)),
+        Instr::Write { value: Operand::Temp(5), ty: IrType::Int },
+    ]);
+    let allocation = allocate_registers(&function, &["r1", "r2"]);
+    // t3 takes the register of t1, and t4 starts where t2 and t3 end, so their
+    // registers are not free yet
+    assert_eq!(allocation.spilled, [1, 4]);
+    assert_eq!(allocation.register(&Operand::Temp(1)), None);
+    assert_eq!(allocation.register(&Operand::Temp(2)), Some("r2"));
+    assert_eq!(allocation.register(&Operand::Temp(3)), Some("r1"));
+    assert_eq!(allocation.register(&Operand::Temp(5)), Some("r1"));
+}
+
+#[test]
+fn test_registers_live_across_a_call_are_reported() {
+    let function = function(vec![
+        add(Operand::Temp(1), var("a"), Operand::Int(1)),
+        add(Operand::Temp(2), var("b"), Operand::Int(1)),
+        Instr::Call { dest: Some(Operand::Temp(3)), function: "f".to_string(), args: vec![Operand::Temp(2)] },
+        add(Operand::Temp(4), Operand::Temp(1), Operand::Temp(3)),
+        Instr::Return(Some(Operand::Temp(4))),
+    ]);
+    let allocation = allocate_registers(&function, &ALLOCATABLE_REGISTERS);
+    assert_eq!(allocation.registers.len(), 4);
+    assert_eq!(allocation.live_across(2), [(1, "r8")]);
+    assert_eq!(allocation.live_across(3), []);
+}
+
+#[test]
+fn test_allocated_code_takes_fewer_cycles() {
+    let ir = lower("
+function sum(values: integer[], count: integer) => integer {
+  localvar i: integer;
+  localvar total: integer;
+  i = 0;
+  total = 0;
+  while (i < count) {
+    total = total + values[i] * (i + 1) - values[count - i - 1];
+    i = i + 1;
+  };
+  return (total);
+}
+function main() => void {
+  localvar values: integer[10];
+  localvar i: integer;
+  i = 0;
+  while (i < 10) { values[i] = i * i - 3 * i; i = i + 1; };
+  write(sum(values, 10));
+}
+");
+    let naive = profile_moon(&generate_moon(&ir, false), "").unwrap();
+    let allocated = profile_moon(&generate_moon(&ir, true), "").unwrap();
+    assert_eq!(naive.output, "1170\n");
+    assert_eq!(allocated.output, naive.output);
+    assert!(allocated.instructions < naive.instructions);
+    assert!(allocated.cycles < naive.cycles);
+}
@@ -2,10 +2,12 @@ use std::collections::HashMap;
 use std::hash::Hash;
 use std::vec;
 
-use crate::semantic_graph::Edge;
+use crate::semantic_graph::{Edge, SemanticTree};
 use super::semantic_analysis::*;
 use crate::lexical_analysis::Token;
 use crate::lexical_analysis::TokenType;
+use crate::lexical_analysis::Scanner;
+use crate::syntactic_analysis::parse_program;
 
 #[cfg(test)]
 
@@ -17,19 +19,18 @@ fn test_semantic_action() {
         Box::new(PushHigherLevelNode{description: "+".to_string()})
     );
     let mut stack : Vec<SemanticNode> = vec![];
-    let mut all_nodes: Vec<SemanticNode> = vec![];
-    let mut edges: Vec<(SemanticNode, SemanticNode)> = vec![];
+    let mut tree = SemanticTree::new();
     let curr_token = Token::from(TokenType::Plus, "plus".to_string());
     match production_element {
         ProductionElement::SemanticElement(handler) =>  {
-            handler.take_action(&mut stack, &mut all_nodes, &mut edges, Some(&curr_token));
+            handler.take_action(&mut stack, &mut tree, Some(&curr_token));
         },
         _ => {
             panic!("Wrong production type!");
         }
     }
     assert_eq!(stack.len(), 1);
-    assert_eq!(all_nodes.len(), 1);
+    assert_eq!(tree.len(), 1);
     match stack.pop().unwrap() {
         SemanticNode::HigherLevelNode { id, description } => {
             assert_eq!(id, 0);
@@ -84,3 +85,49 @@ pub fn test_create_adjacency_matrix() {
     adjacency_matrix.insert(node1, vec![]);
     assert_eq!(adjacency_matrix.len(), 1);
 }
+
+fn node_labels(tree: &SemanticTree, ids: &[usize]) -> Vec<String> {
+    ids.iter().map(|&id| tree.node(id).as_string()).collect()
+}
+
+#[test]
+fn test_gather_keeps_source_order() {
+    let mut stack: Vec<SemanticNode> = vec![];
+    let mut tree = SemanticTree::new();
+    for description in ["first", "second", "third"] {
+        PushHigherLevelNode { description: description.to_string() }.take_action(&mut stack, &mut tree, None);
+    }
+    MarkListBegin.take_action(&mut stack, &mut tree, None);
+    PushHigherLevelNode { description: "a".to_string() }.take_action(&mut stack, &mut tree, None);
+    PushHigherLevelNode { description: "b".to_string() }.take_action(&mut stack, &mut tree, None);
+    CollectList { list_name: "List".to_string() }.take_action(&mut stack, &mut tree, None);
+    let list_id = stack.last().unwrap().id();
+    assert_eq!(node_labels(&tree, tree.children(list_id)), vec!["a_ID4", "b_ID5"]);
+    assert_eq!(tree.parent(4), Some(list_id));
+    assert_eq!(tree.parent(list_id), None);
+}
+
+#[test]
+fn test_tree_traversals_follow_source_order() {
+    let (program, tree) = parse_program(
+        &mut Scanner::from("function f() => void { x = 1 + 2; write(x); }".to_string()).get_all_tokens(),
+        &mut std::io::sink(),
+    );
+    assert_eq!(program.functions.len(), 1);
+    let roots = tree.roots();
+    assert_eq!(roots.len(), 1);
+    let labels: Vec<String> = tree.pre_order(roots[0]).iter()
+        .map(|&id| tree.node(id).as_string().split("_ID").next().unwrap().to_string())
+        .collect();
+    assert_eq!(labels, vec![
+        "Program", "FuncDef_f", "FuncHead_f", "Name_f", "Type_void", "Block",
+        "Assign", "Var_x", "Add", "IntLit", "Op_Add", "IntLit", "Write", "Var_x",
+    ].into_iter().map(|label| label.to_string()).collect::<Vec<String>>());
+    let post_order = tree.post_order(roots[0]);
+    assert_eq!(*post_order.last().unwrap(), roots[0]);
+    for id in post_order {
+        for &child in tree.children(id) {
+            assert_eq!(tree.parent(child), Some(id));
+        }
+    }
+}
@@ -0,0 +1,163 @@
+#![cfg(test)]
+use super::lexical_analysis::*;
+use super::passes::*;
+use super::semantic_checks::*;
+use super::symbol_table::*;
+use super::syntactic_analysis::*;
+use super::test_support::*;
+
+#[test]
+fn test_polynomial_example_has_no_diagnostics() {
+    let source = read_source_file("assignment1.COMP442-6421.paquet.2023.4/example-polynomial.src".to_string())
+        .replace("localvar counter: integer = 1;", "localvar counter: integer;");
+    let mut program = parse_source(source);
+    let mut manager = PassManager::new();
+    manager.add_pass(Box::new(SymbolTablePass));
+    manager.add_pass(Box::new(SemanticCheckPass));
+    let context = manager.run(&mut program);
+    assert_eq!(context.errors().len(), 0, "{:?}", context.diagnostics);
+    assert_eq!(context.warnings().len(), 2);
+    assert!(context.warnings()[0].message.contains("member function 'evaluate' in class 'LINEAR' shadows"));
+}
+
+#[test]
+fn test_undeclared_identifiers() {
+    let diagnostics = check_source("
+class A {
+  public attribute n: integer;
+  public function get: () => integer;
+};
+function A::get() => integer { return (m); }
+function main() => void {
+  localvar a: A;
+  localvar b: B;
+  a.n = a.k + a.size();
+  write(f(a.n));
+}
+", check_declarations);
+    assert_eq!(diagnostics, vec![
+        "[error] line 6: undeclared local variable 'm'",
+        "[error] line 9: undeclared class 'B'",
+        "[error] line 10: undeclared data member 'k' in class 'A'",
+        "[error] line 10: undeclared member function 'size' in class 'A'",
+        "[error] line 11: undeclared free function 'f'",
+    ]);
+}
+
+#[test]
+fn test_multiply_declared_identifiers() {
+    let diagnostics = check_source("
+class A { public attribute n: integer; private attribute n: float; };
+class A { };
+function f(x: integer, x: float) => void { localvar x: integer; localvar y: A; localvar y: A; }
+function f(x: integer, x: float) => void { }
+function main() => void { }
+", check_declarations);
+    assert_eq!(diagnostics, vec![
+        "[error] line 2: multiply declared data member 'n' in class 'A'",
+        "[error] line 3: multiply declared class 'A'",
+        "[error] line 4: multiply declared parameter 'x' in function 'f'",
+        "[error] line 4: multiply declared local variable 'x' in function 'f'",
+        "[error] line 4: multiply declared local variable 'y' in function 'f'",
+        "[error] line 5: multiply declared parameter 'x' in function 'f'",
+        "[error] line 5: multiply defined function 'f'",
+    ]);
+}
+
+#[test]
+fn test_multiply_defined_functions_are_checked_on_their_own_tables() {
+    let mut program = parse_source("
+class A { public function g: () => integer; };
+function A::g() => integer { localvar a: integer; a = 1; return (a); }
+function A::g() => integer { localvar b: integer; b = 2; return (b); }
+function free(x: integer) => integer { localvar a: integer; a = x; return (a); }
+function free(x: integer) => integer { localvar b: integer; b = x + c; return (b); }
+function main() => void { }
+".to_string());
+    let context = PassManager::front_end().run(&mut program);
+    let errors: Vec<String> = context.errors().iter().map(|error| error.as_string()).collect();
+    assert_eq!(errors, vec![
+        "[error] line 4: multiply defined function 'A::g'",
+        "[error] line 6: multiply defined function 'free'",
+        "[error] line 6: undeclared local variable 'c'",
+    ]);
+}
+
+#[test]
+fn test_declarations_and_definitions_must_match() {
+    let diagnostics = check_source("
+class A {
+  public function f: (x: integer) => integer;
+  public function g: () => void;
+};
+function A::f(x: float) => integer { return (1); }
+function A::g() => void { }
+function B::h() => void { }
+function main() => void { }
+", check_declarations);
+    assert_eq!(diagnostics, vec![
+        "[error] line 3: member function 'A::f' is declared but not defined",
+        "[error] line 6: definition of 'A::f' has no matching declaration",
+        "[error] line 8: undeclared class 'B'",
+    ]);
+}
+
+#[test]
+fn test_shadowing_and_overloading_warnings() {
+    let diagnostics = check_source("
+class A { public attribute n: integer; public function f: () => void; };
+class B isa A {
+  public attribute n: float;
+  public function f: () => void;
+  public function f: (x: integer) => void;
+};
+function A::f() => void { }
+function B::f() => void { n = 1.5; }
+function B::f(x: integer) => void { self.n = 2.5; }
+function g(x: integer) => void { }
+function g(x: float) => void { }
+function main() => void { }
+", check_declarations);
+    assert_eq!(diagnostics, vec![
+        "[warning] line 4: data member 'n' in class 'B' shadows inherited member of class 'A'",
+        "[warning] line 5: member function 'f' in class 'B' shadows inherited member of class 'A'",
+        "[warning] line 6: overloaded member function 'f' in class 'B'",
+        "[warning] line 6: member function 'f' in class 'B' shadows inherited member of class 'A'",
+        "[warning] line 12: overloaded free function 'g'",
+    ]);
+}
+
+#[test]
+fn test_overrides_keep_the_return_type() {
+    let diagnostics = check_source("
+class A { public function f: (x: integer) => integer; public function g: () => void; };
+class B isa A { public function f: (x: integer) => float; public function f: () => float; };
+function A::f(x: integer) => integer { return (x); }
+function A::g() => void { }
+function B::f(x: integer) => float { return (x); }
+function B::f() => float { return (1); }
+function main() => void { }
+", check_declarations);
+    assert_eq!(diagnostics, vec![
+        "[warning] line 3: overloaded member function 'f' in class 'B'",
+        "[warning] line 3: member function 'f' in class 'B' shadows inherited member of class 'A'",
+        "[error] line 3: member function 'f' in class 'B' overrides one of class 'A' returning integer",
+        "[warning] line 3: member function 'f' in class 'B' shadows inherited member of class 'A'",
+    ]);
+}
+
+#[test]
+fn test_array_declarations_need_sizes() {
+    let diagnostics = check_source("
+class A { public attribute values: integer[]; };
+function f(values: integer[][2], empty: float[0]) => void { localvar copy: integer[][2]; }
+function main() => void { localvar big: integer[2147483648]; localvar huge: float[2][99999999999999999999]; }
+", check_declarations);
+    assert_eq!(diagnostics, vec![
+        "[error] line 2: array 'values' needs a size for every dimension",
+        "[error] line 3: array 'empty' has a dimension of size 0",
+        "[error] line 3: array 'copy' needs a size for every dimension",
+        "[error] line 4: array 'big' has a dimension larger than 2147483647",
+        "[error] line 4: array 'huge' has a dimension larger than 2147483647",
+    ]);
+}
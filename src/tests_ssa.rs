@@ -0,0 +1,157 @@
+#![cfg(test)]
+use std::collections::BTreeSet;
+use super::cfg::*;
+use super::interpreter::*;
+use super::ir::*;
+use super::lexical_analysis::*;
+use super::moon_codegen::*;
+use super::moon_vm::*;
+use super::ssa::*;
+use super::test_support::*;
+
+// Output of the program with every function taken in and out of SSA form by
+// `convert`, checked against the interpreter.
+fn run_through_ssa(source: &str, input: &str, convert: fn(&IrFunction) -> IrFunction) -> String {
+    let (program, mut context) = run_passes(source);
+    let mut ir = context.ir.take().unwrap();
+    for function in ir.functions.iter_mut() {
+        *function = convert(function);
+    }
+    let output = run_moon(&generate_moon(&ir, true), input).unwrap();
+    assert_eq!(output, interpret(&program, context.symbol_table(), input).0, "{}", ir.as_string());
+    output
+}
+
+fn round_trip(function: &IrFunction) -> IrFunction {
+    let ssa = to_ssa(function);
+    assert_eq!(ssa.verify(), Vec::<String>::new(), "{}", ssa.as_string());
+    from_ssa(&ssa)
+}
+
+const SOURCE: &str = "
+function main() => void {
+  localvar i: integer;
+  localvar total: integer;
+  read(i);
+  total = 0;
+  while (i > 0) {
+    if (i > 5) then total = total + i; else total = total - 1;;
+    i = i - 1;
+  };
+  write(total);
+}
+";
+
+#[test]
+fn test_dominators_and_frontiers() {
+    let (_, context) = run_passes(SOURCE);
+    let cfg = ControlFlowGraph::build(context.ir.as_ref().unwrap().function("main").unwrap());
+    assert_eq!(cfg.block_names(), ["entry", "B1", "L1", "L2", "L4", "L5", "L6", "L3", "exit"]);
+    let dominators = DominatorTree::build(&cfg);
+    assert_eq!(dominators.idom, [None, Some(0), Some(1), Some(2), Some(3), Some(3), Some(3), Some(2), Some(7)]);
+    assert!(dominators.dominates(2, 6));
+    assert!(!dominators.dominates(4, 6));
+    let frontiers = dominators.frontiers(&cfg);
+    // the branches of the if meet at L6, and the loop body goes back to L1
+    assert_eq!(frontiers[4], BTreeSet::from([6]));
+    assert_eq!(frontiers[6], BTreeSet::from([2]));
+    assert_eq!(frontiers[3], BTreeSet::from([2]));
+    assert_eq!(frontiers[2], BTreeSet::from([2]));
+}
+
+#[test]
+fn test_phi_nodes_are_printed_with_versions() {
+    let (_, context) = run_passes(SOURCE);
+    let ssa = to_ssa(context.ir.as_ref().unwrap().function("main").unwrap());
+    assert_eq!(ssa.as_string(), "\
+function main() => void
+B1:
+    read.i i.1
+    total.1 = 0
+L1:
+    i.2 = phi(B1: i.1, L6: i.3)
+    total.2 = phi(B1: total.1, L6: total.5)
+    t1.1 = i.2 > 0
+    if t1.1 goto L2 else L3
+L2:
+    t2.1 = i.2 > 5
+    if t2.1 goto L4 else L5
+L4:
+    t3.1 = total.2 + i.2
+    total.3 = t3.1
+    goto L6
+L5:
+    t4.1 = total.2 - 1
+    total.4 = t4.1
+L6:
+    total.5 = phi(L4: total.3, L5: total.4)
+    t5.1 = i.2 - 1
+    i.3 = t5.1
+    goto L1
+L3:
+    write.i total.2
+    return
+");
+    assert_eq!(ssa.verify(), Vec::<String>::new());
+}
+
+#[test]
+fn test_verifier_reports_broken_invariants() {
+    let (_, context) = run_passes(SOURCE);
+    let mut ssa = to_ssa(context.ir.as_ref().unwrap().function("main").unwrap());
+    let header = ssa.cfg.block_names().iter().position(|name| *name == "L1").unwrap();
+    ssa.phis[header][0].sources.pop();
+    let join = ssa.cfg.block_names().iter().position(|name| *name == "L6").unwrap();
+    ssa.phis[join][0].sources.swap(0, 1);
+    let end = ssa.cfg.block_names().iter().position(|name| *name == "L3").unwrap();
+    ssa.cfg.blocks[end].instrs.insert(1, Instr::Copy { dest: Operand::Var("i".to_string()), src: Operand::Int(0) });
+    assert_eq!(ssa.verify(), [
+        "phi of i.2 in L1 has 1 sources",
+        "i is assigned outside SSA form",
+        "total.4 does not dominate the end of L4",
+        "total.3 does not dominate the end of L5",
+    ]);
+}
+
+#[test]
+fn test_program_output_is_unchanged_out_of_ssa() {
+    let polynomial = read_source_file("assignment1.COMP442-6421.paquet.2023.4/example-polynomial.src".to_string())
+        .replace("localvar counter: integer = 1;", "localvar counter: integer;")
+        .replace("write(f2.evaluate(counter));", "write(f2.evaluate(counter)); counter = counter + 1;");
+    assert_eq!(run_through_ssa(&polynomial, "", round_trip).lines().count(), 33);
+    assert_eq!(run_through_ssa(SOURCE, "8", round_trip), "16\n");
+}
+
+#[test]
+fn test_copies_on_one_edge_happen_at_once() {
+    let source = "
+function main() => void {
+  localvar a: integer;
+  localvar b: integer;
+  localvar swap: integer;
+  localvar i: integer;
+  a = 1;
+  b = 2;
+  i = 0;
+  while (i < 3) { swap = a; a = b; b = swap; i = i + 1; };
+  write(a);
+  write(b);
+}
+";
+    // what copy propagation would make of the loop: the values of `a` and `b`
+    // going around it are each other's
+    fn swap_phis(function: &IrFunction) -> IrFunction {
+        let mut ssa = to_ssa(function);
+        let header = ssa.cfg.block_names().iter().position(|name| *name == "L1").unwrap();
+        let phis = &mut ssa.phis[header];
+        assert_eq!(phis.len(), 3);
+        let (a, b) = (phis[0].dest.clone(), phis[1].dest.clone());
+        phis[0].sources[1] = b;
+        phis[1].sources[1] = a;
+        assert_eq!(ssa.verify(), Vec::<String>::new());
+        let function = from_ssa(&ssa);
+        assert!(function.temporary_count() > ssa.function.temporary_count());
+        function
+    }
+    assert_eq!(run_through_ssa(source, "", swap_phis), "2\n1\n");
+}
@@ -0,0 +1,86 @@
+#![cfg(test)]
+use super::ast::*;
+use super::lexical_analysis::*;
+use super::symbol_table::*;
+use super::syntactic_analysis::*;
+
+fn polynomial_program() -> Program {
+    let source = read_source_file("assignment1.COMP442-6421.paquet.2023.4/example-polynomial.src".to_string())
+        .replace("localvar counter: integer = 1;", "localvar counter: integer;");
+    parse_source(source)
+}
+
+#[test]
+fn test_global_and_class_tables() {
+    let global = build_symbol_tables(&polynomial_program());
+    let global_entries: Vec<(String, SymbolKind)> = global.entries.iter()
+        .map(|entry| (entry.name.clone(), entry.kind))
+        .collect();
+    assert_eq!(global_entries, vec![
+        ("POLYNOMIAL".to_string(), SymbolKind::Class),
+        ("LINEAR".to_string(), SymbolKind::Class),
+        ("QUADRATIC".to_string(), SymbolKind::Class),
+        ("main".to_string(), SymbolKind::Function),
+    ]);
+
+    let linear = global.class_table("LINEAR").unwrap();
+    let linear_entries: Vec<(String, SymbolKind, String)> = linear.entries.iter()
+        .map(|entry| (entry.name.clone(), entry.kind, entry.type_string()))
+        .collect();
+    assert_eq!(linear_entries, vec![
+        ("POLYNOMIAL".to_string(), SymbolKind::Inherit, "POLYNOMIAL".to_string()),
+        ("a".to_string(), SymbolKind::Attribute, "float".to_string()),
+        ("b".to_string(), SymbolKind::Attribute, "float".to_string()),
+        ("constructor".to_string(), SymbolKind::Constructor, "(float, float):void".to_string()),
+        ("evaluate".to_string(), SymbolKind::Function, "(float):float".to_string()),
+    ]);
+    assert_eq!(linear.lookup("a")[0].visibility, Visibility::Private);
+    assert_eq!(linear.lookup("evaluate")[0].visibility, Visibility::Public);
+}
+
+#[test]
+fn test_function_tables_hold_params_and_locals() {
+    let program = parse_source("
+        class A { public function f: (x: integer[][4], y: float) => integer; };
+        function A::f(x: integer[][4], y: float) => integer {
+            localvar i: integer;
+            while (i < 3) { i = i + 1; };
+            localvar t: A[2];
+            return (i);
+        }
+        function main() => void { localvar a: A; }
+    ".to_string());
+    let global = build_symbol_tables(&program);
+    let member_table = global.function_table(&program.functions[0]).unwrap();
+    assert_eq!(member_table.name, "A::f");
+    let member_entries: Vec<(String, SymbolKind, String)> = member_table.entries.iter()
+        .map(|entry| (entry.name.clone(), entry.kind, entry.type_string()))
+        .collect();
+    assert_eq!(member_entries, vec![
+        ("x".to_string(), SymbolKind::Parameter, "integer[][4]".to_string()),
+        ("y".to_string(), SymbolKind::Parameter, "float".to_string()),
+        ("i".to_string(), SymbolKind::Local, "integer".to_string()),
+        ("t".to_string(), SymbolKind::Local, "A[2]".to_string()),
+    ]);
+    let main_table = global.function_table(&program.functions[1]).unwrap();
+    assert_eq!(main_table.lookup_kind("a", SymbolKind::Local).unwrap().type_name, TypeName::Class("A".to_string()));
+}
+
+#[test]
+fn test_symbol_table_output() {
+    let program = parse_source("
+        class A { private attribute n: integer[3]; };
+        function main() => void { localvar x: float; }
+    ".to_string());
+    let output = build_symbol_tables(&program).as_string();
+    let lines: Vec<&str> = output.lines().filter(|line| !line.contains("====")).collect();
+    assert_eq!(lines, vec![
+        "| table: global",
+        "| class       | A            | A",
+        "|    | table: A",
+        "|    | data        | n            | integer[3] | private",
+        "| function    | main         | ():void | public",
+        "|    | table: main",
+        "|    | local       | x            | float | public",
+    ]);
+}
@@ -0,0 +1,182 @@
+#![cfg(test)]
+use super::ast::*;
+use super::lexical_analysis::*;
+use super::symbol_table::*;
+use super::syntactic_analysis::*;
+use super::test_support::*;
+use super::type_checker::*;
+
+#[test]
+fn test_polynomial_example_type_checks() {
+    let source = read_source_file("assignment1.COMP442-6421.paquet.2023.4/example-polynomial.src".to_string())
+        .replace("localvar counter: integer = 1;", "localvar counter: integer;");
+    let program = parse_source(source);
+    let global = build_symbol_tables(&program);
+    assert_eq!(check_types(&program, &global), vec![]);
+}
+
+#[test]
+fn test_expression_and_assignment_types() {
+    let diagnostics = check_source("
+class P { public attribute x: float; };
+function main() => void {
+  localvar i: integer;
+  localvar f: float;
+  localvar p: P;
+  localvar a: integer[3][4];
+  f = i * 2 + f;
+  i = f;
+  i = p + 1;
+  i = a[1];
+  i = a[1][2][3];
+  i = a[f][1];
+  f = p.x.y;
+  if (i and f > 1) then write(p); else read(a[1][1]);;
+}
+", check_types);
+    assert_eq!(diagnostics, vec![
+        "[error] line 9: type mismatch in assignment: integer and float",
+        "[error] line 10: type mismatch in '+': P and integer",
+        "[error] line 11: arrays cannot be used in assignment",
+        "[error] line 12: too many indices on a value of type integer",
+        "[error] line 13: array index has type float, expected integer",
+        "[error] line 14: '.' applied to a value of type float",
+        "[error] line 15: type mismatch in 'and': integer and float",
+        "[error] line 15: cannot write a value of type P",
+    ]);
+}
+
+#[test]
+fn test_integer_literals_must_fit_in_an_integer() {
+    let diagnostics = check_source("
+function main() => void {
+  localvar i: integer;
+  i = 2147483647 + -2147483648;
+  i = 2147483648;
+  i = 9223372036854775807 - -99999999999999999999;
+}
+", check_types);
+    assert_eq!(diagnostics, vec![
+        "[error] line 5: integer literal is out of range, the largest integer is 2147483647",
+        "[error] line 6: integer literal is out of range, the largest integer is 2147483647",
+        "[error] line 6: integer literal is out of range, the largest integer is 2147483647",
+    ]);
+}
+
+#[test]
+fn test_calls_and_returns() {
+    let diagnostics = check_source("
+class P {
+  public constructor: (x: integer);
+  public function get: () => integer;
+};
+function P::constructor(x: integer) { }
+function P::get() => integer { return (1.5); }
+function f(a: integer, b: float[]) => float { return (a); }
+function g() => void { return (1); }
+function main() => void {
+  localvar p: P(1, 2);
+  localvar q: P(2.5);
+  localvar v: float[4];
+  localvar w: integer[4];
+  write(f(1));
+  write(f(1, w));
+  write(f(1, v) + p.get());
+  write(g());
+}
+", check_types);
+    assert_eq!(diagnostics, vec![
+        "[error] line 7: type mismatch in return: integer and float",
+        "[error] line 9: return statement in a function returning void",
+        "[error] line 11: wrong number of arguments in call to 'P::constructor': expected 1, got 2",
+        "[error] line 12: argument 1 of 'P::constructor' has type float, expected integer",
+        "[error] line 15: wrong number of arguments in call to 'f': expected 2, got 1",
+        "[error] line 16: argument 2 of 'f' has type integer[4], expected float[]",
+        "[error] line 18: cannot write a value of type void",
+    ]);
+}
+
+#[test]
+fn test_overload_resolution() {
+    let source = "
+class SHAPE {
+  public function scale: (factor: integer) => integer;
+  public function scale: (factor: float) => float;
+};
+function SHAPE::scale(factor: integer) => integer { return (factor); }
+function SHAPE::scale(factor: float) => float { return (factor); }
+function mix(a: integer, b: float) => integer { return (1); }
+function mix(a: float, b: integer) => integer { return (2); }
+function main() => void {
+  localvar s: SHAPE;
+  localvar i: integer;
+  localvar f: float;
+  i = s.scale(2);
+  f = s.scale(2.5);
+  i = s.scale(2.5);
+  i = mix(1, 2.5);
+  i = mix(1, 2);
+  i = mix(1.5, 2.5);
+  i = s.scale(1, 2);
+}
+";
+    assert_eq!(check_source(source, check_types), vec![
+        "[error] line 16: type mismatch in assignment: integer and float",
+        "[error] line 18: ambiguous call to 'mix': mix(integer, float) or mix(float, integer)",
+        "[error] line 19: no matching overload for call to 'mix' with arguments (float, float)",
+        "[error] line 20: no matching overload for call to 'scale' with arguments (integer, integer)",
+    ]);
+    let program = parse_source(source.to_string());
+    let global = build_symbol_tables(&program);
+    let signatures: Vec<String> = global.class_table("SHAPE").unwrap().entries.iter().map(|entry| entry.signature()).collect();
+    assert_eq!(signatures, vec!["scale(integer)", "scale(float)"]);
+    let float_scale = global.function_table(&program.functions[1]).unwrap();
+    assert_eq!(float_scale.entries[0].type_name, TypeName::Float);
+}
+
+#[test]
+fn test_objects_are_passed_for_ancestor_parameters() {
+    assert_eq!(check_source("
+class A { public attribute n: integer; };
+class B isa A { };
+class C { };
+function show(a: A) => void { write(a.n); }
+function show(b: B) => void { }
+function count(values: A[]) => void { }
+function main() => void {
+  localvar a: A;
+  localvar b: B;
+  localvar c: C;
+  localvar values: B[2];
+  show(a);
+  show(b);
+  show(c);
+  count(values);
+  a = b;
+}
+", check_types), vec![
+        "[error] line 15: no matching overload for call to 'show' with arguments (C)",
+        "[error] line 16: argument 1 of 'count' has type B[2], expected A[]",
+        "[error] line 17: type mismatch in assignment: A and B",
+    ]);
+}
+
+#[test]
+fn test_functions_returning_a_value_return_on_every_path() {
+    assert_eq!(check_source("
+class A { public function get: () => integer; };
+function A::get() => integer { while (1 == 1) { return (1); }; }
+function v() => integer { localvar z: integer; z = 1; }
+function sign(x: integer) => integer {
+  if (x < 0) then { return (-1); } else { if (x > 0) then { return (1); } else { }; };
+}
+function abs(x: integer) => integer {
+  if (x < 0) then { return (-x); } else { return (x); };
+}
+function main() => void { write(abs(v())); }
+", check_types), vec![
+        "[error] line 3: function 'get' may not return a value",
+        "[error] line 4: function 'v' may not return a value",
+        "[error] line 5: function 'sign' may not return a value",
+    ]);
+}
@@ -0,0 +1,61 @@
+#![cfg(test)]
+use super::ast::*;
+use super::symbol_table::*;
+use super::syntactic_analysis::*;
+use super::type_checker::*;
+use super::types::*;
+
+#[test]
+fn test_array_type_sizes() {
+    let matrix = Type::new(TypeName::Float, vec![Some(2), Some(3)]);
+    assert_eq!(matrix.element_count(), Some(6));
+    assert_eq!(matrix.size(FLOAT_SIZE), Some(48));
+    assert_eq!(matrix.strides(), vec![Some(3), Some(1)]);
+    assert_eq!(matrix.indexed(), Some(Type::new(TypeName::Float, vec![Some(3)])));
+    assert_eq!(matrix.indexed().unwrap().indexed(), Some(Type::scalar(TypeName::Float)));
+    assert_eq!(Type::scalar(TypeName::Float).indexed(), None);
+    let unsized_rows = Type::new(TypeName::Integer, vec![None, Some(4)]);
+    assert!(unsized_rows.has_unsized_dims());
+    assert_eq!(unsized_rows.element_count(), None);
+    assert_eq!(unsized_rows.strides(), vec![Some(4), Some(1)]);
+    assert_eq!(unsized_rows.as_string(), "integer[][4]");
+}
+
+#[test]
+fn test_array_arguments() {
+    let unsized_param = Type::new(TypeName::Integer, vec![None]);
+    let sized_param = Type::new(TypeName::Integer, vec![None, Some(4)]);
+    assert!(unsized_param.accepts_argument(&Type::new(TypeName::Integer, vec![Some(7)])));
+    assert!(!unsized_param.accepts_argument(&Type::new(TypeName::Float, vec![Some(7)])));
+    assert!(!unsized_param.accepts_argument(&Type::new(TypeName::Integer, vec![Some(7), Some(4)])));
+    assert!(sized_param.accepts_argument(&Type::new(TypeName::Integer, vec![Some(2), Some(4)])));
+    assert!(!sized_param.accepts_argument(&Type::new(TypeName::Integer, vec![Some(2), Some(5)])));
+    assert!(Type::scalar(TypeName::Float).accepts_argument(&Type::scalar(TypeName::Integer)));
+}
+
+#[test]
+fn test_array_semantics_in_programs() {
+    let program = parse_source("
+function sum(values: integer[], count: integer) => integer { return (values[count - 1]); }
+function trace(matrix: float[][3]) => float { return (matrix[0][0] + matrix[1][1]); }
+function main() => void {
+  localvar row: integer[5];
+  localvar grid: float[3][3];
+  localvar other: float[3][2];
+  write(sum(row, 5));
+  write(sum(grid[1], 3));
+  write(trace(grid));
+  write(trace(other));
+  write(row[5]);
+  write(grid[1][2][0]);
+}
+".to_string());
+    let global = build_symbol_tables(&program);
+    let diagnostics: Vec<String> = check_types(&program, &global).iter().map(|diagnostic| diagnostic.as_string()).collect();
+    assert_eq!(diagnostics, vec![
+        "[error] line 9: argument 1 of 'sum' has type float[3], expected integer[]",
+        "[error] line 11: argument 1 of 'trace' has type float[3][2], expected float[][3]",
+        "[error] line 12: array index 5 is out of bounds for a dimension of size 5",
+        "[error] line 13: too many indices on a value of type float",
+    ]);
+}
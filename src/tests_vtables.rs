@@ -0,0 +1,70 @@
+#![cfg(test)]
+use super::ast::*;
+use super::ir::*;
+use super::test_support::*;
+use super::types::*;
+use super::vtables::*;
+
+fn entries(vtable: &Vtable) -> Vec<(String, i64)> {
+    vtable.entries.iter().map(|entry| (entry.function.clone(), entry.adjustment)).collect()
+}
+
+#[test]
+fn test_slots_extend_the_first_parent() {
+    let global = layout_of("
+class A { public function f: () => void; public function g: (x: integer) => void; };
+class B { public function h: () => void; };
+class C isa A, B {
+  public function g: (x: integer) => void;
+  public function g: (x: float) => void;
+  public function h: () => void;
+};
+");
+    let names = |class_name: &str| -> Vec<String> {
+        vtable_slots(&global, class_name).iter().map(|slot| slot.signature()).collect()
+    };
+    assert_eq!(names("A"), vec!["f()", "g(integer)"]);
+    assert_eq!(names("C"), vec!["f()", "g(integer)", "g(float)", "h()"]);
+    let params = &vtable_slots(&global, "C")[2].params;
+    assert_eq!(slot_index(&global, "C", "g", params), 2);
+}
+
+#[test]
+fn test_tables_point_to_overrides_with_adjustments() {
+    let global = layout_of("
+class A { public attribute n: integer; public function f: () => void; };
+class B { public attribute x: float; public function h: () => void; public function k: () => void; };
+class C isa A, B { public function h: () => void; };
+class D isa C { public function f: () => void; public function k: () => void; };
+");
+    let vtables = build_vtables(&global);
+    let names: Vec<&str> = vtables.iter().map(|vtable| vtable.name.as_str()).collect();
+    assert_eq!(names, vec!["vtable_A", "vtable_B", "vtable_C", "vtable_C_8", "vtable_D", "vtable_D_8"]);
+    let d = &vtables[4];
+    assert_eq!(entries(d), vec![("D_f".to_string(), 0), ("C_h".to_string(), 0), ("D_k".to_string(), 0)]);
+    // the B part of a D is 8 bytes in, after the A part
+    assert_eq!(entries(&vtables[5]), vec![("C_h".to_string(), -8), ("D_k".to_string(), -8)]);
+    assert_eq!(entries(&vtables[3]), vec![("C_h".to_string(), -8), ("B_k".to_string(), 0)]);
+}
+
+#[test]
+fn test_vtable_pointers_of_nested_objects() {
+    let global = layout_of("
+class A { public attribute n: integer; };
+class B { public attribute x: float; };
+class C isa A, B { public attribute parts: A[2]; };
+class E { public attribute c: C; };
+");
+    let e = Type::scalar(TypeName::Class("E".to_string()));
+    assert_eq!(vtable_pointers(&global, &e), vec![
+        (0, "vtable_E".to_string()),
+        (4, "vtable_C".to_string()),
+        (12, "vtable_C_8".to_string()),
+        (24, "vtable_A".to_string()),
+        (32, "vtable_A".to_string()),
+    ]);
+    let values = Type::new(TypeName::Class("A".to_string()), vec![Some(3)]);
+    let offsets: Vec<usize> = vtable_pointers(&global, &values).iter().map(|(offset, _)| *offset).collect();
+    assert_eq!(offsets, vec![0, 8, 16]);
+    assert_eq!(vtable_pointers(&global, &Type::scalar(TypeName::Integer)), vec![]);
+}
@@ -0,0 +1,305 @@
+#![cfg(test)]
+use std::fs;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use super::interpreter::*;
+use super::lexical_analysis::*;
+use super::test_support::*;
+use super::wasm_codegen::*;
+
+fn generate(source: &str) -> String {
+    generate_wat(run_passes(source).1.ir.as_ref().unwrap())
+}
+
+// The module assembled to its binary format and validated, as a WebAssembly
+// engine does before running anything.
+fn validate(wat: &str) -> Result<Vec<u8>, String> {
+    let binary = wat::parse_str(wat).map_err(|error| error.to_string())?;
+    wasmparser::Validator::new().validate_all(&binary).map_err(|error| error.to_string())?;
+    Ok(binary)
+}
+
+// Host functions for node, which writes the values in full and leaves their
+// formatting to `run_wat`, and a line with the error that stopped the module.
+const RUNNER: &str = r#"
+const fs = require('fs');
+const tokens = fs.readFileSync(0, 'utf8').split(/\s+/).filter(token => token !== '');
+const next = () => {
+  if (tokens.length === 0) throw new Error('unexpected end of input');
+  return tokens.shift();
+};
+const host = {
+  read_int: () => {
+    const token = next();
+    if (!/^[+-]?[0-9]+$/.test(token) || Number(token) !== (Number(token) | 0)) throw new Error('invalid number');
+    return Number(token);
+  },
+  read_float: () => {
+    const value = Number(next());
+    if (Number.isNaN(value)) throw new Error('invalid number');
+    return value;
+  },
+  write_int: value => console.log('int ' + value),
+  write_float: value => console.log('float ' + (Object.is(value, -0) ? '-0' : String(value))),
+};
+try {
+  const module = new WebAssembly.Module(fs.readFileSync(process.argv[1]));
+  new WebAssembly.Instance(module, { host }).exports.main();
+} catch (error) {
+  console.log('trap ' + error.message);
+}
+"#;
+
+// Whether node is there to run the modules: without it, the tests only validate
+// them.
+fn node_installed() -> bool {
+    Command::new("node").arg("--version").output().is_ok()
+}
+
+// Output of `main` in the validated module run by node, formatted like the
+// interpreter's, and the trap that stopped it, if any.
+fn run_wat(wat: &str, name: &str, input: &str) -> (String, Option<String>) {
+    let binary = validate(wat).unwrap_or_else(|error| panic!("{}\n{}", error, wat));
+    let path = std::env::temp_dir().join(format!("wasm_codegen_{}_{}.wasm", std::process::id(), name));
+    fs::write(&path, binary).unwrap();
+    let mut child = Command::new("node")
+        .arg("-e")
+        .arg(RUNNER)
+        .arg(&path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("node should be installed");
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let result = child.wait_with_output().unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(result.status.success());
+    let (mut output, mut trap) = (String::new(), None);
+    for line in String::from_utf8(result.stdout).unwrap().lines() {
+        match line.split_once(' ').unwrap() {
+            ("int", value) => output.push_str(&format!("{}\n", value)),
+            ("float", value) => output.push_str(&format!("{}\n", format_float(value.parse().unwrap()))),
+            ("trap", message) => trap = Some(message.to_string()),
+            _ => panic!("unexpected line {}", line),
+        }
+    }
+    (output, trap)
+}
+
+// Output of the module, checked against the interpreter, or the interpreter's
+// alone for a valid module when node is missing.
+fn run_source(source: &str, name: &str, input: &str) -> String {
+    let (program, context) = run_passes(source);
+    let wat = generate_wat(context.ir.as_ref().unwrap());
+    let expected = interpret(&program, context.symbol_table(), input).0;
+    if !node_installed() {
+        validate(&wat).unwrap_or_else(|error| panic!("{}\n{}", error, wat));
+        return expected;
+    }
+    let (output, trap) = run_wat(&wat, name, input);
+    assert_eq!(trap, None, "{}", wat);
+    assert_eq!(output, expected);
+    output
+}
+
+#[test]
+fn test_functions_keep_their_frame_in_memory() {
+    let source = "
+function twice(x: float) => float { return (x * 2); }
+function main() => void {
+  localvar i: integer;
+  i = 0;
+  while (i < 3) { write(twice(i)); i = i + 1; };
+}
+";
+    let wat = generate(source);
+    assert!(wat.starts_with("\
+(module
+  (import \"host\" \"read_int\" (func $read_int (result i32)))
+  (import \"host\" \"read_float\" (func $read_float (result f64)))
+  (import \"host\" \"write_int\" (func $write_int (param i32)))
+  (import \"host\" \"write_float\" (func $write_float (param f64)))
+  (memory (export \"memory\") 16)
+  (global $sp (mut i32) (i32.const 16))
+  (func $twice_f (param $arg0 f64) (result f64)
+    (local $fp i32) (local $pc i32)
+    global.get $sp
+    local.tee $fp
+    i32.const 40
+    i32.add
+    global.set $sp
+    local.get $fp
+    local.get $arg0
+    f64.store offset=12
+"), "{}", wat);
+    // the loop of `main` goes around the blocks of its labels
+    assert!(wat.contains("
+    loop $dispatch
+      block $L3
+        block $L2
+          block $L1
+            block $entry
+              local.get $pc
+              br_table $entry $L1 $L2 $L3
+            end
+"));
+    assert_eq!(run_source(source, "frame", ""), "0.00\n2.00\n4.00\n");
+}
+
+#[test]
+fn test_generated_programs_match_the_interpreter() {
+    let source = "
+function sum(values: integer[], count: integer) => integer {
+  localvar total: integer;
+  localvar i: integer;
+  total = 0;
+  i = 0;
+  while (i < count) { total = total + values[i]; i = i + 1; };
+  return (total);
+}
+function scale(values: float[][3], factor: float) => void {
+  localvar i: integer;
+  i = 0;
+  while (i < 3) { values[1][i] = values[0][i] * factor; i = i + 1; };
+}
+function main() => void {
+  localvar values: integer[4];
+  localvar grid: float[2][3];
+  localvar n: integer;
+  localvar x: float;
+  read(n);
+  read(x);
+  values[0] = n;
+  values[3] = n * 1000000;
+  write(sum(values, 4));
+  write(values[3] * 3000 / 7);
+  write((-2147483647 - 1) / -1);
+  grid[0][0] = x;
+  grid[0][2] = 1 / 4;
+  grid[0][1] = -x / 3;
+  scale(grid, 2);
+  write(grid[1][0] + grid[1][1] + grid[1][2]);
+  write(-grid[0][2]);
+  if (n and not values[1] == 1) then write(1); else write(0);;
+  write(x <= 2.5);
+  write(x > 3.0);
+}
+";
+    assert_eq!(run_source(source, "programs", "5 2.5"), "5000005\n302156873\n-2147483648\n3.33\n-0.00\n1\n1\n0\n");
+}
+
+#[test]
+fn test_member_functions_are_called_through_the_table() {
+    let source = "
+class SHAPE {
+  public attribute scale: integer;
+  public function area: () => integer;
+  public function describe: () => void;
+};
+class NAMED {
+  public attribute id: integer;
+  public function show: () => void;
+  public function code: () => integer;
+};
+class SQUARE isa SHAPE, NAMED {
+  public attribute side: integer;
+  public function area: () => integer;
+  public function code: () => integer;
+  public constructor: (side: integer);
+};
+function SHAPE::area() => integer { return (0); }
+function SHAPE::describe() => void { write(scale * area()); }
+function NAMED::show() => void { write(id * 1000 + code()); }
+function NAMED::code() => integer { return (1); }
+function SQUARE::area() => integer { return (side * side); }
+function SQUARE::code() => integer { return (side + id); }
+function SQUARE::constructor(side: integer) { self.side = side; scale = 10; id = 7; }
+function report(shape: SHAPE, named: NAMED) => void { shape.describe(); write(shape.area()); named.show(); }
+function main() => void {
+  localvar shape: SHAPE;
+  localvar square: SQUARE(3);
+  localvar squares: SQUARE[2];
+  shape.scale = 2;
+  shape.describe();
+  square.show();
+  report(square, square);
+  squares[1] = square;
+  report(squares[1], square);
+}
+";
+    let wat = generate(source);
+    assert!(wat.contains("  (elem (i32.const 0) $SHAPE_area $SHAPE_describe $NAMED_show $NAMED_code $SQUARE_area $SQUARE_code)\n"));
+    // the NAMED part of a SQUARE has a table of its own: NAMED_show, then
+    // SQUARE_code with `self` moved back by 8 bytes
+    assert!(wat.contains(r#"(data (i32.const 72) "\02\00\00\00\00\00\00\00\05\00\00\00\f8\ff\ff\ff") ;; vtable_SQUARE_8"#));
+    assert!(wat.contains("
+    local.get $fp
+    i32.load offset=4
+    i32.load
+    i32.load offset=8
+    call_indirect (type $t0)
+"));
+    assert_eq!(run_source(source, "dispatch", ""), "0\n7010\n90\n9\n7010\n90\n9\n7010\n");
+    let polynomial = read_source_file("assignment1.COMP442-6421.paquet.2023.4/example-polynomial.src".to_string())
+        .replace("localvar counter: integer = 1;", "localvar counter: integer;")
+        .replace("write(f2.evaluate(counter));", "write(f2.evaluate(counter)); counter = counter + 1;");
+    assert!(run_source(&polynomial, "polynomial", "").starts_with("0\n3.50\n0.00\n1\n5.50\n-1.00\n"));
+}
+
+#[test]
+fn test_division_by_zero_traps() {
+    let wat = generate("
+function main() => void {
+  localvar n: integer;
+  localvar x: float;
+  read(n);
+  read(x);
+  write(x / n);
+  write(1 / n);
+}
+");
+    validate(&wat).unwrap();
+    if !node_installed() {
+        return;
+    }
+    assert_eq!(run_wat(&wat, "zero", "0 1.5"), ("".to_string(), Some("unreachable".to_string())));
+    assert_eq!(run_wat(&wat, "wrap", "-1 1.5"), ("-1.50\n-1\n".to_string(), None));
+    assert_eq!(run_wat(&wat, "input", "2"), ("".to_string(), Some("unexpected end of input".to_string())));
+}
+
+#[test]
+fn test_functions_returning_a_value_end_in_unreachable() {
+    let source = "
+function fact(n: integer) => integer {
+  if (n <= 1) then { return (1); } else { return (n * fact(n - 1)); };
+}
+function sign(x: float) => float {
+  if (x < 0.0) then { return (-1.0); } else { if (x > 0.0) then { return (1.0); } else { return (0.0); }; };
+}
+function main() => void {
+  write(fact(5));
+  write(sign(-2.5));
+  write(sign(0.0));
+}
+";
+    let wat = generate(source);
+    // with nothing to return, the end of `fact` traps instead of returning
+    assert!(wat.contains("      ;; return\n      unreachable\n"), "{}", wat);
+    assert_eq!(run_source(source, "returns", ""), "120\n-1.00\n0.00\n");
+}
+
+#[test]
+fn test_malformed_modules_are_rejected() {
+    let wat = generate("function main() => void { localvar i: integer; i = 1; while (i < 3) { i = i + 1; }; write(i); }");
+    validate(&wat).unwrap();
+    if node_installed() {
+        assert_eq!(run_wat(&wat, "valid", ""), ("3\n".to_string(), None));
+    }
+    let error = |wat: String| validate(&wat).unwrap_err();
+    assert!(error(wat.replacen("br $dispatch", "br $loop", 1)).contains("unknown label"));
+    assert!(error(wat.replacen("call $write_int", "call $write", 1)).contains("unknown func"));
+    assert!(error(wat.replacen("(func $main", "(func $main (", 1)).contains("expected"));
+    assert!(error(wat.replace("i32.add", "f64.add")).contains("type mismatch"));
+    // a return without a value, even where it is never run
+    assert!(error(wat.replacen("(export \"main\")", "(export \"main\") (result i32)", 1)).contains("type mismatch"));
+}
@@ -0,0 +1,229 @@
+#![cfg(test)]
+use std::fs;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use super::interpreter::*;
+use super::lexical_analysis::*;
+use super::test_support::*;
+use super::x86_64_codegen::*;
+
+// Standard output, standard error and exit status of the program assembled and
+// linked with `as` and `ld`, in a directory of its own under the temporary
+// directory.
+fn run_x86_64(assembly: &str, name: &str, input: &str) -> (String, String, i32) {
+    let directory = std::env::temp_dir().join(format!("x86_64_codegen_{}_{}", std::process::id(), name));
+    fs::create_dir_all(&directory).unwrap();
+    let source_path = directory.join("program.s");
+    let object_path = directory.join("program.o");
+    let executable_path = directory.join("program");
+    fs::write(&source_path, assembly).unwrap();
+    let assemble = Command::new("as").arg("-o").arg(&object_path).arg(&source_path).output().expect("as should be installed");
+    assert!(assemble.status.success(), "{}", String::from_utf8_lossy(&assemble.stderr));
+    let link = Command::new("ld").arg("-o").arg(&executable_path).arg(&object_path).output().expect("ld should be installed");
+    assert!(link.status.success(), "{}", String::from_utf8_lossy(&link.stderr));
+    let mut child = Command::new(&executable_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // the program may stop before reading all of it
+    let _ = child.stdin.take().unwrap().write_all(input.as_bytes());
+    let output = child.wait_with_output().unwrap();
+    fs::remove_dir_all(&directory).unwrap();
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+        output.status.code().unwrap_or(-1),
+    )
+}
+
+// Output of the native program, checked against the interpreter: floats are
+// doubles in both, so even their rounding agrees.
+fn run_source(source: &str, name: &str, input: &str) -> String {
+    let (program, context) = run_passes(source);
+    let (output, errors, status) = run_x86_64(&generate_x86_64(context.ir.as_ref().unwrap()), name, input);
+    assert_eq!((errors.as_str(), status), ("", 0));
+    assert_eq!(output, interpret(&program, context.symbol_table(), input).0);
+    output
+}
+
+#[test]
+fn test_arguments_follow_the_calling_convention() {
+    let source = "
+function mix(a: integer, x: float, b: integer, c: integer, d: integer, e: integer, f: integer, g: integer,
+             y: float, z: float, h: integer) => float {
+  return (a + b * 2 + c * 3 + d * 4 + e * 5 + f * 6 + g * 7 + h * 8 + x + y * 10.0 + z * 100.0);
+}
+function many(a: float, b: float, c: float, d: float, e: float, f: float, g: float, h: float, i: float, j: float) => float {
+  return (a + b + c + d + e + f + g + h + i * 1000.0 - j);
+}
+function main() => void {
+  write(mix(1, 0.5, 2, 3, 4, 5, 6, 7, 0.25, 0.125, 8));
+  write(many(1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 0.5));
+}
+";
+    let (_, context) = run_passes(source);
+    let assembly = generate_x86_64(context.ir.as_ref().unwrap());
+    // the seventh integer and the ninth float come on the stack, past %rbp and
+    // the return address
+    assert!(assembly.contains("\
+mix_i_f_i_i_i_i_i_i_f_f_i:
+    pushq %rbp
+    movq %rsp, %rbp
+    subq $176, %rsp
+    movl %edi, -164(%rbp)
+    movsd %xmm0, -160(%rbp)
+    movl %esi, -152(%rbp)
+    movl %edx, -148(%rbp)
+    movl %ecx, -144(%rbp)
+    movl %r8d, -140(%rbp)
+    movl %r9d, -136(%rbp)
+    movl 16(%rbp), %eax
+    movl %eax, -132(%rbp)
+    movsd %xmm1, -128(%rbp)
+    movsd %xmm2, -120(%rbp)
+    movl 24(%rbp), %eax
+    movl %eax, -112(%rbp)
+"), "{}", assembly);
+    assert_eq!(run_source(source, "arguments", ""), "219.50\n9035.50\n");
+}
+
+#[test]
+fn test_generated_programs_match_the_interpreter() {
+    let source = "
+function sum(values: integer[], count: integer) => integer {
+  localvar total: integer;
+  localvar i: integer;
+  total = 0;
+  i = 0;
+  while (i < count) { total = total + values[i]; i = i + 1; };
+  return (total);
+}
+function scale(values: float[][3], factor: float) => void {
+  localvar i: integer;
+  i = 0;
+  while (i < 3) { values[1][i] = values[0][i] * factor; i = i + 1; };
+}
+function main() => void {
+  localvar values: integer[4];
+  localvar grid: float[2][3];
+  localvar n: integer;
+  localvar x: float;
+  read(n);
+  read(x);
+  values[0] = n;
+  values[3] = n * 1000000;
+  write(sum(values, 4));
+  write(values[3] * 3000 / 7);
+  write((-2147483647 - 1) / -1);
+  grid[0][0] = x;
+  grid[0][2] = 1 / 4;
+  grid[0][1] = -x / 3;
+  scale(grid, 2);
+  write(grid[1][0] + grid[1][1] + grid[1][2]);
+  write(-grid[0][2]);
+  if (n and not values[1] == 1) then write(1); else write(0);;
+  write(x <= 2.5);
+  write(x > 3.0);
+}
+";
+    assert_eq!(run_source(source, "programs", "5 2.5"), "5000005\n302156873\n-2147483648\n3.33\n-0.00\n1\n1\n0\n");
+}
+
+#[test]
+fn test_member_functions_are_dispatched_on_the_object() {
+    let source = "
+class SHAPE {
+  public attribute scale: integer;
+  public function area: () => integer;
+  public function describe: () => void;
+};
+class NAMED {
+  public attribute id: integer;
+  public function show: () => void;
+  public function code: () => integer;
+};
+class SQUARE isa SHAPE, NAMED {
+  public attribute side: integer;
+  public function area: () => integer;
+  public function code: () => integer;
+  public constructor: (side: integer);
+};
+function SHAPE::area() => integer { return (0); }
+function SHAPE::describe() => void { write(scale * area()); }
+function NAMED::show() => void { write(id * 1000 + code()); }
+function NAMED::code() => integer { return (1); }
+function SQUARE::area() => integer { return (side * side); }
+function SQUARE::code() => integer { return (side + id); }
+function SQUARE::constructor(side: integer) { self.side = side; scale = 10; id = 7; }
+function report(shape: SHAPE, named: NAMED) => void { shape.describe(); write(shape.area()); named.show(); }
+function main() => void {
+  localvar shape: SHAPE;
+  localvar square: SQUARE(3);
+  localvar squares: SQUARE[2];
+  shape.scale = 2;
+  shape.describe();
+  square.show();
+  report(square, square);
+  squares[1] = square;
+  report(squares[1], square);
+}
+";
+    let (_, context) = run_passes(source);
+    let assembly = generate_x86_64(context.ir.as_ref().unwrap());
+    // the function and the adjustment of `self` come from the table of the object,
+    // and the NAMED part of a SQUARE has a table of its own
+    assert!(assembly.contains("\
+    movl (%rax), %r11d
+    movl 12(%r11), %r10d
+    addl %r10d, %eax
+    movl 8(%r11), %r11d
+    movl %eax, %edi
+    call *%r11
+"));
+    assert!(assembly.contains("vtable_SQUARE_8:\n    .long NAMED_show, 0\n    .long SQUARE_code, -8\n"), "{}", assembly);
+    assert_eq!(run_source(source, "dispatch", ""), "0\n7010\n90\n9\n7010\n90\n9\n7010\n");
+    let polynomial = read_source_file("assignment1.COMP442-6421.paquet.2023.4/example-polynomial.src".to_string())
+        .replace("localvar counter: integer = 1;", "localvar counter: integer;")
+        .replace("write(f2.evaluate(counter));", "write(f2.evaluate(counter)); counter = counter + 1;");
+    assert!(run_source(&polynomial, "polynomial", "").starts_with("0\n3.50\n0.00\n1\n5.50\n-1.00\n"));
+}
+
+#[test]
+fn test_numbers_are_read_and_written_like_the_interpreter() {
+    let source = "
+function main() => void {
+  localvar i: integer;
+  localvar x: float;
+  localvar count: integer;
+  count = 0;
+  while (count < 3) { read(i); write(i); count = count + 1; };
+  count = 0;
+  while (count < 7) { read(x); write(x); count = count + 1; };
+}
+";
+    let output = run_source(source, "numbers", " -2147483648\n+17 0042\t1e2 -0.5 .125 2.675 1234567.891 3.5E-3 0.0001");
+    assert_eq!(output, "-2147483648\n17\n42\n100.00\n-0.50\n0.12\n2.67\n1234567.89\n0.00\n0.00\n");
+}
+
+#[test]
+fn test_runtime_errors_stop_the_program() {
+    let source = "
+function main() => void {
+  localvar n: integer;
+  localvar x: float;
+  read(n);
+  write(n);
+  read(x);
+  write(x / n);
+  write(1 / n);
+}
+";
+    let (_, context) = run_passes(source);
+    let assembly = generate_x86_64(context.ir.as_ref().unwrap());
+    assert_eq!(run_x86_64(&assembly, "zero", "0 1.5"), ("0\n".to_string(), "division by zero\n".to_string(), 1));
+    assert_eq!(run_x86_64(&assembly, "input", "3"), ("3\n".to_string(), "unexpected end of input\n".to_string(), 1));
+    assert_eq!(run_x86_64(&assembly, "invalid", "3 1.5x"), ("3\n".to_string(), "invalid number in input\n".to_string(), 1));
+    assert_eq!(run_x86_64(&assembly, "done", "2 3"), ("2\n1.50\n0\n".to_string(), String::new(), 0));
+}
@@ -0,0 +1,449 @@
+// Type checking of function bodies. Every expression gets a `Type`; an
+// expression whose type cannot be computed gets `None` after its error has been
+// reported, so a single mistake is not reported again by every enclosing node.
+// `integer` values are converted to `float` where a float is expected.
+
+use crate::ast::*;
+use crate::passes::{Diagnostic, Pass, PassContext};
+use crate::symbol_table::*;
+use crate::types::*;
+
+// Scope of a single function body: its own table, plus the attributes and member
+// functions of its class for member functions.
+pub struct FunctionScope<'a> {
+    pub global: &'a SymbolTable,
+    pub table: SymbolTable,
+    pub class_name: Option<String>,
+    pub return_type: TypeName,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> FunctionScope<'a> {
+    pub fn new(global: &'a SymbolTable, function: &FuncDef) -> Self {
+        let table = match global.function_table(function) {
+            Some(table) => table.clone(),
+            None => function_table_from(function.qualified_name(), &function.sig, &function.body),
+        };
+        FunctionScope {
+            global,
+            table,
+            class_name: function.class_name.clone(),
+            return_type: function.sig.return_type.clone(),
+            diagnostics: vec![],
+        }
+    }
+
+    fn error(&mut self, message: String, span: Span) {
+        self.diagnostics.push(Diagnostic::error(message, span));
+    }
+
+    pub fn lookup_variable(&self, name: &str) -> Option<SymbolEntry> {
+        if let Some(entry) = self.table.entries.iter().find(|entry| entry.name == name) {
+            return Some(entry.clone());
+        }
+        let class_name = self.class_name.as_ref()?;
+        if name == "self" {
+            return Some(SymbolEntry::new(name.to_string(), SymbolKind::Local, TypeName::Class(class_name.clone()), Span::default()));
+        }
+        self.global.lookup_member(class_name, name).into_iter()
+            .find(|entry| entry.kind == SymbolKind::Attribute)
+            .cloned()
+    }
+
+    // Member functions named `name` of `receiver_class`, or the functions a call
+    // without receiver can reach: member functions of the current class first.
+    // Member functions come with the class that declares them.
+    pub fn call_candidates(&self, receiver_class: Option<&str>, name: &str) -> (Option<String>, Vec<SymbolEntry>) {
+        let member_functions = |class_name: &str| match self.global.find_member(class_name, name) {
+            Some((owner, entries)) => {
+                let functions: Vec<SymbolEntry> = entries.into_iter().filter(|entry| entry.kind == SymbolKind::Function).cloned().collect();
+                (Some(owner), functions)
+            }
+            None => (None, vec![]),
+        };
+        if let Some(class_name) = receiver_class {
+            return member_functions(class_name);
+        }
+        if let Some(class_name) = &self.class_name {
+            let (owner, functions) = member_functions(class_name);
+            if !functions.is_empty() {
+                return (owner, functions);
+            }
+        }
+        let functions = self.global.lookup(name).into_iter().filter(|entry| entry.kind == SymbolKind::Function).cloned().collect();
+        (None, functions)
+    }
+
+    // Private members can only be used in the member functions of their own class.
+    fn check_access(&mut self, owner: &str, entry: &SymbolEntry, span: Span) {
+        if entry.visibility == Visibility::Private && self.class_name.as_deref() != Some(owner) {
+            let kind = match entry.kind {
+                SymbolKind::Attribute => "data member",
+                SymbolKind::Constructor => "constructor",
+                _ => "member function",
+            };
+            self.error(format!("private {} '{}' of class '{}' is not accessible here", kind, entry.name, owner), span);
+        }
+    }
+
+    pub fn expr_type(&mut self, expr: &Expr) -> Option<Type> {
+        match expr {
+            Expr::Literal { value: Literal::Int(value), span } => {
+                if *value > i32::MAX as i64 {
+                    self.error(format!("integer literal is out of range, the largest integer is {}", i32::MAX), *span);
+                }
+                Some(Type::scalar(TypeName::Integer))
+            }
+            Expr::Literal { value: Literal::Float(_), .. } => Some(Type::scalar(TypeName::Float)),
+            Expr::Var { name, span } => match self.lookup_variable(name) {
+                Some(entry) => {
+                    if entry.kind == SymbolKind::Attribute {
+                        let class_name = self.class_name.clone().unwrap();
+                        if let Some((owner, _)) = self.global.find_member(&class_name, name) {
+                            self.check_access(&owner, &entry, *span);
+                        }
+                    }
+                    Some(Type::of_entry(&entry))
+                }
+                None => {
+                    self.error(format!("undeclared local variable '{}'", name), *span);
+                    None
+                }
+            },
+            Expr::Binary { op, lhs, rhs, span } => {
+                let lhs_type = self.expr_type(lhs);
+                let rhs_type = self.expr_type(rhs);
+                let (lhs_type, rhs_type) = (lhs_type?, rhs_type?);
+                self.binary_type(*op, &lhs_type, &rhs_type, *span)
+            }
+            // the smallest integer is only written as the negation of a literal one
+            // larger than the largest
+            Expr::Unary { op: UnaryOp::Neg, operand, .. }
+                if matches!(**operand, Expr::Literal { value: Literal::Int(value), .. } if value == -(i32::MIN as i64)) => {
+                Some(Type::scalar(TypeName::Integer))
+            }
+            Expr::Unary { op, operand, span } => {
+                let operand_type = self.expr_type(operand)?;
+                let is_valid = match op {
+                    UnaryOp::Not => operand_type.is_integer(),
+                    UnaryOp::Plus | UnaryOp::Neg => operand_type.is_numeric(),
+                };
+                if !is_valid {
+                    self.error(format!("invalid operand type {} for '{}'", operand_type.as_string(), op.as_string()), *span);
+                    return None;
+                }
+                Some(operand_type)
+            }
+            Expr::Index { base, index, span } => {
+                let base_type = self.expr_type(base);
+                if let Some(index_type) = self.expr_type(index) {
+                    if !index_type.is_integer() {
+                        self.error(format!("array index has type {}, expected integer", index_type.as_string()), index.span());
+                    }
+                }
+                let base_type = base_type?;
+                if let (Some(Some(size)), Expr::Literal { value: Literal::Int(value), .. }) = (base_type.dims.first(), &**index) {
+                    if *value < 0 || *value as usize >= *size {
+                        self.error(format!("array index {} is out of bounds for a dimension of size {}", value, size), index.span());
+                    }
+                }
+                match base_type.indexed() {
+                    Some(indexed_type) => Some(indexed_type),
+                    None => {
+                        self.error(format!("too many indices on a value of type {}", base_type.as_string()), *span);
+                        None
+                    }
+                }
+            }
+            Expr::Member { base, name, span } => {
+                let class_name = self.class_of(base)?;
+                let attribute = self.global.find_member(&class_name, name).and_then(|(owner, entries)| {
+                    entries.into_iter()
+                        .find(|entry| entry.kind == SymbolKind::Attribute)
+                        .map(|entry| (owner, entry.clone()))
+                });
+                match attribute {
+                    Some((owner, entry)) => {
+                        self.check_access(&owner, &entry, *span);
+                        Some(Type::of_entry(&entry))
+                    }
+                    None => {
+                        self.error(format!("undeclared data member '{}' in class '{}'", name, class_name), *span);
+                        None
+                    }
+                }
+            }
+            Expr::Call { receiver, name, args, span } => {
+                let receiver_class = match receiver {
+                    Some(receiver) => Some(self.class_of(receiver)?),
+                    None => None,
+                };
+                let (owner, candidates) = self.call_candidates(receiver_class.as_deref(), name);
+                // reported like the semantic check does, so the two are merged
+                if candidates.is_empty() {
+                    for arg in args {
+                        self.expr_type(arg);
+                    }
+                    let message = match &receiver_class {
+                        Some(class_name) => format!("undeclared member function '{}' in class '{}'", name, class_name),
+                        None => format!("undeclared free function '{}'", name),
+                    };
+                    self.error(message, *span);
+                    return None;
+                }
+                let function = self.resolve_call(&candidates, name, args, *span)?;
+                if let Some(owner) = owner {
+                    self.check_access(&owner, &function, *span);
+                }
+                Some(Type::scalar(function.type_name))
+            }
+        }
+    }
+
+    // Class of an expression that must be a single object, as the base of `.`.
+    fn class_of(&mut self, expr: &Expr) -> Option<String> {
+        let expr_type = self.expr_type(expr)?;
+        match expr_type.type_name {
+            TypeName::Class(class_name) if !expr_type.is_array() => Some(class_name),
+            _ => {
+                self.error(format!("'.' applied to a value of type {}", expr_type.as_string()), expr.span());
+                None
+            }
+        }
+    }
+
+    fn binary_type(&mut self, op: BinaryOp, lhs: &Type, rhs: &Type, span: Span) -> Option<Type> {
+        let is_valid = if op.is_logical() {
+            lhs.is_integer() && rhs.is_integer()
+        } else {
+            lhs.is_numeric() && rhs.is_numeric()
+        };
+        if !is_valid {
+            self.error(format!("type mismatch in '{}': {} and {}", op.as_string(), lhs.as_string(), rhs.as_string()), span);
+            return None;
+        }
+        if op.is_relational() || op.is_logical() || (lhs.is_integer() && rhs.is_integer()) {
+            Some(Type::scalar(TypeName::Integer))
+        } else {
+            Some(Type::scalar(TypeName::Float))
+        }
+    }
+
+    // Objects are passed by address, so an object of a derived class can be passed
+    // for a parameter of one of its ancestors.
+    fn accepts_argument(&self, param_type: &Type, arg_type: &Type) -> bool {
+        match (&param_type.type_name, &arg_type.type_name) {
+            (TypeName::Class(param_class), TypeName::Class(arg_class)) if !param_type.is_array() && !arg_type.is_array() => {
+                param_class == arg_class || self.global.ancestors(arg_class).contains(param_class)
+            }
+            _ => param_type.accepts_argument(arg_type),
+        }
+    }
+
+    // Number of conversions (`integer` to `float`, or to an ancestor class) needed
+    // to call `function` with arguments of the given types, `None` if it cannot be
+    // called with them.
+    fn conversion_cost(&self, function: &SymbolEntry, arg_types: &[Option<Type>]) -> Option<usize> {
+        if function.params.len() != arg_types.len() {
+            return None;
+        }
+        let mut cost = 0;
+        for (param, arg_type) in function.params.iter().zip(arg_types) {
+            let arg_type = match arg_type {
+                Some(arg_type) => arg_type,
+                None => continue,
+            };
+            let param_type = Type::of_var(param);
+            if !self.accepts_argument(&param_type, arg_type) {
+                return None;
+            }
+            if param_type.type_name != arg_type.type_name {
+                cost += 1;
+            }
+        }
+        Some(cost)
+    }
+
+    // Picks the function called with `args` among the overloads in `candidates`:
+    // the one needing the fewest conversions, which must be unique.
+    pub fn resolve_call(&mut self, candidates: &[SymbolEntry], name: &str, args: &[Expr], span: Span) -> Option<SymbolEntry> {
+        let arg_types: Vec<Option<Type>> = args.iter().map(|arg| self.expr_type(arg)).collect();
+        if candidates.is_empty() {
+            self.error(format!("undeclared function '{}'", name), span);
+            return None;
+        }
+        let costs: Vec<(usize, &SymbolEntry)> = candidates.iter()
+            .filter_map(|function| self.conversion_cost(function, &arg_types).map(|cost| (cost, function)))
+            .collect();
+        if let Some(lowest_cost) = costs.iter().map(|(cost, _)| *cost).min() {
+            let best: Vec<&SymbolEntry> = costs.iter()
+                .filter(|(cost, _)| *cost == lowest_cost)
+                .map(|(_, function)| *function)
+                .collect();
+            if best.len() > 1 {
+                let signatures: Vec<String> = best.iter().map(|function| function.signature()).collect();
+                self.error(format!("ambiguous call to '{}': {}", name, signatures.join(" or ")), span);
+                return None;
+            }
+            return Some(best[0].clone());
+        }
+        if candidates.len() > 1 {
+            let arg_strings: Vec<String> = arg_types.iter()
+                .map(|arg_type| arg_type.as_ref().map(|arg_type| arg_type.as_string()).unwrap_or_else(|| "?".to_string()))
+                .collect();
+            self.error(format!("no matching overload for call to '{}' with arguments ({})", name, arg_strings.join(", ")), span);
+            return None;
+        }
+        let function = &candidates[0];
+        if function.params.len() != args.len() {
+            self.error(format!("wrong number of arguments in call to '{}': expected {}, got {}", name, function.params.len(), args.len()), span);
+            return None;
+        }
+        for (position, (param, arg_type)) in function.params.iter().zip(&arg_types).enumerate() {
+            if let Some(arg_type) = arg_type {
+                let param_type = Type::of_var(param);
+                if !self.accepts_argument(&param_type, arg_type) {
+                    self.error(format!("argument {} of '{}' has type {}, expected {}", position + 1, name, arg_type.as_string(), param_type.as_string()), span);
+                }
+            }
+        }
+        None
+    }
+
+    fn check_constructor_call(&mut self, var: &VarDecl) {
+        let args = match &var.constructor_args {
+            Some(args) => args,
+            None => return,
+        };
+        let class_name = match &var.type_name {
+            TypeName::Class(class_name) if var.dims.is_empty() => class_name.clone(),
+            _ => {
+                self.error(format!("constructor arguments given for '{}' of type {}", var.name, type_string(&var.type_name, &var.dims)), var.span);
+                return;
+            }
+        };
+        let constructors: Vec<SymbolEntry> = self.global.class_table(&class_name)
+            .map(|class_table| class_table.entries.iter().filter(|entry| entry.kind == SymbolKind::Constructor).cloned().collect())
+            .unwrap_or_default();
+        if constructors.is_empty() {
+            for arg in args {
+                self.expr_type(arg);
+            }
+            self.error(format!("class '{}' has no constructor", class_name), var.span);
+            return;
+        }
+        if let Some(constructor) = self.resolve_call(&constructors, &format!("{}::constructor", class_name), args, var.span) {
+            self.check_access(&class_name, &constructor, var.span);
+        }
+    }
+
+    fn check_assignable(&mut self, to: &Type, from: &Type, context: &str, span: Span) {
+        if to.is_array() || from.is_array() {
+            self.error(format!("arrays cannot be used in {}", context), span);
+        } else if !is_assignable(to, from) {
+            self.error(format!("type mismatch in {}: {} and {}", context, to.as_string(), from.as_string()), span);
+        }
+    }
+
+    pub fn check_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Local { var, .. } => self.check_constructor_call(var),
+            Stmt::Assign { target, value, span } => {
+                let target_type = self.expr_type(target);
+                let value_type = self.expr_type(value);
+                if let (Some(target_type), Some(value_type)) = (target_type, value_type) {
+                    self.check_assignable(&target_type, &value_type, "assignment", *span);
+                }
+            }
+            Stmt::If { condition, then_block, else_block, .. } => {
+                self.check_condition(condition);
+                for stmt in then_block.iter().chain(else_block) {
+                    self.check_stmt(stmt);
+                }
+            }
+            Stmt::While { condition, body, .. } => {
+                self.check_condition(condition);
+                for stmt in body {
+                    self.check_stmt(stmt);
+                }
+            }
+            Stmt::Read { target, span } => {
+                if let Some(target_type) = self.expr_type(target) {
+                    if !target_type.is_numeric() {
+                        self.error(format!("cannot read a value of type {}", target_type.as_string()), *span);
+                    }
+                }
+            }
+            Stmt::Write { value, span } => {
+                if let Some(value_type) = self.expr_type(value) {
+                    if !value_type.is_numeric() {
+                        self.error(format!("cannot write a value of type {}", value_type.as_string()), *span);
+                    }
+                }
+            }
+            Stmt::Return { value, span } => {
+                let value_type = self.expr_type(value);
+                if self.return_type == TypeName::Void {
+                    self.error("return statement in a function returning void".to_string(), *span);
+                } else if let Some(value_type) = value_type {
+                    let return_type = Type::scalar(self.return_type.clone());
+                    self.check_assignable(&return_type, &value_type, "return", *span);
+                }
+            }
+            Stmt::Call { call, .. } => {
+                self.expr_type(call);
+            }
+        }
+    }
+
+    fn check_condition(&mut self, condition: &Expr) {
+        if let Some(condition_type) = self.expr_type(condition) {
+            if !condition_type.is_integer() {
+                self.error(format!("condition has type {}, expected integer", condition_type.as_string()), condition.span());
+            }
+        }
+    }
+}
+
+// Whether every path through `body` ends in a return statement. A loop may run
+// no times, so only the branches of an `if` count.
+fn always_returns(body: &[Stmt]) -> bool {
+    body.iter().any(|stmt| match stmt {
+        Stmt::Return { .. } => true,
+        Stmt::If { then_block, else_block, .. } => always_returns(then_block) && always_returns(else_block),
+        _ => false,
+    })
+}
+
+pub fn check_types(program: &Program, global: &SymbolTable) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    for function in &program.functions {
+        let mut scope = FunctionScope::new(global, function);
+        for stmt in &function.body {
+            scope.check_stmt(stmt);
+        }
+        if function.sig.return_type != TypeName::Void && !always_returns(&function.body) {
+            scope.error(format!("function '{}' may not return a value", function.sig.name), function.sig.span);
+        }
+        diagnostics.extend(scope.diagnostics);
+    }
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span.line);
+    diagnostics
+}
+
+pub struct TypeCheckPass;
+
+impl Pass for TypeCheckPass {
+    fn name(&self) -> String {
+        "type-check".to_string()
+    }
+
+    fn needs_valid_program(&self) -> bool {
+        false
+    }
+
+    fn run(&mut self, program: &mut Program, context: &mut PassContext) {
+        let diagnostics = check_types(program, context.symbol_table());
+        context.report(diagnostics);
+    }
+}
@@ -0,0 +1,103 @@
+// Types of values: a base type plus one entry per array dimension, `None` for the
+// unsized dimensions of parameters such as `a: integer[]`. Sizes are in bytes, as
+// laid out by the code generator.
+
+use crate::ast::*;
+use crate::symbol_table::{type_string, SymbolEntry};
+
+pub const INTEGER_SIZE: usize = 4;
+pub const FLOAT_SIZE: usize = 8;
+// arrays with an unsized dimension are passed by address
+pub const ADDRESS_SIZE: usize = 4;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Type {
+    pub type_name: TypeName,
+    pub dims: Vec<Option<usize>>,
+}
+
+impl Type {
+    pub fn new(type_name: TypeName, dims: Vec<Option<usize>>) -> Self {
+        Type { type_name, dims }
+    }
+
+    pub fn scalar(type_name: TypeName) -> Self {
+        Type { type_name, dims: vec![] }
+    }
+
+    pub fn of_var(var: &VarDecl) -> Self {
+        Type::new(var.type_name.clone(), var.dims.clone())
+    }
+
+    pub fn of_entry(entry: &SymbolEntry) -> Self {
+        Type::new(entry.type_name.clone(), entry.dims.clone())
+    }
+
+    pub fn is_array(&self) -> bool {
+        !self.dims.is_empty()
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        !self.is_array() && matches!(self.type_name, TypeName::Integer | TypeName::Float)
+    }
+
+    pub fn is_integer(&self) -> bool {
+        !self.is_array() && self.type_name == TypeName::Integer
+    }
+
+    pub fn has_unsized_dims(&self) -> bool {
+        self.dims.iter().any(|dim| dim.is_none())
+    }
+
+    // Type of `value[i]`, `None` for a value that is not an array.
+    pub fn indexed(&self) -> Option<Type> {
+        if !self.is_array() {
+            return None;
+        }
+        Some(Type::new(self.type_name.clone(), self.dims[1..].to_vec()))
+    }
+
+    pub fn element_type(&self) -> Type {
+        Type::scalar(self.type_name.clone())
+    }
+
+    // Number of elements, `None` when a dimension is unsized.
+    pub fn element_count(&self) -> Option<usize> {
+        self.dims.iter().try_fold(1, |count, dim| dim.map(|size| count * size))
+    }
+
+    // Size of a value of this type given the size of its base type, `None` when a
+    // dimension is unsized.
+    pub fn size(&self, element_size: usize) -> Option<usize> {
+        self.element_count().map(|count| count * element_size)
+    }
+
+    // Distance in elements between `value[i]` and `value[i + 1]` for each
+    // dimension, for row-major layout.
+    pub fn strides(&self) -> Vec<Option<usize>> {
+        (0..self.dims.len())
+            .map(|index| Type::new(self.type_name.clone(), self.dims[index + 1..].to_vec()).element_count())
+            .collect()
+    }
+
+    // Whether an argument of type `arg` can be passed for a parameter of this type:
+    // arrays must agree on the base type and on every dimension the parameter sizes.
+    pub fn accepts_argument(&self, arg: &Type) -> bool {
+        if !self.is_array() {
+            return is_assignable(self, arg);
+        }
+        self.type_name == arg.type_name
+            && self.dims.len() == arg.dims.len()
+            && self.dims.iter().zip(&arg.dims).all(|(param_dim, arg_dim)| param_dim.is_none() || param_dim == arg_dim)
+    }
+
+    pub fn as_string(&self) -> String {
+        type_string(&self.type_name, &self.dims)
+    }
+}
+
+// Whether a value of type `from` can be stored in a variable of type `to`, with
+// `integer` converted to `float`.
+pub fn is_assignable(to: &Type, from: &Type) -> bool {
+    to == from || (to.type_name == TypeName::Float && !to.is_array() && from.is_integer())
+}
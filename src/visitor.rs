@@ -0,0 +1,136 @@
+// Visitors over the typed AST. Every `visit_*` method defaults to the matching
+// `walk_*` function, which visits the children in source order; override only the
+// node kinds a pass cares about and call `walk_*` to keep descending.
+
+use crate::ast::*;
+
+pub trait Visitor {
+    fn visit_program(&mut self, program: &Program) {
+        walk_program(self, program);
+    }
+
+    fn visit_class_decl(&mut self, class: &ClassDecl) {
+        walk_class_decl(self, class);
+    }
+
+    fn visit_member_decl(&mut self, member: &MemberDecl) {
+        walk_member_decl(self, member);
+    }
+
+    fn visit_func_def(&mut self, function: &FuncDef) {
+        walk_func_def(self, function);
+    }
+
+    fn visit_func_sig(&mut self, sig: &FuncSig) {
+        walk_func_sig(self, sig);
+    }
+
+    fn visit_var_decl(&mut self, var: &VarDecl) {
+        walk_var_decl(self, var);
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr);
+    }
+}
+
+pub fn walk_program<V: Visitor + ?Sized>(visitor: &mut V, program: &Program) {
+    for class in &program.classes {
+        visitor.visit_class_decl(class);
+    }
+    for function in &program.functions {
+        visitor.visit_func_def(function);
+    }
+}
+
+pub fn walk_class_decl<V: Visitor + ?Sized>(visitor: &mut V, class: &ClassDecl) {
+    for member in &class.members {
+        visitor.visit_member_decl(member);
+    }
+}
+
+pub fn walk_member_decl<V: Visitor + ?Sized>(visitor: &mut V, member: &MemberDecl) {
+    match member {
+        MemberDecl::Attribute { var, .. } => visitor.visit_var_decl(var),
+        MemberDecl::Function { sig, .. } => visitor.visit_func_sig(sig),
+    }
+}
+
+pub fn walk_func_def<V: Visitor + ?Sized>(visitor: &mut V, function: &FuncDef) {
+    visitor.visit_func_sig(&function.sig);
+    for stmt in &function.body {
+        visitor.visit_stmt(stmt);
+    }
+}
+
+pub fn walk_func_sig<V: Visitor + ?Sized>(visitor: &mut V, sig: &FuncSig) {
+    for param in &sig.params {
+        visitor.visit_var_decl(param);
+    }
+}
+
+pub fn walk_var_decl<V: Visitor + ?Sized>(visitor: &mut V, var: &VarDecl) {
+    if let Some(args) = &var.constructor_args {
+        for arg in args {
+            visitor.visit_expr(arg);
+        }
+    }
+}
+
+pub fn walk_stmt<V: Visitor + ?Sized>(visitor: &mut V, stmt: &Stmt) {
+    match stmt {
+        Stmt::Local { var, .. } => visitor.visit_var_decl(var),
+        Stmt::Assign { target, value, .. } => {
+            visitor.visit_expr(target);
+            visitor.visit_expr(value);
+        }
+        Stmt::If { condition, then_block, else_block, .. } => {
+            visitor.visit_expr(condition);
+            for stmt in then_block {
+                visitor.visit_stmt(stmt);
+            }
+            for stmt in else_block {
+                visitor.visit_stmt(stmt);
+            }
+        }
+        Stmt::While { condition, body, .. } => {
+            visitor.visit_expr(condition);
+            for stmt in body {
+                visitor.visit_stmt(stmt);
+            }
+        }
+        Stmt::Read { target, .. } => visitor.visit_expr(target),
+        Stmt::Write { value, .. } => visitor.visit_expr(value),
+        Stmt::Return { value, .. } => visitor.visit_expr(value),
+        Stmt::Call { call, .. } => visitor.visit_expr(call),
+    }
+}
+
+pub fn walk_expr<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expr) {
+    match expr {
+        Expr::Binary { lhs, rhs, .. } => {
+            visitor.visit_expr(lhs);
+            visitor.visit_expr(rhs);
+        }
+        Expr::Unary { operand, .. } => visitor.visit_expr(operand),
+        Expr::Literal { .. } => {}
+        Expr::Var { .. } => {}
+        Expr::Call { receiver, args, .. } => {
+            if let Some(receiver) = receiver {
+                visitor.visit_expr(receiver);
+            }
+            for arg in args {
+                visitor.visit_expr(arg);
+            }
+        }
+        Expr::Index { base, index, .. } => {
+            visitor.visit_expr(base);
+            visitor.visit_expr(index);
+        }
+        Expr::Member { base, .. } => visitor.visit_expr(base),
+    }
+}
@@ -0,0 +1,133 @@
+// Virtual function tables. Every member function call goes through the table whose
+// address starts the part of the object that belongs to the class declaring the
+// function. The slots of a class are the ones of its first parent, followed by the
+// member functions it declares that are not already there, so a table made for a
+// class also works for its first parent, which shares its table address. An
+// object of class `X` has a table address for `X` at offset 0 and one for every
+// other parent part, recursively. Each entry holds the function that a call on
+// an `X` runs, found like the interpreter does, and the amount to add to the
+// address of the part to get the address of the part declaring that function.
+
+use crate::ast::*;
+use crate::ir::{Vtable, VtableEntry};
+use crate::memory_layout::base_offset;
+use crate::symbol_table::*;
+use crate::synthesis::mangled_name;
+use crate::types::Type;
+
+fn parents(global: &SymbolTable, class_name: &str) -> Vec<SymbolEntry> {
+    global.class_table(class_name)
+        .map(|class_table| class_table.entries.iter().filter(|entry| entry.kind == SymbolKind::Inherit).cloned().collect())
+        .unwrap_or_default()
+}
+
+// Member functions in the table of `class_name`, in slot order.
+pub fn vtable_slots(global: &SymbolTable, class_name: &str) -> Vec<SymbolEntry> {
+    let mut slots = match parents(global, class_name).first() {
+        Some(parent) => vtable_slots(global, &parent.name),
+        None => vec![],
+    };
+    let class_table = match global.class_table(class_name) {
+        Some(class_table) => class_table,
+        None => return slots,
+    };
+    for entry in class_table.entries.iter().filter(|entry| entry.kind == SymbolKind::Function) {
+        if !slots.iter().any(|slot| slot.name == entry.name && slot.has_same_params(&entry.params)) {
+            slots.push(entry.clone());
+        }
+    }
+    slots
+}
+
+// Slot of the member function `name` with parameters `params` declared by `class_name`.
+pub fn slot_index(global: &SymbolTable, class_name: &str, name: &str, params: &[VarDecl]) -> usize {
+    vtable_slots(global, class_name).iter()
+        .position(|slot| slot.name == name && slot.has_same_params(params))
+        .unwrap_or_else(|| panic!("{}::{} has no slot", class_name, name))
+}
+
+pub fn vtable_name(class_name: &str, offset: usize) -> String {
+    match offset {
+        0 => format!("vtable_{}", class_name),
+        _ => format!("vtable_{}_{}", class_name, offset),
+    }
+}
+
+// Offset and class of the parts of an object of class `class_name` that hold a
+// table address: the object itself and every parent but the first, recursively.
+fn table_parts(global: &SymbolTable, class_name: &str) -> Vec<(usize, String)> {
+    fn visit(global: &SymbolTable, class_name: &str, offset: usize, parts: &mut Vec<(usize, String)>) {
+        for (index, parent) in parents(global, class_name).iter().enumerate() {
+            let parent_offset = offset + parent.offset.unwrap();
+            if index > 0 {
+                parts.push((parent_offset, parent.name.clone()));
+            }
+            visit(global, &parent.name, parent_offset, parts);
+        }
+    }
+    let mut parts = vec![(0, class_name.to_string())];
+    visit(global, class_name, 0, &mut parts);
+    parts
+}
+
+// Offset and table of every table address in a variable of type `var_type`,
+// including the ones of objects that are data members, by increasing offset.
+// The offsets are the ones of the memory layout of `global`.
+pub fn vtable_pointers(global: &SymbolTable, var_type: &Type) -> Vec<(usize, String)> {
+    let class_name = match &var_type.type_name {
+        TypeName::Class(class_name) => class_name,
+        _ => return vec![],
+    };
+    let mut pointers: Vec<(usize, String)> = table_parts(global, class_name).into_iter()
+        .map(|(offset, _)| (offset, vtable_name(class_name, offset)))
+        .collect();
+    // data members of every part
+    let mut parts = vec![(0, class_name.clone())];
+    while let Some((part_offset, part)) = parts.pop() {
+        let class_table = match global.class_table(&part) {
+            Some(class_table) => class_table,
+            None => continue,
+        };
+        for entry in &class_table.entries {
+            match entry.kind {
+                SymbolKind::Inherit => parts.push((part_offset + entry.offset.unwrap(), entry.name.clone())),
+                SymbolKind::Attribute => {
+                    let offset = part_offset + entry.offset.unwrap();
+                    pointers.extend(vtable_pointers(global, &Type::of_entry(entry)).into_iter()
+                        .map(|(inner_offset, name)| (offset + inner_offset, name)));
+                }
+                _ => {}
+            }
+        }
+    }
+    let element_pointers = pointers;
+    let element_size = global.class_table(class_name).and_then(|class_table| class_table.size).unwrap_or(0);
+    let mut pointers = vec![];
+    for element in 0..var_type.element_count().unwrap_or(1) {
+        pointers.extend(element_pointers.iter().map(|(offset, name)| (element * element_size + offset, name.clone())));
+    }
+    pointers.sort();
+    pointers
+}
+
+// Tables of every class, for objects of that exact class, with the adjustments
+// of `self` taken from the memory layout of `global`.
+pub fn build_vtables(global: &SymbolTable) -> Vec<Vtable> {
+    let mut vtables = vec![];
+    for class in global.entries.iter().filter(|entry| entry.kind == SymbolKind::Class) {
+        for (offset, part) in table_parts(global, &class.name) {
+            let entries = vtable_slots(global, &part).iter()
+                .map(|slot| {
+                    let owner = global.find_override(&class.name, &slot.name, &slot.params).unwrap();
+                    let owner_offset = base_offset(global, &class.name, &owner).unwrap();
+                    VtableEntry {
+                        function: mangled_name(Some(&owner), &slot.name, &slot.params),
+                        adjustment: owner_offset as i64 - offset as i64,
+                    }
+                })
+                .collect();
+            vtables.push(Vtable { name: vtable_name(&class.name, offset), entries });
+        }
+    }
+    vtables
+}
@@ -0,0 +1,502 @@
+// WebAssembly back end, writing the text format. As in the Moon back end, every
+// variable and temporary lives in its slot of the function's frame, laid out by
+// the memory layout pass, but frames are in linear memory: the `$sp` global
+// points past the last one, and each function keeps the base of its own in the
+// `$fp` local. Arguments are WebAssembly parameters, which the callee stores in
+// their slots, and the result is the WebAssembly result.
+//
+// The IR jumps anywhere, while WebAssembly only has structured control flow, so a
+// function with labels becomes a loop around nested blocks, one per label: `$pc`
+// holds the index of the label to go to, a `br_table` leaves the blocks up to the
+// code after that label, and a jump sets `$pc` and goes back to the top of the
+// loop. Code falls through to the next label without going around.
+//
+// Virtual function tables are data at the start of the memory with the layout
+// of the Moon ones, two words per slot: the index of the function in the table
+// of `call_indirect` and the adjustment of `self`. `read` and `write` call the
+// functions the host provides as `host.read_int`, `host.read_float`,
+// `host.write_int` and `host.write_float`, and the host calls the exported
+// `main`. A division by zero traps.
+
+use crate::ast::{BinaryOp, UnaryOp};
+use crate::ir::*;
+
+// Address of the first table, leaving 0 unused.
+pub const DATA_START: usize = 16;
+// 64 KiB pages of memory, for the tables and the frames.
+pub const MEMORY_PAGES: usize = 16;
+
+// Text with two spaces of indentation per level.
+#[derive(Default)]
+pub struct WatWriter {
+    lines: Vec<String>,
+    depth: usize,
+}
+
+impl WatWriter {
+    pub fn line(&mut self, text: &str) {
+        self.lines.push(format!("{}{}", "  ".repeat(self.depth), text));
+    }
+
+    // A line that opens a block, whose content is indented until `close`.
+    pub fn open(&mut self, text: &str) {
+        self.line(text);
+        self.depth += 1;
+    }
+
+    pub fn close(&mut self, text: &str) {
+        self.depth -= 1;
+        self.line(text);
+    }
+
+    pub fn text(self) -> String {
+        let mut text = self.lines.join("\n");
+        text.push('\n');
+        text
+    }
+}
+
+fn value_type(ty: IrType) -> &'static str {
+    match ty {
+        IrType::Int => "i32",
+        IrType::Float => "f64",
+    }
+}
+
+pub fn wat_float(value: f64) -> String {
+    if value.is_nan() {
+        "nan".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "inf" } else { "-inf" }.to_string()
+    } else {
+        format!("{:?}", value)
+    }
+}
+
+// `(param ..) (result ..)` of a function, as a type use.
+fn signature(function: &IrFunction) -> String {
+    let mut parts: Vec<String> = function.params.iter()
+        .map(|param| format!("(param {})", value_type(function.value_type(&Operand::Var(param.clone())))))
+        .collect();
+    if let Some(ty) = function.return_type {
+        parts.push(format!("(result {})", value_type(ty)));
+    }
+    parts.join(" ")
+}
+
+fn int_opcode(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Add => "i32.add",
+        BinaryOp::Sub => "i32.sub",
+        BinaryOp::Mul => "i32.mul",
+        BinaryOp::Div => "call $rt_idiv",
+        BinaryOp::Eq => "i32.eq",
+        BinaryOp::Neq => "i32.ne",
+        BinaryOp::Lt => "i32.lt_s",
+        BinaryOp::Gt => "i32.gt_s",
+        BinaryOp::Leq => "i32.le_s",
+        BinaryOp::Geq => "i32.ge_s",
+        BinaryOp::And => "i32.and",
+        BinaryOp::Or => "i32.or",
+    }
+}
+
+fn float_opcode(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Add => "f64.add",
+        BinaryOp::Sub => "f64.sub",
+        BinaryOp::Mul => "f64.mul",
+        BinaryOp::Div => "call $rt_fdiv",
+        BinaryOp::Eq => "f64.eq",
+        BinaryOp::Neq => "f64.ne",
+        BinaryOp::Lt => "f64.lt",
+        BinaryOp::Gt => "f64.gt",
+        BinaryOp::Leq => "f64.le",
+        BinaryOp::Geq => "f64.ge",
+        _ => unreachable!("{:?} has no float instruction", op),
+    }
+}
+
+// Functions called through the tables, in table order, and the types of the
+// calls, in order of first use.
+struct Tables {
+    functions: Vec<String>,
+    types: Vec<String>,
+}
+
+impl Tables {
+    fn new(ir: &IrProgram) -> Self {
+        let mut tables = Tables { functions: vec![], types: vec![] };
+        for entry in ir.vtables.iter().flat_map(|vtable| &vtable.entries) {
+            if !tables.functions.contains(&entry.function) {
+                tables.functions.push(entry.function.clone());
+            }
+        }
+        for instr in ir.functions.iter().flat_map(|function| &function.body) {
+            if let Instr::CallVirtual { function, .. } = instr {
+                let signature = signature(ir.function(function).unwrap());
+                if !tables.types.contains(&signature) {
+                    tables.types.push(signature);
+                }
+            }
+        }
+        tables
+    }
+
+    fn function_index(&self, name: &str) -> usize {
+        self.functions.iter().position(|function| function == name).unwrap()
+    }
+
+    fn type_index(&self, signature: &str) -> usize {
+        self.types.iter().position(|ty| ty == signature).unwrap()
+    }
+}
+
+struct FunctionCodegen<'a> {
+    program: &'a IrProgram,
+    function: &'a IrFunction,
+    tables: &'a Tables,
+    // labels starting each part of the body after the first
+    labels: Vec<String>,
+    writer: &'a mut WatWriter,
+}
+
+impl<'a> FunctionCodegen<'a> {
+    fn frame_size(&self) -> usize {
+        self.function.frame.size.unwrap().div_ceil(8) * 8
+    }
+
+    fn slot(&self, operand: &Operand) -> usize {
+        let entry = match operand {
+            Operand::Var(name) => self.function.variable(name),
+            Operand::Temp(number) => self.function.temporary(*number),
+            _ => None,
+        };
+        entry.and_then(|entry| entry.offset)
+            .unwrap_or_else(|| panic!("{} has no slot in {}", operand.as_string(), self.function.name))
+    }
+
+    fn load(&mut self, operand: &Operand) {
+        match operand {
+            Operand::Int(value) => self.writer.line(&format!("i32.const {}", *value as i32)),
+            Operand::Float(value) => self.writer.line(&format!("f64.const {}", wat_float(*value))),
+            _ => {
+                let (ty, slot) = (self.function.value_type(operand), self.slot(operand));
+                self.writer.line("local.get $fp");
+                self.writer.line(&format!("{}.load offset={}", value_type(ty), slot));
+            }
+        }
+    }
+
+    // Loads `operand` as 1 if it is not zero, or 0.
+    fn load_truth(&mut self, operand: &Operand) {
+        self.load(operand);
+        match self.function.value_type(operand) {
+            IrType::Int => {
+                self.writer.line("i32.const 0");
+                self.writer.line("i32.ne");
+            }
+            IrType::Float => {
+                self.writer.line("f64.const 0");
+                self.writer.line("f64.ne");
+            }
+        }
+    }
+
+    // The frame base goes under the value computed by `compute`, of type `ty`,
+    // which is then stored in the slot of `dest`, converted if it is a float.
+    fn store(&mut self, dest: &Operand, ty: IrType, compute: impl FnOnce(&mut Self)) {
+        let (dest_type, slot) = (self.function.value_type(dest), self.slot(dest));
+        self.writer.line("local.get $fp");
+        compute(self);
+        if (ty, dest_type) == (IrType::Int, IrType::Float) {
+            self.writer.line("f64.convert_i32_s");
+        }
+        self.writer.line(&format!("{}.store offset={}", value_type(dest_type), slot));
+    }
+
+    fn jump(&mut self, label: &str) {
+        let index = self.labels.iter().position(|other| other == label).unwrap() + 1;
+        self.writer.line(&format!("i32.const {}", index));
+        self.writer.line("local.set $pc");
+        self.writer.line("br $dispatch");
+    }
+
+    fn generate(&mut self) {
+        let mut header = format!("func ${}", self.function.name);
+        if self.function.name == "main" {
+            header.push_str(" (export \"main\")");
+        }
+        for (index, param) in self.function.params.iter().enumerate() {
+            let ty = self.function.value_type(&Operand::Var(param.clone()));
+            header.push_str(&format!(" (param $arg{} {})", index, value_type(ty)));
+        }
+        if let Some(ty) = self.function.return_type {
+            header.push_str(&format!(" (result {})", value_type(ty)));
+        }
+        self.writer.open(&format!("({}", header));
+        self.writer.line("(local $fp i32) (local $pc i32)");
+        self.writer.line("global.get $sp");
+        self.writer.line("local.tee $fp");
+        self.writer.line(&format!("i32.const {}", self.frame_size()));
+        self.writer.line("i32.add");
+        self.writer.line("global.set $sp");
+        for (index, param) in self.function.params.iter().enumerate() {
+            let param = Operand::Var(param.clone());
+            let ty = self.function.value_type(&param);
+            self.store(&param, ty, |codegen| codegen.writer.line(&format!("local.get $arg{}", index)));
+        }
+        self.labels = self.function.body.iter()
+            .filter_map(|instr| match instr {
+                Instr::Label(label) => Some(label.clone()),
+                _ => None,
+            })
+            .collect();
+        if !self.labels.is_empty() {
+            self.writer.open("loop $dispatch");
+            for label in self.labels.iter().rev() {
+                self.writer.open(&format!("block ${}", label));
+            }
+            self.writer.open("block $entry");
+            self.writer.line("local.get $pc");
+            let targets: Vec<String> = std::iter::once("entry").chain(self.labels.iter().map(String::as_str))
+                .map(|label| format!("${}", label))
+                .collect();
+            self.writer.line(&format!("br_table {}", targets.join(" ")));
+            self.writer.close("end");
+        }
+        for (position, instr) in self.function.body.iter().enumerate() {
+            match instr {
+                Instr::Label(_) => self.writer.close("end"),
+                _ => {
+                    self.writer.line(&format!(";; {}", instr.as_string()));
+                    self.generate_instr(position, instr);
+                }
+            }
+        }
+        if !self.labels.is_empty() {
+            self.writer.close("end");
+        }
+        // the body always returns, but only the IR knows it
+        self.writer.line("unreachable");
+        self.writer.close(")");
+    }
+
+    fn generate_instr(&mut self, position: usize, instr: &Instr) {
+        match instr {
+            Instr::Label(_) => {}
+            Instr::Copy { dest, src } => {
+                let ty = self.function.value_type(src);
+                self.store(dest, ty, |codegen| codegen.load(src));
+            }
+            Instr::Binary { dest, op, lhs, rhs, .. } if op.is_logical() => self.store(dest, IrType::Int, |codegen| {
+                codegen.load_truth(lhs);
+                codegen.load_truth(rhs);
+                codegen.writer.line(int_opcode(*op));
+            }),
+            Instr::Binary { dest, op, ty, lhs, rhs } => {
+                let result_type = if op.is_relational() { IrType::Int } else { *ty };
+                self.store(dest, result_type, |codegen| {
+                    codegen.load(lhs);
+                    codegen.load(rhs);
+                    codegen.writer.line(match ty {
+                        IrType::Int => int_opcode(*op),
+                        IrType::Float => float_opcode(*op),
+                    });
+                });
+            }
+            Instr::Unary { dest, op: UnaryOp::Not, operand, .. } => self.store(dest, IrType::Int, |codegen| {
+                codegen.load_truth(operand);
+                codegen.writer.line("i32.eqz");
+            }),
+            Instr::Unary { dest, ty: IrType::Int, operand, .. } => self.store(dest, IrType::Int, |codegen| {
+                codegen.writer.line("i32.const 0");
+                codegen.load(operand);
+                codegen.writer.line("i32.sub");
+            }),
+            Instr::Unary { dest, ty: IrType::Float, operand, .. } => self.store(dest, IrType::Float, |codegen| {
+                codegen.load(operand);
+                codegen.writer.line("f64.neg");
+            }),
+            Instr::IntToFloat { dest, src } => self.store(dest, IrType::Float, |codegen| {
+                codegen.load(src);
+                codegen.writer.line("f64.convert_i32_s");
+            }),
+            Instr::AddressOf { dest, var } => {
+                let slot = self.slot(&Operand::Var(var.clone()));
+                self.store(dest, IrType::Int, |codegen| {
+                    codegen.writer.line("local.get $fp");
+                    codegen.writer.line(&format!("i32.const {}", slot));
+                    codegen.writer.line("i32.add");
+                });
+            }
+            Instr::Load { dest, ty, address, offset } => self.store(dest, *ty, |codegen| {
+                codegen.load(address);
+                codegen.writer.line(&format!("{}.load offset={}", value_type(*ty), offset));
+            }),
+            Instr::Store { ty, address, offset, value } => {
+                self.load(address);
+                self.load(value);
+                self.writer.line(&format!("{}.store offset={}", value_type(*ty), offset));
+            }
+            Instr::Jump(label) => {
+                // falling through to the next label
+                if self.function.body.get(position + 1) != Some(&Instr::Label(label.clone())) {
+                    self.jump(label);
+                }
+            }
+            Instr::Branch { condition, if_true, if_false } => {
+                let index = |label: &String| self.labels.iter().position(|other| other == label).unwrap() + 1;
+                let (if_true, if_false) = (index(if_true), index(if_false));
+                self.writer.line(&format!("i32.const {}", if_true));
+                self.writer.line(&format!("i32.const {}", if_false));
+                self.load(condition);
+                self.writer.line("select");
+                self.writer.line("local.set $pc");
+                self.writer.line("br $dispatch");
+            }
+            Instr::Call { dest, function, args } => self.generate_call(dest.as_ref(), function, None, args),
+            Instr::CallVirtual { dest, function, slot, args } => self.generate_call(dest.as_ref(), function, Some(*slot), args),
+            Instr::VtableAddress { dest, vtable } => {
+                let address = vtable_addresses(self.program)[&vtable.as_str()];
+                self.store(dest, IrType::Int, |codegen| codegen.writer.line(&format!("i32.const {}", address)));
+            }
+            // added by IR generation at the end of a function returning a value,
+            // which has nothing to return if control gets there
+            Instr::Return(None) if self.function.return_type.is_some() => self.writer.line("unreachable"),
+            Instr::Return(value) => {
+                self.writer.line("local.get $fp");
+                self.writer.line("global.set $sp");
+                if let Some(value) = value {
+                    self.load(value);
+                }
+                self.writer.line("return");
+            }
+            Instr::Read { dest, ty } => self.store(dest, *ty, |codegen| match ty {
+                IrType::Int => codegen.writer.line("call $read_int"),
+                IrType::Float => codegen.writer.line("call $read_float"),
+            }),
+            Instr::Write { value, ty } => {
+                self.load(value);
+                match ty {
+                    IrType::Int => self.writer.line("call $write_int"),
+                    IrType::Float => self.writer.line("call $write_float"),
+                }
+            }
+        }
+    }
+
+    // With a `slot`, `name` only gives the type of the callee, whose index in the
+    // table comes from the table of the object passed first.
+    fn generate_call(&mut self, dest: Option<&Operand>, name: &str, slot: Option<usize>, args: &[Operand]) {
+        let callee = self.program.function(name).unwrap_or_else(|| panic!("call to unknown function {}", name));
+        let call = |codegen: &mut Self| {
+            for (index, arg) in args.iter().enumerate() {
+                codegen.load(arg);
+                if let (Some(slot), 0) = (slot, index) {
+                    codegen.load(arg);
+                    codegen.writer.line("i32.load");
+                    codegen.writer.line(&format!("i32.load offset={}", 8 * slot + 4));
+                    codegen.writer.line("i32.add");
+                }
+            }
+            match slot {
+                Some(slot) => {
+                    codegen.load(&args[0]);
+                    codegen.writer.line("i32.load");
+                    codegen.writer.line(&format!("i32.load offset={}", 8 * slot));
+                    let type_index = codegen.tables.type_index(&signature(callee));
+                    codegen.writer.line(&format!("call_indirect (type $t{})", type_index));
+                }
+                None => codegen.writer.line(&format!("call ${}", name)),
+            }
+        };
+        match (dest, callee.return_type) {
+            (Some(dest), Some(ty)) => self.store(dest, ty, call),
+            (_, Some(_)) => {
+                call(self);
+                self.writer.line("drop");
+            }
+            (_, None) => call(self),
+        }
+    }
+}
+
+// Addresses of the tables in memory, from `DATA_START`.
+fn vtable_addresses(ir: &IrProgram) -> std::collections::HashMap<&str, usize> {
+    let mut address = DATA_START;
+    ir.vtables.iter()
+        .map(|vtable| {
+            let start = address;
+            address += 8 * vtable.entries.len();
+            (vtable.name.as_str(), start)
+        })
+        .collect()
+}
+
+// `$rt_idiv` wraps like the interpreter where `i32.div_s` would trap, and
+// `$rt_fdiv` traps on a division by zero like `i32.div_s`.
+const RUNTIME: &str = "  (func $rt_idiv (param $lhs i32) (param $rhs i32) (result i32)
+    local.get $rhs
+    i32.const -1
+    i32.eq
+    if
+      i32.const 0
+      local.get $lhs
+      i32.sub
+      return
+    end
+    local.get $lhs
+    local.get $rhs
+    i32.div_s
+  )
+  (func $rt_fdiv (param $lhs f64) (param $rhs f64) (result f64)
+    local.get $rhs
+    f64.const 0
+    f64.eq
+    if
+      unreachable
+    end
+    local.get $lhs
+    local.get $rhs
+    f64.div
+  )
+";
+
+// WebAssembly module for `ir`, which must contain a `main` function.
+pub fn generate_wat(ir: &IrProgram) -> String {
+    let tables = Tables::new(ir);
+    let mut writer = WatWriter::default();
+    writer.open("(module");
+    for (index, ty) in tables.types.iter().enumerate() {
+        writer.line(&format!("(type $t{} (func {}))", index, ty));
+    }
+    writer.line("(import \"host\" \"read_int\" (func $read_int (result i32)))");
+    writer.line("(import \"host\" \"read_float\" (func $read_float (result f64)))");
+    writer.line("(import \"host\" \"write_int\" (func $write_int (param i32)))");
+    writer.line("(import \"host\" \"write_float\" (func $write_float (param f64)))");
+    writer.line(&format!("(memory (export \"memory\") {})", MEMORY_PAGES));
+    let data_size: usize = ir.vtables.iter().map(|vtable| 8 * vtable.entries.len()).sum();
+    writer.line(&format!("(global $sp (mut i32) (i32.const {}))", (DATA_START + data_size).div_ceil(8) * 8));
+    if !tables.functions.is_empty() {
+        let functions: Vec<String> = tables.functions.iter().map(|function| format!("${}", function)).collect();
+        writer.line(&format!("(table {} funcref)", functions.len()));
+        writer.line(&format!("(elem (i32.const 0) {})", functions.join(" ")));
+    }
+    let addresses = vtable_addresses(ir);
+    for vtable in ir.vtables.iter().filter(|vtable| !vtable.entries.is_empty()) {
+        let bytes: String = vtable.entries.iter()
+            .flat_map(|entry| [tables.function_index(&entry.function) as i32, entry.adjustment as i32])
+            .flat_map(i32::to_le_bytes)
+            .map(|byte| format!("\\{:02x}", byte))
+            .collect();
+        writer.line(&format!("(data (i32.const {}) \"{}\") ;; {}", addresses[&vtable.name.as_str()], bytes, vtable.name));
+    }
+    for function in &ir.functions {
+        FunctionCodegen { program: ir, function, tables: &tables, labels: vec![], writer: &mut writer }.generate();
+    }
+    let mut text = writer.text();
+    text.push_str(RUNTIME);
+    text.push_str(")\n");
+    text
+}
@@ -0,0 +1,850 @@
+// x86-64 back end, writing GNU assembler text in AT&T syntax for Linux. As in the
+// Moon back end, every variable and temporary lives in its slot of the function's
+// frame, laid out by the memory layout pass: each IR instruction loads its
+// operands into scratch registers, computes, and stores the result back. The
+// frame is the `frame size` bytes below `%rbp`, rounded up to keep the stack
+// aligned on 16 bytes.
+//
+// Functions follow the System V calling convention: integers and addresses are
+// passed in `%rdi`, `%rsi`, `%rdx`, `%rcx`, `%r8` and `%r9`, floats in `%xmm0` to
+// `%xmm7`, the rest on the stack, and results come back in `%eax` or `%xmm0`. The
+// callee copies its parameters into their slots. Integers are 32-bit and floats
+// are doubles, computed with SSE instructions.
+//
+// Addresses are 32-bit integers in the IR, so the program has to be linked at a
+// fixed low address (`ld`, or `cc -nostdlib -static -no-pie`) and `_start` moves
+// the stack into `.bss`. Virtual function tables keep the layout of the Moon
+// ones, two 32-bit words per slot: the address of the function and the
+// adjustment of `self`. `read`, `write` and runtime errors go through the Linux
+// system calls in the runtime emitted with the program, without the C library.
+
+use crate::ast::{BinaryOp, UnaryOp};
+use crate::ir::*;
+
+pub const INT_ARGUMENT_REGISTERS: [&str; 6] = ["edi", "esi", "edx", "ecx", "r8d", "r9d"];
+pub const FLOAT_ARGUMENT_REGISTERS: usize = 8;
+
+// Assembly text with labels in the first column.
+#[derive(Default)]
+pub struct AsmWriter {
+    lines: Vec<String>,
+}
+
+impl AsmWriter {
+    pub fn label(&mut self, label: &str) {
+        self.lines.push(format!("{}:", label));
+    }
+
+    pub fn instr(&mut self, text: &str) {
+        self.lines.push(format!("    {}", text));
+    }
+
+    pub fn comment(&mut self, text: &str) {
+        self.lines.push(format!("    # {}", text));
+    }
+
+    pub fn text(self) -> String {
+        let mut text = self.lines.join("\n");
+        text.push('\n');
+        text
+    }
+}
+
+// Where each argument goes, in the order of the parameters: a register, or the
+// position among the arguments passed on the stack.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ArgumentLocation {
+    IntRegister(usize),
+    FloatRegister(usize),
+    Stack(usize),
+}
+
+fn argument_locations(types: &[IrType]) -> Vec<ArgumentLocation> {
+    let (mut ints, mut floats, mut stack) = (0, 0, 0);
+    types.iter().map(|ty| match ty {
+        IrType::Int if ints < INT_ARGUMENT_REGISTERS.len() => {
+            ints += 1;
+            ArgumentLocation::IntRegister(ints - 1)
+        }
+        IrType::Float if floats < FLOAT_ARGUMENT_REGISTERS => {
+            floats += 1;
+            ArgumentLocation::FloatRegister(floats - 1)
+        }
+        _ => {
+            stack += 1;
+            ArgumentLocation::Stack(stack - 1)
+        }
+    }).collect()
+}
+
+fn parameter_types(function: &IrFunction) -> Vec<IrType> {
+    function.params.iter().map(|param| function.value_type(&Operand::Var(param.clone()))).collect()
+}
+
+fn condition_code(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Eq => "e",
+        BinaryOp::Neq => "ne",
+        BinaryOp::Lt => "l",
+        BinaryOp::Gt => "g",
+        BinaryOp::Leq => "le",
+        BinaryOp::Geq => "ge",
+        _ => unreachable!("{:?} is not a comparison", op),
+    }
+}
+
+struct FunctionCodegen<'a> {
+    program: &'a IrProgram,
+    function: &'a IrFunction,
+    writer: &'a mut AsmWriter,
+}
+
+impl<'a> FunctionCodegen<'a> {
+    fn label(&self, label: &str) -> String {
+        format!(".L{}_{}", self.function.name, label)
+    }
+
+    fn frame_size(&self) -> usize {
+        self.function.frame.size.unwrap().div_ceil(16) * 16
+    }
+
+    fn slot(&self, operand: &Operand) -> String {
+        let entry = match operand {
+            Operand::Var(name) => self.function.variable(name),
+            Operand::Temp(number) => self.function.temporary(*number),
+            _ => None,
+        };
+        let offset = entry.and_then(|entry| entry.offset)
+            .unwrap_or_else(|| panic!("{} has no slot in {}", operand.as_string(), self.function.name));
+        format!("{}(%rbp)", offset as i64 - self.frame_size() as i64)
+    }
+
+    // Loads an integer into a 32-bit register, which clears the upper half.
+    fn load_int(&mut self, register: &str, operand: &Operand) {
+        match operand {
+            Operand::Int(value) => self.writer.instr(&format!("movl ${}, %{}", *value as i32, register)),
+            _ => {
+                let slot = self.slot(operand);
+                self.writer.instr(&format!("movl {}, %{}", slot, register));
+            }
+        }
+    }
+
+    // Float constants go through `%r11`.
+    fn load_float(&mut self, register: &str, operand: &Operand) {
+        match operand {
+            Operand::Float(value) => {
+                self.writer.instr(&format!("movabsq ${}, %r11", value.to_bits() as i64));
+                self.writer.instr(&format!("movq %r11, %{}", register));
+            }
+            _ => {
+                let slot = self.slot(operand);
+                self.writer.instr(&format!("movsd {}, %{}", slot, register));
+            }
+        }
+    }
+
+    fn store_int(&mut self, dest: &Operand, register: &str) {
+        let slot = self.slot(dest);
+        self.writer.instr(&format!("movl %{}, {}", register, slot));
+    }
+
+    fn store_float(&mut self, dest: &Operand, register: &str) {
+        let slot = self.slot(dest);
+        self.writer.instr(&format!("movsd %{}, {}", register, slot));
+    }
+
+    // Stores the flag in `%al` as 0 or 1 in `dest`, converted if it is a float.
+    fn store_flag(&mut self, dest: &Operand) {
+        self.writer.instr("movzbl %al, %eax");
+        match self.function.value_type(dest) {
+            IrType::Int => self.store_int(dest, "eax"),
+            IrType::Float => {
+                self.writer.instr("cvtsi2sdl %eax, %xmm0");
+                self.store_float(dest, "xmm0");
+            }
+        }
+    }
+
+    // Sets `%al` to whether `operand` is not zero.
+    fn test_not_zero(&mut self, operand: &Operand) {
+        match self.function.value_type(operand) {
+            IrType::Int => {
+                self.load_int("edx", operand);
+                self.writer.instr("testl %edx, %edx");
+                self.writer.instr("setne %al");
+            }
+            IrType::Float => {
+                self.load_float("xmm0", operand);
+                self.writer.instr("xorpd %xmm1, %xmm1");
+                self.writer.instr("ucomisd %xmm1, %xmm0");
+                // unordered, so not equal, for NaN
+                self.writer.instr("setne %al");
+                self.writer.instr("setp %dl");
+                self.writer.instr("orb %dl, %al");
+            }
+        }
+    }
+
+    fn generate(&mut self) {
+        let name = self.function.name.clone();
+        self.writer.label(&name);
+        self.writer.instr("pushq %rbp");
+        self.writer.instr("movq %rsp, %rbp");
+        self.writer.instr(&format!("subq ${}, %rsp", self.frame_size()));
+        let locations = argument_locations(&parameter_types(self.function));
+        for (param, location) in self.function.params.iter().zip(locations) {
+            let param = Operand::Var(param.clone());
+            match (self.function.value_type(&param), location) {
+                (_, ArgumentLocation::IntRegister(index)) => self.store_int(&param, INT_ARGUMENT_REGISTERS[index]),
+                (_, ArgumentLocation::FloatRegister(index)) => self.store_float(&param, &format!("xmm{}", index)),
+                // past the saved %rbp and the return address
+                (IrType::Int, ArgumentLocation::Stack(index)) => {
+                    self.writer.instr(&format!("movl {}(%rbp), %eax", 16 + 8 * index));
+                    self.store_int(&param, "eax");
+                }
+                (IrType::Float, ArgumentLocation::Stack(index)) => {
+                    self.writer.instr(&format!("movsd {}(%rbp), %xmm0", 16 + 8 * index));
+                    self.store_float(&param, "xmm0");
+                }
+            }
+        }
+        for instr in &self.function.body {
+            if !matches!(instr, Instr::Label(_)) {
+                self.writer.comment(&instr.as_string());
+            }
+            self.generate_instr(instr);
+        }
+    }
+
+    fn generate_instr(&mut self, instr: &Instr) {
+        match instr {
+            Instr::Label(label) => {
+                let label = self.label(label);
+                self.writer.label(&label);
+            }
+            Instr::Copy { dest, src } => match self.function.value_type(dest) {
+                IrType::Int => {
+                    self.load_int("eax", src);
+                    self.store_int(dest, "eax");
+                }
+                IrType::Float => {
+                    self.load_float("xmm0", src);
+                    self.store_float(dest, "xmm0");
+                }
+            },
+            Instr::Binary { dest, op, lhs, rhs, .. } if op.is_logical() => {
+                self.test_not_zero(lhs);
+                self.writer.instr("movb %al, %cl");
+                self.test_not_zero(rhs);
+                match op {
+                    BinaryOp::And => self.writer.instr("andb %cl, %al"),
+                    _ => self.writer.instr("orb %cl, %al"),
+                }
+                self.store_flag(dest);
+            }
+            Instr::Binary { dest, op, ty: IrType::Int, lhs, rhs } => {
+                if *op == BinaryOp::Div {
+                    self.load_int("edi", lhs);
+                    self.load_int("esi", rhs);
+                    self.writer.instr("call rt_idiv");
+                    self.store_int(dest, "eax");
+                    return;
+                }
+                self.load_int("eax", lhs);
+                self.load_int("ecx", rhs);
+                match op {
+                    BinaryOp::Add => self.writer.instr("addl %ecx, %eax"),
+                    BinaryOp::Sub => self.writer.instr("subl %ecx, %eax"),
+                    BinaryOp::Mul => self.writer.instr("imull %ecx, %eax"),
+                    _ => {
+                        self.writer.instr("cmpl %ecx, %eax");
+                        self.writer.instr(&format!("set{} %al", condition_code(*op)));
+                        self.store_flag(dest);
+                        return;
+                    }
+                }
+                self.store_int(dest, "eax");
+            }
+            Instr::Binary { dest, op, ty: IrType::Float, lhs, rhs } => {
+                self.load_float("xmm0", lhs);
+                self.load_float("xmm1", rhs);
+                match op {
+                    BinaryOp::Add => self.writer.instr("addsd %xmm1, %xmm0"),
+                    BinaryOp::Sub => self.writer.instr("subsd %xmm1, %xmm0"),
+                    BinaryOp::Mul => self.writer.instr("mulsd %xmm1, %xmm0"),
+                    BinaryOp::Div => self.writer.instr("call rt_fdiv"),
+                    // `a` and `b` (carry) are false when either side is NaN
+                    BinaryOp::Lt | BinaryOp::Leq => {
+                        self.writer.instr("ucomisd %xmm0, %xmm1");
+                        self.writer.instr(if *op == BinaryOp::Lt { "seta %al" } else { "setae %al" });
+                    }
+                    BinaryOp::Gt | BinaryOp::Geq => {
+                        self.writer.instr("ucomisd %xmm1, %xmm0");
+                        self.writer.instr(if *op == BinaryOp::Gt { "seta %al" } else { "setae %al" });
+                    }
+                    BinaryOp::Eq => {
+                        self.writer.instr("ucomisd %xmm1, %xmm0");
+                        self.writer.instr("sete %al");
+                        self.writer.instr("setnp %cl");
+                        self.writer.instr("andb %cl, %al");
+                    }
+                    _ => {
+                        self.writer.instr("ucomisd %xmm1, %xmm0");
+                        self.writer.instr("setne %al");
+                        self.writer.instr("setp %cl");
+                        self.writer.instr("orb %cl, %al");
+                    }
+                }
+                match op.is_relational() {
+                    true => self.store_flag(dest),
+                    false => self.store_float(dest, "xmm0"),
+                }
+            }
+            Instr::Unary { dest, op: UnaryOp::Not, operand, .. } => {
+                self.test_not_zero(operand);
+                self.writer.instr("xorb $1, %al");
+                self.store_flag(dest);
+            }
+            Instr::Unary { dest, ty: IrType::Int, operand, .. } => {
+                self.load_int("eax", operand);
+                self.writer.instr("negl %eax");
+                self.store_int(dest, "eax");
+            }
+            Instr::Unary { dest, ty: IrType::Float, operand, .. } => {
+                // flipping the sign bit, so that -0.0 stays negative
+                self.load_float("xmm0", operand);
+                self.writer.instr("movabsq $-9223372036854775808, %rax");
+                self.writer.instr("movq %rax, %xmm1");
+                self.writer.instr("xorpd %xmm1, %xmm0");
+                self.store_float(dest, "xmm0");
+            }
+            Instr::IntToFloat { dest, src } => {
+                self.load_int("eax", src);
+                self.writer.instr("cvtsi2sdl %eax, %xmm0");
+                self.store_float(dest, "xmm0");
+            }
+            Instr::AddressOf { dest, var } => {
+                let slot = self.slot(&Operand::Var(var.clone()));
+                self.writer.instr(&format!("leaq {}, %rax", slot));
+                self.store_int(dest, "eax");
+            }
+            Instr::Load { dest, ty, address, offset } => {
+                self.load_int("eax", address);
+                match ty {
+                    IrType::Int => {
+                        self.writer.instr(&format!("movl {}(%rax), %ecx", offset));
+                        self.store_int(dest, "ecx");
+                    }
+                    IrType::Float => {
+                        self.writer.instr(&format!("movsd {}(%rax), %xmm0", offset));
+                        self.store_float(dest, "xmm0");
+                    }
+                }
+            }
+            Instr::Store { ty, address, offset, value } => {
+                self.load_int("eax", address);
+                match ty {
+                    IrType::Int => {
+                        self.load_int("ecx", value);
+                        self.writer.instr(&format!("movl %ecx, {}(%rax)", offset));
+                    }
+                    IrType::Float => {
+                        self.load_float("xmm0", value);
+                        self.writer.instr(&format!("movsd %xmm0, {}(%rax)", offset));
+                    }
+                }
+            }
+            Instr::Jump(label) => {
+                let label = self.label(label);
+                self.writer.instr(&format!("jmp {}", label));
+            }
+            Instr::Branch { condition, if_true, if_false } => {
+                let (if_true, if_false) = (self.label(if_true), self.label(if_false));
+                self.load_int("eax", condition);
+                self.writer.instr("testl %eax, %eax");
+                self.writer.instr(&format!("jz {}", if_false));
+                self.writer.instr(&format!("jmp {}", if_true));
+            }
+            Instr::Call { dest, function, args } => self.generate_call(dest.as_ref(), function, None, args),
+            Instr::CallVirtual { dest, function, slot, args } => self.generate_call(dest.as_ref(), function, Some(*slot), args),
+            Instr::VtableAddress { dest, vtable } => {
+                self.writer.instr(&format!("movl ${}, %eax", vtable));
+                self.store_int(dest, "eax");
+            }
+            Instr::Return(value) => {
+                match (value, self.function.return_type) {
+                    (Some(value), Some(IrType::Float)) => self.load_float("xmm0", value),
+                    (Some(value), _) => self.load_int("eax", value),
+                    (None, _) => {}
+                }
+                self.writer.instr("leave");
+                self.writer.instr("ret");
+            }
+            Instr::Read { dest, ty } => match ty {
+                IrType::Int => {
+                    self.writer.instr("call rt_read_int");
+                    self.store_int(dest, "eax");
+                }
+                IrType::Float => {
+                    self.writer.instr("call rt_read_float");
+                    self.store_float(dest, "xmm0");
+                }
+            },
+            Instr::Write { value, ty } => match ty {
+                IrType::Int => {
+                    self.load_int("edi", value);
+                    self.writer.instr("call rt_write_int");
+                }
+                IrType::Float => {
+                    self.load_float("xmm0", value);
+                    self.writer.instr("call rt_write_float");
+                }
+            },
+        }
+    }
+
+    // With a `slot`, `name` only gives the parameters of the callee, and the
+    // function is taken from the table of the object passed first, once the other
+    // arguments are in place.
+    fn generate_call(&mut self, dest: Option<&Operand>, name: &str, slot: Option<usize>, args: &[Operand]) {
+        let callee = self.program.function(name).unwrap_or_else(|| panic!("call to unknown function {}", name));
+        let types = parameter_types(callee);
+        let locations = argument_locations(&types);
+        let stacked: Vec<usize> = (0..args.len()).filter(|index| matches!(locations[*index], ArgumentLocation::Stack(_))).collect();
+        // the stack stays aligned on 16 bytes at the call
+        let padding = if stacked.len() % 2 == 1 { 8 } else { 0 };
+        if padding > 0 {
+            self.writer.instr("subq $8, %rsp");
+        }
+        for index in stacked.iter().rev() {
+            match (&args[*index], types[*index]) {
+                (Operand::Float(value), _) => self.writer.instr(&format!("movabsq ${}, %rax", value.to_bits() as i64)),
+                (arg, IrType::Float) => {
+                    let slot = self.slot(arg);
+                    self.writer.instr(&format!("movq {}, %rax", slot));
+                }
+                (arg, IrType::Int) => self.load_int("eax", arg),
+            }
+            self.writer.instr("pushq %rax");
+        }
+        for (index, (arg, location)) in args.iter().zip(&locations).enumerate() {
+            if slot.is_some() && index == 0 {
+                continue;
+            }
+            match location {
+                ArgumentLocation::IntRegister(register) => self.load_int(INT_ARGUMENT_REGISTERS[*register], arg),
+                ArgumentLocation::FloatRegister(register) => self.load_float(&format!("xmm{}", register), arg),
+                ArgumentLocation::Stack(_) => {}
+            }
+        }
+        match slot {
+            Some(slot) => {
+                self.load_int("eax", &args[0]);
+                self.writer.instr("movl (%rax), %r11d");
+                self.writer.instr(&format!("movl {}(%r11), %r10d", 8 * slot + 4));
+                self.writer.instr("addl %r10d, %eax");
+                self.writer.instr(&format!("movl {}(%r11), %r11d", 8 * slot));
+                self.writer.instr("movl %eax, %edi");
+                self.writer.instr("call *%r11");
+            }
+            None => self.writer.instr(&format!("call {}", name)),
+        }
+        if !stacked.is_empty() {
+            self.writer.instr(&format!("addq ${}, %rsp", 8 * stacked.len() + padding));
+        }
+        if let Some(dest) = dest {
+            match callee.return_type {
+                Some(IrType::Float) => self.store_float(dest, "xmm0"),
+                _ => self.store_int(dest, "eax"),
+            }
+        }
+    }
+}
+
+// `rt_write_int` writes `%edi` and a newline, `rt_write_float` writes `%xmm0` with
+// two decimals rounded half to even like the interpreter, up to 2^63 hundredths.
+// `rt_read_int` reads an integer into `%eax` and `rt_read_float` a float, with an
+// optional exponent, into `%xmm0`; both stop the program at the end of the input
+// or on a token that is not a number. `rt_idiv` computes `%edi / %esi` into
+// `%eax`, wrapping like the interpreter, and `rt_fdiv` `%xmm0 / %xmm1` into
+// `%xmm0`; both stop the program on a division by zero. The routines follow the
+// calling convention, and keep nothing in the registers a callee has to save but
+// `%rbx`, which `rt_read_token` restores.
+const RUNTIME: &str = "
+    # runtime: output
+rt_write_int:
+    movslq %edi, %rax
+    movq %rax, %r8
+    testq %rax, %rax
+    jns 1f
+    negq %rax
+1:  leaq rt_output+32(%rip), %rdi
+    movb $10, (%rdi)
+    call rt_digits
+    jmp rt_write_sign
+rt_write_float:
+    movq %xmm0, %r8
+    movabsq $0x7fffffffffffffff, %rax
+    movq %rax, %xmm1
+    andpd %xmm1, %xmm0
+    # %xmm0 + %xmm4 is exactly 100 times %xmm3, splitting it in two halves of
+    # 26 bits whose products by 100 are exact
+    movapd %xmm0, %xmm3
+    movabsq $0x4059000000000000, %rax
+    movq %rax, %xmm1
+    mulsd %xmm1, %xmm0
+    movabsq $0x41a0000002000000, %rax
+    movq %rax, %xmm4
+    mulsd %xmm3, %xmm4
+    movapd %xmm4, %xmm5
+    subsd %xmm3, %xmm5
+    subsd %xmm5, %xmm4
+    movapd %xmm3, %xmm5
+    subsd %xmm4, %xmm5
+    mulsd %xmm1, %xmm4
+    subsd %xmm0, %xmm4
+    mulsd %xmm1, %xmm5
+    addsd %xmm5, %xmm4
+    # rounded half to even, unless the product was only rounded to a half
+    cvtsd2si %xmm0, %rax
+    cvtsi2sdq %rax, %xmm2
+    subsd %xmm2, %xmm0
+    xorpd %xmm6, %xmm6
+    movabsq $0x3fe0000000000000, %rdx
+    movq %rdx, %xmm7
+    ucomisd %xmm7, %xmm0
+    jne 1f
+    ucomisd %xmm6, %xmm4
+    jbe 2f
+    incq %rax
+    jmp 2f
+1:  movabsq $0xbfe0000000000000, %rdx
+    movq %rdx, %xmm7
+    ucomisd %xmm7, %xmm0
+    jne 2f
+    ucomisd %xmm6, %xmm4
+    jae 2f
+    decq %rax
+2:  movl $100, %ecx
+    xorl %edx, %edx
+    divq %rcx
+    movq %rax, %r9
+    movl %edx, %eax
+    movb $10, %cl
+    divb %cl
+    addb $48, %al
+    addb $48, %ah
+    leaq rt_output+32(%rip), %rdi
+    movb $10, (%rdi)
+    movb %ah, -1(%rdi)
+    movb %al, -2(%rdi)
+    movb $46, -3(%rdi)
+    subq $3, %rdi
+    movq %r9, %rax
+    call rt_digits
+rt_write_sign:
+    testq %r8, %r8
+    jns 1f
+    decq %rdi
+    movb $45, (%rdi)
+1:  leaq rt_output+33(%rip), %rdx
+    subq %rdi, %rdx
+    movq %rdi, %rsi
+    movl $1, %edi
+    movl $1, %eax
+    syscall
+    ret
+    # the decimal digits of %rax, written backwards from %rdi, which is left on
+    # the first one
+rt_digits:
+    movl $10, %ecx
+1:  xorl %edx, %edx
+    divq %rcx
+    addb $48, %dl
+    decq %rdi
+    movb %dl, (%rdi)
+    testq %rax, %rax
+    jnz 1b
+    ret
+    # runtime: input
+    # the next byte of the input in %eax, or -1 at its end
+rt_getc:
+    movq rt_input_position(%rip), %rax
+    cmpq rt_input_length(%rip), %rax
+    jb 1f
+    xorl %eax, %eax
+    xorl %edi, %edi
+    leaq rt_input(%rip), %rsi
+    movl $4096, %edx
+    syscall
+    testq %rax, %rax
+    jle 2f
+    movq %rax, rt_input_length(%rip)
+    xorl %eax, %eax
+1:  leaq rt_input(%rip), %rdx
+    movzbl (%rdx,%rax), %edx
+    incq %rax
+    movq %rax, rt_input_position(%rip)
+    movl %edx, %eax
+    ret
+2:  movl $-1, %eax
+    ret
+    # the next token of the input in rt_token, with its length in %rcx
+rt_read_token:
+    pushq %rbx
+    xorl %ebx, %ebx
+1:  call rt_getc
+    cmpl $32, %eax
+    jg 2f
+    cmpl $-1, %eax
+    je 3f
+    testl %ebx, %ebx
+    jz 1b
+    jmp 3f
+2:  cmpl $64, %ebx
+    jae rt_invalid_number
+    leaq rt_token(%rip), %rdx
+    movb %al, (%rdx,%rbx)
+    incl %ebx
+    jmp 1b
+3:  testl %ebx, %ebx
+    jz rt_end_of_input
+    movq %rbx, %rcx
+    popq %rbx
+    ret
+    # the sign at the start of the token: %edx is past it, %r8d is 1 for a minus
+rt_read_sign:
+    leaq rt_token(%rip), %rsi
+    xorl %edx, %edx
+    xorl %r8d, %r8d
+    movzbl (%rsi), %eax
+    cmpl $45, %eax
+    jne 1f
+    movl $1, %r8d
+    incl %edx
+    ret
+1:  cmpl $43, %eax
+    jne 2f
+    incl %edx
+2:  ret
+rt_read_int:
+    call rt_read_token
+    call rt_read_sign
+    cmpl %ecx, %edx
+    jae rt_invalid_number
+    xorl %eax, %eax
+1:  movzbl (%rsi,%rdx), %r9d
+    subl $48, %r9d
+    cmpl $9, %r9d
+    ja rt_invalid_number
+    imull $10, %eax, %eax
+    addl %r9d, %eax
+    incl %edx
+    cmpl %ecx, %edx
+    jb 1b
+    testl %r8d, %r8d
+    jz 2f
+    negl %eax
+2:  ret
+    # digits past the 18th only count in the exponent (%r10), and %r11 counts
+    # the digits
+rt_read_float:
+    call rt_read_token
+    call rt_read_sign
+    xorl %eax, %eax
+    xorl %r10d, %r10d
+    xorl %r11d, %r11d
+    movabsq $100000000000000000, %rdi
+1:  cmpl %ecx, %edx
+    jae 6f
+    movzbl (%rsi,%rdx), %r9d
+    incl %edx
+    cmpl $46, %r9d
+    je 2f
+    cmpl $101, %r9d
+    je 4f
+    cmpl $69, %r9d
+    je 4f
+    subl $48, %r9d
+    cmpl $9, %r9d
+    ja rt_invalid_number
+    incl %r11d
+    cmpq %rdi, %rax
+    jae 11f
+    imulq $10, %rax, %rax
+    addq %r9, %rax
+    jmp 1b
+11: incl %r10d
+    jmp 1b
+2:  cmpl %ecx, %edx
+    jae 6f
+    movzbl (%rsi,%rdx), %r9d
+    incl %edx
+    cmpl $101, %r9d
+    je 4f
+    cmpl $69, %r9d
+    je 4f
+    subl $48, %r9d
+    cmpl $9, %r9d
+    ja rt_invalid_number
+    incl %r11d
+    cmpq %rdi, %rax
+    jae 2b
+    imulq $10, %rax, %rax
+    addq %r9, %rax
+    decl %r10d
+    jmp 2b
+    # the exponent, with its sign in %r11d, added to %r10d
+4:  testl %r11d, %r11d
+    jz rt_invalid_number
+    xorl %r11d, %r11d
+    cmpl %ecx, %edx
+    jae rt_invalid_number
+    movzbl (%rsi,%rdx), %r9d
+    cmpl $45, %r9d
+    jne 41f
+    movl $1, %r11d
+    incl %edx
+    jmp 42f
+41: cmpl $43, %r9d
+    jne 42f
+    incl %edx
+42: cmpl %ecx, %edx
+    jae rt_invalid_number
+    xorl %edi, %edi
+5:  movzbl (%rsi,%rdx), %r9d
+    subl $48, %r9d
+    cmpl $9, %r9d
+    ja rt_invalid_number
+    cmpl $100000, %edi
+    jae 51f
+    imull $10, %edi, %edi
+    addl %r9d, %edi
+51: incl %edx
+    cmpl %ecx, %edx
+    jb 5b
+    testl %r11d, %r11d
+    jz 52f
+    negl %edi
+52: addl %edi, %r10d
+    jmp 61f
+6:  testl %r11d, %r11d
+    jz rt_invalid_number
+    # the mantissa in %rax, scaled by 10^%r10d
+61: cvtsi2sdq %rax, %xmm0
+    movabsq $0x3ff0000000000000, %rax
+    movq %rax, %xmm2
+    movabsq $0x4024000000000000, %rax
+    movq %rax, %xmm1
+    movl %r10d, %ecx
+    testl %ecx, %ecx
+    jns 7f
+    negl %ecx
+7:  testl %ecx, %ecx
+    jz 8f
+    mulsd %xmm1, %xmm2
+    decl %ecx
+    jmp 7b
+8:  testl %r10d, %r10d
+    js 9f
+    mulsd %xmm2, %xmm0
+    jmp 10f
+9:  divsd %xmm2, %xmm0
+10: testl %r8d, %r8d
+    jz 12f
+    movabsq $-9223372036854775808, %rax
+    movq %rax, %xmm1
+    xorpd %xmm1, %xmm0
+12: ret
+    # runtime: division and errors
+rt_idiv:
+    testl %esi, %esi
+    jz rt_division_by_zero
+    movl %edi, %eax
+    cmpl $-1, %esi
+    jne 1f
+    negl %eax
+    ret
+1:  cltd
+    idivl %esi
+    ret
+rt_fdiv:
+    xorpd %xmm2, %xmm2
+    ucomisd %xmm2, %xmm1
+    jp 1f
+    je rt_division_by_zero
+1:  divsd %xmm1, %xmm0
+    ret
+rt_division_by_zero:
+    leaq rt_division_by_zero_message(%rip), %rsi
+    movl $17, %edx
+    jmp rt_fail
+rt_end_of_input:
+    leaq rt_end_of_input_message(%rip), %rsi
+    movl $24, %edx
+    jmp rt_fail
+rt_invalid_number:
+    leaq rt_invalid_number_message(%rip), %rsi
+    movl $24, %edx
+    # writes the message at %rsi, of %rdx bytes, to the standard error and exits
+    # with status 1
+rt_fail:
+    movl $2, %edi
+    movl $1, %eax
+    syscall
+    movl $60, %eax
+    movl $1, %edi
+    syscall
+    .section .rodata
+rt_division_by_zero_message:
+    .ascii \"division by zero\\n\"
+rt_end_of_input_message:
+    .ascii \"unexpected end of input\\n\"
+rt_invalid_number_message:
+    .ascii \"invalid number in input\\n\"
+    .bss
+    .align 16
+rt_input_position:
+    .skip 8
+rt_input_length:
+    .skip 8
+rt_input:
+    .skip 4096
+rt_token:
+    .skip 64
+rt_output:
+    .skip 48
+rt_stack:
+    .skip 8388608
+rt_stack_end:
+";
+
+// GNU assembler program for `ir`, which must contain a `main` function.
+pub fn generate_x86_64(ir: &IrProgram) -> String {
+    let mut writer = AsmWriter::default();
+    writer.instr(".text");
+    writer.instr(".globl _start");
+    writer.label("_start");
+    writer.instr("movq $rt_stack_end, %rsp");
+    writer.instr("call main");
+    writer.instr("movl $60, %eax");
+    writer.instr("xorl %edi, %edi");
+    writer.instr("syscall");
+    for function in &ir.functions {
+        FunctionCodegen { program: ir, function, writer: &mut writer }.generate();
+    }
+    let mut text = writer.text();
+    text.push_str(RUNTIME);
+    let mut writer = AsmWriter::default();
+    writer.instr(".section .rodata");
+    writer.instr(".align 4");
+    for vtable in &ir.vtables {
+        writer.label(&vtable.name);
+        for entry in &vtable.entries {
+            writer.instr(&format!(".long {}, {}", entry.function, entry.adjustment));
+        }
+    }
+    text.push_str(&writer.text());
+    text
+}